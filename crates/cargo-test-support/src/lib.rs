@@ -1627,6 +1627,9 @@ fn substitute_macros(input: &str) -> String {
         ("[UPDATING]", "    Updating"),
         ("[ADDING]", "      Adding"),
         ("[REMOVING]", "    Removing"),
+        ("[HOLDING]", "     Holding"),
+        ("[UPGRADING]", "   Upgrading"),
+        ("[MERGING]", "     Merging"),
         ("[DOCTEST]", "   Doc-tests"),
         ("[PACKAGING]", "   Packaging"),
         ("[DOWNLOADING]", " Downloading"),
@@ -1643,6 +1646,9 @@ fn substitute_macros(input: &str) -> String {
         ("[IGNORED]", "     Ignored"),
         ("[INSTALLED]", "   Installed"),
         ("[REPLACED]", "    Replaced"),
+        ("[LOGIN]", "       Login"),
+        ("[LOGOUT]", "      Logout"),
+        ("[DIRTY]", "       Dirty"),
     ];
     let mut result = input.to_owned();
     for &(pat, subst) in &macros {
@@ -1,14 +1,20 @@
 use crate::git::repo;
 use crate::paths;
 use cargo::sources::CRATES_IO_INDEX;
-use cargo::util::Sha256;
+use cargo::util::{Checksum, ChecksumAlgorithm, Sha256};
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use std::collections::HashMap;
 use std::fs::{self, File};
 use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::path::{Path, PathBuf};
-use tar::{Builder, Header};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::thread;
+use tar::{Builder, EntryType, Header};
 use url::Url;
 
 /// Gets the path to the local index pretending to be crates.io. This is a Git repo
@@ -139,6 +145,7 @@ pub struct Package {
     deps: Vec<Dependency>,
     files: Vec<(String, String)>,
     extra_files: Vec<(String, String)>,
+    symlinks: Vec<(String, String)>,
     yanked: bool,
     features: HashMap<String, Vec<String>>,
     local: bool,
@@ -146,6 +153,8 @@ pub struct Package {
     invalid_json: bool,
     proc_macro: bool,
     links: Option<String>,
+    rust_version: Option<String>,
+    checksum_algorithm: Option<ChecksumAlgorithm>,
 }
 
 #[derive(Clone)]
@@ -229,6 +238,176 @@ pub fn init_registry(registry_path: PathBuf, dl_url: String, api_url: Url, api_p
     fs::create_dir_all(api_path.join("api/v1/crates")).unwrap();
 }
 
+/// A minimal HTTP/1.1 server for testing sparse registries and remote build
+/// caches.
+///
+/// It serves the files under `paths::root()` (so the index of `registry_path`
+/// is found under `/registry/`), tags every response with an `ETag` derived
+/// from the file contents, and answers `If-None-Match` requests with `304 Not
+/// Modified` when the file hasn't changed. `PUT` requests write the files
+/// under `paths::root()`, and are answered with `201 Created`. Every request
+/// is logged so tests can check which files Cargo actually fetched.
+pub struct HttpServer {
+    addr: SocketAddr,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl HttpServer {
+    pub fn new() -> HttpServer {
+        HttpServer::start(None)
+    }
+
+    /// Starts a server which answers `401 Unauthorized` to the requests
+    /// which don't have `token` as their `Authorization` header.
+    pub fn with_token(token: &str) -> HttpServer {
+        HttpServer::start(Some(token.to_string()))
+    }
+
+    fn start(token: Option<String>) -> HttpServer {
+        let listener = t!(TcpListener::bind("127.0.0.1:0"));
+        let addr = t!(listener.local_addr());
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let root = paths::root();
+        let server_log = log.clone();
+        thread::spawn(move || {
+            for conn in listener.incoming() {
+                let conn = match conn {
+                    Ok(conn) => conn,
+                    Err(_) => return,
+                };
+                let root = root.clone();
+                let log = server_log.clone();
+                let token = token.clone();
+                thread::spawn(move || HttpServer::handle(conn, &root, token.as_deref(), &log));
+            }
+        });
+        HttpServer { addr, log }
+    }
+
+    /// The base URL of this server, such as `http://127.0.0.1:1234`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Returns the log of requests received so far, one `<path> <status>`
+    /// line each, such as `/registry/3/b/bar 200`.
+    pub fn requests(&self) -> Vec<String> {
+        self.log.lock().unwrap().clone()
+    }
+
+    fn handle(conn: TcpStream, root: &Path, token: Option<&str>, log: &Mutex<Vec<String>>) {
+        let mut reader = BufReader::new(t!(conn.try_clone()));
+        let mut conn = conn;
+        loop {
+            let mut request = String::new();
+            match reader.read_line(&mut request) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {}
+            }
+            let method = request
+                .split_whitespace()
+                .next()
+                .unwrap_or("GET")
+                .to_string();
+            let path = request.split_whitespace().nth(1).unwrap_or("/");
+            // Query strings, such as the one of the first request of git's
+            // HTTP protocol, are ignored.
+            let path = path.split('?').next().unwrap().to_string();
+            let mut if_none_match = None;
+            let mut authorization = None;
+            let mut content_length = 0;
+            let mut expect_continue = false;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                    return;
+                }
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                let mut parts = line.splitn(2, ':');
+                let name = parts.next().unwrap().trim().to_ascii_lowercase();
+                let value = parts.next().unwrap_or("").trim().to_string();
+                match name.as_str() {
+                    "if-none-match" => if_none_match = Some(value),
+                    "authorization" => authorization = Some(value),
+                    "content-length" => content_length = value.parse().unwrap_or(0),
+                    "expect" => expect_continue = value.eq_ignore_ascii_case("100-continue"),
+                    _ => {}
+                }
+            }
+            let mut uploaded = vec![0; content_length];
+            if method == "PUT" {
+                if expect_continue && conn.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").is_err() {
+                    return;
+                }
+                if reader.read_exact(&mut uploaded).is_err() {
+                    return;
+                }
+            }
+
+            let authorized = token.is_none() || authorization.as_deref() == token;
+            let file = root.join(&path[1..]);
+            let (status, etag, body) = match fs::read(&file) {
+                _ if !authorized => ("401 Unauthorized", None, Vec::new()),
+                _ if method == "PUT" => {
+                    t!(fs::create_dir_all(file.parent().unwrap()));
+                    t!(fs::write(&file, &uploaded));
+                    ("201 Created", None, Vec::new())
+                }
+                Ok(contents) => {
+                    let etag = format!("\"{}\"", &cksum(&contents)[..16]);
+                    if if_none_match.as_ref() == Some(&etag) {
+                        ("304 Not Modified", Some(etag), Vec::new())
+                    } else {
+                        ("200 OK", Some(etag), contents)
+                    }
+                }
+                Err(_) => ("404 Not Found", None, Vec::new()),
+            };
+            log.lock()
+                .unwrap()
+                .push(format!("{} {}", path, &status[..3]));
+
+            let mut response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n", status, body.len());
+            if let Some(etag) = etag {
+                response.push_str(&format!("ETag: {}\r\n", etag));
+            }
+            response.push_str("\r\n");
+            if conn.write_all(response.as_bytes()).is_err() || conn.write_all(&body).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Starts an `HttpServer` and reconfigures the sources set up by `init` so
+/// that both the replacement for crates.io and the `alternative` registry
+/// are accessed as sparse registries through it.
+pub fn init_sparse() -> HttpServer {
+    init();
+    let server = HttpServer::new();
+    t!(fs::write(
+        paths::home().join(".cargo/config"),
+        format!(
+            r#"
+                [source.crates-io]
+                registry = 'https://wut'
+                replace-with = 'dummy-registry'
+
+                [source.dummy-registry]
+                registry = 'sparse+{url}/registry'
+
+                [registries.alternative]
+                index = 'sparse+{url}/alternative-registry'
+            "#,
+            url = server.url()
+        )
+    ));
+    server
+}
+
 impl Package {
     /// Creates a new package builder.
     /// Call `publish()` to finalize and build the package.
@@ -240,6 +419,7 @@ impl Package {
             deps: Vec::new(),
             files: Vec::new(),
             extra_files: Vec::new(),
+            symlinks: Vec::new(),
             yanked: false,
             features: HashMap::new(),
             local: false,
@@ -247,6 +427,8 @@ impl Package {
             invalid_json: false,
             proc_macro: false,
             links: None,
+            rust_version: None,
+            checksum_algorithm: None,
         }
     }
 
@@ -289,6 +471,13 @@ impl Package {
         self
     }
 
+    /// Adds a symlink to `target` in the package, which Cargo refuses to
+    /// unpack.
+    pub fn symlink(&mut self, name: &str, target: &str) -> &mut Package {
+        self.symlinks.push((name.to_string(), target.to_string()));
+        self
+    }
+
     /// Adds a normal dependency. Example:
     /// ```
     /// [dependencies]
@@ -375,6 +564,20 @@ impl Package {
         self
     }
 
+    /// Sets the `rust-version` of the package, in its manifest and its index
+    /// entry.
+    pub fn rust_version(&mut self, rust_version: &str) -> &mut Package {
+        self.rust_version = Some(rust_version.to_string());
+        self
+    }
+
+    /// Sets the algorithm of the checksum in the index entry, which is
+    /// SHA-256 by default.
+    pub fn checksum_algorithm(&mut self, algorithm: ChecksumAlgorithm) -> &mut Package {
+        self.checksum_algorithm = Some(algorithm);
+        self
+    }
+
     /// Creates the package and place it in the registry.
     ///
     /// This does not actually use Cargo's publishing system, but instead
@@ -413,14 +616,17 @@ impl Package {
             .collect::<Vec<_>>();
         let cksum = {
             let c = t!(fs::read(&self.archive_dst()));
-            cksum(&c)
+            match self.checksum_algorithm {
+                Some(algorithm) => Checksum::compute(algorithm, &c).to_string(),
+                None => cksum(&c),
+            }
         };
         let name = if self.invalid_json {
             serde_json::json!(1)
         } else {
             serde_json::json!(self.name)
         };
-        let line = serde_json::json!({
+        let mut line = serde_json::json!({
             "name": name,
             "vers": self.vers,
             "deps": deps,
@@ -428,8 +634,11 @@ impl Package {
             "features": self.features,
             "yanked": self.yanked,
             "links": self.links,
-        })
-        .to_string();
+        });
+        if let Some(rust_version) = &self.rust_version {
+            line["rust_version"] = serde_json::json!(rust_version);
+        }
+        let line = line.to_string();
 
         let file = match self.name.len() {
             1 => format!("1/{}", self.name),
@@ -499,6 +708,15 @@ impl Package {
         for &(ref name, ref contents) in self.extra_files.iter() {
             self.append_extra(&mut a, name, contents);
         }
+        for &(ref name, ref target) in self.symlinks.iter() {
+            let mut header = Header::new_ustar();
+            header.set_entry_type(EntryType::Symlink);
+            header.set_size(0);
+            t!(header.set_path(format!("{}-{}/{}", self.name, self.vers, name)));
+            t!(header.set_link_name(target));
+            header.set_cksum();
+            t!(a.append(&header, &[][..]));
+        }
     }
 
     fn append_manifest<W: Write>(&self, ar: &mut Builder<W>) {
@@ -511,6 +729,9 @@ impl Package {
         "#,
             self.name, self.vers
         );
+        if let Some(rust_version) = &self.rust_version {
+            manifest.push_str(&format!("rust-version = \"{}\"\n", rust_version));
+        }
         for dep in self.deps.iter() {
             let target = match dep.target {
                 None => String::new(),
@@ -571,6 +792,92 @@ impl Package {
     }
 }
 
+/// An SSH key for signing the commits of a test registry index, like a
+/// registry operator would with `git config gpg.format ssh`.
+///
+/// The key is generated with `ssh-keygen`, which also makes the signatures.
+pub struct IndexSigningKey {
+    path: PathBuf,
+}
+
+impl IndexSigningKey {
+    /// Whether `ssh-keygen` can make signatures, which requires OpenSSH 8.0 or
+    /// later. Tests using signing keys should be skipped otherwise.
+    pub fn is_available() -> bool {
+        static AVAILABLE: AtomicBool = AtomicBool::new(false);
+        static CHECK: Once = Once::new();
+        CHECK.call_once(|| {
+            let path = paths::root().join("ssh-keygen-check");
+            let ssh_keygen = |args: &[&str]| {
+                Command::new("ssh-keygen")
+                    .args(args)
+                    .arg(&path)
+                    .stdin(Stdio::null())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .status()
+                    .map_or(false, |status| status.success())
+            };
+            let available = ssh_keygen(&["-q", "-t", "ed25519", "-N", "", "-f"])
+                && ssh_keygen(&["-Y", "sign", "-n", "git", "-f"]);
+            AVAILABLE.store(available, Ordering::SeqCst);
+        });
+        AVAILABLE.load(Ordering::SeqCst)
+    }
+
+    /// Generates a new ed25519 key, stored in the test root.
+    pub fn generate(name: &str) -> IndexSigningKey {
+        let path = paths::root().join(format!("{}-signing-key", name));
+        let status = t!(Command::new("ssh-keygen")
+            .args(&["-q", "-t", "ed25519", "-N", "", "-C", name, "-f"])
+            .arg(&path)
+            .status());
+        assert!(status.success(), "failed to generate a key with ssh-keygen");
+        IndexSigningKey { path }
+    }
+
+    /// The public key, in the OpenSSH format used in configs.
+    pub fn public_key(&self) -> String {
+        let public_key = t!(fs::read_to_string(self.path.with_extension("pub")));
+        public_key.trim().to_string()
+    }
+
+    /// Replaces the latest commit of the index at `registry_path` with a copy
+    /// signed with this key.
+    pub fn sign_index(&self, registry_path: &Path) {
+        let repo = t!(git2::Repository::open(registry_path));
+        let head = t!(t!(repo.head()).peel_to_commit());
+        let parents = head.parents().collect::<Vec<_>>();
+        let content = t!(repo.commit_create_buffer(
+            &head.author(),
+            &head.committer(),
+            head.message().unwrap(),
+            &t!(head.tree()),
+            &parents.iter().collect::<Vec<_>>(),
+        ));
+        let content = content.as_str().unwrap();
+
+        let mut child = t!(Command::new("ssh-keygen")
+            .args(&["-Y", "sign", "-n", "git", "-f"])
+            .arg(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn());
+        t!(child.stdin.take().unwrap().write_all(content.as_bytes()));
+        let output = t!(child.wait_with_output());
+        assert!(
+            output.status.success(),
+            "failed to sign with ssh-keygen: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let signature = t!(String::from_utf8(output.stdout));
+
+        let signed = t!(repo.commit_signed(content, &signature, None));
+        t!(repo.reference("refs/heads/master", signed, true, "sign the index"));
+    }
+}
+
 pub fn cksum(s: &[u8]) -> String {
     Sha256::new().update(s).finish_hex()
 }
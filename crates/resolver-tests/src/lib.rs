@@ -185,6 +185,7 @@ pub fn resolve_with_config_raw(
         &[],
         &mut registry,
         &HashSet::new(),
+        None,
         config,
         true,
     );
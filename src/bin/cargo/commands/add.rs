@@ -0,0 +1,131 @@
+use crate::command_prelude::*;
+
+use cargo::core::dependency::DepKind;
+use cargo::core::{Package, Workspace};
+use cargo::ops::{self, AddOptions};
+use cargo::util::toml_mut::DepTable;
+use cargo::CargoResult;
+
+pub fn cli() -> App {
+    subcommand("add")
+        .about("Add dependencies to a Cargo.toml manifest file")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg(
+            Arg::with_name("crate")
+                .value_name("DEP_ID")
+                .multiple(true)
+                .required_unless("path")
+                .help("Package to add, optionally with a version requirement as `name@version`"),
+        )
+        .arg(opt("dev", "Add as a development dependency").conflicts_with("build"))
+        .arg(opt("build", "Add as a build dependency"))
+        .arg(opt("target", "Add as a dependency of the given target platform").value_name("TARGET"))
+        .arg(multi_opt(
+            "features",
+            "FEATURES",
+            "Space or comma separated list of features to activate",
+        ))
+        .arg(opt("optional", "Mark the dependency as optional"))
+        .arg(opt("rename", "Rename the dependency").value_name("NAME"))
+        .arg(
+            opt("path", "Filesystem path to the local package to add")
+                .value_name("PATH")
+                .conflicts_with_all(&["git", "registry"]),
+        )
+        .arg(
+            opt("git", "Git repository to add the package from")
+                .value_name("URL")
+                .conflicts_with("registry"),
+        )
+        .arg(
+            opt("branch", "Branch to use when adding from git")
+                .value_name("BRANCH")
+                .requires("git")
+                .conflicts_with_all(&["tag", "rev"]),
+        )
+        .arg(
+            opt("tag", "Tag to use when adding from git")
+                .value_name("TAG")
+                .requires("git")
+                .conflicts_with("rev"),
+        )
+        .arg(
+            opt("rev", "Specific commit to use when adding from git")
+                .value_name("REV")
+                .requires("git"),
+        )
+        .arg(opt("registry", "Registry to add the package from").value_name("REGISTRY"))
+        .arg_package("Package to add the dependencies to")
+        .arg_manifest_path()
+        .after_help("Run `cargo help add` for more detailed information.\n")
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    // `--features` are the features of the new dependency, not ones to
+    // enable in the workspace, so they are fine in a virtual workspace.
+    let ws = Workspace::new(&args.root_manifest(config)?, config)?;
+    let package = selected_package(&ws, args)?;
+
+    let registry = if args.is_present("path") || args.is_present("git") {
+        None
+    } else {
+        args.registry(config)?
+    };
+    let opts = AddOptions {
+        config,
+        manifest_path: package.manifest_path(),
+        crates: values(args, "crate"),
+        table: dep_table(args),
+        rename: args.value_of("rename").map(String::from),
+        features: values(args, "features")
+            .iter()
+            .flat_map(|s| s.split_whitespace())
+            .flat_map(|s| s.split(','))
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect(),
+        optional: args.is_present("optional"),
+        registry,
+        path: args.value_of_path("path", config),
+        git: args.value_of("git").map(String::from),
+        branch: args.value_of("branch").map(String::from),
+        tag: args.value_of("tag").map(String::from),
+        rev: args.value_of("rev").map(String::from),
+    };
+    ops::add(&opts)?;
+    Ok(())
+}
+
+/// Returns the dependency table selected by `--dev`, `--build` and
+/// `--target`. Shared with `cargo remove`.
+pub fn dep_table(args: &ArgMatches<'_>) -> DepTable {
+    let kind = if args.is_present("dev") {
+        DepKind::Development
+    } else if args.is_present("build") {
+        DepKind::Build
+    } else {
+        DepKind::Normal
+    };
+    let table = DepTable::new().set_kind(kind);
+    match args.value_of("target") {
+        Some(target) => table.set_target(target),
+        None => table,
+    }
+}
+
+/// Returns the package selected by `-p`, or the current one. Shared with
+/// `cargo remove`.
+pub fn selected_package<'a>(
+    ws: &'a Workspace<'_>,
+    args: &ArgMatches<'_>,
+) -> CargoResult<&'a Package> {
+    match args.value_of("package") {
+        Some(name) => ws
+            .members()
+            .find(|pkg| pkg.name().as_str() == name)
+            .ok_or_else(|| {
+                anyhow::format_err!("package `{}` is not a member of the workspace", name)
+            }),
+        None => ws.current(),
+    }
+}
@@ -0,0 +1,36 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, AuditFormat, AuditOptions};
+
+pub fn cli() -> App {
+    subcommand("audit")
+        .about("Check the dependencies against a database of security advisories")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg(multi_opt(
+            "ignore",
+            "ID",
+            "Advisory to ignore (can be specified multiple times)",
+        ))
+        .arg(
+            opt("format", "Output format")
+                .value_name("FMT")
+                .possible_values(&["human", "json"])
+                .default_value("human"),
+        )
+        .arg_manifest_path()
+        .after_help("Run `cargo help audit` for more detailed information.\n")
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let format = match args.value_of("format").unwrap() {
+        "json" => AuditFormat::Json,
+        _ => AuditFormat::Human,
+    };
+    let opts = AuditOptions {
+        format,
+        ignore: values(args, "ignore"),
+    };
+    ops::audit(&ws, &opts)?;
+    Ok(())
+}
@@ -40,6 +40,8 @@ pub fn cli() -> App {
         .arg_target_dir()
         .arg_manifest_path()
         .arg_message_format()
+        .arg_explain_rebuilds()
+        .arg_ignore_rust_version()
         .arg(opt(
             "no-fail-fast",
             "Run all benchmarks regardless of failure",
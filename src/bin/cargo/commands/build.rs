@@ -40,6 +40,8 @@ pub fn cli() -> App {
         )
         .arg_manifest_path()
         .arg_message_format()
+        .arg_explain_rebuilds()
+        .arg_ignore_rust_version()
         .arg_build_plan()
         .arg_unit_graph()
         .after_help("Run `cargo help build` for more detailed information.\n")
@@ -33,6 +33,8 @@ pub fn cli() -> App {
         .arg_target_dir()
         .arg_manifest_path()
         .arg_message_format()
+        .arg_explain_rebuilds()
+        .arg_ignore_rust_version()
         .arg_unit_graph()
         .after_help("Run `cargo help check` for more detailed information.\n")
 }
@@ -31,6 +31,7 @@ pub fn cli() -> App {
         .arg_target_dir()
         .arg_manifest_path()
         .arg_message_format()
+        .arg_ignore_rust_version()
         .arg(
             Arg::with_name("broken-code")
                 .long("broken-code")
@@ -6,12 +6,22 @@ pub fn cli() -> App {
     subcommand("generate-lockfile")
         .about("Generate the lockfile for a package")
         .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg(opt(
+            "merge-conflicts",
+            "Merge the sides of the git conflicts in the lock file",
+        ))
         .arg_manifest_path()
+        .arg(
+            opt("message-format", "Error format")
+                .value_name("FMT")
+                .possible_values(&["human", "json"]),
+        )
         .after_help("Run `cargo help generate-lockfile` for more detailed information.\n")
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
-    let ws = args.workspace(config)?;
+    let mut ws = args.workspace(config)?;
+    ws.set_merge_lock_conflicts(args.is_present("merge-conflicts"));
     ops::generate_lockfile(&ws)?;
     Ok(())
 }
@@ -58,6 +58,7 @@ pub fn cli() -> App {
         )
         .arg_target_triple("Build for the target triple")
         .arg_target_dir()
+        .arg_ignore_rust_version()
         .arg(opt("root", "Directory to install packages into").value_name("DIR"))
         .arg(
             opt("index", "Registry index to install from")
@@ -0,0 +1,28 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, LicensesFormat, LicensesOptions};
+
+pub fn cli() -> App {
+    subcommand("licenses")
+        .about("Display the licenses of the packages in the dependency graph")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg(
+            opt("format", "Output format")
+                .value_name("FMT")
+                .possible_values(&["text", "json", "csv"])
+                .default_value("text"),
+        )
+        .arg_manifest_path()
+        .after_help("Run `cargo help licenses` for more detailed information.\n")
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let format = match args.value_of("format").unwrap() {
+        "json" => LicensesFormat::Json,
+        "csv" => LicensesFormat::Csv,
+        _ => LicensesFormat::Text,
+    };
+    ops::licenses(&ws, &LicensesOptions { format })?;
+    Ok(())
+}
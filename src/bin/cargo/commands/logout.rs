@@ -0,0 +1,23 @@
+use crate::command_prelude::*;
+
+use anyhow::format_err;
+use cargo::ops;
+
+pub fn cli() -> App {
+    subcommand("logout")
+        .about("Remove an API token from the registry locally")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg(opt("registry", "Registry to use").value_name("REGISTRY"))
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    if !config.cli_unstable().unstable_options {
+        return Err(format_err!(
+            "the `cargo logout` command is unstable, pass `-Z unstable-options` to enable it"
+        )
+        .into());
+    }
+    config.load_credentials()?;
+    ops::registry_logout(config, args.value_of("registry").map(String::from))?;
+    Ok(())
+}
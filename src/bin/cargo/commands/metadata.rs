@@ -30,7 +30,10 @@ pub fn cli() -> App {
 }
 
 pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
-    let ws = args.workspace(config)?;
+    let mut ws = args.workspace(config)?;
+    // The output is JSON anyway, so resolver conflicts are reported as JSON
+    // messages too.
+    ws.set_json_messages(true);
 
     let version = match args.value_of("format-version") {
         None => {
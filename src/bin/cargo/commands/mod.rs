@@ -2,6 +2,8 @@ use crate::command_prelude::*;
 
 pub fn builtin() -> Vec<App> {
     vec![
+        add::cli(),
+        audit::cli(),
         bench::cli(),
         build::cli(),
         check::cli(),
@@ -13,15 +15,19 @@ pub fn builtin() -> Vec<App> {
         git_checkout::cli(),
         init::cli(),
         install::cli(),
+        licenses::cli(),
         locate_project::cli(),
         login::cli(),
+        logout::cli(),
         metadata::cli(),
         new::cli(),
+        outdated::cli(),
         owner::cli(),
         package::cli(),
         pkgid::cli(),
         publish::cli(),
         read_manifest::cli(),
+        remove::cli(),
         run::cli(),
         rustc::cli(),
         rustdoc::cli(),
@@ -30,6 +36,7 @@ pub fn builtin() -> Vec<App> {
         tree::cli(),
         uninstall::cli(),
         update::cli(),
+        upgrade::cli(),
         vendor::cli(),
         verify_project::cli(),
         version::cli(),
@@ -39,6 +46,8 @@ pub fn builtin() -> Vec<App> {
 
 pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> CliResult> {
     let f = match cmd {
+        "add" => add::exec,
+        "audit" => audit::exec,
         "bench" => bench::exec,
         "build" => build::exec,
         "check" => check::exec,
@@ -50,15 +59,19 @@ pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> Cli
         "git-checkout" => git_checkout::exec,
         "init" => init::exec,
         "install" => install::exec,
+        "licenses" => licenses::exec,
         "locate-project" => locate_project::exec,
         "login" => login::exec,
+        "logout" => logout::exec,
         "metadata" => metadata::exec,
         "new" => new::exec,
+        "outdated" => outdated::exec,
         "owner" => owner::exec,
         "package" => package::exec,
         "pkgid" => pkgid::exec,
         "publish" => publish::exec,
         "read-manifest" => read_manifest::exec,
+        "remove" => remove::exec,
         "run" => run::exec,
         "rustc" => rustc::exec,
         "rustdoc" => rustdoc::exec,
@@ -67,6 +80,7 @@ pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> Cli
         "tree" => tree::exec,
         "uninstall" => uninstall::exec,
         "update" => update::exec,
+        "upgrade" => upgrade::exec,
         "vendor" => vendor::exec,
         "verify-project" => verify_project::exec,
         "version" => version::exec,
@@ -76,6 +90,8 @@ pub fn builtin_exec(cmd: &str) -> Option<fn(&mut Config, &ArgMatches<'_>) -> Cli
     Some(f)
 }
 
+pub mod add;
+pub mod audit;
 pub mod bench;
 pub mod build;
 pub mod check;
@@ -88,15 +104,19 @@ pub mod git_checkout;
 pub mod help;
 pub mod init;
 pub mod install;
+pub mod licenses;
 pub mod locate_project;
 pub mod login;
+pub mod logout;
 pub mod metadata;
 pub mod new;
+pub mod outdated;
 pub mod owner;
 pub mod package;
 pub mod pkgid;
 pub mod publish;
 pub mod read_manifest;
+pub mod remove;
 pub mod run;
 pub mod rustc;
 pub mod rustdoc;
@@ -105,6 +125,7 @@ pub mod test;
 pub mod tree;
 pub mod uninstall;
 pub mod update;
+pub mod upgrade;
 pub mod vendor;
 pub mod verify_project;
 pub mod version;
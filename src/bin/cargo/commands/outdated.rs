@@ -0,0 +1,35 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, OutdatedFormat, OutdatedOptions};
+
+pub fn cli() -> App {
+    subcommand("outdated")
+        .about("Display the dependencies which have newer versions available")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg(opt(
+            "root-deps-only",
+            "Only check the dependencies of the workspace members",
+        ))
+        .arg(
+            opt("format", "Output format")
+                .value_name("FMT")
+                .possible_values(&["human", "json"])
+                .default_value("human"),
+        )
+        .arg_manifest_path()
+        .after_help("Run `cargo help outdated` for more detailed information.\n")
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let format = match args.value_of("format").unwrap() {
+        "json" => OutdatedFormat::Json,
+        _ => OutdatedFormat::Human,
+    };
+    let opts = OutdatedOptions {
+        format,
+        root_deps_only: args.is_present("root-deps-only"),
+    };
+    ops::outdated(&ws, &opts)?;
+    Ok(())
+}
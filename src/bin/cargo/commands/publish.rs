@@ -23,6 +23,11 @@ pub fn cli() -> App {
         .arg_jobs()
         .arg_dry_run("Perform all checks without uploading")
         .arg(opt("registry", "Registry to publish to").value_name("REGISTRY"))
+        .arg(
+            opt("to", "Directory of a local registry to publish to")
+                .value_name("PATH")
+                .conflicts_with_all(&["index", "registry", "token"]),
+        )
         .after_help("Run `cargo help publish` for more detailed information.\n")
 }
 
@@ -45,6 +50,7 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
             jobs: args.jobs()?,
             dry_run: args.is_present("dry-run"),
             registry,
+            to: args.value_of_path("to", config),
             features: args._values_of("features"),
             all_features: args.is_present("all-features"),
             no_default_features: args.is_present("no-default-features"),
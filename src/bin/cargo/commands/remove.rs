@@ -0,0 +1,46 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, RemoveOptions};
+
+use super::add::{dep_table, selected_package};
+
+pub fn cli() -> App {
+    subcommand("remove")
+        // subcommand aliases are handled in aliased_command()
+        // .alias("rm")
+        .about("Remove dependencies from a Cargo.toml manifest file")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg(
+            Arg::with_name("dependencies")
+                .value_name("DEP_ID")
+                .multiple(true)
+                .required(true)
+                .help("Dependencies to remove"),
+        )
+        .arg(opt("dev", "Remove as a development dependency").conflicts_with("build"))
+        .arg(opt("build", "Remove as a build dependency"))
+        .arg(
+            opt(
+                "target",
+                "Remove as a dependency of the given target platform",
+            )
+            .value_name("TARGET"),
+        )
+        .arg_package("Package to remove the dependencies from")
+        .arg_manifest_path()
+        .after_help("Run `cargo help remove` for more detailed information.\n")
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+    let package = selected_package(&ws, args)?;
+
+    let opts = RemoveOptions {
+        config,
+        manifest_path: package.manifest_path(),
+        dependencies: values(args, "dependencies"),
+        table: dep_table(args),
+    };
+    ops::remove(&opts)?;
+    Ok(())
+}
@@ -89,6 +89,11 @@ pub fn cli() -> App {
                 .short("f")
                 .default_value("{p}"),
         )
+        .arg(
+            opt("message-format", "Error format")
+                .value_name("FMT")
+                .possible_values(&["human", "json"]),
+        )
         .arg(
             // Backwards compatibility with old cargo-tree.
             Arg::with_name("version")
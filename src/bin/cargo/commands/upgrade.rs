@@ -0,0 +1,30 @@
+use crate::command_prelude::*;
+
+use cargo::ops::{self, UpgradeOptions};
+
+pub fn cli() -> App {
+    subcommand("upgrade")
+        .about("Upgrade the dependency requirements in the manifests of the workspace")
+        .arg(opt("quiet", "No output printed to stdout").short("q"))
+        .arg_package_spec_simple("Dependency to upgrade")
+        .arg(opt(
+            "incompatible",
+            "Upgrade to the latest versions, even if they aren't semver compatible",
+        ))
+        .arg_dry_run("Don't actually write the manifests or the lockfile")
+        .arg_manifest_path()
+        .after_help("Run `cargo help upgrade` for more detailed information.\n")
+}
+
+pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
+    let ws = args.workspace(config)?;
+
+    let opts = UpgradeOptions {
+        config,
+        to_upgrade: values(args, "package"),
+        incompatible: args.is_present("incompatible"),
+        dry_run: args.is_present("dry-run"),
+    };
+    ops::upgrade(&ws, &opts)?;
+    Ok(())
+}
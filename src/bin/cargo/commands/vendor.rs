@@ -32,6 +32,17 @@ pub fn cli() -> App {
                 .long("versioned-dirs")
                 .help("Always include version in subdir name"),
         )
+        .arg(
+            opt("format", "Layout of the vendored crates")
+                .value_name("FORMAT")
+                .possible_values(&["directory", "local-registry"])
+                .default_value("directory"),
+        )
+        .arg(multi_opt(
+            "platform",
+            "TRIPLE",
+            "Only vendor the crates used when building for the target triple",
+        ))
         .arg(
             Arg::with_name("no-merge-sources")
                 .long("no-merge-sources")
@@ -108,6 +119,8 @@ https://github.com/rust-lang/cargo/issues/new
                 .unwrap_or_default()
                 .map(|s| PathBuf::from(s.to_os_string()))
                 .collect(),
+            format: ops::VendorFormat::from_name(args.value_of("format").unwrap())?,
+            platforms: args._values_of("platform"),
         },
     )?;
     Ok(())
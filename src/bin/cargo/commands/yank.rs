@@ -15,6 +15,11 @@ pub fn cli() -> App {
         .arg(opt("index", "Registry index to yank from").value_name("INDEX"))
         .arg(opt("token", "API token to use when authenticating").value_name("TOKEN"))
         .arg(opt("registry", "Registry to use").value_name("REGISTRY"))
+        .arg(
+            opt("to", "Directory of a local registry to yank from")
+                .value_name("PATH")
+                .conflicts_with_all(&["index", "registry", "token"]),
+        )
         .after_help("Run `cargo help yank` for more detailed information.\n")
 }
 
@@ -31,6 +36,7 @@ pub fn exec(config: &mut Config, args: &ArgMatches<'_>) -> CliResult {
         args.value_of("index").map(|s| s.to_string()),
         args.is_present("undo"),
         registry,
+        args.value_of_path("to", config),
     )?;
     Ok(())
 }
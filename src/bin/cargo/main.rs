@@ -49,10 +49,11 @@ fn main() {
 
 /// Table for defining the aliases which come builtin in `Cargo`.
 /// The contents are structured as: `(alias, aliased_command, description)`.
-const BUILTIN_ALIASES: [(&str, &str, &str); 4] = [
+const BUILTIN_ALIASES: [(&str, &str, &str); 5] = [
     ("b", "build", "alias: build"),
     ("c", "check", "alias: check"),
     ("r", "run", "alias: run"),
+    ("rm", "remove", "alias: remove"),
     ("t", "test", "alias: test"),
 ];
 
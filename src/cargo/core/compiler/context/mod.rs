@@ -12,10 +12,11 @@ use crate::util::profile;
 
 use super::build_plan::BuildPlan;
 use super::custom_build::{self, BuildDeps, BuildScriptOutputs, BuildScripts};
-use super::fingerprint::Fingerprint;
+use super::fingerprint::{DirtyReason, Fingerprint};
 use super::job_queue::JobQueue;
 use super::layout::Layout;
 use super::lto::Lto;
+use super::shared_cache::SharedCache;
 use super::unit_graph::UnitDep;
 use super::{BuildContext, Compilation, CompileKind, CompileMode, Executor, FileFlavor};
 
@@ -37,6 +38,8 @@ pub struct Context<'a, 'cfg> {
     pub build_explicit_deps: HashMap<Unit, BuildDeps>,
     /// Fingerprints used to detect if a unit is out-of-date.
     pub fingerprints: HashMap<Unit, Arc<Fingerprint>>,
+    /// Why dirty units are rebuilt, only recorded with `--explain-rebuilds`.
+    pub dirty_reasons: HashMap<Unit, DirtyReason>,
     /// Cache of file mtimes to reduce filesystem hits.
     pub mtime_cache: HashMap<PathBuf, FileTime>,
     /// A set used to track which units have been compiled.
@@ -77,6 +80,10 @@ pub struct Context<'a, 'cfg> {
     /// compilation is happening (only object, only bitcode, both, etc), and is
     /// precalculated early on.
     pub lto: HashMap<Unit, Lto>,
+
+    /// The cache of compiled artifacts shared with other target directories,
+    /// if `build.shared-cache` is set.
+    pub shared_cache: Option<SharedCache>,
 }
 
 impl<'a, 'cfg> Context<'a, 'cfg> {
@@ -106,6 +113,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             compilation: Compilation::new(bcx)?,
             build_script_outputs: Arc::new(Mutex::new(BuildScriptOutputs::default())),
             fingerprints: HashMap::new(),
+            dirty_reasons: HashMap::new(),
             mtime_cache: HashMap::new(),
             compiled: HashSet::new(),
             build_scripts: HashMap::new(),
@@ -117,6 +125,7 @@ impl<'a, 'cfg> Context<'a, 'cfg> {
             rustc_clients: HashMap::new(),
             pipelining,
             lto: HashMap::new(),
+            shared_cache: SharedCache::new(bcx.config)?,
         })
     }
 
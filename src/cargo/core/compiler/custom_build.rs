@@ -279,12 +279,16 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
         Arc::clone(&build_script_outputs),
         output_file.clone(),
         script_out_dir.clone(),
+        unit.pkg.root().to_path_buf(),
     );
     let build_scripts = cx.build_scripts.get(unit).cloned();
     let json_messages = bcx.build_config.emit_json();
     let extra_verbose = bcx.config.extra_verbose();
     let (prev_output, prev_script_out_dir) = prev_build_output(cx, unit);
     let metadata_hash = cx.get_run_build_script_metadata(unit);
+    let checksum_freshness = cx.bcx.config.cli_unstable().checksum_freshness;
+    let pkg_root = unit.pkg.root().to_path_buf();
+    let target_root = cx.bcx.ws.target_dir().into_path_unlocked();
 
     paths::create_dir_all(&script_dir)?;
     paths::create_dir_all(&script_out_dir)?;
@@ -389,11 +393,27 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
         paths::write(&output_file, &output.stdout)?;
         // This mtime shift allows Cargo to detect if a source file was
         // modified in the middle of the build.
-        paths::set_file_time_no_err(output_file, timestamp);
+        paths::set_file_time_no_err(&output_file, timestamp);
         paths::write(&err_file, &output.stderr)?;
         paths::write(&root_output_file, util::path2bytes(&script_out_dir)?)?;
-        let parsed_output =
-            BuildOutput::parse(&output.stdout, &pkg_name, &script_out_dir, &script_out_dir)?;
+        let parsed_output = BuildOutput::parse(
+            &output.stdout,
+            &pkg_name,
+            &pkg_root,
+            &script_out_dir,
+            &script_out_dir,
+        )?;
+        fingerprint::write_rerun_if_changed_checksums(
+            &output_file,
+            &pkg_root,
+            &target_root,
+            &parsed_output.rerun_if_changed,
+            if checksum_freshness {
+                Some(timestamp)
+            } else {
+                None
+            },
+        )?;
 
         if json_messages {
             emit_build_output(state, &parsed_output, script_out_dir.as_path(), id);
@@ -409,12 +429,13 @@ fn build_work(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Job> {
     // itself to run when we actually end up just discarding what we calculated
     // above.
     let fresh = Work::new(move |state| {
-        let (id, pkg_name, build_script_outputs, output_file, script_out_dir) = all;
+        let (id, pkg_name, build_script_outputs, output_file, script_out_dir, pkg_root) = all;
         let output = match prev_output {
             Some(output) => output,
             None => BuildOutput::parse_file(
                 &output_file,
                 &pkg_name,
+                &pkg_root,
                 &prev_script_out_dir,
                 &script_out_dir,
             )?,
@@ -464,6 +485,7 @@ impl BuildOutput {
     pub fn parse_file(
         path: &Path,
         pkg_name: &str,
+        pkg_root: &Path,
         script_out_dir_when_generated: &Path,
         script_out_dir: &Path,
     ) -> CargoResult<BuildOutput> {
@@ -471,16 +493,19 @@ impl BuildOutput {
         BuildOutput::parse(
             &contents,
             pkg_name,
+            pkg_root,
             script_out_dir_when_generated,
             script_out_dir,
         )
     }
 
     // Parses the output of a script.
-    // The `pkg_name` is used for error messages.
+    // The `pkg_name` is used for error messages, and the `rerun-if-changed`
+    // paths are relative to `pkg_root`.
     pub fn parse(
         input: &[u8],
         pkg_name: &str,
+        pkg_root: &Path,
         script_out_dir_when_generated: &Path,
         script_out_dir: &Path,
     ) -> CargoResult<BuildOutput> {
@@ -539,7 +564,20 @@ impl BuildOutput {
                 "rustc-cfg" => cfgs.push(value.to_string()),
                 "rustc-env" => env.push(BuildOutput::parse_rustc_env(&value, &whence)?),
                 "warning" => warnings.push(value.to_string()),
-                "rerun-if-changed" => rerun_if_changed.push(PathBuf::from(value)),
+                "rerun-if-changed" => {
+                    // Existing paths are never taken as patterns, see
+                    // `fingerprint::expand_rerun_if_changed`.
+                    if fingerprint::is_glob_pattern(&value) && !pkg_root.join(&value).exists() {
+                        if let Err(e) = glob::Pattern::new(&value) {
+                            warnings.push(format!(
+                                "invalid glob pattern `{}` in `rerun-if-changed`, \
+                                 checking it as a path instead: {}",
+                                value, e
+                            ));
+                        }
+                    }
+                    rerun_if_changed.push(PathBuf::from(value))
+                }
                 "rerun-if-env-changed" => rerun_if_env_changed.push(value.to_string()),
                 _ => metadata.push((key.to_string(), value.to_string())),
             }
@@ -788,6 +826,7 @@ fn prev_build_output(cx: &mut Context<'_, '_>, unit: &Unit) -> (Option<BuildOutp
         BuildOutput::parse_file(
             &output_file,
             &unit.pkg.to_string(),
+            unit.pkg.root(),
             &prev_script_out_dir,
             &script_out_dir,
         )
@@ -30,6 +30,11 @@
 //!      details. If any input files are missing, or are newer than the
 //!      dep-info, then the unit is dirty.
 //!
+//!      With `-Z checksum-freshness`, the dep-info file also records the size
+//!      and a hash of the input files (see `FileChecksum`), and the input
+//!      files are compared with those instead. This also applies to the
+//!      `rerun-if-changed` paths of build scripts.
+//!
 //! Note: Fingerprinting is not a perfect solution. Filesystem mtime tracking
 //! is notoriously imprecise and problematic. Only a small part of the
 //! environment is captured. This is a balance of performance, simplicity, and
@@ -104,8 +109,10 @@
 //!   quick loading and comparison.
 //! - A `.json` file that contains details about the Fingerprint. This is only
 //!   used to log details about *why* a fingerprint is considered dirty.
+//!   `cargo build --explain-rebuilds` displays the first difference with the
+//!   current fingerprint, see `DirtyReason`, and
 //!   `CARGO_LOG=cargo::core::compiler::fingerprint=trace cargo build` can be
-//!   used to display this log information.
+//!   used to display more log information.
 //! - A "dep-info" file which is a translation of rustc's `*.d` dep-info files
 //!   to a Cargo-specific format that tweaks file names and is optimized for
 //!   reading quickly.
@@ -313,14 +320,17 @@
 //! <https://github.com/rust-lang/cargo/issues?q=is%3Aissue+is%3Aopen+label%3AA-rebuild-detection>
 
 use std::collections::hash_map::{Entry, HashMap};
+use std::collections::HashSet;
 use std::env;
+use std::fmt;
+use std::fs::File;
 use std::hash::{self, Hasher};
 use std::path::{Path, PathBuf};
 use std::str;
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 
-use anyhow::{bail, format_err};
+use anyhow::bail;
 use filetime::FileTime;
 use log::{debug, info};
 use serde::de;
@@ -332,6 +342,7 @@ use crate::core::Package;
 use crate::util;
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::interning::InternedString;
+use crate::util::machine_message::{self, Message};
 use crate::util::paths;
 use crate::util::{internal, profile, ProcessBuilder};
 
@@ -340,6 +351,7 @@ use super::job::{
     Freshness::{Dirty, Fresh},
     Job, Work,
 };
+use super::shared_cache;
 use super::{BuildContext, Context, FileFlavor, Unit};
 
 /// Determines if a `unit` is up-to-date, and if not prepares necessary work to
@@ -397,6 +409,11 @@ pub fn prepare_target(cx: &mut Context<'_, '_>, unit: &Unit, force: bool) -> Car
     if compare.is_ok() && !force {
         return Ok(Job::new(Work::noop(), Fresh));
     }
+    let dirty_reason = if bcx.build_config.explain_rebuilds {
+        Some(dirty_reason(&loc, &compare))
+    } else {
+        None
+    };
 
     // Clear out the old fingerprint file if it exists. This protects when
     // compilation is interrupted leaving a corrupt file. For example, a
@@ -424,6 +441,7 @@ pub fn prepare_target(cx: &mut Context<'_, '_>, unit: &Unit, force: bool) -> Car
     // But the executable is corrupt and needs to be rebuilt. Clearing the
     // fingerprint at step 3 ensures that Cargo never mistakes a partially
     // written output as up-to-date.
+    let fingerprint_hash = fingerprint.hash();
     if loc.exists() {
         // Truncate instead of delete so that compare_old_fingerprint will
         // still log the reason for the fingerprint failure instead of just
@@ -468,9 +486,102 @@ pub fn prepare_target(cx: &mut Context<'_, '_>, unit: &Unit, force: bool) -> Car
         Work::new(move |_| write_fingerprint(&loc, &fingerprint))
     };
 
+    // Units which don't depend on the workspace may have been compiled
+    // already in another target directory, and stored in the shared cache.
+    // Their outputs are copied by the work `compile` adds to the job.
+    if !force {
+        if let Some(key) = shared_cache::key(cx, unit, fingerprint_hash) {
+            if shared_cache::lookup(cx, unit, &key)? {
+                return Ok(Job::new(write_fingerprint, Fresh));
+            }
+        }
+    }
+
+    if let Some(reason) = dirty_reason {
+        cx.dirty_reasons.insert(unit.clone(), reason);
+    }
     Ok(Job::new(write_fingerprint, Dirty))
 }
 
+/// Returns why a unit is dirty, given the result of comparing its fingerprint
+/// with the one at `loc`.
+fn dirty_reason(loc: &Path, compare: &CargoResult<()>) -> DirtyReason {
+    match compare {
+        Ok(()) => DirtyReason::Forced,
+        Err(e) => match e.downcast_ref::<DirtyReason>() {
+            Some(reason) => reason.clone(),
+            None if loc.exists() => DirtyReason::FingerprintUnreadable,
+            None => DirtyReason::FreshBuild,
+        },
+    }
+}
+
+/// Prints why `unit` is rebuilt, for `--explain-rebuilds`, along with the
+/// unit whose rebuild caused it if it's only rebuilt because of a dependency.
+pub fn explain_rebuild(cx: &Context<'_, '_>, unit: &Unit) -> CargoResult<()> {
+    let reason = match cx.dirty_reasons.get(unit) {
+        Some(reason) => reason,
+        None => return Ok(()),
+    };
+    let trigger = rebuild_trigger(cx, unit);
+    let config = cx.bcx.config;
+    if cx.bcx.build_config.emit_json() {
+        let msg = machine_message::RebuildReason {
+            package_id: unit.pkg.package_id(),
+            target: &unit.target,
+            mode: unit.mode,
+            kind: reason.kind(),
+            message: reason.to_string(),
+            triggered_by: trigger.map(|t| machine_message::RebuildTrigger {
+                package_id: t.pkg.package_id(),
+                target: &t.target,
+                mode: t.mode,
+            }),
+        }
+        .to_json_string();
+        writeln!(config.shell().out(), "{}", msg)?;
+        return Ok(());
+    }
+    let mut explanation = format!(
+        "{} ({}): {}",
+        unit.pkg,
+        unit.target.description_named(),
+        reason
+    );
+    if let Some(t) = trigger {
+        explanation.push_str(&format!(
+            ", triggered by {} ({})",
+            t.pkg,
+            t.target.description_named()
+        ));
+    }
+    config.shell().status("Dirty", explanation)
+}
+
+/// Follows the dirty dependencies of `unit` to the unit which started the
+/// cascade of rebuilds, if `unit` is only rebuilt because of a dependency.
+fn rebuild_trigger<'a>(cx: &'a Context<'_, '_>, unit: &Unit) -> Option<&'a Unit> {
+    let mut trigger = None;
+    let mut current = unit;
+    while let Some(name) = cx.dirty_reasons.get(current).and_then(|r| r.dependency()) {
+        let deps = cx.unit_deps(current);
+        let dirty = |dep: &&UnitDep| cx.dirty_reasons.contains_key(&dep.unit);
+        let dep = deps
+            .iter()
+            .filter(dirty)
+            .find(|dep| dep.extern_crate_name == name)
+            .or_else(|| deps.iter().find(dirty));
+        match dep {
+            Some(dep) => {
+                trigger = Some(&dep.unit);
+                current = &dep.unit;
+            }
+            None => break,
+        }
+    }
+    trigger
+}
+
 /// Dependency edge information for fingerprints. This is generated for each
 /// dependency and is stored in a `Fingerprint` below.
 #[derive(Clone)]
@@ -561,8 +672,9 @@ pub struct Fingerprint {
 enum FsStatus {
     /// This unit is to be considered stale, even if hash information all
     /// matches. The filesystem inputs have changed (or are missing) and the
-    /// unit needs to subsequently be recompiled.
-    Stale,
+    /// unit needs to subsequently be recompiled. The reason is `None` until
+    /// the filesystem has been checked.
+    Stale(Option<DirtyReason>),
 
     /// This unit is up-to-date. All outputs and their corresponding mtime are
     /// listed in the payload here for other dependencies to compare against.
@@ -573,14 +685,14 @@ impl FsStatus {
     fn up_to_date(&self) -> bool {
         match self {
             FsStatus::UpToDate { .. } => true,
-            FsStatus::Stale => false,
+            FsStatus::Stale(..) => false,
         }
     }
 }
 
 impl Default for FsStatus {
     fn default() -> FsStatus {
-        FsStatus::Stale
+        FsStatus::Stale(None)
     }
 }
 
@@ -680,7 +792,8 @@ enum LocalFingerprint {
     RerunIfEnvChanged { var: String, val: Option<String> },
 }
 
-enum StaleItem {
+#[derive(Clone, Debug)]
+pub enum StaleItem {
     MissingFile(PathBuf),
     ChangedFile {
         reference: PathBuf,
@@ -693,6 +806,15 @@ enum StaleItem {
         previous: Option<String>,
         current: Option<String>,
     },
+    /// The contents of `stale` differ from the ones recorded by
+    /// `-Z checksum-freshness` in the previous build.
+    ChangedContents {
+        stale: PathBuf,
+        stale_mtime: FileTime,
+    },
+    /// A file matching the `rerun-if-changed` paths of a build script was
+    /// added since the files were recorded by `-Z checksum-freshness`.
+    NewFile(PathBuf),
 }
 
 impl LocalFingerprint {
@@ -707,11 +829,15 @@ impl LocalFingerprint {
     ///
     /// * Another is the `rerun-if-changed` directive from build scripts. This
     ///   is where we'll find whether files have actually changed
+    ///
+    /// With `checksum_freshness`, the files are compared with their recorded
+    /// checksums, if any, rather than by mtime.
     fn find_stale_item(
         &self,
         mtime_cache: &mut HashMap<PathBuf, FileTime>,
         pkg_root: &Path,
         target_root: &Path,
+        checksum_freshness: bool,
     ) -> CargoResult<Option<StaleItem>> {
         match self {
             // We need to parse `dep_info`, learn about the crate's dependencies.
@@ -722,7 +848,7 @@ impl LocalFingerprint {
             // rustc.
             LocalFingerprint::CheckDepInfo { dep_info } => {
                 let dep_info = target_root.join(dep_info);
-                let info = match parse_dep_info(pkg_root, target_root, &dep_info)? {
+                let mut info = match parse_dep_info(pkg_root, target_root, &dep_info)? {
                     Some(info) => info,
                     None => return Ok(Some(StaleItem::MissingFile(dep_info))),
                 };
@@ -737,16 +863,68 @@ impl LocalFingerprint {
                         current,
                     }));
                 }
-                Ok(find_stale_file(mtime_cache, &dep_info, info.files.iter()))
+                if !checksum_freshness {
+                    info.checksums.clear();
+                }
+                let stale =
+                    find_stale_file(mtime_cache, &dep_info, info.files.iter(), &info.checksums);
+                if stale.is_none() && !info.checksums.is_empty() {
+                    refresh_checksum_mtimes(&dep_info, pkg_root, target_root, mtime_cache);
+                }
+                Ok(stale)
             }
 
             // We need to verify that no paths listed in `paths` are newer than
             // the `output` path itself, or the last time the build script ran.
-            LocalFingerprint::RerunIfChanged { output, paths } => Ok(find_stale_file(
-                mtime_cache,
-                &target_root.join(output),
-                paths.iter().map(|p| pkg_root.join(p)),
-            )),
+            // Directories and glob patterns are expanded to the files they
+            // contain. With `-Z checksum-freshness` these files are recorded
+            // next to `output` along with their checksums, so that files added
+            // or removed since then are found whatever their mtimes are.
+            LocalFingerprint::RerunIfChanged { output, paths } => {
+                let output = target_root.join(output);
+                let recorded = if checksum_freshness {
+                    parse_dep_info(pkg_root, target_root, &checksums_loc(&output))?
+                } else {
+                    None
+                };
+                // Otherwise the directories searched tell about added or
+                // removed files.
+                let include_dirs = recorded.is_none();
+                let mut expanded = Vec::new();
+                for path in paths {
+                    expanded.extend(expand_rerun_if_changed(
+                        mtime_cache,
+                        pkg_root,
+                        target_root,
+                        path,
+                        include_dirs,
+                    ));
+                }
+                let checksums = match recorded {
+                    Some(recorded) => {
+                        let current: HashSet<&PathBuf> = expanded.iter().collect();
+                        let previous: HashSet<&PathBuf> = recorded.files.iter().collect();
+                        if let Some(removed) = previous.difference(&current).next() {
+                            return Ok(Some(StaleItem::MissingFile(removed.to_path_buf())));
+                        }
+                        if let Some(added) = current.difference(&previous).next() {
+                            return Ok(Some(StaleItem::NewFile(added.to_path_buf())));
+                        }
+                        recorded.checksums
+                    }
+                    None => HashMap::new(),
+                };
+                let stale = find_stale_file(mtime_cache, &output, expanded, &checksums);
+                if stale.is_none() && !checksums.is_empty() {
+                    refresh_checksum_mtimes(
+                        &checksums_loc(&output),
+                        pkg_root,
+                        target_root,
+                        mtime_cache,
+                    );
+                }
+                Ok(stale)
+            }
 
             // These have no dependencies on the filesystem, and their values
             // are included natively in the `Fingerprint` hash so nothing
@@ -783,7 +961,7 @@ impl Fingerprint {
             rustflags: Vec::new(),
             metadata: 0,
             config: 0,
-            fs_status: FsStatus::Stale,
+            fs_status: FsStatus::Stale(None),
             outputs: Vec::new(),
         }
     }
@@ -811,51 +989,52 @@ impl Fingerprint {
     /// serialized to filesystem.
     ///
     /// The purpose of this is exclusively to produce a diagnostic message
-    /// indicating why we're recompiling something. This function always returns
-    /// an error, it will never return success.
-    fn compare(&self, old: &Fingerprint) -> CargoResult<()> {
+    /// indicating why we're recompiling something, the first component of the
+    /// fingerprints which differs.
+    fn compare(&self, old: &Fingerprint) -> DirtyReason {
         if self.rustc != old.rustc {
-            bail!("rust compiler has changed")
+            return DirtyReason::RustcChanged;
         }
         if self.features != old.features {
-            bail!(
-                "features have changed: {} != {}",
-                self.features,
-                old.features
-            )
+            return DirtyReason::FeaturesChanged {
+                old: old.features.clone(),
+                new: self.features.clone(),
+            };
         }
         if self.target != old.target {
-            bail!("target configuration has changed")
+            return DirtyReason::TargetConfigurationChanged;
         }
         if self.path != old.path {
-            bail!("path to the source has changed")
+            return DirtyReason::PathToSourceChanged;
         }
         if self.profile != old.profile {
-            bail!("profile configuration has changed")
+            return DirtyReason::ProfileConfigurationChanged;
         }
         if self.rustflags != old.rustflags {
-            bail!(
-                "RUSTFLAGS has changed: {:?} != {:?}",
-                self.rustflags,
-                old.rustflags
-            )
+            return DirtyReason::RustflagsChanged {
+                old: old.rustflags.clone(),
+                new: self.rustflags.clone(),
+            };
         }
         if self.metadata != old.metadata {
-            bail!("metadata changed")
+            return DirtyReason::MetadataChanged;
         }
         if self.config != old.config {
-            bail!("configuration settings have changed")
+            return DirtyReason::ConfigSettingsChanged;
         }
         let my_local = self.local.lock().unwrap();
         let old_local = old.local.lock().unwrap();
         if my_local.len() != old_local.len() {
-            bail!("local lens changed");
+            return DirtyReason::LocalLengthsChanged;
         }
         for (new, old) in my_local.iter().zip(old_local.iter()) {
             match (new, old) {
                 (LocalFingerprint::Precalculated(a), LocalFingerprint::Precalculated(b)) => {
                     if a != b {
-                        bail!("precalculated components have changed: {} != {}", a, b)
+                        return DirtyReason::PrecalculatedComponentsChanged {
+                            old: b.clone(),
+                            new: a.clone(),
+                        };
                     }
                 }
                 (
@@ -863,7 +1042,10 @@ impl Fingerprint {
                     LocalFingerprint::CheckDepInfo { dep_info: bdep },
                 ) => {
                     if adep != bdep {
-                        bail!("dep info output changed: {:?} != {:?}", adep, bdep)
+                        return DirtyReason::DepInfoOutputChanged {
+                            old: bdep.clone(),
+                            new: adep.clone(),
+                        };
                     }
                 }
                 (
@@ -877,14 +1059,16 @@ impl Fingerprint {
                     },
                 ) => {
                     if aout != bout {
-                        bail!("rerun-if-changed output changed: {:?} != {:?}", aout, bout)
+                        return DirtyReason::RerunIfChangedOutputFileChanged {
+                            old: bout.clone(),
+                            new: aout.clone(),
+                        };
                     }
                     if apaths != bpaths {
-                        bail!(
-                            "rerun-if-changed output changed: {:?} != {:?}",
-                            apaths,
-                            bpaths,
-                        )
+                        return DirtyReason::RerunIfChangedOutputPathsChanged {
+                            old: bpaths.clone(),
+                            new: apaths.clone(),
+                        };
                     }
                 }
                 (
@@ -898,57 +1082,60 @@ impl Fingerprint {
                     },
                 ) => {
                     if *akey != *bkey {
-                        bail!("env vars changed: {} != {}", akey, bkey);
+                        return DirtyReason::EnvVarsChanged {
+                            old: bkey.clone(),
+                            new: akey.clone(),
+                        };
                     }
                     if *avalue != *bvalue {
-                        bail!(
-                            "env var `{}` changed: previously {:?} now {:?}",
-                            akey,
-                            bvalue,
-                            avalue
-                        )
+                        return DirtyReason::EnvVarChanged {
+                            name: akey.clone(),
+                            old: bvalue.clone(),
+                            new: avalue.clone(),
+                        };
+                    }
+                }
+                (a, b) => {
+                    return DirtyReason::LocalFingerprintTypeChanged {
+                        old: b.kind(),
+                        new: a.kind(),
                     }
                 }
-                (a, b) => bail!(
-                    "local fingerprint type has changed ({} => {})",
-                    b.kind(),
-                    a.kind()
-                ),
             }
         }
 
         if self.deps.len() != old.deps.len() {
-            bail!("number of dependencies has changed")
+            return DirtyReason::NumberOfDependenciesChanged {
+                old: old.deps.len(),
+                new: self.deps.len(),
+            };
         }
         for (a, b) in self.deps.iter().zip(old.deps.iter()) {
             if a.name != b.name {
-                let e = format_err!("`{}` != `{}`", a.name, b.name)
-                    .context("unit dependency name changed");
-                return Err(e);
+                return DirtyReason::UnitDependencyNameChanged {
+                    old: b.name,
+                    new: a.name,
+                };
             }
 
             if a.fingerprint.hash() != b.fingerprint.hash() {
-                let e = format_err!(
-                    "new ({}/{:x}) != old ({}/{:x})",
-                    a.name,
-                    a.fingerprint.hash(),
-                    b.name,
-                    b.fingerprint.hash()
-                )
-                .context("unit dependency information changed");
-                return Err(e);
+                return DirtyReason::UnitDependencyInfoChanged {
+                    name: a.name,
+                    old_fingerprint: b.fingerprint.hash(),
+                    new_fingerprint: a.fingerprint.hash(),
+                };
             }
         }
 
-        if !self.fs_status.up_to_date() {
-            bail!("current filesystem status shows we're outdated");
+        if let FsStatus::Stale(reason) = &self.fs_status {
+            return reason.clone().unwrap_or(DirtyReason::FsStatusOutdated);
         }
 
         // This typically means some filesystem modifications happened or
         // something transitive was odd. In general we should strive to provide
         // a better error message than this, so if you see this message a lot it
         // likely means this method needs to be updated!
-        bail!("two fingerprint comparison turned up nothing obvious");
+        DirtyReason::NothingObvious
     }
 
     /// Dynamically inspect the local filesystem to update the `fs_status` field
@@ -964,6 +1151,7 @@ impl Fingerprint {
         mtime_cache: &mut HashMap<PathBuf, FileTime>,
         pkg_root: &Path,
         target_root: &Path,
+        checksum_freshness: bool,
     ) -> CargoResult<()> {
         assert!(!self.fs_status.up_to_date());
 
@@ -981,6 +1169,10 @@ impl Fingerprint {
                 // exists, so leave ourselves as stale and bail out.
                 Err(e) => {
                     debug!("failed to get mtime of {:?}: {}", output, e);
+                    let reason = DirtyReason::MissingOutput {
+                        path: output.clone(),
+                    };
+                    self.fs_status = FsStatus::Stale(Some(reason));
                     return Ok(());
                 }
             };
@@ -1008,7 +1200,11 @@ impl Fingerprint {
             let dep_mtimes = match &dep.fingerprint.fs_status {
                 FsStatus::UpToDate { mtimes } => mtimes,
                 // If our dependency is stale, so are we, so bail out.
-                FsStatus::Stale => return Ok(()),
+                FsStatus::Stale(..) => {
+                    let reason = DirtyReason::DependencyStale { name: dep.name };
+                    self.fs_status = FsStatus::Stale(Some(reason));
+                    return Ok(());
+                }
             };
 
             // If our dependency edge only requires the rmeta file to be present
@@ -1046,6 +1242,12 @@ impl Fingerprint {
                     "dependency on `{}` is newer than we are {} > {} {:?}",
                     dep.name, dep_mtime, max_mtime, pkg_root
                 );
+                let reason = DirtyReason::DependencyNewer {
+                    name: dep.name,
+                    dep_mtime: *dep_mtime,
+                    max_mtime: *max_mtime,
+                };
+                self.fs_status = FsStatus::Stale(Some(reason));
                 return Ok(());
             }
         }
@@ -1055,8 +1257,11 @@ impl Fingerprint {
         // files for this package itself. If we do find something log a helpful
         // message and bail out so we stay stale.
         for local in self.local.get_mut().unwrap().iter() {
-            if let Some(item) = local.find_stale_item(mtime_cache, pkg_root, target_root)? {
+            if let Some(item) =
+                local.find_stale_item(mtime_cache, pkg_root, target_root, checksum_freshness)?
+            {
                 item.log();
+                self.fs_status = FsStatus::Stale(Some(DirtyReason::StaleItem(item)));
                 return Ok(());
             }
         }
@@ -1197,10 +1402,306 @@ impl StaleItem {
                 info!("stale: changed env {:?}", var);
                 info!("       {:?} != {:?}", previous, current);
             }
+            StaleItem::ChangedContents { stale, stale_mtime } => {
+                info!("stale: changed contents {:?}", stale);
+                info!("       modified at {:?}", stale_mtime);
+            }
+            StaleItem::NewFile(path) => {
+                info!("stale: new file {:?}", path);
+            }
+        }
+    }
+}
+
+/// The reason a unit is dirty, the first difference found between its
+/// current fingerprint and the one of its previous build.
+///
+/// These are displayed by `--explain-rebuilds`, and logged otherwise.
+#[derive(Clone, Debug)]
+pub enum DirtyReason {
+    /// The unit was never built with this configuration. Its fingerprint is
+    /// stored in a directory named after its `Metadata` hash, which covers
+    /// the profile, the features and the version of rustc, so changing them
+    /// leads here rather than to the matching variants below.
+    FreshBuild,
+    /// The fingerprint of the previous build couldn't be read.
+    FingerprintUnreadable,
+    /// The unit is rebuilt unconditionally, such as the target of `cargo rustc`.
+    Forced,
+    RustcChanged,
+    FeaturesChanged {
+        old: String,
+        new: String,
+    },
+    TargetConfigurationChanged,
+    PathToSourceChanged,
+    ProfileConfigurationChanged,
+    RustflagsChanged {
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    MetadataChanged,
+    ConfigSettingsChanged,
+    LocalLengthsChanged,
+    PrecalculatedComponentsChanged {
+        old: String,
+        new: String,
+    },
+    DepInfoOutputChanged {
+        old: PathBuf,
+        new: PathBuf,
+    },
+    RerunIfChangedOutputFileChanged {
+        old: PathBuf,
+        new: PathBuf,
+    },
+    RerunIfChangedOutputPathsChanged {
+        old: Vec<PathBuf>,
+        new: Vec<PathBuf>,
+    },
+    EnvVarsChanged {
+        old: String,
+        new: String,
+    },
+    EnvVarChanged {
+        name: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    LocalFingerprintTypeChanged {
+        old: &'static str,
+        new: &'static str,
+    },
+    NumberOfDependenciesChanged {
+        old: usize,
+        new: usize,
+    },
+    UnitDependencyNameChanged {
+        old: InternedString,
+        new: InternedString,
+    },
+    /// The fingerprint of the dependency `name` changed, it's rebuilt too.
+    UnitDependencyInfoChanged {
+        name: InternedString,
+        old_fingerprint: u64,
+        new_fingerprint: u64,
+    },
+    /// The dependency `name` is stale, so it's rebuilt before this unit.
+    DependencyStale {
+        name: InternedString,
+    },
+    /// The dependency `name` was rebuilt after this unit was last built.
+    DependencyNewer {
+        name: InternedString,
+        dep_mtime: FileTime,
+        max_mtime: FileTime,
+    },
+    MissingOutput {
+        path: PathBuf,
+    },
+    StaleItem(StaleItem),
+    FsStatusOutdated,
+    NothingObvious,
+}
+
+impl DirtyReason {
+    /// A short name of the reason, used in JSON messages.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DirtyReason::FreshBuild => "fresh-build",
+            DirtyReason::FingerprintUnreadable => "fingerprint-unreadable",
+            DirtyReason::Forced => "forced",
+            DirtyReason::RustcChanged => "rustc-changed",
+            DirtyReason::FeaturesChanged { .. } => "features-changed",
+            DirtyReason::TargetConfigurationChanged => "target-changed",
+            DirtyReason::PathToSourceChanged => "path-changed",
+            DirtyReason::ProfileConfigurationChanged => "profile-changed",
+            DirtyReason::RustflagsChanged { .. } => "rustflags-changed",
+            DirtyReason::MetadataChanged => "metadata-changed",
+            DirtyReason::ConfigSettingsChanged => "config-changed",
+            DirtyReason::LocalLengthsChanged
+            | DirtyReason::PrecalculatedComponentsChanged { .. }
+            | DirtyReason::DepInfoOutputChanged { .. }
+            | DirtyReason::RerunIfChangedOutputFileChanged { .. }
+            | DirtyReason::RerunIfChangedOutputPathsChanged { .. }
+            | DirtyReason::EnvVarsChanged { .. }
+            | DirtyReason::LocalFingerprintTypeChanged { .. } => "local-changed",
+            DirtyReason::EnvVarChanged { .. } => "env-var-changed",
+            DirtyReason::NumberOfDependenciesChanged { .. }
+            | DirtyReason::UnitDependencyNameChanged { .. } => "dependencies-changed",
+            DirtyReason::UnitDependencyInfoChanged { .. }
+            | DirtyReason::DependencyStale { .. }
+            | DirtyReason::DependencyNewer { .. } => "dependency-rebuilt",
+            DirtyReason::MissingOutput { .. } => "missing-output",
+            DirtyReason::StaleItem(StaleItem::MissingFile(..)) => "missing-file",
+            DirtyReason::StaleItem(StaleItem::NewFile(..)) => "new-file",
+            DirtyReason::StaleItem(StaleItem::ChangedFile { .. })
+            | DirtyReason::StaleItem(StaleItem::ChangedContents { .. }) => "file-changed",
+            DirtyReason::StaleItem(StaleItem::ChangedEnv { .. }) => "env-var-changed",
+            DirtyReason::FsStatusOutdated | DirtyReason::NothingObvious => "unknown",
+        }
+    }
+
+    /// Returns the name of the dependency this unit is dirty because of, if
+    /// it's only dirty because that dependency is rebuilt.
+    pub fn dependency(&self) -> Option<InternedString> {
+        match self {
+            DirtyReason::UnitDependencyInfoChanged { name, .. }
+            | DirtyReason::DependencyStale { name }
+            | DirtyReason::DependencyNewer { name, .. } => Some(*name),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DirtyReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DirtyReason::FreshBuild => write!(
+                f,
+                "no previous build with the same profile, features and compiler was found"
+            ),
+            DirtyReason::FingerprintUnreadable => {
+                write!(f, "the fingerprint of the previous build couldn't be read")
+            }
+            DirtyReason::Forced => write!(f, "it is always rebuilt"),
+            DirtyReason::RustcChanged => write!(f, "the rust compiler has changed"),
+            DirtyReason::FeaturesChanged { old, new } => write!(
+                f,
+                "the features have changed: previously {}, now {}",
+                old, new
+            ),
+            DirtyReason::TargetConfigurationChanged => {
+                write!(f, "the target configuration has changed")
+            }
+            DirtyReason::PathToSourceChanged => write!(f, "the path to the source has changed"),
+            DirtyReason::ProfileConfigurationChanged => {
+                write!(f, "the profile configuration has changed")
+            }
+            DirtyReason::RustflagsChanged { old, new } => write!(
+                f,
+                "RUSTFLAGS has changed: previously {:?}, now {:?}",
+                old, new
+            ),
+            DirtyReason::MetadataChanged => write!(f, "the metadata has changed"),
+            DirtyReason::ConfigSettingsChanged => {
+                write!(f, "the configuration settings have changed")
+            }
+            DirtyReason::LocalLengthsChanged => {
+                write!(f, "the inputs of the build script have changed")
+            }
+            DirtyReason::PrecalculatedComponentsChanged { old, new } => write!(
+                f,
+                "the precalculated components have changed: previously {}, now {}",
+                old, new
+            ),
+            DirtyReason::DepInfoOutputChanged { old, new } => write!(
+                f,
+                "the dep-info output has changed: previously {:?}, now {:?}",
+                old, new
+            ),
+            DirtyReason::RerunIfChangedOutputFileChanged { old, new } => write!(
+                f,
+                "the rerun-if-changed output has changed: previously {:?}, now {:?}",
+                old, new
+            ),
+            DirtyReason::RerunIfChangedOutputPathsChanged { old, new } => write!(
+                f,
+                "the rerun-if-changed paths have changed: previously {:?}, now {:?}",
+                old, new
+            ),
+            DirtyReason::EnvVarsChanged { old, new } => write!(
+                f,
+                "the rerun-if-env-changed variables have changed: previously `{}`, now `{}`",
+                old, new
+            ),
+            DirtyReason::EnvVarChanged { name, old, new } => write!(
+                f,
+                "the environment variable `{}` has changed: previously {:?}, now {:?}",
+                name, old, new
+            ),
+            DirtyReason::LocalFingerprintTypeChanged { old, new } => write!(
+                f,
+                "the local fingerprint type has changed: previously {}, now {}",
+                old, new
+            ),
+            DirtyReason::NumberOfDependenciesChanged { old, new } => write!(
+                f,
+                "the number of dependencies has changed: previously {}, now {}",
+                old, new
+            ),
+            DirtyReason::UnitDependencyNameChanged { old, new } => write!(
+                f,
+                "the name of a dependency has changed: previously `{}`, now `{}`",
+                old, new
+            ),
+            DirtyReason::UnitDependencyInfoChanged {
+                name,
+                old_fingerprint,
+                new_fingerprint,
+            } => write!(
+                f,
+                "the dependency `{}` has changed: fingerprint previously {:x}, now {:x}",
+                name, old_fingerprint, new_fingerprint
+            ),
+            DirtyReason::DependencyStale { name } => {
+                write!(f, "the dependency `{}` is rebuilt", name)
+            }
+            DirtyReason::DependencyNewer {
+                name,
+                dep_mtime,
+                max_mtime,
+            } => write!(
+                f,
+                "the dependency `{}` was rebuilt since the last build ({} > {})",
+                name, dep_mtime, max_mtime
+            ),
+            DirtyReason::MissingOutput { path } => {
+                write!(f, "the output `{}` is missing", path.display())
+            }
+            DirtyReason::StaleItem(StaleItem::MissingFile(path)) => {
+                write!(f, "the file `{}` is missing", path.display())
+            }
+            DirtyReason::StaleItem(StaleItem::NewFile(path)) => {
+                write!(f, "the file `{}` was added", path.display())
+            }
+            DirtyReason::StaleItem(StaleItem::ChangedFile {
+                reference_mtime,
+                stale,
+                stale_mtime,
+                ..
+            }) => write!(
+                f,
+                "the file `{}` has changed: modified at {}, after the last build at {}",
+                stale.display(),
+                stale_mtime,
+                reference_mtime
+            ),
+            DirtyReason::StaleItem(StaleItem::ChangedContents { stale, stale_mtime }) => write!(
+                f,
+                "the contents of the file `{}` have changed: modified at {}",
+                stale.display(),
+                stale_mtime
+            ),
+            DirtyReason::StaleItem(StaleItem::ChangedEnv {
+                var,
+                previous,
+                current,
+            }) => write!(
+                f,
+                "the environment variable `{}` has changed: previously {:?}, now {:?}",
+                var, previous, current
+            ),
+            DirtyReason::FsStatusOutdated => {
+                write!(f, "the filesystem status shows it's outdated")
+            }
+            DirtyReason::NothingObvious => write!(f, "no difference was found"),
         }
     }
 }
 
+impl std::error::Error for DirtyReason {}
+
 /// Calculates the fingerprint for a `unit`.
 ///
 /// This fingerprint is used by Cargo to learn about when information such as:
@@ -1230,7 +1731,13 @@ fn calculate(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Arc<Fingerpri
     // After we built the initial `Fingerprint` be sure to update the
     // `fs_status` field of it.
     let target_root = target_root(cx);
-    fingerprint.check_filesystem(&mut cx.mtime_cache, unit.pkg.root(), &target_root)?;
+    let checksum_freshness = cx.bcx.config.cli_unstable().checksum_freshness;
+    fingerprint.check_filesystem(
+        &mut cx.mtime_cache,
+        unit.pkg.root(),
+        &target_root,
+        checksum_freshness,
+    )?;
 
     let fingerprint = Arc::new(fingerprint);
     cx.fingerprints
@@ -1313,9 +1820,7 @@ fn calculate_normal(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Finger
         rustc: util::hash_u64(&cx.bcx.rustc().verbose_version),
         target: util::hash_u64(&unit.target),
         profile: profile_hash,
-        // Note that .0 is hashed here, not .1 which is the cwd. That doesn't
-        // actually affect the output artifact so there's no need to hash it.
-        path: util::hash_u64(super::path_args(cx.bcx, unit).0),
+        path: util::hash_u64(fingerprint_path(cx.bcx, unit)),
         features: format!("{:?}", unit.features),
         deps,
         local: Mutex::new(local),
@@ -1323,11 +1828,35 @@ fn calculate_normal(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Finger
         metadata,
         config,
         rustflags: extra_flags,
-        fs_status: FsStatus::Stale,
+        fs_status: FsStatus::Stale(None),
         outputs,
     })
 }
 
+/// Returns the path of the source of `unit` hashed in its fingerprint.
+///
+/// This is the path passed to rustc, not the cwd, which doesn't actually
+/// affect the output artifact. Packages from registries and git repositories
+/// are passed by absolute path, inside `CARGO_HOME`, so the path relative to
+/// it is used instead: otherwise machines with a different `CARGO_HOME` would
+/// never share the entries of the shared cache, which are keyed on the
+/// fingerprint. The rest of the path still changes along with the package,
+/// such as when a git dependency moves to another revision, or when its
+/// source is replaced by a directory.
+///
+/// This is only done with `-Z shared-cache`, so that the fingerprints of
+/// existing target directories stay the same otherwise.
+fn fingerprint_path(bcx: &BuildContext<'_, '_>, unit: &Unit) -> PathBuf {
+    let path = super::path_args(bcx, unit).0;
+    if !bcx.config.cli_unstable().shared_cache || unit.pkg.package_id().source_id().is_path() {
+        return path;
+    }
+    match path.strip_prefix(bcx.config.home().as_path_unlocked()) {
+        Ok(relative) => relative.to_path_buf(),
+        Err(_) => path,
+    }
+}
+
 /// Calculate a fingerprint for an "execute a build script" unit.  This is an
 /// internal helper of `calculate`, don't call directly.
 fn calculate_run_custom_build(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<Fingerprint> {
@@ -1616,9 +2145,7 @@ fn compare_old_fingerprint(
     if !old_fingerprint_short.is_empty() {
         debug_assert_eq!(util::to_hex(old_fingerprint.hash()), old_fingerprint_short);
     }
-    let result = new_fingerprint.compare(&old_fingerprint);
-    assert!(result.is_err());
-    result
+    Err(new_fingerprint.compare(&old_fingerprint).into())
 }
 
 fn log_compare(unit: &Unit, compare: &CargoResult<()>) {
@@ -1662,17 +2189,58 @@ pub fn parse_dep_info(
     };
     let mut ret = RustcDepInfo::default();
     ret.env = info.env;
-    for (ty, path) in info.files {
-        let path = match ty {
-            DepInfoPathType::PackageRootRelative => pkg_root.join(path),
-            // N.B. path might be absolute here in which case the join will have no effect
-            DepInfoPathType::TargetRootRelative => target_root.join(path),
-        };
-        ret.files.push(path);
+    for (ty, path) in info.files.iter() {
+        ret.files.push(ty.resolve(pkg_root, target_root, path));
+    }
+    for (index, checksum) in info.checksums {
+        ret.checksums.insert(ret.files[index].clone(), checksum);
     }
     Ok(Some(ret))
 }
 
+/// Updates the mtimes recorded along with the checksums in the dep-info file
+/// `loc` to the current ones, in `mtime_cache`, once all its files were found
+/// fresh. The files whose mtime changed were rehashed and still have the same
+/// contents, and would otherwise be rehashed again by every build.
+///
+/// The mtime of `loc` is kept, as other files are compared with it. Failing
+/// to update it only costs rehashing the files again.
+fn refresh_checksum_mtimes(
+    loc: &Path,
+    pkg_root: &Path,
+    target_root: &Path,
+    mtime_cache: &HashMap<PathBuf, FileTime>,
+) {
+    let result = (|| -> CargoResult<()> {
+        let mut info = match EncodedDepInfo::parse(&paths::read_bytes(loc)?) {
+            Some(info) => info,
+            None => return Ok(()),
+        };
+        let mut refreshed = false;
+        for (index, checksum) in info.checksums.iter_mut() {
+            let (ty, path) = &info.files[*index];
+            let path = ty.resolve(pkg_root, target_root, path);
+            match mtime_cache.get(&path) {
+                Some(&mtime) if mtime != checksum.mtime => {
+                    checksum.mtime = mtime;
+                    refreshed = true;
+                }
+                _ => {}
+            }
+        }
+        if !refreshed {
+            return Ok(());
+        }
+        let mtime = paths::mtime(loc)?;
+        paths::write(loc, info.serialize()?)?;
+        paths::set_file_time_no_err(loc, mtime);
+        Ok(())
+    })();
+    if let Err(e) = result {
+        debug!("failed to refresh the checksums in {:?}: {:?}", loc, e);
+    }
+}
+
 fn pkg_fingerprint(bcx: &BuildContext<'_, '_>, pkg: &Package) -> CargoResult<String> {
     let source_id = pkg.package_id().source_id();
     let sources = bcx.packages.sources();
@@ -1687,6 +2255,7 @@ fn find_stale_file<I>(
     mtime_cache: &mut HashMap<PathBuf, FileTime>,
     reference: &Path,
     paths: I,
+    checksums: &HashMap<PathBuf, FileChecksum>,
 ) -> Option<StaleItem>
 where
     I: IntoIterator,
@@ -1710,6 +2279,19 @@ where
             }
         };
 
+        // With `-Z checksum-freshness` the contents of the file are compared
+        // instead, so that restoring a source tree with different mtimes
+        // neither rebuilds nor misses a change.
+        if let Some(checksum) = checksums.get(path) {
+            if checksum.matches(path, path_mtime) {
+                continue;
+            }
+            return Some(StaleItem::ChangedContents {
+                stale: path.to_path_buf(),
+                stale_mtime: path_mtime,
+            });
+        }
+
         // TODO: fix #5918.
         // Note that equal mtimes should be considered "stale". For filesystems with
         // not much timestamp precision like 1s this is would be a conservative approximation
@@ -1755,6 +2337,17 @@ enum DepInfoPathType {
     TargetRootRelative,
 }
 
+impl DepInfoPathType {
+    /// Returns the absolute path of `path`, which is of this type.
+    fn resolve(&self, pkg_root: &Path, target_root: &Path, path: &Path) -> PathBuf {
+        match self {
+            DepInfoPathType::PackageRootRelative => pkg_root.join(path),
+            // N.B. path might be absolute here in which case the join will have no effect
+            DepInfoPathType::TargetRootRelative => target_root.join(path),
+        }
+    }
+}
+
 /// Parses the dep-info file coming out of rustc into a Cargo-specific format.
 ///
 /// This function will parse `rustc_dep_info` as a makefile-style dep info to
@@ -1778,6 +2371,10 @@ enum DepInfoPathType {
 ///
 /// The serialized Cargo format will contain a list of files, all of which are
 /// relative if they're under `root`. or absolute if they're elsewhere.
+///
+/// With `-Z checksum-freshness`, `checksums_since` is the time rustc started
+/// at, and the checksums of the files are recorded as well, see
+/// `FileChecksum`.
 pub fn translate_dep_info(
     rustc_dep_info: &Path,
     cargo_dep_info: &Path,
@@ -1786,6 +2383,7 @@ pub fn translate_dep_info(
     target_root: &Path,
     rustc_cmd: &ProcessBuilder,
     allow_package: bool,
+    checksums_since: Option<FileTime>,
 ) -> CargoResult<()> {
     let depinfo = parse_rustc_dep_info(rustc_dep_info)?;
 
@@ -1844,12 +2442,225 @@ pub fn translate_dep_info(
             // effect.
             (DepInfoPathType::TargetRootRelative, &*abs_file)
         };
+        if let Some(since) = checksums_since {
+            on_disk_info.add_checksum(&canon_file, since);
+        }
         on_disk_info.files.push((ty, path.to_owned()));
     }
     paths::write(cargo_dep_info, on_disk_info.serialize()?)?;
     Ok(())
 }
 
+/// Returns the location of the checksums of the `rerun-if-changed` paths of
+/// the build script whose output is at `output`.
+fn checksums_loc(output: &Path) -> PathBuf {
+    output.with_file_name("checksums")
+}
+
+/// Records the files the `rerun-if-changed` paths of a build script expand
+/// to, along with their checksums, for `-Z checksum-freshness`, once it ran.
+/// `since` is the time it started at, and `output` is the location of its
+/// output.
+///
+/// They are stored in the same format as the dep-info files of rustc
+/// invocations. Without `since`, the checksums of a previous run are removed,
+/// as they no longer match this one.
+pub fn write_rerun_if_changed_checksums(
+    output: &Path,
+    pkg_root: &Path,
+    target_root: &Path,
+    rerun_if_changed: &[PathBuf],
+    since: Option<FileTime>,
+) -> CargoResult<()> {
+    let loc = checksums_loc(output);
+    let since = match since {
+        Some(since) => since,
+        None if loc.exists() => return paths::remove_file(&loc),
+        None => return Ok(()),
+    };
+    let mut info = EncodedDepInfo::default();
+    let mut mtime_cache = HashMap::new();
+    for path in rerun_if_changed {
+        for abs_path in
+            expand_rerun_if_changed(&mut mtime_cache, pkg_root, target_root, path, false)
+        {
+            info.add_checksum(&abs_path, since);
+            let path = match abs_path.strip_prefix(pkg_root) {
+                Ok(stripped) => (DepInfoPathType::PackageRootRelative, stripped.to_path_buf()),
+                Err(_) => (DepInfoPathType::TargetRootRelative, abs_path.clone()),
+            };
+            info.files.push(path);
+        }
+    }
+    paths::write(&loc, info.serialize()?)
+}
+
+/// The directories of version control systems, which `rerun-if-changed`
+/// directories are searched without.
+const VCS_DIRS: &[&str] = &[".git", ".hg", ".svn", ".bzr", ".pijul", ".jj"];
+
+/// Returns whether the `rerun-if-changed` path of a build script is a glob
+/// pattern, such as `schema/**/*.proto`.
+pub fn is_glob_pattern(path: &str) -> bool {
+    path.contains(|c| matches!(c, '*' | '?' | '['))
+}
+
+/// Expands the `rerun-if-changed` path `path` of a build script, relative to
+/// `pkg_root`, into the paths whose mtimes are compared with the last run of
+/// the script, newest first:
+///
+/// * A directory expands to the files inside it, recursively.
+/// * A glob pattern which isn't an existing path expands to the files
+///   matching it.
+/// * Any other path is kept as is, so that it's stale if it's missing. This
+///   includes invalid glob patterns, which `BuildOutput::parse` warns about.
+///
+/// The target directory, `target_root`, and the directories of version
+/// control systems aren't searched: with a path such as `.`, the outputs of
+/// the build would otherwise make the script run again in every build.
+///
+/// With `include_dirs`, the directories searched are included too, as their
+/// mtimes change when files are added or removed. The mtimes are added to
+/// `mtime_cache`.
+fn expand_rerun_if_changed(
+    mtime_cache: &mut HashMap<PathBuf, FileTime>,
+    pkg_root: &Path,
+    target_root: &Path,
+    path: &Path,
+    include_dirs: bool,
+) -> Vec<PathBuf> {
+    let abs_path = pkg_root.join(path);
+    // Paths may contain glob characters, such as `data[1].json`, so these are
+    // only taken as patterns if there is no such path.
+    let pattern = if is_glob_pattern(&path.to_string_lossy()) && !abs_path.exists() {
+        let pattern = if path.is_absolute() {
+            path.to_string_lossy().into_owned()
+        } else {
+            let root = glob::Pattern::escape(&pkg_root.to_string_lossy());
+            format!("{}/{}", root, path.to_string_lossy())
+        };
+        match glob::Pattern::new(&pattern) {
+            Ok(pattern) => Some(pattern),
+            Err(_) => return vec![abs_path],
+        }
+    } else if abs_path.is_dir() {
+        None
+    } else {
+        return vec![abs_path];
+    };
+
+    // The directory to search, the longest prefix of a pattern without glob
+    // characters.
+    let base: PathBuf = match pattern {
+        Some(_) => pkg_root.join(
+            path.components()
+                .take_while(|c| !is_glob_pattern(&c.as_os_str().to_string_lossy()))
+                .collect::<PathBuf>(),
+        ),
+        None => abs_path,
+    };
+    if !base.is_dir() {
+        return vec![base];
+    }
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..glob::MatchOptions::new()
+    };
+    let skipped = |entry: &walkdir::DirEntry| {
+        entry.depth() > 0
+            && entry.file_type().is_dir()
+            && (entry.path() == target_root
+                || entry.path() == pkg_root.join("target")
+                || entry
+                    .file_name()
+                    .to_str()
+                    .map_or(false, |name| VCS_DIRS.contains(&name)))
+    };
+    let mut expanded = Vec::new();
+    for entry in walkdir::WalkDir::new(&base)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|entry| !skipped(entry))
+    {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                debug!("failed to search {:?}: {}", base, e);
+                continue;
+            }
+        };
+        let is_dir = entry.file_type().is_dir();
+        let included = if is_dir {
+            include_dirs
+        } else {
+            match &pattern {
+                Some(pattern) => pattern.matches_path_with(entry.path(), options),
+                None => true,
+            }
+        };
+        if !included {
+            continue;
+        }
+        let mtime = match paths::mtime(entry.path()) {
+            Ok(mtime) => mtime,
+            Err(_) => continue,
+        };
+        mtime_cache.insert(entry.path().to_path_buf(), mtime);
+        expanded.push((mtime, entry.into_path()));
+    }
+    expanded.sort_by(|a, b| b.cmp(a));
+    if let Some((mtime, newest)) = expanded.first() {
+        debug!(
+            "newest path of rerun-if-changed {:?} is {:?} mtime={}",
+            path, newest, mtime
+        );
+    }
+    expanded.into_iter().map(|(_, path)| path).collect()
+}
+
+/// The contents of a file as recorded by `-Z checksum-freshness`: its size
+/// and a fast hash of its contents, along with its mtime at that time.
+///
+/// The file is only read again if its mtime changed, so that files which
+/// are touched but not modified don't cause rebuilds, and files restored with
+/// an older mtime but different contents do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileChecksum {
+    mtime: FileTime,
+    size: u64,
+    hash: u64,
+}
+
+impl FileChecksum {
+    fn compute(path: &Path) -> CargoResult<FileChecksum> {
+        let file = File::open(path)?;
+        let meta = file.metadata()?;
+        if !meta.is_file() {
+            bail!("`{}` isn't a file", path.display());
+        }
+        Ok(FileChecksum {
+            mtime: FileTime::from_last_modification_time(&meta),
+            size: meta.len(),
+            hash: util::hex::hash_u64_file(&file)?,
+        })
+    }
+
+    /// Returns whether `path`, whose mtime is now `mtime`, still has these
+    /// contents.
+    fn matches(&self, path: &Path, mtime: FileTime) -> bool {
+        if mtime == self.mtime {
+            return true;
+        }
+        match FileChecksum::compute(path) {
+            Ok(current) => {
+                debug!("rehashed {:?}, modified at {}", path, mtime);
+                current.size == self.size && current.hash == self.hash
+            }
+            Err(_) => false,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct RustcDepInfo {
     /// The list of files that the main target in the dep-info file depends on.
@@ -1862,6 +2673,9 @@ pub struct RustcDepInfo {
     /// means that the env var wasn't actually set and the compilation depends
     /// on it not being set.
     pub env: Vec<(String, Option<String>)>,
+    /// The checksums recorded by `-Z checksum-freshness` of some of the
+    /// `files`.
+    pub checksums: HashMap<PathBuf, FileChecksum>,
 }
 
 // Same as `RustcDepInfo` except avoids absolute paths as much as possible to
@@ -1873,9 +2687,27 @@ pub struct RustcDepInfo {
 struct EncodedDepInfo {
     files: Vec<(DepInfoPathType, PathBuf)>,
     env: Vec<(String, Option<String>)>,
+    /// The checksums of some of the `files`, along with their index. They are
+    /// only written if there are any, so that dep-info files are the same as
+    /// before `-Z checksum-freshness`.
+    checksums: Vec<(usize, FileChecksum)>,
 }
 
 impl EncodedDepInfo {
+    /// Records the checksum of `path`, which is the next file to be added,
+    /// unless it was modified after `since`, the time the compilation started
+    /// at. It could differ from what was compiled then, so it's compared by
+    /// mtime in the next build instead.
+    fn add_checksum(&mut self, path: &Path, since: FileTime) {
+        match FileChecksum::compute(path) {
+            Ok(checksum) if checksum.mtime <= since => {
+                self.checksums.push((self.files.len(), checksum));
+            }
+            Ok(_) => debug!("not recording the checksum of {:?}, modified", path),
+            Err(e) => debug!("failed to compute the checksum of {:?}: {}", path, e),
+        }
+    }
+
     fn parse(mut bytes: &[u8]) -> Option<EncodedDepInfo> {
         let bytes = &mut bytes;
         let nfiles = read_usize(bytes)?;
@@ -1901,7 +2733,30 @@ impl EncodedDepInfo {
             };
             env.push((key, val));
         }
-        return Some(EncodedDepInfo { files, env });
+
+        let mut checksums = Vec::new();
+        if !bytes.is_empty() {
+            let nchecksums = read_usize(bytes)?;
+            for _ in 0..nchecksums {
+                let index = read_usize(bytes)?;
+                if index >= files.len() {
+                    return None;
+                }
+                let seconds = read_u64(bytes)? as i64;
+                let nanos = read_usize(bytes)? as u32;
+                let checksum = FileChecksum {
+                    mtime: FileTime::from_unix_time(seconds, nanos),
+                    size: read_u64(bytes)?,
+                    hash: read_u64(bytes)?,
+                };
+                checksums.push((index, checksum));
+            }
+        }
+        return Some(EncodedDepInfo {
+            files,
+            env,
+            checksums,
+        });
 
         fn read_usize(bytes: &mut &[u8]) -> Option<usize> {
             let ret = bytes.get(..4)?;
@@ -1914,6 +2769,12 @@ impl EncodedDepInfo {
             )
         }
 
+        fn read_u64(bytes: &mut &[u8]) -> Option<u64> {
+            let low = read_usize(bytes)? as u64;
+            let high = read_usize(bytes)? as u64;
+            Some(low | (high << 32))
+        }
+
         fn read_u8(bytes: &mut &[u8]) -> Option<u8> {
             let ret = *bytes.get(0)?;
             *bytes = &bytes[1..];
@@ -1951,6 +2812,17 @@ impl EncodedDepInfo {
                 }
             }
         }
+
+        if !self.checksums.is_empty() {
+            write_usize(dst, self.checksums.len());
+            for (index, checksum) in self.checksums.iter() {
+                write_usize(dst, *index);
+                write_u64(dst, checksum.mtime.unix_seconds() as u64);
+                write_usize(dst, checksum.mtime.nanoseconds() as usize);
+                write_u64(dst, checksum.size);
+                write_u64(dst, checksum.hash);
+            }
+        }
         return Ok(ret);
 
         fn write_bytes(dst: &mut Vec<u8>, val: impl AsRef<[u8]>) {
@@ -1959,6 +2831,11 @@ impl EncodedDepInfo {
             dst.extend_from_slice(val);
         }
 
+        fn write_u64(dst: &mut Vec<u8>, val: u64) {
+            write_usize(dst, val as usize);
+            write_usize(dst, (val >> 32) as usize);
+        }
+
         fn write_usize(dst: &mut Vec<u8>, val: usize) {
             dst.push(val as u8);
             dst.push((val >> 8) as u8);
@@ -63,10 +63,12 @@ use jobserver::{Acquired, Client, HelperThread};
 use log::{debug, info, trace};
 
 use super::context::OutputFile;
+use super::fingerprint;
 use super::job::{
     Freshness::{self, Dirty, Fresh},
     Job,
 };
+use super::shared_cache;
 use super::timings::Timings;
 use super::{BuildContext, BuildPlan, CompileMode, Context, Unit};
 use crate::core::{PackageId, Shell, TargetKind};
@@ -823,6 +825,9 @@ impl<'cfg> DrainState<'cfg> {
         let rmeta_required = cx.rmeta_required(unit);
 
         if !cx.bcx.build_config.build_plan {
+            if fresh == Dirty && cx.bcx.build_config.explain_rebuilds {
+                fingerprint::explain_rebuild(cx, unit)?;
+            }
             // Print out some nice progress information.
             self.note_working_on(cx.bcx.config, unit, fresh)?;
         }
@@ -928,6 +933,9 @@ impl<'cfg> DrainState<'cfg> {
         if unit.mode.is_run_custom_build() && unit.show_warnings(cx.bcx.config) {
             self.emit_warnings(None, unit, cx)?;
         }
+        if artifact == Artifact::All {
+            shared_cache::store(cx, unit)?;
+        }
         let unlocked = self.queue.finish(unit, &artifact);
         match artifact {
             Artifact::All => self.timings.unit_finished(id, unlocked),
@@ -13,7 +13,9 @@ mod layout;
 mod links;
 mod lto;
 mod output_depinfo;
+mod remote_cache;
 pub mod rustdoc;
+mod shared_cache;
 pub mod standard_lib;
 mod timings;
 mod unit;
@@ -137,12 +139,7 @@ fn compile<'cfg>(
         let force = exec.force_rebuild(unit) || force_rebuild;
         let mut job = fingerprint::prepare_target(cx, unit, force)?;
         job.before(if job.freshness() == Freshness::Dirty {
-            let work = if unit.mode.is_doc() {
-                rustdoc(cx, unit)?
-            } else {
-                rustc(cx, unit, exec)?
-            };
-            work.then(link_targets(cx, unit, false)?)
+            compile_work(cx, unit, exec)?
         } else {
             let work = if unit.show_warnings(bcx.config) {
                 replay_output_cache(
@@ -156,7 +153,15 @@ fn compile<'cfg>(
                 Work::noop()
             };
             // Need to link targets on both the dirty and fresh.
-            work.then(link_targets(cx, unit, true)?)
+            let work = work.then(link_targets(cx, unit, true)?);
+            if shared_cache::is_restored(cx, unit) {
+                // Compiled instead if the entry is gone by the time the job
+                // runs.
+                let compile = compile_work(cx, unit, exec)?;
+                shared_cache::restore(cx, unit, compile, work)?
+            } else {
+                work
+            }
         });
 
         job
@@ -176,6 +181,21 @@ fn compile<'cfg>(
     Ok(())
 }
 
+/// Returns the work compiling `unit` and linking its outputs.
+fn compile_work(
+    cx: &mut Context<'_, '_>,
+    unit: &Unit,
+    exec: &Arc<dyn Executor>,
+) -> CargoResult<Work> {
+    let work = if unit.mode.is_doc() {
+        rustdoc(cx, unit)?
+    } else {
+        rustc(cx, unit, exec)?
+    };
+    let work = shared_cache::fetch_or_store(cx, unit, work)?;
+    Ok(work.then(link_targets(cx, unit, false)?))
+}
+
 fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> CargoResult<Work> {
     let mut rustc = prepare_rustc(cx, &unit.target.rustc_crate_types(), unit)?;
     let build_plan = cx.bcx.build_config.build_plan;
@@ -227,6 +247,7 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
     let fingerprint_dir = cx.files().fingerprint_dir(unit);
     let script_metadata = cx.find_build_script_metadata(unit.clone());
     let is_local = unit.is_local();
+    let checksum_freshness = cx.bcx.config.cli_unstable().checksum_freshness;
 
     return Ok(Work::new(move |state| {
         // Only at runtime have we discovered what the extra -L and -l
@@ -304,6 +325,11 @@ fn rustc(cx: &mut Context<'_, '_>, unit: &Unit, exec: &Arc<dyn Executor>) -> Car
                 &rustc,
                 // Do not track source files in the fingerprint for registry dependencies.
                 is_local,
+                if checksum_freshness {
+                    Some(timestamp)
+                } else {
+                    None
+                },
             )
             .chain_err(|| {
                 internal(format!(
@@ -0,0 +1,259 @@
+//! A remote store for the entries of the shared cache, so that compiled
+//! dependencies can be reused across machines, such as the ones of a CI farm.
+//!
+//! The remote cache is any HTTP server at the `build.remote-cache` URL which
+//! answers `GET` and `PUT` requests, such as a static file server accepting
+//! uploads. The entry `<key>` of the shared cache, whose key covers the
+//! `Metadata` hash of the unit and the version of rustc, is stored as two
+//! files:
+//!
+//! ```text
+//! <url>/<key>.tar.gz      # a gzipped tarball of the files of the entry
+//! <url>/<key>.cksum       # the checksum of the tarball, such as `3f8a…`
+//! ```
+//!
+//! Entries missing from the shared cache are downloaded from the remote
+//! cache, and their checksum is verified before they are used. With
+//! `build.remote-cache-mode = "read-write"`, the entries added to the shared
+//! cache are uploaded too, the checksum last so that an entry is never seen
+//! incomplete.
+//!
+//! The checksum only catches tarballs corrupted in transit or in storage. It
+//! is served by the same server as the tarball, so it doesn't protect against
+//! a malicious or compromised remote cache, which can serve any compiled code
+//! it wants: only remote caches whose writers are trusted should be used.
+//!
+//! The transfers happen in the jobs of the units, each with its own HTTP
+//! handle, so that they don't hold up the rest of the build. The build never
+//! fails because of the remote cache: if it can't be reached, a warning is
+//! printed and it isn't used for the rest of the build.
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use curl::easy::Easy;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::debug;
+use tar::{Archive, Builder, EntryType};
+
+use crate::util::config::CargoBuildConfig;
+use crate::util::errors::{CargoResult, CargoResultExt, HttpNot200};
+use crate::util::{paths, Checksum, ChecksumAlgorithm, Config};
+
+pub struct RemoteCache {
+    url: String,
+    read_write: bool,
+    /// Set once the remote cache failed to answer, so that the rest of the
+    /// build doesn't wait for it again.
+    disabled: AtomicBool,
+    /// The warnings of the jobs, printed once they finish as they can't
+    /// access the shell.
+    warnings: Mutex<Vec<String>>,
+}
+
+impl RemoteCache {
+    /// Returns the remote cache configured with `build.remote-cache`, if any.
+    pub fn new(
+        config: &Config,
+        build_config: &CargoBuildConfig,
+    ) -> CargoResult<Option<RemoteCache>> {
+        let url = match &build_config.remote_cache {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => return Ok(None),
+        };
+        let read_write = match build_config.remote_cache_mode.as_deref() {
+            None | Some("read-only") => false,
+            Some("read-write") => true,
+            Some(mode) => anyhow::bail!(
+                "invalid `build.remote-cache-mode` value `{}`, \
+                 expected `read-only` or `read-write`",
+                mode
+            ),
+        };
+        Ok(Some(RemoteCache {
+            url,
+            read_write,
+            disabled: AtomicBool::new(!config.network_allowed()),
+            warnings: Mutex::new(Vec::new()),
+        }))
+    }
+
+    /// Returns whether the remote cache is still used by this build.
+    pub fn is_enabled(&self) -> bool {
+        !self.disabled.load(Ordering::SeqCst)
+    }
+
+    /// Returns the warnings of the transfers which happened since the last
+    /// call.
+    pub fn take_warnings(&self) -> Vec<String> {
+        std::mem::take(&mut *self.warnings.lock().unwrap())
+    }
+
+    /// Downloads the entry `key` into the directory `dst` with `handle`,
+    /// returning whether the remote cache has it.
+    pub fn download(&self, handle: &mut Easy, key: &str, dst: &Path) -> bool {
+        if !self.is_enabled() {
+            return false;
+        }
+        let (cksum, tarball) = match self.fetch(handle, key) {
+            Ok(Some(fetched)) => fetched,
+            Ok(None) => return false,
+            Err(e) => {
+                self.disable(e);
+                return false;
+            }
+        };
+        if let Err(e) = unpack(&cksum, &tarball, dst) {
+            self.warn(format!(
+                "ignoring the entry `{}` of the remote cache at `{}`: {:?}",
+                key, self.url, e
+            ));
+            return false;
+        }
+        debug!("downloaded {} from the remote cache", key);
+        true
+    }
+
+    fn fetch(&self, handle: &mut Easy, key: &str) -> CargoResult<Option<(String, Vec<u8>)>> {
+        let cksum = match get(handle, &format!("{}/{}.cksum", self.url, key))? {
+            Some(cksum) => String::from_utf8_lossy(&cksum).trim().to_string(),
+            None => return Ok(None),
+        };
+        let tarball = match get(handle, &format!("{}/{}.tar.gz", self.url, key))? {
+            Some(tarball) => tarball,
+            None => return Ok(None),
+        };
+        Ok(Some((cksum, tarball)))
+    }
+
+    /// Uploads the entry `key` with `handle`, whose files are in the
+    /// directory `src`, if the remote cache is writable.
+    pub fn upload(&self, handle: &mut Easy, key: &str, src: &Path) -> CargoResult<()> {
+        if !self.is_enabled() || !self.read_write {
+            return Ok(());
+        }
+        let tarball = pack(src)?;
+        let cksum = Checksum::compute(ChecksumAlgorithm::Sha256, &tarball);
+        let result =
+            put(handle, &format!("{}/{}.tar.gz", self.url, key), &tarball).and_then(|()| {
+                put(
+                    handle,
+                    &format!("{}/{}.cksum", self.url, key),
+                    cksum.to_string().as_bytes(),
+                )
+            });
+        match result {
+            Ok(()) => debug!("uploaded {} to the remote cache", key),
+            Err(e) => self.disable(e),
+        }
+        Ok(())
+    }
+
+    fn disable(&self, e: anyhow::Error) {
+        // Only the first job failing to reach the remote cache warns.
+        if !self.disabled.swap(true, Ordering::SeqCst) {
+            self.warn(format!(
+                "the remote cache at `{}` is unavailable, it won't be used for the rest \
+                 of the build: {:?}",
+                self.url, e
+            ));
+        }
+    }
+
+    fn warn(&self, warning: String) {
+        self.warnings.lock().unwrap().push(warning);
+    }
+}
+
+/// Fetches `url`, returning `None` if it doesn't exist.
+fn get(handle: &mut Easy, url: &str) -> CargoResult<Option<Vec<u8>>> {
+    handle.get(true)?;
+    handle.url(url)?;
+    let mut body = Vec::new();
+    {
+        let mut transfer = handle.transfer();
+        transfer.write_function(|buf| {
+            body.extend_from_slice(buf);
+            Ok(buf.len())
+        })?;
+        transfer.perform()?;
+    }
+    match handle.response_code()? {
+        200 => Ok(Some(body)),
+        404 | 410 => Ok(None),
+        code => Err(HttpNot200 {
+            code,
+            url: url.to_string(),
+        }
+        .into()),
+    }
+}
+
+/// Uploads `data` to `url`.
+fn put(handle: &mut Easy, url: &str, mut data: &[u8]) -> CargoResult<()> {
+    handle.upload(true)?;
+    handle.in_filesize(data.len() as u64)?;
+    handle.url(url)?;
+    {
+        let mut transfer = handle.transfer();
+        transfer.read_function(|buf| Ok(data.read(buf).unwrap_or(0)))?;
+        transfer.perform()?;
+    }
+    match handle.response_code()? {
+        200 | 201 | 204 => Ok(()),
+        code => Err(HttpNot200 {
+            code,
+            url: url.to_string(),
+        }
+        .into()),
+    }
+}
+
+fn pack(src: &Path) -> CargoResult<Vec<u8>> {
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    let mut files = fs::read_dir(src)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<CargoResult<Vec<_>>>()?;
+    files.sort();
+    for file in files {
+        builder
+            .append_path_with_name(&file, file.file_name().unwrap())
+            .chain_err(|| format!("failed to archive `{}`", file.display()))?;
+    }
+    Ok(builder.into_inner()?.finish()?)
+}
+
+/// Verifies `tarball` against `cksum`, and extracts its files into `dst`.
+fn unpack(cksum: &str, tarball: &[u8], dst: &Path) -> CargoResult<()> {
+    let expected = Checksum::parse(cksum)?;
+    let actual = Checksum::compute(expected.algorithm(), tarball);
+    if actual != expected {
+        anyhow::bail!(
+            "checksum mismatch, expected `{}` but the tarball has `{}`",
+            expected,
+            actual
+        );
+    }
+    paths::create_dir_all(dst)?;
+    let mut archive = Archive::new(GzDecoder::new(tarball));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        // Entries only hold files, never directories or links, so nothing
+        // can be written outside of `dst`.
+        let name = match path.file_name() {
+            Some(name) if path.parent() == Some(Path::new("")) => name.to_owned(),
+            _ => anyhow::bail!("invalid path `{}` in the tarball", path.display()),
+        };
+        if entry.header().entry_type() != EntryType::Regular {
+            anyhow::bail!("`{}` isn't a file", path.display());
+        }
+        entry.unpack(dst.join(name))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,475 @@
+//! A cache of compiled artifacts shared between target directories, enabled
+//! with `-Z shared-cache` and the `build.shared-cache` config value.
+//!
+//! Packages from registries and git repositories are compiled the same way
+//! in every workspace using them with the same features, profile and
+//! compiler, so their outputs can be reused instead of compiled again in each
+//! target directory. An entry of the cache holds the outputs of one unit,
+//! along with its dep-info file, and is keyed on the `Metadata` hash of the
+//! unit and on the hash of its `Fingerprint`, which covers the fingerprints
+//! of all its dependencies.
+//!
+//! Only units which can't depend on the contents of the workspace are cached:
+//! units of path packages, and of packages depending on path packages, are
+//! always compiled.
+//!
+//! Units of packages with a build script, and every unit depending on one,
+//! even indirectly, are never cached either. Their fingerprint covers the
+//! fingerprint of the build script run, but not its output, which isn't known
+//! until it runs. Only the compiled build scripts themselves are cached. This
+//! excludes a large share of the packages from registries, such as all the
+//! `-sys` crates and everything depending on them.
+//!
+//! The layout of the cache is:
+//!
+//! ```text
+//! .lock                   # locked while entries are added or evicted
+//! entries/
+//!     foo-<metadata>-<fingerprint>/
+//!         libfoo-<metadata>.rlib
+//!         libfoo-<metadata>.rmeta
+//!         dep-info
+//! tmp/                    # entries being written, renamed into `entries`
+//! ```
+//!
+//! When the cache grows beyond `build.shared-cache-max-size` bytes, the least
+//! recently used entries are removed.
+//!
+//! Entries can also be shared with other machines through a remote cache,
+//! see the `remote_cache` module.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use filetime::FileTime;
+use log::debug;
+
+use super::job::Work;
+use super::remote_cache::RemoteCache;
+use super::{CompileMode, Context, FileFlavor, Unit};
+use crate::ops;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{paths, Config, Filesystem};
+
+/// The default of `build.shared-cache-max-size`, 10 GiB.
+const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024 * 1024;
+
+/// The name of the dep-info file of the unit in an entry.
+const DEP_INFO: &str = "dep-info";
+
+pub struct SharedCache {
+    root: Filesystem,
+    max_size: u64,
+    /// The units which can be cached in this build, whose entries are added
+    /// once their job finishes if it wrote them.
+    pending: HashMap<Unit, Pending>,
+    /// The entries of the units found in the cache, which are copied into the
+    /// target directory once their job runs.
+    restored: HashMap<Unit, PathBuf>,
+    /// Whether units can be cached, memoized as this depends on all their
+    /// dependencies.
+    cacheable: HashMap<Unit, bool>,
+    remote: Option<Arc<RemoteCache>>,
+    /// What the jobs did with the entries of their units, by key.
+    outcomes: Arc<Mutex<HashMap<String, Outcome>>>,
+}
+
+struct Pending {
+    key: String,
+    /// The directory the job writes the entry into, before it's added with
+    /// `add_entry` on the main thread.
+    tmp: PathBuf,
+}
+
+enum Outcome {
+    /// The entry was downloaded from the remote cache.
+    Downloaded,
+    /// The unit was compiled, and its outputs copied into the entry.
+    Compiled,
+    /// The unit was compiled, but its outputs couldn't be copied.
+    Failed(anyhow::Error),
+}
+
+impl SharedCache {
+    /// Returns the cache configured with `build.shared-cache`, if any.
+    pub fn new(config: &Config) -> CargoResult<Option<SharedCache>> {
+        if !config.cli_unstable().shared_cache {
+            return Ok(None);
+        }
+        let build_config = config.build_config()?;
+        let remote = RemoteCache::new(config, build_config)?;
+        let root = match &build_config.shared_cache {
+            Some(path) => path.resolve_path(config),
+            None if remote.is_some() => {
+                anyhow::bail!("`build.remote-cache` requires `build.shared-cache` to be set")
+            }
+            None => return Ok(None),
+        };
+        Ok(Some(SharedCache {
+            root: Filesystem::new(root),
+            max_size: build_config
+                .shared_cache_max_size
+                .unwrap_or(DEFAULT_MAX_SIZE),
+            pending: HashMap::new(),
+            restored: HashMap::new(),
+            cacheable: HashMap::new(),
+            remote: remote.map(Arc::new),
+            outcomes: Arc::new(Mutex::new(HashMap::new())),
+        }))
+    }
+}
+
+/// Returns the key of `unit` in the shared cache, whose fingerprint hashes to
+/// `fingerprint`, or `None` if there is no cache or `unit` can't be cached.
+pub fn key(cx: &mut Context<'_, '_>, unit: &Unit, fingerprint: u64) -> Option<String> {
+    if cx.shared_cache.is_none() || !is_cacheable(cx, unit) {
+        return None;
+    }
+    let metadata = cx.files().metadata(unit)?;
+    Some(format!(
+        "{}-{}-{}",
+        unit.pkg.name(),
+        metadata,
+        crate::util::to_hex(fingerprint)
+    ))
+}
+
+/// Returns whether `unit` only depends on packages which aren't in the
+/// workspace, and doesn't depend on the output of a build script.
+fn is_cacheable(cx: &mut Context<'_, '_>, unit: &Unit) -> bool {
+    let cache = cx.shared_cache.as_ref().unwrap();
+    if let Some(&cacheable) = cache.cacheable.get(unit) {
+        return cacheable;
+    }
+    let mut cacheable = !unit.is_local()
+        && !unit.is_std
+        && matches!(unit.mode, CompileMode::Build | CompileMode::Check { .. })
+        && cx.files().metadata(unit).is_some();
+    if cacheable {
+        let deps = Vec::from(cx.unit_deps(unit));
+        cacheable = deps.iter().all(|dep| is_cacheable(cx, &dep.unit));
+    }
+    cx.shared_cache
+        .as_mut()
+        .unwrap()
+        .cacheable
+        .insert(unit.clone(), cacheable);
+    cacheable
+}
+
+/// Returns whether the shared cache has the entry `key` for `unit`.
+///
+/// The outputs of `unit` are only copied from the entry by the work returned
+/// by `restore`, when its job runs. If `unit` isn't in the cache, the work
+/// returned by `fetch_or_store` downloads it from the remote cache, or stores
+/// its outputs once it is compiled.
+pub fn lookup(cx: &mut Context<'_, '_>, unit: &Unit, key: &str) -> CargoResult<bool> {
+    let config = cx.bcx.config;
+    let cache = cx.shared_cache.as_mut().unwrap();
+    let root = cache.root.as_path_unlocked();
+    // Also stored if the unit is compiled after all, in case the entry is
+    // evicted before the job runs.
+    cache.pending.insert(
+        unit.clone(),
+        Pending {
+            key: key.to_string(),
+            tmp: root
+                .join("tmp")
+                .join(format!("{}.{}", key, std::process::id())),
+        },
+    );
+
+    let entry = root.join("entries").join(key);
+    if !entry.exists() {
+        debug!("shared cache miss for {:?}: {}", unit, key);
+        return Ok(false);
+    }
+
+    debug!("shared cache hit for {:?}: {}", unit, key);
+    // Entries are evicted in the order they were last used in.
+    paths::set_file_time_no_err(&entry, FileTime::now());
+    cache.restored.insert(unit.clone(), entry);
+    config.shell().verbose(|shell| {
+        shell.status(
+            "Restored",
+            format!(
+                "{} ({}) from the shared cache",
+                unit.pkg,
+                unit.target.description_named()
+            ),
+        )
+    })?;
+    Ok(true)
+}
+
+/// Returns whether `lookup` found `unit` in the shared cache.
+pub fn is_restored(cx: &Context<'_, '_>, unit: &Unit) -> bool {
+    cx.shared_cache
+        .as_ref()
+        .map_or(false, |c| c.restored.contains_key(unit))
+}
+
+/// Returns the work copying the outputs of `unit`, which `lookup` found in
+/// the shared cache, from its entry and then running `fresh`.
+///
+/// The entry may be evicted by another build before the job runs, in which
+/// case `compile` is run instead.
+pub fn restore(
+    cx: &mut Context<'_, '_>,
+    unit: &Unit,
+    compile: Work,
+    fresh: Work,
+) -> CargoResult<Work> {
+    let entry = cx
+        .shared_cache
+        .as_mut()
+        .and_then(|c| c.restored.remove(unit))
+        .expect("unit wasn't restored");
+    let outputs = outputs(cx, unit)?;
+    let dep_info = super::fingerprint::dep_info_loc(cx, unit);
+    Ok(Work::new(move |state| {
+        match copy_entry(&entry, &outputs, &dep_info) {
+            Ok(()) => fresh.call(state),
+            Err(e) => {
+                debug!(
+                    "failed to restore {}, compiling it: {:?}",
+                    entry.display(),
+                    e
+                );
+                compile.call(state)
+            }
+        }
+    }))
+}
+
+/// Copies the files of `entry` to the outputs of its unit.
+///
+/// Their mtimes are updated, so that they are newer than the ones of the
+/// dependencies of the unit, which are finished by the time its job runs.
+/// Otherwise the next build would consider the unit out of date.
+fn copy_entry(entry: &Path, outputs: &[PathBuf], dep_info: &Path) -> CargoResult<()> {
+    for output in outputs {
+        let src = entry.join(output.file_name().unwrap());
+        paths::create_dir_all(output.parent().unwrap())?;
+        paths::copy(&src, output)?;
+    }
+    let src = entry.join(DEP_INFO);
+    if src.exists() {
+        paths::create_dir_all(dep_info.parent().unwrap())?;
+        paths::copy(&src, dep_info)?;
+    }
+    let now = FileTime::now();
+    for output in outputs.iter().map(|p| p.as_path()).chain(Some(dep_info)) {
+        if output.exists() {
+            filetime::set_file_mtime(output, now)
+                .chain_err(|| format!("failed to set mtime of `{}`", output.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Wraps the work compiling `unit`, if it can be cached, so that it's
+/// downloaded from the remote cache instead if it has it, or that its outputs
+/// are written into a new entry of the shared cache, and uploaded to the
+/// remote cache, once it's compiled.
+///
+/// The transfers happen in the job, with an HTTP handle of its own, so that
+/// they don't hold up the rest of the build. The entry is only added to the
+/// shared cache by `store` once the job finishes.
+pub fn fetch_or_store(cx: &mut Context<'_, '_>, unit: &Unit, compile: Work) -> CargoResult<Work> {
+    let (key, tmp) = match cx.shared_cache.as_ref().and_then(|c| c.pending.get(unit)) {
+        Some(pending) => (pending.key.clone(), pending.tmp.clone()),
+        None => return Ok(compile),
+    };
+    let outputs = outputs(cx, unit)?;
+    let dep_info = super::fingerprint::dep_info_loc(cx, unit);
+    let cache = cx.shared_cache.as_ref().unwrap();
+    let mut remote = match &cache.remote {
+        Some(remote) if remote.is_enabled() => {
+            Some((Arc::clone(remote), ops::http_handle(cx.bcx.config)?))
+        }
+        _ => None,
+    };
+    let outcomes = Arc::clone(&cache.outcomes);
+    Ok(Work::new(move |state| {
+        if let Some((remote, handle)) = &mut remote {
+            if download(remote, handle, &key, &tmp, &outputs, &dep_info) {
+                outcomes.lock().unwrap().insert(key, Outcome::Downloaded);
+                return Ok(());
+            }
+        }
+        compile.call(state)?;
+        let result = write_entry(&tmp, &outputs, &dep_info).and_then(|()| {
+            if let Some((remote, handle)) = &mut remote {
+                remote.upload(handle, &key, &tmp)?;
+            }
+            Ok(())
+        });
+        let outcome = match result {
+            Ok(()) => Outcome::Compiled,
+            Err(e) => {
+                drop(fs::remove_dir_all(&tmp));
+                Outcome::Failed(e)
+            }
+        };
+        outcomes.lock().unwrap().insert(key, outcome);
+        Ok(())
+    }))
+}
+
+/// Downloads the entry `key` into `tmp` and copies its files to the outputs
+/// of its unit, returning whether it succeeded.
+fn download(
+    remote: &RemoteCache,
+    handle: &mut curl::easy::Easy,
+    key: &str,
+    tmp: &Path,
+    outputs: &[PathBuf],
+    dep_info: &Path,
+) -> bool {
+    let result = empty_dir(tmp).and_then(|()| {
+        if remote.download(handle, key, tmp) {
+            copy_entry(tmp, outputs, dep_info).map(|()| true)
+        } else {
+            Ok(false)
+        }
+    });
+    match result {
+        Ok(true) => true,
+        Ok(false) => {
+            drop(fs::remove_dir_all(tmp));
+            false
+        }
+        Err(e) => {
+            debug!("failed to restore {} from the remote cache: {:?}", key, e);
+            drop(fs::remove_dir_all(tmp));
+            false
+        }
+    }
+}
+
+/// Copies the outputs of a unit into the new entry `tmp`.
+fn write_entry(tmp: &Path, outputs: &[PathBuf], dep_info: &Path) -> CargoResult<()> {
+    empty_dir(tmp)?;
+    for output in outputs {
+        paths::copy(output, tmp.join(output.file_name().unwrap()))?;
+    }
+    if dep_info.exists() {
+        paths::copy(dep_info, tmp.join(DEP_INFO))?;
+    }
+    Ok(())
+}
+
+fn empty_dir(path: &Path) -> CargoResult<()> {
+    if path.exists() {
+        paths::remove_dir_all(path)?;
+    }
+    paths::create_dir_all(path)
+}
+
+/// Adds the entry written by the job of `unit` to the shared cache, once it
+/// finished, and prints the warnings of the remote cache.
+///
+/// Failing to add it isn't an error, as the build itself succeeded.
+pub fn store(cx: &mut Context<'_, '_>, unit: &Unit) -> CargoResult<()> {
+    let config = cx.bcx.config;
+    let cache = match &mut cx.shared_cache {
+        Some(cache) => cache,
+        None => return Ok(()),
+    };
+    if let Some(remote) = &cache.remote {
+        for warning in remote.take_warnings() {
+            config.shell().warn(warning)?;
+        }
+    }
+    let pending = match cache.pending.remove(unit) {
+        Some(pending) => pending,
+        None => return Ok(()),
+    };
+    let outcome = cache.outcomes.lock().unwrap().remove(&pending.key);
+    let result = match outcome {
+        // Restored from the shared cache.
+        None => return Ok(()),
+        Some(Outcome::Downloaded) => {
+            config.shell().verbose(|shell| {
+                shell.status(
+                    "Restored",
+                    format!(
+                        "{} ({}) from the remote cache",
+                        unit.pkg,
+                        unit.target.description_named()
+                    ),
+                )
+            })?;
+            add_entry(cache, config, &pending.key, &pending.tmp)
+        }
+        Some(Outcome::Compiled) => add_entry(cache, config, &pending.key, &pending.tmp),
+        Some(Outcome::Failed(e)) => Err(e),
+    };
+    if let Err(e) = result {
+        config.shell().warn(format!(
+            "failed to store `{}` in the shared cache: {:?}",
+            unit.pkg, e
+        ))?;
+    }
+    Ok(())
+}
+
+/// Adds the entry `key`, whose files were written in the directory `tmp`,
+/// and evicts older entries if the cache became too large.
+///
+/// The entry is renamed into place, so that it's never seen incomplete, even
+/// if Cargo is interrupted.
+fn add_entry(cache: &SharedCache, config: &Config, key: &str, tmp: &Path) -> CargoResult<()> {
+    let _lock = cache.root.open_rw(".lock", config, "shared cache")?;
+    let root = cache.root.as_path_unlocked();
+    let entry = root.join("entries").join(key);
+    if entry.exists() {
+        // Added by another build in the meantime.
+        return paths::remove_dir_all(tmp);
+    }
+    paths::create_dir_all(root.join("entries"))?;
+    fs::rename(tmp, &entry).chain_err(|| format!("failed to rename `{}`", tmp.display()))?;
+    debug!("stored {} in the shared cache", key);
+
+    evict(root, cache.max_size)
+}
+
+/// Removes the least recently used entries until the cache is no larger
+/// than `max_size` bytes.
+fn evict(root: &Path, max_size: u64) -> CargoResult<()> {
+    let mut entries = Vec::new();
+    let mut total = 0;
+    for entry in fs::read_dir(root.join("entries"))? {
+        let path = entry?.path();
+        let mut size = 0;
+        for file in fs::read_dir(&path)? {
+            size += file?.metadata()?.len();
+        }
+        total += size;
+        entries.push((paths::mtime(&path)?, size, path));
+    }
+    entries.sort();
+    for (_, size, path) in entries {
+        if total <= max_size {
+            break;
+        }
+        debug!("evicting {} from the shared cache", path.display());
+        paths::remove_dir_all(&path)?;
+        total -= size;
+    }
+    Ok(())
+}
+
+/// The outputs of `unit` which are cached, the same ones that its
+/// fingerprint checks the mtimes of.
+fn outputs(cx: &Context<'_, '_>, unit: &Unit) -> CargoResult<Vec<PathBuf>> {
+    Ok(cx
+        .outputs(unit)?
+        .iter()
+        .filter(|output| !matches!(output.flavor, FileFlavor::DebugInfo | FileFlavor::Auxiliary))
+        .map(|output| output.path.clone())
+        .collect())
+}
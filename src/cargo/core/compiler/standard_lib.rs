@@ -66,6 +66,8 @@ pub fn resolve_std<'cfg>(
         /*default_members*/ &None,
         /*exclude*/ &None,
         /*custom_metadata*/ &None,
+        /*incompatible_rust_versions*/ None,
+        /*dependency_policy*/ None,
     ));
     let virtual_manifest = crate::core::VirtualManifest::new(
         /*replace*/ Vec::new(),
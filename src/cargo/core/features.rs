@@ -217,6 +217,15 @@ features! {
 
         // Allow to specify whether binaries should be stripped.
         [unstable] strip: bool,
+
+        // Inheriting package metadata and dependencies from the workspace.
+        [unstable] workspace_inheritance: bool,
+
+        // Preferring dependency versions compatible with the workspace's `rust-version`.
+        [unstable] msrv_policy: bool,
+
+        // Enforcing a dependency policy for the workspace.
+        [unstable] dependency_policy: bool,
     }
 }
 
@@ -360,6 +369,10 @@ pub struct CliUnstable {
     pub multitarget: bool,
     pub rustdoc_map: bool,
     pub terminal_width: Option<Option<usize>>,
+    pub sparse_registry: bool,
+    pub credential_provider: bool,
+    pub shared_cache: bool,
+    pub checksum_freshness: bool,
 }
 
 fn deserialize_build_std<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
@@ -465,6 +478,10 @@ impl CliUnstable {
             "multitarget" => self.multitarget = parse_empty(k, v)?,
             "rustdoc-map" => self.rustdoc_map = parse_empty(k, v)?,
             "terminal-width" => self.terminal_width = Some(parse_usize_opt(v)?),
+            "sparse-registry" => self.sparse_registry = parse_empty(k, v)?,
+            "credential-provider" => self.credential_provider = parse_empty(k, v)?,
+            "shared-cache" => self.shared_cache = parse_empty(k, v)?,
+            "checksum-freshness" => self.checksum_freshness = parse_empty(k, v)?,
             _ => bail!("unknown `-Z` flag specified: {}", k),
         }
 
@@ -95,6 +95,8 @@ pub struct ManifestMetadata {
     pub documentation: Option<String>, // URL
     pub badges: BTreeMap<String, BTreeMap<String, String>>,
     pub links: Option<String>,
+    /// The minimum Rust version the package can be built with.
+    pub rust_version: Option<String>,
 }
 
 #[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -10,7 +10,7 @@ use std::time::{Duration, Instant};
 
 use anyhow::Context;
 use bytesize::ByteSize;
-use curl::easy::{Easy, HttpVersion};
+use curl::easy::{Easy, HttpVersion, List};
 use curl::multi::{EasyHandle, Multi};
 use lazycell::LazyCell;
 use log::{debug, warn};
@@ -97,6 +97,7 @@ struct SerializedPackage<'a> {
     readme: Option<&'a str>,
     repository: Option<&'a str>,
     edition: &'a str,
+    rust_version: Option<&'a str>,
     links: Option<&'a str>,
     #[serde(skip_serializing_if = "Option::is_none")]
     metabuild: Option<&'a Vec<String>>,
@@ -147,6 +148,7 @@ impl ser::Serialize for Package {
             readme,
             repository,
             edition: &self.manifest().edition().to_string(),
+            rust_version: self.rust_version(),
             links: self.manifest().links(),
             metabuild: self.manifest().metabuild(),
             publish: self.publish().as_ref(),
@@ -217,6 +219,11 @@ impl Package {
     pub fn publish(&self) -> &Option<Vec<String>> {
         self.manifest().publish()
     }
+    /// Gets the minimum Rust version the package can be built with, if
+    /// specified.
+    pub fn rust_version(&self) -> Option<&str> {
+        self.manifest().metadata().rust_version.as_deref()
+    }
     /// Returns `true` if this package is a proc-macro.
     pub fn proc_macro(&self) -> bool {
         self.targets().iter().any(|target| target.proc_macro())
@@ -612,13 +619,17 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
         let pkg = source
             .download(id)
             .chain_err(|| anyhow::format_err!("unable to get packages from source"))?;
-        let (url, descriptor) = match pkg {
+        let (url, descriptor, authorization) = match pkg {
             MaybePackage::Ready(pkg) => {
                 debug!("{} doesn't need a download", id);
                 assert!(slot.fill(pkg).is_ok());
                 return Ok(Some(slot.borrow().unwrap()));
             }
-            MaybePackage::Download { url, descriptor } => (url, descriptor),
+            MaybePackage::Download {
+                url,
+                descriptor,
+                authorization,
+            } => (url, descriptor, authorization),
         };
 
         // Ok we're going to download this crate, so let's set up all our
@@ -635,6 +646,14 @@ impl<'a, 'cfg> Downloads<'a, 'cfg> {
         handle.url(&url)?;
         handle.follow_location(true)?; // follow redirects
 
+        // Registries which require authentication get the token of the
+        // registry, as for API requests.
+        if let Some(authorization) = &authorization {
+            let mut headers = List::new();
+            headers.append(&format!("Authorization: {}", authorization))?;
+            handle.http_headers(headers)?;
+        }
+
         // Enable HTTP/2 to be used as it'll allow true multiplexing which makes
         // downloads much faster.
         //
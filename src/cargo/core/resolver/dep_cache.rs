@@ -19,6 +19,7 @@ use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::interning::InternedString;
 use crate::util::Config;
 use log::debug;
+use semver::{Version, VersionReq};
 use std::cmp::Ordering;
 use std::collections::{BTreeSet, HashMap, HashSet};
 use std::rc::Rc;
@@ -31,6 +32,9 @@ pub struct RegistryQueryer<'a> {
     /// versions first. That allows `cargo update -Z minimal-versions` which will
     /// specify minimum dependency versions to be used.
     minimal_versions: bool,
+    /// If set, candidates which declare a newer `rust-version` than this are
+    /// sorted after the ones which don't.
+    rust_version: Option<&'a Version>,
     /// a cache of `Candidate`s that fulfil a `Dependency`
     registry_cache: HashMap<Dependency, Rc<Vec<Summary>>>,
     /// a cache of `Dependency`s that are required for a `Summary`
@@ -52,6 +56,7 @@ impl<'a> RegistryQueryer<'a> {
         replacements: &'a [(PackageIdSpec, Dependency)],
         try_to_use: &'a HashSet<PackageId>,
         minimal_versions: bool,
+        rust_version: Option<&'a Version>,
         config: Option<&'a Config>,
     ) -> Self {
         RegistryQueryer {
@@ -59,6 +64,7 @@ impl<'a> RegistryQueryer<'a> {
             replacements,
             try_to_use,
             minimal_versions,
+            rust_version,
             registry_cache: HashMap::new(),
             summary_cache: HashMap::new(),
             used_replacements: HashMap::new(),
@@ -210,13 +216,27 @@ impl<'a> RegistryQueryer<'a> {
 
         // When we attempt versions for a package we'll want to do so in a
         // sorted fashion to pick the "best candidates" first. Currently we try
-        // prioritized summaries (those in `try_to_use`) and failing that we
+        // prioritized summaries (those in `try_to_use`), then the ones
+        // compatible with `rust_version` if it's set, and failing that we
         // list everything from the maximum version to the lowest version.
+        let incompatible: HashSet<PackageId> = match self.rust_version {
+            Some(rust_version) => ret
+                .iter()
+                .filter(|s| !is_rust_version_compatible(s, rust_version))
+                .map(|s| s.package_id())
+                .collect(),
+            None => HashSet::new(),
+        };
         ret.sort_unstable_by(|a, b| {
             let a_in_previous = self.try_to_use.contains(&a.package_id());
             let b_in_previous = self.try_to_use.contains(&b.package_id());
-            let previous_cmp = a_in_previous.cmp(&b_in_previous).reverse();
-            match previous_cmp {
+            let a_compatible = !incompatible.contains(&a.package_id());
+            let b_compatible = !incompatible.contains(&b.package_id());
+            let preferred_cmp = a_in_previous
+                .cmp(&b_in_previous)
+                .then(a_compatible.cmp(&b_compatible))
+                .reverse();
+            match preferred_cmp {
                 Ordering::Equal => {
                     let cmp = a.version().cmp(b.version());
                     if self.minimal_versions {
@@ -227,7 +247,7 @@ impl<'a> RegistryQueryer<'a> {
                         cmp.reverse()
                     }
                 }
-                _ => previous_cmp,
+                _ => preferred_cmp,
             }
         });
 
@@ -511,3 +531,15 @@ impl Requirements<'_> {
         Ok(())
     }
 }
+
+/// Returns whether `summary` can be built by `rust_version`, which is the case
+/// if it doesn't declare a `rust-version` or declares an older one.
+pub fn is_rust_version_compatible(summary: &Summary, rust_version: &Version) -> bool {
+    match summary.rust_version() {
+        // The index doesn't validate this field, so values which can't be
+        // parsed are treated as compatible rather than rejecting the package.
+        Some(required) => VersionReq::parse(&format!(">={}", required))
+            .map_or(true, |req| req.matches(rust_version)),
+        None => true,
+    }
+}
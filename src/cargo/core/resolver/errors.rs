@@ -1,9 +1,11 @@
 use std::fmt;
 
 use crate::core::{Dependency, PackageId, Registry, Summary};
+use crate::util::interning::InternedString;
 use crate::util::lev_distance::lev_distance;
 use crate::util::Config;
 use anyhow::Error;
+use serde::Serialize;
 
 use super::context::Context;
 use super::types::{ConflictMap, ConflictReason};
@@ -12,6 +14,7 @@ use super::types::{ConflictMap, ConflictReason};
 pub struct ResolveError {
     cause: Error,
     package_path: Vec<PackageId>,
+    conflict_report: Option<ConflictReport>,
 }
 
 impl ResolveError {
@@ -19,6 +22,7 @@ impl ResolveError {
         Self {
             cause: cause.into(),
             package_path,
+            conflict_report: None,
         }
     }
 
@@ -27,6 +31,226 @@ impl ResolveError {
     pub fn package_path(&self) -> &[PackageId] {
         &self.package_path
     }
+
+    /// Returns the explanation of the conflict, if resolution failed because
+    /// all candidates conflict with previously selected packages.
+    pub fn conflict_report(&self) -> Option<&ConflictReport> {
+        self.conflict_report.as_ref()
+    }
+}
+
+/// A structured explanation of why no version of a dependency could be
+/// selected: the requirement which failed, and for each previously selected
+/// package standing in the way, the dependents which required it and why it
+/// conflicts with the candidates.
+///
+/// It is built from the conflicts the resolver kept while backtracking. Its
+/// `Display` renders the explanation as a chain of reasons, and it is
+/// emitted as a `resolve-conflict` message with `--message-format=json`.
+#[derive(Debug, Serialize)]
+pub struct ConflictReport {
+    /// The name of the package no version could be selected for.
+    pub package: InternedString,
+    /// The requirement which couldn't be satisfied.
+    pub requirement: Requirement,
+    /// The versions matching `requirement`, all of which conflict.
+    pub candidates: Vec<semver::Version>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// A dependency of `dependent` on some package.
+#[derive(Debug, Serialize)]
+pub struct Requirement {
+    pub dependent: PackageId,
+    pub req: String,
+    /// The chain of packages depending on `dependent`, up to the root of
+    /// the dependency graph.
+    pub depended_on_by: Vec<PackageId>,
+}
+
+/// A previously selected package which conflicts with every candidate.
+#[derive(Debug, Serialize)]
+pub struct Conflict {
+    pub selected: PackageId,
+    /// The chain of packages depending on `selected`, up to the root of the
+    /// dependency graph.
+    pub depended_on_by: Vec<PackageId>,
+    /// The dependencies which caused `selected` to be selected.
+    pub required_by: Vec<Requirement>,
+    #[serde(flatten)]
+    pub reason: ConflictKind,
+}
+
+/// Why a previously selected package conflicts, see `ConflictReason`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum ConflictKind {
+    /// `selected` is a semver compatible version of the candidates.
+    Semver,
+    /// `selected` links to the same native library as the candidates.
+    Links { links: InternedString },
+    /// `selected` enables features which the candidates don't have.
+    MissingFeatures { features: String },
+    /// `selected` enables features which are required dependencies of the
+    /// candidates.
+    RequiredDependencyAsFeatures { features: InternedString },
+    /// The candidates would violate the public dependency rules.
+    PublicDependency,
+}
+
+impl ConflictReport {
+    fn new(
+        cx: &Context,
+        parent: &Summary,
+        dep: &Dependency,
+        conflicting_activations: &ConflictMap,
+        candidates: &[Summary],
+    ) -> ConflictReport {
+        let depended_on_by = |id: &PackageId| -> Vec<PackageId> {
+            cx.parents
+                .path_to_bottom(id)
+                .into_iter()
+                .skip(1)
+                .cloned()
+                .collect()
+        };
+        let conflicts = conflicting_activations
+            .iter()
+            .rev()
+            .map(|(&selected, reason)| {
+                let required_by = cx
+                    .parents
+                    .edges(&selected)
+                    .flat_map(|(&dependent, deps)| {
+                        deps.iter().map(move |dep| Requirement {
+                            dependent,
+                            req: dep.version_req().to_string(),
+                            depended_on_by: depended_on_by(&dependent),
+                        })
+                    })
+                    .collect();
+                let reason = match reason {
+                    ConflictReason::Semver => ConflictKind::Semver,
+                    ConflictReason::Links(links) => ConflictKind::Links { links: *links },
+                    ConflictReason::MissingFeatures(features) => ConflictKind::MissingFeatures {
+                        features: features.clone(),
+                    },
+                    ConflictReason::RequiredDependencyAsFeatures(features) => {
+                        ConflictKind::RequiredDependencyAsFeatures {
+                            features: *features,
+                        }
+                    }
+                    ConflictReason::PublicDependency(_) | ConflictReason::PubliclyExports(_) => {
+                        ConflictKind::PublicDependency
+                    }
+                };
+                Conflict {
+                    selected,
+                    depended_on_by: depended_on_by(&selected),
+                    required_by,
+                    reason,
+                }
+            })
+            .collect();
+        ConflictReport {
+            package: dep.package_name(),
+            requirement: Requirement {
+                dependent: parent.package_id(),
+                req: dep.version_req().to_string(),
+                depended_on_by: depended_on_by(&parent.package_id()),
+            },
+            candidates: candidates.iter().map(|s| s.version().clone()).collect(),
+            conflicts,
+        }
+    }
+}
+
+/// Writes `package `id``, followed by the chain of packages depending on it.
+fn write_package(f: &mut fmt::Formatter<'_>, id: PackageId, chain: &[PackageId]) -> fmt::Result {
+    write!(f, "package `{}`", id)?;
+    for dependent in chain {
+        write!(f, "\n    ... which is depended on by `{}`", dependent)?;
+    }
+    writeln!(f)
+}
+
+impl fmt::Display for ConflictReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.package;
+        let req = &self.requirement;
+        write!(f, "because `{} {}` is required by ", name, req.req)?;
+        write_package(f, req.dependent, &req.depended_on_by)?;
+        let candidates: Vec<_> = self.candidates.iter().map(|v| v.to_string()).collect();
+        writeln!(
+            f,
+            "and the versions matching it are {}",
+            candidates.join(", ")
+        )?;
+        let mut semver = false;
+        for conflict in &self.conflicts {
+            match &conflict.reason {
+                ConflictKind::Semver => {
+                    semver = true;
+                    if conflict.required_by.is_empty() {
+                        writeln!(f, "and `{}` was selected", conflict.selected)?;
+                    }
+                    for req in &conflict.required_by {
+                        write!(
+                            f,
+                            "and `{}` was selected, as `{} {}` is required by ",
+                            conflict.selected, name, req.req
+                        )?;
+                        write_package(f, req.dependent, &req.depended_on_by)?;
+                    }
+                }
+                ConflictKind::Links { links } => {
+                    write!(
+                        f,
+                        "and the native library `{}` is also linked to by ",
+                        links
+                    )?;
+                    write_package(f, conflict.selected, &conflict.depended_on_by)?;
+                    writeln!(f, "and only one package may link to a native library")?;
+                }
+                ConflictKind::MissingFeatures { features } => {
+                    write!(
+                        f,
+                        "and the features `{}` of `{}` are enabled by ",
+                        features, name
+                    )?;
+                    write_package(f, conflict.selected, &conflict.depended_on_by)?;
+                    writeln!(f, "and none of the versions has them")?;
+                }
+                ConflictKind::RequiredDependencyAsFeatures { features } => {
+                    write!(
+                        f,
+                        "and `{}` of `{}` is enabled as a feature by ",
+                        features, name
+                    )?;
+                    write_package(f, conflict.selected, &conflict.depended_on_by)?;
+                    writeln!(
+                        f,
+                        "and it is a required dependency of every version, \
+                         but only optional dependencies can be used as features"
+                    )?;
+                }
+                ConflictKind::PublicDependency => {
+                    write!(
+                        f,
+                        "and two versions of a public dependency would be visible to "
+                    )?;
+                    write_package(f, conflict.selected, &conflict.depended_on_by)?;
+                }
+            }
+        }
+        if semver {
+            writeln!(
+                f,
+                "and only one semver compatible version of a package can be selected"
+            )?;
+        }
+        write!(f, "so no version of `{}` can be selected", name)
+    }
 }
 
 impl std::error::Error for ResolveError {
@@ -88,103 +312,15 @@ pub(super) fn activation_error(
     };
 
     if !candidates.is_empty() {
-        let mut msg = format!("failed to select a version for `{}`.", dep.package_name());
-        msg.push_str("\n    ... required by ");
-        msg.push_str(&describe_path(
-            &cx.parents.path_to_bottom(&parent.package_id()),
-        ));
-
-        msg.push_str("\nversions that meet the requirements `");
-        msg.push_str(&dep.version_req().to_string());
-        msg.push_str("` are: ");
-        msg.push_str(
-            &candidates
-                .iter()
-                .map(|v| v.version())
-                .map(|v| v.to_string())
-                .collect::<Vec<_>>()
-                .join(", "),
+        let report = ConflictReport::new(cx, parent, dep, conflicting_activations, candidates);
+        let msg = format!(
+            "failed to select a version for `{}`\n\n{}",
+            dep.package_name(),
+            report
         );
-
-        let mut conflicting_activations: Vec<_> = conflicting_activations.iter().collect();
-        conflicting_activations.sort_unstable();
-        let (links_errors, mut other_errors): (Vec<_>, Vec<_>) = conflicting_activations
-            .drain(..)
-            .rev()
-            .partition(|&(_, r)| r.is_links());
-
-        for &(p, r) in links_errors.iter() {
-            if let ConflictReason::Links(ref link) = *r {
-                msg.push_str("\n\nthe package `");
-                msg.push_str(&*dep.package_name());
-                msg.push_str("` links to the native library `");
-                msg.push_str(link);
-                msg.push_str("`, but it conflicts with a previous package which links to `");
-                msg.push_str(link);
-                msg.push_str("` as well:\n");
-            }
-            msg.push_str(&describe_path(&cx.parents.path_to_bottom(p)));
-        }
-
-        let (features_errors, mut other_errors): (Vec<_>, Vec<_>) = other_errors
-            .drain(..)
-            .partition(|&(_, r)| r.is_missing_features());
-
-        for &(p, r) in features_errors.iter() {
-            if let ConflictReason::MissingFeatures(ref features) = *r {
-                msg.push_str("\n\nthe package `");
-                msg.push_str(&*p.name());
-                msg.push_str("` depends on `");
-                msg.push_str(&*dep.package_name());
-                msg.push_str("`, with features: `");
-                msg.push_str(features);
-                msg.push_str("` but `");
-                msg.push_str(&*dep.package_name());
-                msg.push_str("` does not have these features.\n");
-            }
-            // p == parent so the full path is redundant.
-        }
-
-        let (required_dependency_as_features_errors, other_errors): (Vec<_>, Vec<_>) = other_errors
-            .drain(..)
-            .partition(|&(_, r)| r.is_required_dependency_as_features());
-
-        for &(p, r) in required_dependency_as_features_errors.iter() {
-            if let ConflictReason::RequiredDependencyAsFeatures(ref features) = *r {
-                msg.push_str("\n\nthe package `");
-                msg.push_str(&*p.name());
-                msg.push_str("` depends on `");
-                msg.push_str(&*dep.package_name());
-                msg.push_str("`, with features: `");
-                msg.push_str(features);
-                msg.push_str("` but `");
-                msg.push_str(&*dep.package_name());
-                msg.push_str("` does not have these features.\n");
-                msg.push_str(
-                    " It has a required dependency with that name, \
-                     but only optional dependencies can be used as features.\n",
-                );
-            }
-            // p == parent so the full path is redundant.
-        }
-
-        if !other_errors.is_empty() {
-            msg.push_str(
-                "\n\nall possible versions conflict with \
-                 previously selected packages.",
-            );
-        }
-
-        for &(p, _) in other_errors.iter() {
-            msg.push_str("\n\n  previously selected ");
-            msg.push_str(&describe_path(&cx.parents.path_to_bottom(p)));
-        }
-
-        msg.push_str("\n\nfailed to select a version for `");
-        msg.push_str(&*dep.package_name());
-        msg.push_str("` which could resolve this conflict");
-
-        return to_resolve_err(anyhow::format_err!("{}", msg));
+        let mut err = to_resolve_err(anyhow::format_err!("{}", msg));
+        err.conflict_report = Some(report);
+        return err;
     }
 
     // We didn't actually find any candidates, so we need to
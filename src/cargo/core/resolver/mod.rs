@@ -53,6 +53,7 @@ use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 use log::{debug, trace};
+use semver::Version;
 
 use crate::core::PackageIdSpec;
 use crate::core::{Dependency, PackageId, Registry, Summary};
@@ -66,12 +67,14 @@ use self::features::RequestedFeatures;
 use self::types::{ConflictMap, ConflictReason, DepsFrame};
 use self::types::{FeaturesSet, RcVecIter, RemainingDeps, ResolverProgress};
 
+pub use self::dep_cache::is_rust_version_compatible;
 pub use self::encode::Metadata;
 pub use self::encode::{EncodableDependency, EncodablePackageId, EncodableResolve};
 pub use self::errors::{ActivateError, ActivateResult, ResolveError};
+pub use self::errors::{Conflict, ConflictKind, ConflictReport, Requirement};
 pub use self::features::{ForceAllTargets, HasDevUnits};
 pub use self::resolve::{Resolve, ResolveVersion};
-pub use self::types::{ResolveBehavior, ResolveOpts};
+pub use self::types::{IncompatibleRustVersions, ResolveBehavior, ResolveOpts};
 
 mod conflict_cache;
 mod context;
@@ -106,6 +109,10 @@ mod types;
 ///   when sorting candidates to activate, but otherwise this isn't used
 ///   anywhere else.
 ///
+/// * `rust_version` - if set, candidates whose `rust-version` is newer than
+///   this are sorted after the others, so they're only picked if nothing
+///   else works.
+///
 /// * `config` - a location to print warnings and such, or `None` if no warnings
 ///   should be printed
 ///
@@ -124,6 +131,7 @@ pub fn resolve(
     replacements: &[(PackageIdSpec, Dependency)],
     registry: &mut dyn Registry,
     try_to_use: &HashSet<PackageId>,
+    rust_version: Option<&Version>,
     config: Option<&Config>,
     check_public_visible_dependencies: bool,
 ) -> CargoResult<Resolve> {
@@ -133,8 +141,14 @@ pub fn resolve(
         Some(config) => config.cli_unstable().minimal_versions,
         None => false,
     };
-    let mut registry =
-        RegistryQueryer::new(registry, replacements, try_to_use, minimal_versions, config);
+    let mut registry = RegistryQueryer::new(
+        registry,
+        replacements,
+        try_to_use,
+        minimal_versions,
+        rust_version,
+        config,
+    );
     let cx = activate_deps_loop(cx, &mut registry, summaries, config)?;
 
     let mut cksums = HashMap::new();
@@ -3,7 +3,7 @@ use crate::core::dependency::DepKind;
 use crate::core::{Dependency, PackageId, PackageIdSpec, Summary, Target};
 use crate::util::errors::CargoResult;
 use crate::util::interning::InternedString;
-use crate::util::Graph;
+use crate::util::{Checksum, ChecksumAlgorithm, Graph};
 use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
@@ -129,7 +129,73 @@ impl Resolve {
         }
     }
 
-    pub fn merge_from(&mut self, previous: &Resolve) -> CargoResult<()> {
+    /// Combines the two sides of a `Cargo.lock` merge conflict into a single
+    /// resolve, to be used as the previous resolve of a new resolution.
+    ///
+    /// Every package locked on either side is kept, except that if the sides
+    /// lock different semver compatible versions of a package, only the
+    /// newest one is, so that an update made on one side wins. Where the
+    /// sides otherwise disagree, `ours` wins.
+    pub fn merge_conflict_sides(ours: &Resolve, theirs: &Resolve) -> Resolve {
+        let mut newest: HashMap<_, PackageId> = HashMap::new();
+        for id in ours.iter().chain(theirs.iter()) {
+            let kept = newest.entry(id.as_activations_key()).or_insert(id);
+            if id.version() > kept.version() {
+                *kept = id;
+            }
+        }
+        let pick = |id: PackageId| newest[&id.as_activations_key()];
+
+        let mut graph: Graph<PackageId, HashSet<Dependency>> = Graph::new();
+        let mut replacements = HashMap::new();
+        let mut checksums = HashMap::new();
+        let mut metadata = Metadata::new();
+        let mut unused_patches = Vec::new();
+        for side in &[ours, theirs] {
+            for id in side.iter().filter(|&id| pick(id) == id) {
+                graph.add(id);
+                for (dep, deps) in side.graph.edges(&id) {
+                    graph.link(id, pick(*dep)).extend(deps.iter().cloned());
+                }
+                if let Some(replacement) = side.replacements.get(&id) {
+                    replacements.entry(id).or_insert(*replacement);
+                }
+                if let Some(checksum) = side.checksums.get(&id) {
+                    checksums.entry(id).or_insert_with(|| checksum.clone());
+                }
+            }
+            for (key, value) in side.metadata.iter() {
+                metadata.entry(key.clone()).or_insert_with(|| value.clone());
+            }
+            for id in side.unused_patches.iter() {
+                if !unused_patches.contains(id) {
+                    unused_patches.push(*id);
+                }
+            }
+        }
+
+        Resolve::new(
+            graph,
+            replacements,
+            HashMap::new(),
+            checksums,
+            metadata,
+            unused_patches,
+            ours.version.max(theirs.version),
+            HashMap::new(),
+        )
+    }
+
+    /// Carries over the metadata of the lock file `previous`, checking that
+    /// the checksums of the packages locked in both are the same.
+    ///
+    /// The checksums of the packages in `updated`, which `cargo update` was
+    /// asked to update, may also have moved to another algorithm.
+    pub fn merge_from(
+        &mut self,
+        previous: &Resolve,
+        updated: Option<&HashSet<PackageId>>,
+    ) -> CargoResult<()> {
         // Given a previous instance of resolve, it should be forbidden to ever
         // have a checksums which *differ*. If the same package ID has differing
         // checksums, then something has gone wrong such as:
@@ -144,7 +210,11 @@ impl Resolve {
         // never run into this.
         for (id, cksum) in previous.checksums.iter() {
             if let Some(mine) = self.checksums.get(id) {
-                if mine == cksum {
+                if mine == cksum || checksums_compatible(mine, cksum) {
+                    continue;
+                }
+                let is_updated = updated.map_or(false, |updated| updated.contains(id));
+                if is_updated && changed_algorithms(mine, cksum).is_some() {
                     continue;
                 }
 
@@ -193,6 +263,31 @@ unable to verify that `{0}` is the same as when the lockfile was generated
                         id.source_id()
                     )
 
+                // A registry moving to another checksum algorithm changes the
+                // checksums of all of its packages at once. They can't be
+                // compared, so they have to be accepted on purpose, either
+                // one package at a time by updating it to the same version,
+                // or all at once.
+                } else if let Some((from, to)) = changed_algorithms(mine, cksum) {
+                    anyhow::bail!(
+                        "\
+checksum for `{}` changed from a `{}` checksum to a `{}` checksum between \
+lock files
+
+this happens when the registry moves to another checksum algorithm, but \
+could also indicate that the source was tampered with
+
+if the source is trusted, run `cargo update -p {}:{} --precise {4}` to accept \
+the new checksum of this package, or `cargo update` to accept the new \
+checksums of all packages
+",
+                        id,
+                        from.name(),
+                        to.name(),
+                        id.name(),
+                        id.version()
+                    );
+
                 // If the checksums aren't equal, and neither is None, then they
                 // must both be Some, in which case the checksum now differs.
                 // That's quite bad!
@@ -412,3 +507,30 @@ impl Default for ResolveVersion {
         ResolveVersion::V2
     }
 }
+
+/// Returns whether the checksums are the same, even if only one of them
+/// spells out the `sha256:` prefix.
+fn checksums_compatible(mine: &Option<String>, previous: &Option<String>) -> bool {
+    match (parse_checksum(mine), parse_checksum(previous)) {
+        (Some(mine), Some(previous)) => mine == previous,
+        _ => false,
+    }
+}
+
+/// Returns the algorithms of `previous` and `mine`, if they differ.
+fn changed_algorithms(
+    mine: &Option<String>,
+    previous: &Option<String>,
+) -> Option<(ChecksumAlgorithm, ChecksumAlgorithm)> {
+    let mine = parse_checksum(mine)?.algorithm();
+    let previous = parse_checksum(previous)?.algorithm();
+    if mine == previous {
+        None
+    } else {
+        Some((previous, mine))
+    }
+}
+
+fn parse_checksum(cksum: &Option<String>) -> Option<Checksum> {
+    cksum.as_deref().and_then(|c| Checksum::parse(c).ok())
+}
@@ -126,6 +126,32 @@ impl ResolveBehavior {
     }
 }
 
+/// How the resolver treats candidate versions whose `rust-version` is newer
+/// than the one of the workspace, set by the `incompatible-rust-versions`
+/// field of `[workspace]`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum IncompatibleRustVersions {
+    /// The `rust-version` of candidates is not taken into account.
+    Allow,
+    /// Candidates with a compatible `rust-version` are tried first, and the
+    /// others only if nothing else works.
+    Fallback,
+}
+
+impl IncompatibleRustVersions {
+    pub fn from_manifest(value: &str) -> CargoResult<IncompatibleRustVersions> {
+        match value {
+            "allow" => Ok(IncompatibleRustVersions::Allow),
+            "fallback" => Ok(IncompatibleRustVersions::Fallback),
+            s => anyhow::bail!(
+                "`incompatible-rust-versions` setting `{}` is not valid, \
+                 valid options are \"allow\" and \"fallback\"",
+                s
+            ),
+        }
+    }
+}
+
 /// Options for how the resolve should work.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
 pub struct ResolveOpts {
@@ -105,7 +105,13 @@ pub trait Source {
 
 pub enum MaybePackage {
     Ready(Package),
-    Download { url: String, descriptor: String },
+    Download {
+        url: String,
+        descriptor: String,
+        /// The `Authorization` header to send along with the request, for
+        /// registries which require authentication.
+        authorization: Option<String>,
+    },
 }
 
 impl<'a, T: Source + ?Sized + 'a> Source for Box<T> {
@@ -232,6 +232,15 @@ impl SourceId {
         }
     }
 
+    /// Returns the name of the alternative registry this source is for, if
+    /// it is known.
+    ///
+    /// It is only known for the IDs created with `alt_registry`, and not for
+    /// the ones read from a lock file for example.
+    pub fn alt_registry_key(self) -> Option<&'static str> {
+        self.inner.name.as_deref()
+    }
+
     /// Returns `true` if this source is from a filesystem path.
     pub fn is_path(self) -> bool {
         self.inner.kind == SourceKind::Path
@@ -253,6 +262,16 @@ impl SourceId {
         matches!(self.inner.kind, SourceKind::Registry)
     }
 
+    /// Returns `true` if this source is a remote registry whose index is
+    /// fetched file-by-file over HTTP rather than cloned with git.
+    ///
+    /// These are spelled with a `sparse+` prefix on the index URL, such as
+    /// `sparse+https://example.com/index/`.
+    pub fn is_sparse(self) -> bool {
+        matches!(self.inner.kind, SourceKind::Registry)
+            && self.inner.url.scheme().starts_with("sparse+")
+    }
+
     /// Returns `true` if this source from a Git repository.
     pub fn is_git(self) -> bool {
         matches!(self.inner.kind, SourceKind::Git(_))
@@ -274,30 +293,48 @@ impl SourceId {
                 };
                 Ok(Box::new(PathSource::new(&path, self, config)))
             }
-            SourceKind::Registry => Ok(Box::new(RegistrySource::remote(
-                self,
-                yanked_whitelist,
-                config,
-            ))),
-            SourceKind::LocalRegistry => {
+            SourceKind::Registry | SourceKind::LocalRegistry => {
+                Ok(Box::new(self.load_registry(config, yanked_whitelist)?))
+            }
+            SourceKind::Directory => {
                 let path = match self.inner.url.to_file_path() {
                     Ok(p) => p,
                     Err(()) => panic!("path sources cannot be remote"),
                 };
-                Ok(Box::new(RegistrySource::local(
-                    self,
-                    &path,
-                    yanked_whitelist,
-                    config,
-                )))
+                Ok(Box::new(DirectorySource::new(&path, self, config)))
             }
-            SourceKind::Directory => {
+        }
+    }
+
+    /// Creates the source for a registry, for callers which need to access
+    /// its index beyond what the `Source` trait offers.
+    ///
+    /// Panics if this isn't a registry.
+    pub fn load_registry<'a>(
+        self,
+        config: &'a Config,
+        yanked_whitelist: &HashSet<PackageId>,
+    ) -> CargoResult<RegistrySource<'a>> {
+        match self.inner.kind {
+            SourceKind::Registry if self.is_sparse() => {
+                if !config.cli_unstable().sparse_registry {
+                    anyhow::bail!(
+                        "usage of sparse registries requires `-Z sparse-registry`, \
+                         found index `{}`",
+                        self.inner.url
+                    );
+                }
+                Ok(RegistrySource::http(self, yanked_whitelist, config))
+            }
+            SourceKind::Registry => Ok(RegistrySource::remote(self, yanked_whitelist, config)),
+            SourceKind::LocalRegistry => {
                 let path = match self.inner.url.to_file_path() {
                     Ok(p) => p,
                     Err(()) => panic!("path sources cannot be remote"),
                 };
-                Ok(Box::new(DirectorySource::new(&path, self, config)))
+                Ok(RegistrySource::local(self, &path, yanked_whitelist, config))
             }
+            _ => panic!("`{}` is not a registry", self),
         }
     }
 
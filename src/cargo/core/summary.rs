@@ -30,6 +30,7 @@ struct Inner {
     checksum: Option<String>,
     links: Option<InternedString>,
     namespaced_features: bool,
+    rust_version: Option<InternedString>,
 }
 
 impl Summary {
@@ -68,6 +69,7 @@ impl Summary {
                 checksum: None,
                 links: links.map(|l| l.into()),
                 namespaced_features,
+                rust_version: None,
             }),
         })
     }
@@ -99,6 +101,9 @@ impl Summary {
     pub fn namespaced_features(&self) -> bool {
         self.inner.namespaced_features
     }
+    pub fn rust_version(&self) -> Option<InternedString> {
+        self.inner.rust_version
+    }
 
     pub fn override_id(mut self, id: PackageId) -> Summary {
         Rc::make_mut(&mut self.inner).package_id = id;
@@ -109,6 +114,10 @@ impl Summary {
         Rc::make_mut(&mut self.inner).checksum = Some(cksum);
     }
 
+    pub fn set_rust_version(&mut self, rust_version: Option<InternedString>) {
+        Rc::make_mut(&mut self.inner).rust_version = rust_version;
+    }
+
     pub fn map_dependencies<F>(mut self, f: F) -> Summary
     where
         F: FnMut(Dependency) -> Dependency,
@@ -7,12 +7,13 @@ use std::slice;
 
 use glob::glob;
 use log::debug;
+use semver::{Version, VersionReq};
 use url::Url;
 
 use crate::core::features::Features;
 use crate::core::registry::PackageRegistry;
 use crate::core::resolver::features::RequestedFeatures;
-use crate::core::resolver::ResolveBehavior;
+use crate::core::resolver::{IncompatibleRustVersions, ResolveBehavior};
 use crate::core::{Dependency, PackageId, PackageIdSpec};
 use crate::core::{EitherManifest, Package, SourceId, VirtualManifest};
 use crate::ops;
@@ -87,6 +88,15 @@ pub struct Workspace<'cfg> {
     // file. This is set for `cargo install` without `--locked`.
     ignore_lock: bool,
 
+    // If `true`, a `Cargo.lock` with git merge conflicts is loaded by merging
+    // both sides of the conflicts, instead of being an error. This is set for
+    // `cargo generate-lockfile --merge-conflicts`.
+    merge_lock_conflicts: bool,
+
+    // If `true`, resolver conflicts are also reported as a `resolve-conflict`
+    // JSON message on stdout. This is set with `--message-format=json`.
+    json_messages: bool,
+
     /// The resolver behavior specified with the `resolver` field.
     resolve_behavior: Option<ResolveBehavior>,
 
@@ -131,6 +141,34 @@ pub struct WorkspaceRootConfig {
     default_members: Option<Vec<String>>,
     exclude: Vec<String>,
     custom_metadata: Option<toml::Value>,
+    incompatible_rust_versions: Option<IncompatibleRustVersions>,
+    dependency_policy: Option<DependencyPolicy>,
+}
+
+/// The `[workspace.dependency-policy]` table, which restricts the packages
+/// the resolved dependency graph may contain.
+///
+/// Path dependencies, such as the workspace members, are always allowed.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyPolicy {
+    pub ban: Vec<BannedDependency>,
+    /// If set, the names of the only registries packages may come from,
+    /// `crates-io` included.
+    pub allow_registries: Option<Vec<String>>,
+    /// If set, the only git repositories packages may come from, as hosts
+    /// (like `github.com`) or URL prefixes.
+    pub allow_git: Option<Vec<String>>,
+    /// The most semver incompatible versions of a package that the graph may
+    /// contain.
+    pub max_versions: Option<usize>,
+}
+
+/// A package which may not be depended on, at least in the versions matching
+/// `version` if it's set.
+#[derive(Debug, Clone)]
+pub struct BannedDependency {
+    pub name: InternedString,
+    pub version: Option<VersionReq>,
 }
 
 /// An iterator over the member packages of a workspace, returned by
@@ -189,6 +227,8 @@ impl<'cfg> Workspace<'cfg> {
             require_optional_deps: true,
             loaded_packages: RefCell::new(HashMap::new()),
             ignore_lock: false,
+            merge_lock_conflicts: false,
+            json_messages: false,
             resolve_behavior: None,
             custom_metadata: None,
         }
@@ -315,12 +355,16 @@ impl<'cfg> Workspace<'cfg> {
     /// That is, this returns the path of the directory containing the
     /// `Cargo.toml` which is the root of this workspace.
     pub fn root(&self) -> &Path {
+        self.root_manifest().parent().unwrap()
+    }
+
+    /// Returns the path of the `Cargo.toml` which is the root of this
+    /// workspace.
+    pub fn root_manifest(&self) -> &Path {
         match self.root_manifest {
             Some(ref p) => p,
             None => &self.current_manifest,
         }
-        .parent()
-        .unwrap()
     }
 
     /// Returns the root Package or VirtualManifest.
@@ -404,6 +448,24 @@ impl<'cfg> Workspace<'cfg> {
         self
     }
 
+    pub fn merge_lock_conflicts(&self) -> bool {
+        self.merge_lock_conflicts
+    }
+
+    pub fn set_merge_lock_conflicts(&mut self, merge: bool) -> &mut Workspace<'cfg> {
+        self.merge_lock_conflicts = merge;
+        self
+    }
+
+    pub fn json_messages(&self) -> bool {
+        self.json_messages
+    }
+
+    pub fn set_json_messages(&mut self, json_messages: bool) -> &mut Workspace<'cfg> {
+        self.json_messages = json_messages;
+        self
+    }
+
     pub fn custom_metadata(&self) -> Option<&toml::Value> {
         self.custom_metadata.as_ref()
     }
@@ -637,6 +699,42 @@ impl<'cfg> Workspace<'cfg> {
         self.resolve_behavior.unwrap_or(ResolveBehavior::V1)
     }
 
+    /// How the resolver treats dependency versions which need a newer Rust
+    /// than the workspace's `rust-version`.
+    pub fn incompatible_rust_versions(&self) -> IncompatibleRustVersions {
+        match self.root_maybe().workspace_config() {
+            WorkspaceConfig::Root(root_config) => root_config
+                .incompatible_rust_versions
+                .unwrap_or(IncompatibleRustVersions::Allow),
+            WorkspaceConfig::Member { .. } => IncompatibleRustVersions::Allow,
+        }
+    }
+
+    /// The dependency policy set in `[workspace.dependency-policy]`, if any.
+    pub fn dependency_policy(&self) -> Option<&DependencyPolicy> {
+        match self.root_maybe().workspace_config() {
+            WorkspaceConfig::Root(root_config) => root_config.dependency_policy.as_ref(),
+            WorkspaceConfig::Member { .. } => None,
+        }
+    }
+
+    /// Returns the oldest `rust-version` declared by the members of the
+    /// workspace, if any.
+    pub fn rust_version(&self) -> Option<Version> {
+        self.members()
+            .filter_map(|pkg| pkg.rust_version())
+            .filter_map(|rust_version| {
+                // `rust-version` may leave out the patch version.
+                let full = if rust_version.matches('.').count() == 1 {
+                    format!("{}.0", rust_version)
+                } else {
+                    rust_version.to_string()
+                };
+                Version::parse(&full).ok()
+            })
+            .min()
+    }
+
     pub fn allows_unstable_package_features(&self) -> bool {
         self.config().cli_unstable().package_features
             || match self.resolve_behavior() {
@@ -1161,6 +1259,8 @@ impl WorkspaceRootConfig {
         default_members: &Option<Vec<String>>,
         exclude: &Option<Vec<String>>,
         custom_metadata: &Option<toml::Value>,
+        incompatible_rust_versions: Option<IncompatibleRustVersions>,
+        dependency_policy: Option<DependencyPolicy>,
     ) -> WorkspaceRootConfig {
         WorkspaceRootConfig {
             root_dir: root_dir.to_path_buf(),
@@ -1168,13 +1268,15 @@ impl WorkspaceRootConfig {
             default_members: default_members.clone(),
             exclude: exclude.clone().unwrap_or_default(),
             custom_metadata: custom_metadata.clone(),
+            incompatible_rust_versions,
+            dependency_policy,
         }
     }
 
     /// Checks the path against the `excluded` list.
     ///
     /// This method does **not** consider the `members` list.
-    fn is_excluded(&self, manifest_path: &Path) -> bool {
+    pub fn is_excluded(&self, manifest_path: &Path) -> bool {
         let excluded = self
             .exclude
             .iter()
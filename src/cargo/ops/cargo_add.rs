@@ -0,0 +1,300 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+
+use crate::core::source::Source;
+use crate::core::{Dependency, SourceId, Summary};
+use crate::ops;
+use crate::sources::SourceConfigMap;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::toml_mut::{self, DepTable, DependencySource, LocalManifest};
+use crate::util::{validate_package_name, Config};
+
+pub struct AddOptions<'a> {
+    pub config: &'a Config,
+    /// The manifest of the package to add the dependencies to.
+    pub manifest_path: &'a Path,
+    /// The dependencies to add, as `name` or `name@version-req`.
+    pub crates: Vec<String>,
+    /// The table to add the dependencies to.
+    pub table: DepTable,
+    pub rename: Option<String>,
+    pub features: Vec<String>,
+    pub optional: bool,
+    /// The registry to take the dependencies from, `None` for crates.io.
+    pub registry: Option<String>,
+    /// Take the dependency from the package in this directory.
+    pub path: Option<PathBuf>,
+    /// Take the dependency from this git repository.
+    pub git: Option<String>,
+    pub branch: Option<String>,
+    pub tag: Option<String>,
+    pub rev: Option<String>,
+}
+
+/// Adds the dependencies described by `opts` to a manifest, or updates them
+/// if the manifest already has them.
+pub fn add(opts: &AddOptions<'_>) -> CargoResult<()> {
+    if opts.crates.len() > 1 {
+        for (flag, set) in &[
+            ("rename", opts.rename.is_some()),
+            ("path", opts.path.is_some()),
+            ("git", opts.git.is_some()),
+        ] {
+            if *set {
+                bail!(
+                    "`--{}` cannot be used when adding more than one crate",
+                    flag
+                );
+            }
+        }
+    }
+    if opts.crates.is_empty() && opts.path.is_none() {
+        bail!("no crate to add was specified");
+    }
+
+    let mut manifest = LocalManifest::try_new(opts.manifest_path)?;
+    let specs = if opts.crates.is_empty() {
+        vec![None]
+    } else {
+        opts.crates.iter().map(Some).collect()
+    };
+    for spec in specs {
+        let dep = resolve_dependency(opts, &manifest, spec.map(String::as_str))?;
+        manifest.insert_into_table(&opts.table, &dep)?;
+    }
+    manifest.write()?;
+
+    Ok(())
+}
+
+/// Builds the manifest entry for the crate `spec`, which may only be `None`
+/// when adding a dependency on a local path.
+fn resolve_dependency(
+    opts: &AddOptions<'_>,
+    manifest: &LocalManifest,
+    spec: Option<&str>,
+) -> CargoResult<toml_mut::Dependency> {
+    let config = opts.config;
+    let (name, req) = match spec {
+        Some(spec) => {
+            let (name, req) = match spec.find('@') {
+                Some(i) => (&spec[..i], Some(&spec[i + 1..])),
+                None => (spec, None),
+            };
+            validate_package_name(name, "crate name", "")?;
+            (Some(name), req)
+        }
+        None => (None, None),
+    };
+
+    let summary;
+    let mut dep = if let Some(path) = &opts.path {
+        let source_id = SourceId::for_path(path)?;
+        let (package, _) = ops::read_package(&path.join("Cargo.toml"), source_id, config)?;
+        if let Some(name) = name {
+            if package.name().as_str() != name {
+                bail!(
+                    "the package at `{}` is `{}`, not `{}`",
+                    path.display(),
+                    package.name(),
+                    name
+                );
+            }
+        }
+        let mut dep = toml_mut::Dependency::new(&package.name());
+        dep.source = DependencySource::Path(path.clone());
+        dep.version = req.map(String::from);
+        summary = Some(package.summary().clone());
+        dep
+    } else if let Some(git) = &opts.git {
+        let name = match name {
+            Some(name) => name,
+            None => bail!(
+                "the name of the crate to add from `{}` must be specified",
+                git
+            ),
+        };
+        let mut dep = toml_mut::Dependency::new(name);
+        dep.source = DependencySource::Git {
+            url: git.clone(),
+            branch: opts.branch.clone(),
+            tag: opts.tag.clone(),
+            rev: opts.rev.clone(),
+        };
+        dep.version = req.map(String::from);
+        summary = None;
+        dep
+    } else {
+        let name = name.unwrap();
+        let key = opts.rename.as_deref().unwrap_or(name);
+        let existing = manifest.get_dependency(&opts.table, key).transpose()?;
+        if existing.as_ref().map(|d| &d.source) == Some(&DependencySource::Workspace) {
+            // Everything but `features` and `optional` comes from
+            // `[workspace.dependencies]`, so that is all that can change.
+            if req.is_some() || opts.registry.is_some() || opts.rename.is_some() {
+                bail!(
+                    "dependency `{}` is inherited from the workspace, \
+                     only `--features` and `--optional` can be used to change it",
+                    key
+                );
+            }
+            let mut dep = toml_mut::Dependency::new(name);
+            dep.source = DependencySource::Workspace;
+            summary = None;
+            dep
+        } else if opts.registry.is_none()
+            && existing
+                .as_ref()
+                .map_or(false, |d| d.source != DependencySource::Registry)
+        {
+            // Without an explicit source, a path or git dependency which is
+            // already there stays one.
+            let existing = existing.unwrap();
+            if opts.rename.is_some() && existing.name != name {
+                bail!(
+                    "dependency `{}` is taken from {}, use `--path`, `--git` or \
+                     `--registry` to say where `{}` should be taken from",
+                    key,
+                    match existing.source {
+                        DependencySource::Path(_) => "a local path",
+                        _ => "a git repository",
+                    },
+                    name
+                );
+            }
+            summary = match &existing.source {
+                DependencySource::Path(path) => {
+                    let source_id = SourceId::for_path(path)?;
+                    let (package, _) =
+                        ops::read_package(&path.join("Cargo.toml"), source_id, config)?;
+                    Some(package.summary().clone())
+                }
+                _ => None,
+            };
+            let mut dep = toml_mut::Dependency::new(&existing.name);
+            dep.rename = existing.rename;
+            dep.source = existing.source;
+            dep.version = req.map(String::from).or(existing.version);
+            dep
+        } else {
+            let existing = existing.filter(|d| {
+                d.source == DependencySource::Registry
+                    && (opts.registry.is_none() || d.registry == opts.registry)
+            });
+            // Unless it is renamed again, a renamed dependency which is
+            // already there stays a dependency on the same package.
+            let (name, rename) = match &existing {
+                Some(d) if opts.rename.is_none() => (d.name.as_str(), d.rename.clone()),
+                _ => (name, None),
+            };
+            let mut dep = toml_mut::Dependency::new(name);
+            dep.rename = rename;
+            dep.registry = opts
+                .registry
+                .clone()
+                .or_else(|| existing.as_ref().and_then(|d| d.registry.clone()));
+            // Without an explicit requirement, a dependency which is already
+            // there keeps the requirement it has.
+            dep.version = req
+                .map(String::from)
+                .or_else(|| existing.as_ref().and_then(|d| d.version.clone()));
+            let latest = query_latest(config, name, dep.version.as_deref(), &dep.registry)?;
+            if dep.version.is_none() {
+                dep.version = Some(latest.version().to_string());
+            }
+            summary = Some(latest);
+            dep
+        }
+    };
+
+    if opts.rename.is_some() {
+        dep.rename = opts.rename.clone();
+    }
+    if !opts.features.is_empty() {
+        dep.features = Some(opts.features.clone());
+    }
+    if opts.optional {
+        dep.optional = Some(true);
+    }
+
+    if let Some(summary) = &summary {
+        for feature in &opts.features {
+            let is_optional_dep = summary
+                .dependencies()
+                .iter()
+                .any(|d| d.is_optional() && d.name_in_toml().as_str() == feature);
+            if !summary.features().contains_key(feature.as_str()) && !is_optional_dep {
+                bail!(
+                    "`{}` v{} does not have a feature named `{}`",
+                    summary.name(),
+                    summary.version(),
+                    feature
+                );
+            }
+        }
+    }
+
+    let description = match (&dep.source, &dep.version) {
+        (DependencySource::Path(_), _) => format!("{} (local)", dep.name),
+        (DependencySource::Git { .. }, _) => format!("{} (git)", dep.name),
+        (DependencySource::Workspace, _) => format!("{} (workspace)", dep.name),
+        (DependencySource::Registry, Some(v)) if v.starts_with(|c: char| c.is_ascii_digit()) => {
+            format!("{} v{}", dep.name, v)
+        }
+        (DependencySource::Registry, Some(v)) => format!("{} {}", dep.name, v),
+        (DependencySource::Registry, None) => dep.name.clone(),
+    };
+    let description = match &dep.rename {
+        Some(rename) => format!("{} as {}", description, rename),
+        None => description,
+    };
+    config
+        .shell()
+        .status("Adding", format!("{} to {}", description, opts.table))?;
+
+    Ok(dep)
+}
+
+/// Returns the newest version of `name` in the registry matching `req`.
+///
+/// Like the resolver, this skips yanked versions. Without a `req`,
+/// pre-releases are only picked if there is nothing else.
+fn query_latest(
+    config: &Config,
+    name: &str,
+    req: Option<&str>,
+    registry: &Option<String>,
+) -> CargoResult<Summary> {
+    let source_id = match registry {
+        Some(registry) => SourceId::alt_registry(config, registry)?,
+        None => SourceId::crates_io(config)?,
+    };
+    let dep = Dependency::parse_no_deprecated(name, req, source_id)?;
+
+    // This may update the index and takes the package cache lock to do so,
+    // as other Cargo commands do.
+    let _lock = config.acquire_package_cache_lock()?;
+    let map = SourceConfigMap::new(config)?;
+    let mut source = map.load(source_id, &HashSet::new())?;
+    source
+        .update()
+        .chain_err(|| format!("failed to update {}", source_id))?;
+    let mut summaries = source.query_vec(&dep)?;
+    if req.is_none() && summaries.iter().any(|s| !s.version().is_prerelease()) {
+        summaries.retain(|s| !s.version().is_prerelease());
+    }
+    match summaries.into_iter().max_by_key(|s| s.package_id()) {
+        Some(summary) => Ok(summary),
+        None => match req {
+            Some(req) => bail!(
+                "could not find `{}` in {} with version `{}`",
+                name,
+                source_id,
+                req
+            ),
+            None => bail!("could not find `{}` in {}", name, source_id),
+        },
+    }
+}
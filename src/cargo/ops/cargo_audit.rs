@@ -0,0 +1,442 @@
+//! Implementation of `cargo audit`, which checks the packages in the lock
+//! file against a database of security advisories in the [RustSec] format.
+//!
+//! The database is a directory with one file per advisory, at
+//! `crates/<package>/<id>.md` (a TOML front matter in a ```` ```toml ````
+//! block followed by a Markdown description whose first heading is the
+//! title) or at `crates/<package>/<id>.toml` (the older format). It is either
+//! a local directory, or a git repository fetched like git dependencies are.
+//!
+//! [RustSec]: https://github.com/RustSec/advisory-db
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use crate::core::{GitReference, PackageId, Resolve, SourceId, Workspace};
+use crate::drop_println;
+use crate::ops;
+use crate::sources::GitSource;
+use crate::util::config::ConfigRelativePath;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{Config, IntoUrl};
+
+/// The database used when `audit.database` and `audit.database-url` aren't
+/// set.
+const DEFAULT_DATABASE_URL: &str = "https://github.com/RustSec/advisory-db";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditFormat {
+    Human,
+    Json,
+}
+
+pub struct AuditOptions {
+    pub format: AuditFormat,
+    /// Advisories to ignore, in addition to the `audit.ignore` config.
+    pub ignore: Vec<String>,
+}
+
+/// The `[audit]` config table.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct AuditConfig {
+    /// A local directory with the advisory database.
+    database: Option<ConfigRelativePath>,
+    /// A git repository with the advisory database.
+    database_url: Option<String>,
+    /// Advisory IDs to ignore.
+    ignore: Option<Vec<String>>,
+    /// What to do when a package being compiled has a vulnerability:
+    /// `allow` (the default, no check is done), `warn` or `deny`.
+    build: Option<String>,
+}
+
+impl AuditConfig {
+    fn load(config: &Config) -> CargoResult<AuditConfig> {
+        config.get::<AuditConfig>("audit")
+    }
+
+    /// Parses `audit.build`, returning `None` if compiling isn't checked,
+    /// and otherwise whether vulnerabilities fail it.
+    fn build_policy(&self) -> CargoResult<Option<bool>> {
+        match self.build.as_deref() {
+            None | Some("allow") => Ok(None),
+            Some("warn") => Ok(Some(false)),
+            Some("deny") => Ok(Some(true)),
+            Some(other) => anyhow::bail!(
+                "invalid value for `audit.build`: `{}`, expected `allow`, `warn` or `deny`",
+                other
+            ),
+        }
+    }
+}
+
+/// An advisory about a package.
+#[derive(Debug)]
+struct Advisory {
+    id: String,
+    package: String,
+    title: String,
+    date: Option<String>,
+    url: Option<String>,
+    /// Set for advisories which aren't about a vulnerability, such as
+    /// `unmaintained` or `unsound`.
+    informational: Option<String>,
+    /// The versions which have the fix.
+    patched: Vec<VersionReq>,
+    /// The versions which never had the issue.
+    unaffected: Vec<VersionReq>,
+}
+
+impl Advisory {
+    fn affects(&self, version: &Version) -> bool {
+        !self
+            .patched
+            .iter()
+            .chain(&self.unaffected)
+            .any(|req| req.matches(version))
+    }
+}
+
+#[derive(Deserialize)]
+struct AdvisoryFile {
+    advisory: AdvisoryMetadata,
+    #[serde(default)]
+    versions: AdvisoryVersions,
+}
+
+#[derive(Deserialize)]
+struct AdvisoryMetadata {
+    id: String,
+    package: String,
+    /// Only in the older format, the newer one has it in the Markdown.
+    title: Option<String>,
+    date: Option<String>,
+    url: Option<String>,
+    informational: Option<String>,
+    withdrawn: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+struct AdvisoryVersions {
+    #[serde(default)]
+    patched: Vec<String>,
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+/// The advisories of a database, by package name.
+struct AdvisoryDatabase {
+    advisories: HashMap<String, Vec<Advisory>>,
+}
+
+impl AdvisoryDatabase {
+    /// Loads the database configured in `audit`.
+    ///
+    /// A git repository is fetched first if `fetch` is set, unless offline.
+    /// Otherwise the last fetched copy is used, and `None` is returned if
+    /// there is none.
+    fn load(
+        config: &Config,
+        audit: &AuditConfig,
+        fetch: bool,
+    ) -> CargoResult<Option<AdvisoryDatabase>> {
+        let root = match (&audit.database, &audit.database_url) {
+            (Some(_), Some(_)) => anyhow::bail!(
+                "only one of `audit.database` and `audit.database-url` may be specified"
+            ),
+            (Some(path), None) => {
+                let path = path.resolve_path(config);
+                if !path.is_dir() {
+                    anyhow::bail!("advisory database `{}` does not exist", path.display());
+                }
+                path
+            }
+            (None, url) => {
+                let url = url.as_deref().unwrap_or(DEFAULT_DATABASE_URL).into_url()?;
+                let source_id = SourceId::for_git(&url, GitReference::DefaultBranch)?;
+                let _lock = config.acquire_package_cache_lock()?;
+                let mut source = GitSource::new(source_id, config)?;
+                let root = if fetch {
+                    source.checkout().map(Some)
+                } else {
+                    source.checkout_cached()
+                };
+                match root.chain_err(|| format!("failed to fetch advisory database `{}`", url))? {
+                    Some(root) => root,
+                    None => return Ok(None),
+                }
+            }
+        };
+        AdvisoryDatabase::read(&root)
+            .map(Some)
+            .chain_err(|| format!("failed to read advisory database `{}`", root.display()))
+    }
+
+    fn read(root: &Path) -> CargoResult<AdvisoryDatabase> {
+        let mut advisories: HashMap<String, Vec<Advisory>> = HashMap::new();
+        for dir in sorted_entries(&root.join("crates"))? {
+            if !dir.is_dir() {
+                continue;
+            }
+            for path in sorted_entries(&dir)? {
+                let advisory = match path.extension().and_then(|ext| ext.to_str()) {
+                    Some("md") | Some("toml") => parse_advisory(&path)
+                        .chain_err(|| format!("failed to parse `{}`", path.display()))?,
+                    _ => continue,
+                };
+                if let Some(advisory) = advisory {
+                    advisories
+                        .entry(advisory.package.clone())
+                        .or_default()
+                        .push(advisory);
+                }
+            }
+        }
+        Ok(AdvisoryDatabase { advisories })
+    }
+
+    /// Returns the advisories affecting the crates.io packages in `resolve`,
+    /// except the `ignored` ones.
+    fn check<'a>(
+        &'a self,
+        resolve: &Resolve,
+        ignored: &[String],
+    ) -> Vec<(PackageId, &'a Advisory)> {
+        let mut found = Vec::new();
+        let mut ids: Vec<PackageId> = resolve
+            .iter()
+            .filter(|id| id.source_id().is_default_registry())
+            .collect();
+        ids.sort();
+        for id in ids {
+            let advisories = match self.advisories.get(id.name().as_str()) {
+                Some(advisories) => advisories,
+                None => continue,
+            };
+            for advisory in advisories {
+                if advisory.affects(id.version()) && !ignored.contains(&advisory.id) {
+                    found.push((id, advisory));
+                }
+            }
+        }
+        found
+    }
+}
+
+fn sorted_entries(dir: &Path) -> CargoResult<Vec<PathBuf>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir).chain_err(|| format!("failed to read `{}`", dir.display()))? {
+        entries.push(entry?.path());
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// Parses the advisory at `path`, returning `None` if it was withdrawn.
+fn parse_advisory(path: &Path) -> CargoResult<Option<Advisory>> {
+    let contents = fs::read_to_string(path)?;
+    let (front_matter, description) = if path.extension().unwrap() == "md" {
+        let rest = contents
+            .trim_start()
+            .strip_prefix("```toml")
+            .ok_or_else(|| anyhow::format_err!("expected a ```toml front matter"))?;
+        let end = rest
+            .find("\n```")
+            .ok_or_else(|| anyhow::format_err!("unterminated front matter"))?;
+        (&rest[..end], Some(&rest[end + 4..]))
+    } else {
+        (contents.as_str(), None)
+    };
+    let file: AdvisoryFile = toml::from_str(front_matter)?;
+    let metadata = file.advisory;
+    if metadata.withdrawn.is_some() {
+        return Ok(None);
+    }
+
+    let title = description
+        .and_then(|d| d.lines().find_map(|line| line.strip_prefix("# ")))
+        .map(|title| title.trim().to_string())
+        .or(metadata.title)
+        .unwrap_or_default();
+    let parse_reqs = |reqs: &[String]| -> CargoResult<Vec<VersionReq>> {
+        reqs.iter()
+            .map(|req| {
+                VersionReq::parse(req)
+                    .chain_err(|| format!("invalid version requirement `{}`", req))
+            })
+            .collect()
+    };
+    Ok(Some(Advisory {
+        patched: parse_reqs(&file.versions.patched)?,
+        unaffected: parse_reqs(&file.versions.unaffected)?,
+        id: metadata.id,
+        package: metadata.package,
+        title,
+        date: metadata.date,
+        url: metadata.url,
+        informational: metadata.informational,
+    }))
+}
+
+#[derive(Serialize)]
+struct AuditReport<'a> {
+    version: u32,
+    vulnerabilities: Vec<Finding<'a>>,
+    warnings: Vec<Finding<'a>>,
+}
+
+#[derive(Serialize)]
+struct Finding<'a> {
+    id: &'a str,
+    package: PackageId,
+    title: &'a str,
+    date: Option<&'a str>,
+    url: Option<&'a str>,
+    /// `None` for vulnerabilities, otherwise the kind of informational
+    /// advisory.
+    informational: Option<&'a str>,
+    /// The requirements which the fixed versions match.
+    patched: Vec<String>,
+}
+
+impl<'a> Finding<'a> {
+    fn new(package: PackageId, advisory: &'a Advisory) -> Finding<'a> {
+        Finding {
+            id: &advisory.id,
+            package,
+            title: &advisory.title,
+            date: advisory.date.as_deref(),
+            url: advisory.url.as_deref(),
+            informational: advisory.informational.as_deref(),
+            patched: advisory.patched.iter().map(|req| req.to_string()).collect(),
+        }
+    }
+
+    fn solution(&self) -> String {
+        if self.patched.is_empty() {
+            "no patched version available".to_string()
+        } else {
+            format!("upgrade to {}", self.patched.join(" or "))
+        }
+    }
+}
+
+/// Checks the packages in the lock file of `ws` against the advisory
+/// database, printing the ones affected and failing if any is vulnerable.
+pub fn audit(ws: &Workspace<'_>, opts: &AuditOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let (_, resolve) = ops::resolve_ws(ws)?;
+    let audit_config = AuditConfig::load(config)?;
+    let db = AdvisoryDatabase::load(config, &audit_config, true)?
+        .expect("the advisory database is fetched if missing");
+
+    let mut ignored = audit_config.ignore.clone().unwrap_or_default();
+    ignored.extend(opts.ignore.iter().cloned());
+    let (warnings, vulnerabilities): (Vec<_>, Vec<_>) = db
+        .check(&resolve, &ignored)
+        .into_iter()
+        .map(|(id, advisory)| Finding::new(id, advisory))
+        .partition(|finding| finding.informational.is_some());
+
+    let count = vulnerabilities.len();
+    match opts.format {
+        AuditFormat::Json => {
+            config.shell().print_json(&AuditReport {
+                version: 1,
+                vulnerabilities,
+                warnings,
+            });
+        }
+        AuditFormat::Human => {
+            for finding in vulnerabilities.iter().chain(&warnings) {
+                print_finding(config, finding);
+            }
+            if count == 0 && warnings.is_empty() {
+                config.shell().note("no vulnerable packages found")?;
+            }
+        }
+    }
+    match count {
+        0 => Ok(()),
+        1 => anyhow::bail!("1 vulnerability found"),
+        n => anyhow::bail!("{} vulnerabilities found", n),
+    }
+}
+
+fn print_finding(config: &Config, finding: &Finding<'_>) {
+    let kind = finding.informational.unwrap_or("vulnerability");
+    drop_println!(config, "{}: {}", finding.id, finding.title);
+    drop_println!(config, "  kind:     {}", kind);
+    drop_println!(config, "  package:  {}", finding.package);
+    if let Some(date) = finding.date {
+        drop_println!(config, "  date:     {}", date);
+    }
+    if let Some(url) = finding.url {
+        drop_println!(config, "  url:      {}", url);
+    }
+    drop_println!(config, "  solution: {}", finding.solution());
+    drop_println!(config);
+}
+
+/// Applies the `audit.build` policy to the packages of `resolve`, which are
+/// about to be compiled.
+pub fn audit_build(ws: &Workspace<'_>, resolve: &Resolve) -> CargoResult<()> {
+    let config = ws.config();
+    let audit_config = AuditConfig::load(config)?;
+    let deny = match audit_config.build_policy()? {
+        Some(deny) => deny,
+        None => return Ok(()),
+    };
+    // Compiling doesn't fetch the database, `cargo audit` and `cargo update`
+    // do, so that builds work without network access and don't wait on it.
+    let db = match AdvisoryDatabase::load(config, &audit_config, false)? {
+        Some(db) => db,
+        None => {
+            config.shell().warn(
+                "the advisory database has not been fetched yet, so dependencies \
+                 were not checked against it\n\
+                 Run `cargo audit` or `cargo update` to fetch it.",
+            )?;
+            return Ok(());
+        }
+    };
+    let ignored = audit_config.ignore.clone().unwrap_or_default();
+    let vulnerabilities: Vec<_> = db
+        .check(resolve, &ignored)
+        .into_iter()
+        .filter(|(_, advisory)| advisory.informational.is_none())
+        .collect();
+    for (id, advisory) in &vulnerabilities {
+        let finding = Finding::new(*id, advisory);
+        config.shell().warn(format!(
+            "package `{}` is affected by {}: {} ({})",
+            id,
+            finding.id,
+            finding.title,
+            finding.solution()
+        ))?;
+    }
+    if deny && !vulnerabilities.is_empty() {
+        anyhow::bail!(
+            "refusing to build packages with vulnerabilities, as `audit.build` is `deny`\n\
+             Upgrade the affected packages, or add the advisories to `audit.ignore`."
+        );
+    }
+    Ok(())
+}
+
+/// Fetches the advisory database used by `audit.build`, if it's a git
+/// repository, as compiling only uses the last fetched copy.
+pub fn update_advisory_database(config: &Config) -> CargoResult<()> {
+    let audit_config = AuditConfig::load(config)?;
+    if audit_config.build_policy()?.is_none() || audit_config.database.is_some() {
+        return Ok(());
+    }
+    AdvisoryDatabase::load(config, &audit_config, true)?;
+    Ok(())
+}
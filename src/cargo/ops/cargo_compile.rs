@@ -74,6 +74,9 @@ pub struct CompileOptions {
     /// Whether the `--document-private-items` flags was specified and should
     /// be forwarded to `rustdoc`.
     pub rustdoc_document_private_items: bool,
+    /// Whether to refuse building packages whose `rust-version` is newer
+    /// than the compiler, which `--ignore-rust-version` turns off.
+    pub honor_rust_version: bool,
 }
 
 impl<'a> CompileOptions {
@@ -91,6 +94,7 @@ impl<'a> CompileOptions {
             target_rustc_args: None,
             local_rustdoc_args: None,
             rustdoc_document_private_items: false,
+            honor_rust_version: true,
         })
     }
 }
@@ -286,6 +290,7 @@ pub fn create_bcx<'a, 'cfg>(
         ref target_rustc_args,
         ref local_rustdoc_args,
         rustdoc_document_private_items,
+        honor_rust_version,
     } = *options;
     let config = ws.config();
 
@@ -335,6 +340,7 @@ pub fn create_bcx<'a, 'cfg>(
         targeted_resolve: resolve,
         resolved_features,
     } = resolve;
+    ops::audit_build(ws, &resolve)?;
 
     let std_resolve_features = if let Some(crates) = &config.cli_unstable().build_std {
         if build_config.build_plan {
@@ -499,6 +505,12 @@ pub fn create_bcx<'a, 'cfg>(
         interner,
     )?;
 
+    if honor_rust_version {
+        check_rust_version(&target_data, &unit_graph)?;
+    }
+    let packages: Vec<&Package> = unit_graph.keys().map(|unit| &unit.pkg).collect();
+    ops::check_license_policy(ws, &packages)?;
+
     let bcx = BuildContext::new(
         ws,
         pkg_set,
@@ -513,6 +525,42 @@ pub fn create_bcx<'a, 'cfg>(
     Ok(bcx)
 }
 
+/// Checks that the compiler is at least as new as the `rust-version` of
+/// every package which is going to be built.
+fn check_rust_version(
+    target_data: &RustcTargetData,
+    unit_graph: &unit_graph::UnitGraph,
+) -> CargoResult<()> {
+    // Pre-releases count as the release they lead to, so that a nightly can
+    // build a package which requires the upcoming version.
+    let rustc_version = &target_data.rustc.version;
+    let untagged_version = semver::Version::new(
+        rustc_version.major,
+        rustc_version.minor,
+        rustc_version.patch,
+    );
+    let mut packages: Vec<_> = unit_graph.keys().map(|unit| &unit.pkg).collect();
+    packages.sort_by_key(|pkg| pkg.package_id());
+    packages.dedup_by_key(|pkg| pkg.package_id());
+    for pkg in packages {
+        let rust_version = match pkg.rust_version() {
+            Some(rust_version) => rust_version,
+            None => continue,
+        };
+        let req = semver::VersionReq::parse(&format!(">={}", rust_version))?;
+        if !req.matches(&untagged_version) {
+            anyhow::bail!(
+                "package `{}` cannot be built because it requires rustc {} or newer, \
+                 while the currently active rustc version is {}",
+                pkg,
+                rust_version,
+                rustc_version
+            );
+        }
+    }
+    Ok(())
+}
+
 impl FilterRule {
     pub fn new(targets: Vec<String>, all: bool) -> FilterRule {
         if all {
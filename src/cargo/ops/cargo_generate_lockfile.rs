@@ -1,12 +1,12 @@
 use std::collections::{BTreeMap, HashSet};
 
 use log::debug;
-use termcolor::Color::{self, Cyan, Green, Red};
+use termcolor::Color::{self, Cyan, Green, Red, Yellow};
 
 use crate::core::registry::PackageRegistry;
-use crate::core::resolver::ResolveOpts;
-use crate::core::{PackageId, PackageIdSpec};
-use crate::core::{Resolve, SourceId, Workspace};
+use crate::core::resolver::{is_rust_version_compatible, ResolveOpts};
+use crate::core::{PackageId, PackageIdSpec, Registry};
+use crate::core::{Resolve, SourceId, Summary, Workspace};
 use crate::ops;
 use crate::util::config::Config;
 use crate::util::CargoResult;
@@ -21,15 +21,23 @@ pub struct UpdateOptions<'a> {
 
 pub fn generate_lockfile(ws: &Workspace<'_>) -> CargoResult<()> {
     let mut registry = PackageRegistry::new(ws.config())?;
+    // Normally the lock file is generated from scratch, but when merging a
+    // conflicted one, the versions locked on either side are kept.
+    let previous_resolve = if ws.merge_lock_conflicts() {
+        ops::load_pkg_lockfile(ws)?
+    } else {
+        None
+    };
     let mut resolve = ops::resolve_with_previous(
         &mut registry,
         ws,
         &ResolveOpts::everything(),
-        None,
+        previous_resolve.as_ref(),
         None,
         &[],
         true,
     )?;
+    report_held_back(ws, &mut registry, &resolve, previous_resolve.as_ref(), None)?;
     ops::write_pkg_lockfile(ws, &mut resolve)?;
     Ok(())
 }
@@ -50,6 +58,7 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
     // Updates often require a lot of modifications to the registry, so ensure
     // that we're synchronized against other Cargos.
     let _lock = ws.config().acquire_package_cache_lock()?;
+    ops::update_advisory_database(ws.config())?;
 
     let previous_resolve = match ops::load_pkg_lockfile(ws)? {
         Some(resolve) => resolve,
@@ -122,6 +131,13 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
         &[],
         true,
     )?;
+    report_held_back(
+        ws,
+        &mut registry,
+        &resolve,
+        Some(&previous_resolve),
+        Some(&to_avoid),
+    )?;
 
     // Summarize what is changing for the user.
     let print_change = |status: &str, msg: String, color: Color| {
@@ -246,3 +262,78 @@ pub fn update_lockfile(ws: &Workspace<'_>, opts: &UpdateOptions<'_>) -> CargoRes
         changes.into_iter().map(|(_, v)| v).collect()
     }
 }
+
+/// Tells the user about registry dependencies for which a newer matching
+/// version exists, but wasn't picked because it requires a newer Rust than
+/// the workspace under `incompatible-rust-versions = "fallback"`.
+///
+/// Packages kept at their version from `previous` (those not in `to_avoid`)
+/// aren't reported, as the lock file is what holds them back.
+fn report_held_back(
+    ws: &Workspace<'_>,
+    registry: &mut PackageRegistry<'_>,
+    resolve: &Resolve,
+    previous: Option<&Resolve>,
+    to_avoid: Option<&HashSet<PackageId>>,
+) -> CargoResult<()> {
+    let rust_version = match ops::resolver_rust_version(ws)? {
+        Some(rust_version) => rust_version,
+        None => return Ok(()),
+    };
+    let locked: HashSet<PackageId> = previous
+        .into_iter()
+        .flat_map(|previous| previous.iter())
+        .filter(|id| to_avoid.map_or(true, |to_avoid| !to_avoid.contains(id)))
+        .collect();
+
+    // Map the held back packages to the newest version they'd otherwise be.
+    let mut held_back: BTreeMap<PackageId, Summary> = BTreeMap::new();
+    for pkg in resolve.iter() {
+        for (dep_id, deps) in resolve.deps(pkg) {
+            if !dep_id.source_id().is_registry() || locked.contains(&dep_id) {
+                continue;
+            }
+            for dep in deps {
+                let candidates = registry.query_vec(dep, false)?;
+                let newest = candidates.iter().max_by_key(|s| s.version());
+                let newest_compatible = candidates
+                    .iter()
+                    .filter(|s| is_rust_version_compatible(s, &rust_version))
+                    .max_by_key(|s| s.version());
+                // Only the fallback holds a package back at the newest
+                // version which is compatible, anything older is picked for
+                // another reason.
+                let newest = match (newest, newest_compatible) {
+                    (Some(newest), Some(compatible))
+                        if compatible.version() == dep_id.version()
+                            && newest.version() > dep_id.version() =>
+                    {
+                        newest
+                    }
+                    _ => continue,
+                };
+                match held_back.get(&dep_id) {
+                    Some(prev) if prev.version() >= newest.version() => {}
+                    _ => {
+                        held_back.insert(dep_id, newest.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    for (pkg, newest) in held_back {
+        ws.config().shell().status_with_color(
+            "Holding",
+            format!(
+                "{} v{} back, v{} requires rustc {}",
+                pkg.name(),
+                pkg.version(),
+                newest.version(),
+                newest.rust_version().unwrap()
+            ),
+            Yellow,
+        )?;
+    }
+    Ok(())
+}
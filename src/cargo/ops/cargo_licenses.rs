@@ -0,0 +1,272 @@
+//! Implementation of `cargo licenses`, which reports the licenses of the
+//! packages in the dependency graph, and of the `[licenses]` config policy,
+//! which is also enforced on the packages being compiled.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::{Package, PackageId, Workspace};
+use crate::drop_println;
+use crate::ops;
+use crate::util::errors::CargoResult;
+use crate::util::spdx::{License, LicenseExpr};
+use crate::util::Config;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LicensesFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+pub struct LicensesOptions {
+    pub format: LicensesFormat,
+}
+
+/// The `[licenses]` config table.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct LicensePolicy {
+    /// If set, the only licenses which dependencies may use.
+    allow: Option<Vec<String>>,
+    /// Licenses which dependencies may not use.
+    deny: Option<Vec<String>>,
+    /// Names of the packages the policy doesn't apply to, such as the ones
+    /// whose license file was reviewed.
+    ignore: Option<Vec<String>>,
+}
+
+impl LicensePolicy {
+    fn load(config: &Config) -> CargoResult<LicensePolicy> {
+        config.get::<LicensePolicy>("licenses")
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.allow.is_some() || self.deny.is_some()
+    }
+
+    /// Whether `license` may be used. A list entry matches a license if it's
+    /// its identifier, as exceptions only add permissions, or if it's the
+    /// whole license, such as `GPL-2.0 WITH Classpath-exception-2.0`.
+    fn accepts(&self, license: &License) -> bool {
+        let full = license.to_string();
+        let id = if license.or_later {
+            format!("{}+", license.id)
+        } else {
+            license.id.clone()
+        };
+        let listed = |list: &Option<Vec<String>>| {
+            list.iter()
+                .flatten()
+                .any(|entry| *entry == id || *entry == full)
+        };
+        !listed(&self.deny) && (self.allow.is_none() || listed(&self.allow))
+    }
+
+    /// Returns why `pkg` doesn't comply with the policy, if it doesn't.
+    fn violation(&self, ws: &Workspace<'_>, pkg: &PackageLicense<'_>) -> Option<String> {
+        let ignored = self
+            .ignore
+            .iter()
+            .flatten()
+            .any(|name| *name == pkg.id.name().as_str());
+        if !self.is_enabled() || ignored || ws.members().any(|m| m.package_id() == pkg.id) {
+            return None;
+        }
+        match (&pkg.expr, pkg.license, pkg.license_file) {
+            (Some(expr), Some(license), _) => {
+                if expr.is_satisfied_by(&|license| self.accepts(license)) {
+                    None
+                } else {
+                    Some(format!("its license `{}` is not allowed", license))
+                }
+            }
+            (None, Some(license), _) => Some(format!(
+                "its license `{}` is not a valid SPDX expression",
+                license
+            )),
+            (None, None, Some(file)) => Some(format!(
+                "it only has a license file (`{}`), add it to `licenses.ignore` \
+                 once it was reviewed",
+                file
+            )),
+            _ => Some("it has no license".to_string()),
+        }
+    }
+}
+
+/// What a package declares about its license.
+#[derive(Serialize)]
+struct PackageLicense<'a> {
+    id: PackageId,
+    license: Option<&'a str>,
+    license_file: Option<&'a str>,
+    /// The licenses of the `license` expression.
+    licenses: Vec<String>,
+    /// Why the `license` expression couldn't be parsed.
+    error: Option<String>,
+    /// Why the package doesn't comply with the `[licenses]` policy.
+    violation: Option<String>,
+    #[serde(skip)]
+    expr: Option<LicenseExpr>,
+}
+
+impl<'a> PackageLicense<'a> {
+    fn new(pkg: &'a Package) -> PackageLicense<'a> {
+        let metadata = pkg.manifest().metadata();
+        let license = metadata.license.as_deref();
+        let (expr, error) = match license.map(LicenseExpr::parse) {
+            Some(Ok(expr)) => (Some(expr), None),
+            Some(Err(e)) => (None, Some(e.to_string())),
+            None => (None, None),
+        };
+        PackageLicense {
+            id: pkg.package_id(),
+            license,
+            license_file: metadata.license_file.as_deref(),
+            licenses: expr
+                .iter()
+                .flat_map(|expr| expr.licenses())
+                .map(|license| license.to_string())
+                .collect(),
+            error,
+            violation: None,
+            expr,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct LicensesReport<'a> {
+    version: u32,
+    packages: Vec<PackageLicense<'a>>,
+}
+
+/// Reports the licenses of every package in the dependency graph of `ws`,
+/// failing if any doesn't comply with the `[licenses]` policy.
+pub fn licenses(ws: &Workspace<'_>, opts: &LicensesOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let policy = LicensePolicy::load(config)?;
+    let (package_set, resolve) = ops::resolve_ws(ws)?;
+    let mut packages = package_set.get_many(resolve.iter())?;
+    packages.sort_by_key(|pkg| (pkg.name(), pkg.package_id()));
+
+    let mut licenses: Vec<PackageLicense<'_>> = packages
+        .iter()
+        .map(|pkg| PackageLicense::new(pkg))
+        .collect();
+    for pkg in &mut licenses {
+        pkg.violation = policy.violation(ws, pkg);
+    }
+
+    match opts.format {
+        LicensesFormat::Text => print_text(config, &licenses),
+        LicensesFormat::Csv => print_csv(config, &licenses),
+        LicensesFormat::Json => {}
+    }
+    let violations: Vec<String> = licenses
+        .iter()
+        .filter_map(|pkg| Some(format!("  {}: {}", pkg.id, pkg.violation.as_ref()?)))
+        .collect();
+    if opts.format == LicensesFormat::Json {
+        config.shell().print_json(&LicensesReport {
+            version: 1,
+            packages: licenses,
+        });
+    }
+    if !violations.is_empty() {
+        anyhow::bail!(
+            "the license policy is violated by {} package{}:\n{}",
+            violations.len(),
+            if violations.len() == 1 { "" } else { "s" },
+            violations.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Prints the packages under each license, followed by the ones whose
+/// license isn't known.
+fn print_text(config: &Config, packages: &[PackageLicense<'_>]) {
+    let mut groups: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut file_only = Vec::new();
+    let mut invalid = Vec::new();
+    let mut missing = Vec::new();
+    for pkg in packages {
+        match (pkg.license, pkg.license_file) {
+            (Some(license), _) if pkg.expr.is_none() => {
+                invalid.push(format!("{} (`{}`)", pkg.id, license))
+            }
+            (Some(_), _) => {
+                for license in &pkg.licenses {
+                    groups.entry(license).or_default().push(pkg.id.to_string());
+                }
+            }
+            (None, Some(file)) => file_only.push(format!("{} ({})", pkg.id, file)),
+            (None, None) => missing.push(pkg.id.to_string()),
+        }
+    }
+
+    let print_group = |title: &str, packages: &[String]| {
+        if packages.is_empty() {
+            return;
+        }
+        drop_println!(config, "{} ({})", title, packages.len());
+        for pkg in packages {
+            drop_println!(config, "    {}", pkg);
+        }
+    };
+    for (license, packages) in &groups {
+        print_group(license, packages);
+    }
+    print_group("License file only", &file_only);
+    print_group("Invalid license expression", &invalid);
+    print_group("No license", &missing);
+}
+
+fn print_csv(config: &Config, packages: &[PackageLicense<'_>]) {
+    let escape = |field: &str| {
+        if field.contains(&[',', '"', '\n'][..]) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    };
+    drop_println!(config, "name,version,source,license,license_file,violation");
+    for pkg in packages {
+        let fields = [
+            pkg.id.name().as_str(),
+            &pkg.id.version().to_string(),
+            &pkg.id.source_id().to_string(),
+            pkg.license.unwrap_or(""),
+            pkg.license_file.unwrap_or(""),
+            pkg.violation.as_deref().unwrap_or(""),
+        ];
+        let fields: Vec<String> = fields.iter().map(|field| escape(field)).collect();
+        drop_println!(config, "{}", fields.join(","));
+    }
+}
+
+/// Checks that the `packages` about to be compiled comply with the
+/// `[licenses]` policy, if there is one.
+pub fn check_license_policy(ws: &Workspace<'_>, packages: &[&Package]) -> CargoResult<()> {
+    let policy = LicensePolicy::load(ws.config())?;
+    if !policy.is_enabled() {
+        return Ok(());
+    }
+    let mut packages: Vec<&Package> = packages.to_vec();
+    packages.sort_by_key(|pkg| pkg.package_id());
+    packages.dedup_by_key(|pkg| pkg.package_id());
+    for pkg in packages {
+        if let Some(violation) = policy.violation(ws, &PackageLicense::new(pkg)) {
+            anyhow::bail!(
+                "package `{}` doesn't comply with the license policy: {}\n\
+                 Run `cargo licenses` for the licenses of all the dependencies.",
+                pkg.package_id(),
+                violation
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,294 @@
+//! Implementation of `cargo outdated`, which reports the dependencies that
+//! have newer versions available in their registry.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use semver::{Version, VersionReq};
+use serde::Serialize;
+
+use crate::core::{Dependency, PackageId, Source, SourceId, Summary, Workspace};
+use crate::ops;
+use crate::sources::{RegistrySource, SourceConfigMap};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::interning::InternedString;
+use crate::{drop_print, drop_println};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutdatedFormat {
+    Human,
+    Json,
+}
+
+pub struct OutdatedOptions {
+    pub format: OutdatedFormat,
+    /// Only report the dependencies which workspace members depend on
+    /// directly.
+    pub root_deps_only: bool,
+}
+
+#[derive(Serialize)]
+struct OutdatedReport {
+    version: u32,
+    packages: Vec<OutdatedPackage>,
+}
+
+#[derive(Serialize)]
+struct OutdatedPackage {
+    name: InternedString,
+    source: SourceId,
+    /// The version in the lock file.
+    locked: Version,
+    /// The newest version matching every requirement on this package, `None`
+    /// if no version in the index does (such as with `[patch]`).
+    compatible: Option<Version>,
+    /// The newest version, whatever the requirements are.
+    latest: Option<Version>,
+    kind: DependencyKind,
+    dependents: Vec<PackageId>,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum DependencyKind {
+    /// A workspace member depends on it.
+    Direct,
+    /// Only other dependencies depend on it.
+    Transitive,
+}
+
+/// The requirements that the resolved graph places on a registry package.
+#[derive(Default)]
+struct Requirements {
+    dependents: BTreeSet<PackageId>,
+    /// Whether a workspace member depends on it.
+    direct: bool,
+    reqs: Vec<VersionReq>,
+}
+
+/// Compares the versions in the lock file of `ws` with the ones available in
+/// the registries, and prints the packages which could be upgraded.
+pub fn outdated(ws: &Workspace<'_>, opts: &OutdatedOptions) -> CargoResult<()> {
+    let config = ws.config();
+    let (package_set, resolve) = ops::resolve_ws(ws)?;
+
+    // Updating the index takes the package cache lock, as other commands do.
+    let _lock = config.acquire_package_cache_lock()?;
+    let mut indexes = Indexes {
+        map: SourceConfigMap::new(config)?,
+        sources: HashMap::new(),
+        locked: resolve.iter().collect(),
+    };
+
+    // The dependencies in `resolve` are locked to the selected versions, so
+    // the requirements are taken from the manifests of the dependents.
+    let members: HashSet<PackageId> = ws.members().map(|pkg| pkg.package_id()).collect();
+    let mut wanted: BTreeMap<PackageId, Requirements> = BTreeMap::new();
+    for parent in resolve.iter() {
+        let is_member = members.contains(&parent);
+        if opts.root_deps_only && !is_member {
+            continue;
+        }
+        let mut unlocked = None;
+        for (pkg_id, locked_deps) in resolve.deps(parent) {
+            if !pkg_id.source_id().is_registry() {
+                continue;
+            }
+            if unlocked.is_none() {
+                unlocked = Some(if parent.source_id().is_registry() {
+                    indexes
+                        .summaries(parent)?
+                        .into_iter()
+                        .find(|summary| summary.package_id() == parent)
+                        .map(|summary| summary.dependencies().to_vec())
+                        .unwrap_or_default()
+                } else {
+                    package_set.get_one(parent)?.dependencies().to_vec()
+                });
+            }
+            let mut reqs: Vec<VersionReq> = unlocked
+                .iter()
+                .flatten()
+                .filter(|dep| dep.matches_id(pkg_id))
+                .map(|dep| dep.version_req().clone())
+                .collect();
+            if reqs.is_empty() {
+                // Such as when `[patch]` points the dependency elsewhere.
+                reqs.extend(locked_deps.iter().map(|dep| dep.version_req().clone()));
+            }
+            let requirements = wanted.entry(pkg_id).or_default();
+            requirements.dependents.insert(parent);
+            requirements.direct |= is_member;
+            requirements.reqs.extend(reqs);
+        }
+    }
+
+    let mut packages = Vec::new();
+    for (&pkg_id, requirements) in &wanted {
+        let versions: Vec<Version> = indexes
+            .summaries(pkg_id)?
+            .into_iter()
+            .map(|summary| summary.version().clone())
+            .collect();
+        if versions.is_empty() {
+            continue;
+        }
+
+        let locked = pkg_id.version();
+        let compatible = versions
+            .iter()
+            .filter(|v| requirements.reqs.iter().all(|req| req.matches(v)))
+            .max()
+            .cloned();
+        // Like `cargo add`, only suggest pre-releases to those already on one.
+        let latest = versions
+            .iter()
+            .filter(|v| !v.is_prerelease() || locked.is_prerelease())
+            .max()
+            .cloned();
+        let is_newer = |v: &Option<Version>| v.as_ref().map_or(false, |v| v > locked);
+        if !is_newer(&compatible) && !is_newer(&latest) {
+            continue;
+        }
+        packages.push(OutdatedPackage {
+            name: pkg_id.name(),
+            source: pkg_id.source_id(),
+            locked: locked.clone(),
+            compatible,
+            latest,
+            kind: if requirements.direct {
+                DependencyKind::Direct
+            } else {
+                DependencyKind::Transitive
+            },
+            dependents: requirements.dependents.iter().cloned().collect(),
+        });
+    }
+
+    match opts.format {
+        OutdatedFormat::Json => {
+            config.shell().print_json(&OutdatedReport {
+                version: 1,
+                packages,
+            });
+        }
+        OutdatedFormat::Human if packages.is_empty() => {
+            config.shell().note("all dependencies are up to date")?;
+        }
+        OutdatedFormat::Human => print_table(ws, &packages),
+    }
+    Ok(())
+}
+
+/// The indexes of the registries in the dependency graph, loaded on demand.
+struct Indexes<'cfg> {
+    map: SourceConfigMap<'cfg>,
+    /// `None` for the registries which are replaced with something that
+    /// isn't a registry, such as vendored sources, as those don't know about
+    /// other versions.
+    sources: HashMap<SourceId, Option<RegistrySource<'cfg>>>,
+    /// The packages in the lock file, which are still candidates if they were
+    /// yanked since, as they are for the resolver.
+    locked: HashSet<PackageId>,
+}
+
+impl<'cfg> Indexes<'cfg> {
+    /// Returns the versions of the package `id` in its registry index which
+    /// the resolver could pick.
+    ///
+    /// These come from `RegistryIndex::query_inner`, so yanked versions are
+    /// skipped unless locked, and with `--offline` only the downloaded
+    /// versions are returned if there are any.
+    fn summaries(&mut self, id: PackageId) -> CargoResult<Vec<Summary>> {
+        let source_id = id.source_id().with_precise(None);
+        let source = match self.sources.entry(source_id) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(load_index(&self.map, source_id, &self.locked)?),
+        };
+        let source = match source {
+            Some(source) => source,
+            None => return Ok(Vec::new()),
+        };
+        // Like `ReplacedSource`, present the summaries as coming from the
+        // original source.
+        let replacement = source.source_id();
+        let dep = Dependency::parse_no_deprecated(id.name(), None, replacement)?;
+        let mut summaries = Vec::new();
+        source.query(&dep, &mut |summary| {
+            summaries.push(summary.map_source(replacement, source_id))
+        })?;
+        Ok(summaries)
+    }
+}
+
+/// Returns the index of the registry `id`, following source replacement,
+/// after updating it unless offline.
+fn load_index<'cfg>(
+    map: &SourceConfigMap<'cfg>,
+    id: SourceId,
+    locked: &HashSet<PackageId>,
+) -> CargoResult<Option<RegistrySource<'cfg>>> {
+    let config = map.config();
+    let replacement = map.replacement(id)?;
+    if !replacement.is_registry() {
+        config.shell().warn(format!(
+            "skipping packages from {}, it is replaced with {} which has no index",
+            id, replacement
+        ))?;
+        return Ok(None);
+    }
+    let yanked_whitelist = locked
+        .iter()
+        .filter(|pkg_id| pkg_id.source_id() == id)
+        .map(|pkg_id| pkg_id.map_source(id, replacement))
+        .collect();
+    let mut source = replacement.load_registry(config, &yanked_whitelist)?;
+    source
+        .update()
+        .chain_err(|| format!("failed to update {}", replacement))?;
+    Ok(Some(source))
+}
+
+fn print_table(ws: &Workspace<'_>, packages: &[OutdatedPackage]) {
+    let config = ws.config();
+    let show = |v: &Option<Version>| match v {
+        Some(v) => v.to_string(),
+        None => "-".to_string(),
+    };
+    let mut rows = vec![[
+        "Name".to_string(),
+        "Locked".to_string(),
+        "Compatible".to_string(),
+        "Latest".to_string(),
+        "Kind".to_string(),
+    ]];
+    for pkg in packages {
+        let kind = match pkg.kind {
+            DependencyKind::Direct => "direct",
+            DependencyKind::Transitive => "transitive",
+        };
+        rows.push([
+            pkg.name.to_string(),
+            pkg.locked.to_string(),
+            show(&pkg.compatible),
+            show(&pkg.latest),
+            kind.to_string(),
+        ]);
+    }
+
+    let mut widths = [0; 5];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in &rows {
+        for (i, (width, cell)) in widths.iter().zip(row).enumerate() {
+            if i + 1 == row.len() {
+                drop_println!(config, "{}", cell);
+            } else {
+                drop_print!(config, "{:width$}  ", cell, width = width);
+            }
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use std::path::Path;
+
+use crate::util::errors::CargoResult;
+use crate::util::toml_mut::{DepTable, LocalManifest};
+use crate::util::Config;
+
+pub struct RemoveOptions<'a> {
+    pub config: &'a Config,
+    /// The manifest of the package to remove the dependencies from.
+    pub manifest_path: &'a Path,
+    /// The keys of the dependencies to remove.
+    pub dependencies: Vec<String>,
+    /// The table to remove the dependencies from.
+    pub table: DepTable,
+}
+
+/// Removes dependencies from a manifest.
+///
+/// Entries of `[features]` which refer to a dependency that is gone are
+/// removed too. Nothing is written unless all of the dependencies are found.
+pub fn remove(opts: &RemoveOptions<'_>) -> CargoResult<()> {
+    let mut manifest = LocalManifest::try_new(opts.manifest_path)?;
+    for dep in &opts.dependencies {
+        opts.config
+            .shell()
+            .status("Removing", format!("{} from {}", dep, opts.table))?;
+        manifest.remove_from_table(&opts.table, dep)?;
+    }
+    for dep in &opts.dependencies {
+        manifest.remove_from_features(dep);
+    }
+    manifest.write()
+}
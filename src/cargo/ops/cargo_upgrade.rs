@@ -0,0 +1,330 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use anyhow::bail;
+use semver::{Version, VersionReq};
+
+use crate::core::source::Source;
+use crate::core::{Dependency, PackageIdSpec, SourceId, Workspace};
+use crate::ops::{self, UpdateOptions};
+use crate::sources::SourceConfigMap;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::toml_mut::{self, DepTable, DependencySource, LocalManifest};
+use crate::util::Config;
+
+pub struct UpgradeOptions<'a> {
+    pub config: &'a Config,
+    /// The names of the packages whose requirements to upgrade, or empty to
+    /// upgrade every registry dependency.
+    pub to_upgrade: Vec<String>,
+    /// Upgrade to the newest version, even if it isn't compatible with the
+    /// current requirement.
+    pub incompatible: bool,
+    pub dry_run: bool,
+}
+
+/// Where a dependency is declared in a manifest.
+enum Location {
+    Table(DepTable),
+    /// The `[workspace.dependencies]` table of the workspace root.
+    WorkspaceDependencies,
+}
+
+/// A registry dependency declared in one of the manifests of the workspace.
+struct Declaration {
+    /// Index of the manifest in the list of manifests being edited.
+    manifest: usize,
+    location: Location,
+    dep: toml_mut::Dependency,
+}
+
+/// Bumps the version requirements of the registry dependencies in the
+/// manifests of the workspace, and updates `Cargo.lock` to match.
+///
+/// Unlike `cargo update`, this changes the requirements themselves, so
+/// that new releases can be picked even when the current requirements
+/// don't allow them.
+pub fn upgrade(ws: &Workspace<'_>, opts: &UpgradeOptions<'_>) -> CargoResult<()> {
+    let config = opts.config;
+    if config.offline() {
+        bail!("you can't upgrade in the offline mode");
+    }
+
+    let mut manifests = Vec::new();
+    let mut paths = HashSet::new();
+    for path in ws
+        .members()
+        .map(|pkg| pkg.manifest_path())
+        .chain(Some(ws.root_manifest()))
+    {
+        if paths.insert(path.to_path_buf()) {
+            manifests.push(LocalManifest::try_new(path)?);
+        }
+    }
+
+    let declarations = find_declarations(ws, &manifests)?;
+    let wanted =
+        |name: &str| opts.to_upgrade.is_empty() || opts.to_upgrade.iter().any(|n| n == name);
+    for name in &opts.to_upgrade {
+        if !declarations.iter().any(|decl| &decl.dep.name == name) {
+            bail!(
+                "the registry dependency `{}` could not be found in the workspace",
+                name
+            );
+        }
+    }
+
+    let mut registries = Registries::new(config)?;
+    let mut upgraded = BTreeSet::new();
+    let mut changed = BTreeSet::new();
+    for decl in declarations {
+        let dep = &decl.dep;
+        if !wanted(&dep.name) {
+            continue;
+        }
+        let old_req = dep.version.as_deref().unwrap();
+        // Exact requirements are usually there on purpose, so only upgrade
+        // them if asked to specifically.
+        if old_req.trim_start().starts_with('=') && opts.to_upgrade.is_empty() {
+            continue;
+        }
+        let compatible_with = if opts.incompatible {
+            None
+        } else {
+            match VersionReq::parse(old_req) {
+                Ok(req) => Some(req),
+                Err(_) => continue,
+            }
+        };
+        let latest = match registries.latest(dep, compatible_with.as_ref())? {
+            Some(latest) => latest,
+            None => continue,
+        };
+        let new_req = match upgrade_requirement(old_req, &latest) {
+            Some(new_req) => new_req,
+            None => continue,
+        };
+
+        let manifest = &mut manifests[decl.manifest];
+        let location = match &decl.location {
+            Location::Table(table) => {
+                format!(
+                    "{} ({})",
+                    manifest.package_name().unwrap_or("workspace"),
+                    table
+                )
+            }
+            Location::WorkspaceDependencies => "workspace.dependencies".to_string(),
+        };
+        config.shell().status(
+            "Upgrading",
+            format!("{} {} -> {} in {}", dep.name, old_req, new_req, location),
+        )?;
+        // Fields which are `None` are left as they are in the manifest, so
+        // only the requirement is rewritten.
+        let new_dep = toml_mut::Dependency {
+            version: Some(new_req),
+            features: None,
+            default_features: None,
+            optional: None,
+            ..dep.clone()
+        };
+        match &decl.location {
+            Location::Table(table) => manifest.insert_into_table(table, &new_dep)?,
+            Location::WorkspaceDependencies => {
+                let crate_root = manifest.crate_root().to_path_buf();
+                let item = &mut manifest.data["workspace"]["dependencies"][dep.toml_key()];
+                new_dep.update_toml(&crate_root, item);
+            }
+        }
+        upgraded.insert(dep.name.clone());
+        changed.insert(decl.manifest);
+    }
+
+    if upgraded.is_empty() {
+        config
+            .shell()
+            .note("all dependency requirements are up to date")?;
+        return Ok(());
+    }
+    if opts.dry_run {
+        config.shell().warn("aborting upgrade due to dry run")?;
+        return Ok(());
+    }
+
+    let previous_resolve = ops::load_pkg_lockfile(ws)?;
+    for i in changed {
+        manifests[i].write()?;
+    }
+
+    // Re-resolve with the new requirements, only unlocking the packages which
+    // were upgraded.
+    let ws = Workspace::new(ws.root_manifest(), config)?;
+    let to_update: Vec<String> = previous_resolve
+        .iter()
+        .flat_map(|resolve| resolve.iter())
+        .filter(|id| id.source_id().is_registry() && upgraded.contains(id.name().as_str()))
+        .map(|id| PackageIdSpec::from_package_id(id).to_string())
+        .collect();
+    if previous_resolve.is_some() && to_update.is_empty() {
+        ops::resolve_ws(&ws)?;
+        return Ok(());
+    }
+    ops::update_lockfile(
+        &ws,
+        &UpdateOptions {
+            config,
+            to_update,
+            precise: None,
+            aggressive: false,
+            dry_run: false,
+        },
+    )
+}
+
+/// Returns the registry dependencies with a version requirement declared in
+/// `manifests`, in the order they appear.
+fn find_declarations(
+    ws: &Workspace<'_>,
+    manifests: &[LocalManifest],
+) -> CargoResult<Vec<Declaration>> {
+    let mut declarations = Vec::new();
+    let mut push = |manifest, location, dep: toml_mut::Dependency| {
+        if dep.source == DependencySource::Registry && dep.version.is_some() {
+            declarations.push(Declaration {
+                manifest,
+                location,
+                dep,
+            });
+        }
+    };
+    for (i, manifest) in manifests.iter().enumerate() {
+        let crate_root = manifest.crate_root();
+        let is_member = ws
+            .members()
+            .any(|pkg| pkg.manifest_path() == manifest.path.as_path());
+        if is_member {
+            for (table, t) in manifest.get_tables() {
+                for (key, item) in t.iter() {
+                    let dep = toml_mut::Dependency::from_toml(crate_root, key, item)
+                        .chain_err(|| format!("failed to read `{}`", manifest.path.display()))?;
+                    push(i, Location::Table(table.clone()), dep);
+                }
+            }
+        }
+        if manifest.path.as_path() == ws.root_manifest() {
+            let deps = manifest
+                .data
+                .get("workspace")
+                .and_then(|w| w.get("dependencies"))
+                .and_then(|d| d.as_table_like());
+            for (key, item) in deps.into_iter().flat_map(|d| d.iter()) {
+                let dep = toml_mut::Dependency::from_toml(crate_root, key, item)
+                    .chain_err(|| format!("failed to read `{}`", manifest.path.display()))?;
+                push(i, Location::WorkspaceDependencies, dep);
+            }
+        }
+    }
+    Ok(declarations)
+}
+
+/// The registries which dependencies are upgraded from, each updated once.
+struct Registries<'cfg> {
+    config: &'cfg Config,
+    map: SourceConfigMap<'cfg>,
+    sources: HashMap<SourceId, Box<dyn Source + 'cfg>>,
+}
+
+impl<'cfg> Registries<'cfg> {
+    fn new(config: &'cfg Config) -> CargoResult<Registries<'cfg>> {
+        Ok(Registries {
+            config,
+            map: SourceConfigMap::new(config)?,
+            sources: HashMap::new(),
+        })
+    }
+
+    /// Returns the newest version of `dep` matching `req`, skipping yanked
+    /// versions and pre-releases.
+    fn latest(
+        &mut self,
+        dep: &toml_mut::Dependency,
+        req: Option<&VersionReq>,
+    ) -> CargoResult<Option<Version>> {
+        let source_id = match &dep.registry {
+            Some(registry) => SourceId::alt_registry(self.config, registry)?,
+            None => SourceId::crates_io(self.config)?,
+        };
+        // This may update the index and takes the package cache lock to do
+        // so, as other Cargo commands do.
+        let _lock = self.config.acquire_package_cache_lock()?;
+        let source = match self.sources.get_mut(&source_id) {
+            Some(source) => source,
+            None => {
+                let mut source = self.map.load(source_id, &HashSet::new())?;
+                source
+                    .update()
+                    .chain_err(|| format!("failed to update {}", source_id))?;
+                self.sources.entry(source_id).or_insert(source)
+            }
+        };
+        let query = Dependency::parse_no_deprecated(&dep.name, None, source_id)?;
+        let summaries = source.query_vec(&query)?;
+        Ok(summaries
+            .iter()
+            .map(|s| s.version())
+            .filter(|v| !v.is_prerelease())
+            .filter(|v| req.map_or(true, |req| req.matches(v)))
+            .max()
+            .cloned())
+    }
+}
+
+/// Rewrites the requirement `req` so that its minimum version is `version`,
+/// keeping its operator and precision, such as `~1.2` to `~1.4` for 1.4.1.
+///
+/// Returns `None` if the requirement would not be raised, or if it isn't a
+/// single comparison this knows how to rewrite (like `>=1.0, <1.5`).
+fn upgrade_requirement(req: &str, version: &Version) -> Option<String> {
+    let req = req.trim();
+    let (op, old) = match req.find(|c: char| c.is_ascii_digit()) {
+        Some(i) => (&req[..i], &req[i..]),
+        None => return None,
+    };
+    if !["", "^", "~", "="].contains(&op.trim_end()) {
+        return None;
+    }
+    let old: Vec<u64> = old
+        .split('.')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()?;
+    if old.is_empty() || old.len() > 3 {
+        return None;
+    }
+    let new: Vec<u64> = [version.major, version.minor, version.patch][..old.len()].to_vec();
+    if new <= old {
+        return None;
+    }
+    let new: Vec<String> = new.iter().map(|n| n.to_string()).collect();
+    Some(format!("{}{}", op, new.join(".")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::upgrade_requirement;
+    use semver::Version;
+
+    #[test]
+    fn upgrade_requirements() {
+        let v = |s| Version::parse(s).unwrap();
+        let up = |req, version| upgrade_requirement(req, &v(version));
+        assert_eq!(up("1.0", "1.4.2"), Some("1.4".to_string()));
+        assert_eq!(up("^0.1.3", "0.2.0"), Some("^0.2.0".to_string()));
+        assert_eq!(up("~1.2", "2.0.1"), Some("~2.0".to_string()));
+        assert_eq!(up("= 1.2.3", "1.2.4"), Some("= 1.2.4".to_string()));
+        assert_eq!(up("1", "1.9.0"), None);
+        assert_eq!(up("2.0", "1.9.0"), None);
+        assert_eq!(up(">=1.0, <1.5", "2.0.0"), None);
+        assert_eq!(up("*", "2.0.0"), None);
+        assert_eq!(up("1.0.0-beta.1", "2.0.0"), None);
+    }
+}
@@ -1,4 +1,5 @@
 use std::io::prelude::*;
+use std::path::Path;
 
 use crate::core::{resolver, Resolve, ResolveVersion, Workspace};
 use crate::util::errors::{CargoResult, CargoResultExt};
@@ -18,14 +19,88 @@ pub fn load_pkg_lockfile(ws: &Workspace<'_>) -> CargoResult<Option<Resolve>> {
         .chain_err(|| format!("failed to read file: {}", f.path().display()))?;
 
     let resolve = (|| -> CargoResult<Option<Resolve>> {
-        let resolve: toml::Value = cargo_toml::parse(&s, f.path(), ws.config())?;
-        let v: resolver::EncodableResolve = resolve.try_into()?;
-        Ok(Some(v.into_resolve(&s, ws)?))
+        let (ours, theirs) = match split_merge_conflict(&s)? {
+            Some(sides) => sides,
+            None => return Ok(Some(parse_resolve(&s, f.path(), ws)?)),
+        };
+        if !ws.merge_lock_conflicts() {
+            anyhow::bail!(
+                "the lock file has unresolved merge conflicts\n\
+                 Run `cargo generate-lockfile --merge-conflicts` to merge them, \
+                 keeping the versions locked on either side."
+            );
+        }
+        ws.config()
+            .shell()
+            .status("Merging", "conflicting changes to Cargo.lock")?;
+        let ours = parse_resolve(&ours, f.path(), ws).chain_err(|| "failed to parse our side")?;
+        let theirs =
+            parse_resolve(&theirs, f.path(), ws).chain_err(|| "failed to parse their side")?;
+        Ok(Some(Resolve::merge_conflict_sides(&ours, &theirs)))
     })()
     .chain_err(|| format!("failed to parse lock file at: {}", f.path().display()))?;
     Ok(resolve)
 }
 
+fn parse_resolve(s: &str, path: &Path, ws: &Workspace<'_>) -> CargoResult<Resolve> {
+    let resolve: toml::Value = cargo_toml::parse(s, path, ws.config())?;
+    let v: resolver::EncodableResolve = resolve.try_into()?;
+    v.into_resolve(s, ws)
+}
+
+/// Splits a file with git merge conflict markers into the two sides of the
+/// conflicts, or returns `None` if it has none.
+///
+/// The base section of conflicts in the `diff3` style is dropped.
+fn split_merge_conflict(s: &str) -> CargoResult<Option<(String, String)>> {
+    enum Section {
+        Common,
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    // Markers may be followed by a label, such as `<<<<<<< HEAD`.
+    let is_marker = |line: &str, marker: &str| {
+        line == marker || (line.starts_with(marker) && line[marker.len()..].starts_with(' '))
+    };
+    let mut section = Section::Common;
+    let mut conflicts = 0;
+    let (mut ours, mut theirs) = (String::new(), String::new());
+    for (i, line) in s.split_inclusive('\n').enumerate() {
+        let bare = line.trim_end_matches(&['\r', '\n'][..]);
+        section = match section {
+            Section::Common if is_marker(bare, "<<<<<<<") => {
+                conflicts += 1;
+                Section::Ours
+            }
+            Section::Ours if is_marker(bare, "|||||||") => Section::Base,
+            Section::Ours | Section::Base if bare == "=======" => Section::Theirs,
+            Section::Theirs if is_marker(bare, ">>>>>>>") => Section::Common,
+            Section::Common if is_marker(bare, "=======") || is_marker(bare, ">>>>>>>") => {
+                anyhow::bail!("unexpected merge conflict marker on line {}", i + 1)
+            }
+            section => {
+                match section {
+                    Section::Common => {
+                        ours.push_str(line);
+                        theirs.push_str(line);
+                    }
+                    Section::Ours => ours.push_str(line),
+                    Section::Base => {}
+                    Section::Theirs => theirs.push_str(line),
+                }
+                section
+            }
+        };
+    }
+    match section {
+        Section::Common if conflicts == 0 => Ok(None),
+        Section::Common => Ok(Some((ours, theirs))),
+        _ => anyhow::bail!("unterminated merge conflict"),
+    }
+}
+
 /// Generate a toml String of Cargo.lock from a Resolve.
 pub fn resolve_to_string(ws: &Workspace<'_>, resolve: &mut Resolve) -> CargoResult<String> {
     let (_orig, out, _ws_root) = resolve_to_string_orig(ws, resolve)?;
@@ -0,0 +1,296 @@
+//! Registry authentication through credential providers.
+//!
+//! By default tokens are stored in plaintext in `$CARGO_HOME/credentials`.
+//! With `-Z credential-provider`, the `registry.credential-provider` and
+//! `registries.<name>.credential-provider` config values can name an external
+//! program that is responsible for storing tokens instead.
+//!
+//! Cargo runs the provider once per operation. It writes a single JSON
+//! request to the provider's stdin and reads a single JSON response from its
+//! stdout. The provider's stderr is inherited, so it can print diagnostics or
+//! prompt the user (for example on the controlling terminal).
+//!
+//! A request looks like:
+//!
+//! ```json
+//! {
+//!     "v": 1,
+//!     "action": "get",
+//!     "registry": {"index-url": "https://github.com/rust-lang/crates.io-index", "name": "crates-io"}
+//! }
+//! ```
+//!
+//! `action` is one of `get`, `store` or `erase`. A `store` request also
+//! carries the new `"token"`. A successful response is `{"v": 1}`, with a
+//! `"token"` field for `get`. A failure is reported as
+//! `{"v": 1, "error": {"kind": "not-found"}}` if the provider has no token
+//! for the registry, or `{"v": 1, "error": {"kind": "other", "message": "..."}}`
+//! otherwise.
+//!
+//! The `"v"` field is the protocol version. Cargo rejects responses with a
+//! version it does not understand, so the format can evolve without old
+//! versions of Cargo misinterpreting the output of new providers.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::bail;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::core::SourceId;
+use crate::ops::RegistryConfig;
+use crate::sources::CRATES_IO_REGISTRY;
+use crate::util::config::{self, Config, Definition, PathAndArgs};
+use crate::util::errors::{CargoResult, CargoResultExt};
+
+/// The version of the protocol spoken with credential providers.
+const PROTOCOL_VERSION: u32 = 1;
+
+/// The name of the built-in provider which keeps tokens in the credentials
+/// file. This is the default when no provider is configured.
+const BUILTIN_PLAINTEXT: &str = "cargo:plaintext";
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum Action {
+    Get,
+    Store,
+    Erase,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct RegistryInfo<'a> {
+    index_url: &'a str,
+    name: &'a str,
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    v: u32,
+    action: Action,
+    registry: RegistryInfo<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    v: u32,
+    token: Option<String>,
+    error: Option<ResponseError>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+    kind: ErrorKind,
+    message: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum ErrorKind {
+    NotFound,
+    Other,
+}
+
+/// The outcome of a request which the provider handled successfully.
+enum Reply {
+    /// The request succeeded, carrying the token for `get` requests.
+    Done(Option<String>),
+    /// The provider has no token for the registry.
+    NotFound,
+}
+
+/// Returns the credential provider configured for `registry`, or `None` if
+/// the built-in plaintext provider should be used.
+///
+/// `registries.<name>.credential-provider` takes precedence over
+/// `registry.credential-provider`, which applies to all registries.
+pub(super) fn credential_provider(
+    config: &Config,
+    registry: Option<&str>,
+) -> CargoResult<Option<PathAndArgs>> {
+    if !config.cli_unstable().credential_provider {
+        return Ok(None);
+    }
+    let mut provider = None;
+    if let Some(registry) = registry {
+        provider = config
+            .get::<Option<PathAndArgs>>(&format!("registries.{}.credential-provider", registry))?;
+    }
+    if provider.is_none() {
+        provider = config.get::<Option<PathAndArgs>>("registry.credential-provider")?;
+    }
+    Ok(provider.filter(|p| p.path.raw_value() != BUILTIN_PLAINTEXT || !p.args.is_empty()))
+}
+
+/// Returns the token for the registry at `sid`, if one is available.
+pub(super) fn auth_token(
+    config: &Config,
+    cfg: &RegistryConfig,
+    sid: SourceId,
+    registry: Option<&str>,
+) -> CargoResult<Option<String>> {
+    let provider = match &cfg.credential_provider {
+        Some(provider) => provider,
+        None => return Ok(cfg.token.clone()),
+    };
+    match run(config, provider, Action::Get, sid, registry, None)? {
+        Reply::Done(token) => Ok(token),
+        Reply::NotFound => Ok(None),
+    }
+}
+
+/// Saves `token` as the token for the registry at `sid`.
+pub(super) fn login(
+    config: &Config,
+    cfg: &RegistryConfig,
+    sid: SourceId,
+    registry: Option<&str>,
+    token: String,
+) -> CargoResult<()> {
+    match &cfg.credential_provider {
+        Some(provider) => {
+            run(config, provider, Action::Store, sid, registry, Some(&token))?;
+            Ok(())
+        }
+        None => {
+            config::save_credentials(config, Some(token), registry.map(String::from))?;
+            Ok(())
+        }
+    }
+}
+
+/// Removes the token for the registry at `sid`.
+///
+/// Returns `false` if there was no token to remove.
+pub(super) fn logout(
+    config: &Config,
+    cfg: &RegistryConfig,
+    sid: SourceId,
+    registry: Option<&str>,
+) -> CargoResult<bool> {
+    match &cfg.credential_provider {
+        Some(provider) => match run(config, provider, Action::Erase, sid, registry, None)? {
+            Reply::Done(_) => Ok(true),
+            Reply::NotFound => Ok(false),
+        },
+        None => {
+            let removed = config::save_credentials(config, None, registry.map(String::from))?;
+            // `cfg.token` may also come from the environment or a config
+            // file, which are left alone.
+            if cfg.token.is_some() {
+                let key = match registry {
+                    Some(registry) => format!("registries.{}.token", registry),
+                    None => "registry.token".to_string(),
+                };
+                if let Some(token) = config.get_string(&key)? {
+                    if !is_credentials_file(config, &token.definition) {
+                        config.shell().warn(format!(
+                            "the token for `{}` is still set by {}, \
+                             it has to be removed from there",
+                            registry.unwrap_or("crates.io"),
+                            token.definition
+                        ))?;
+                    }
+                }
+            }
+            Ok(removed)
+        }
+    }
+}
+
+/// Returns whether a config value defined at `def` comes from the
+/// credentials file in `$CARGO_HOME`.
+fn is_credentials_file(config: &Config, def: &Definition) -> bool {
+    match def {
+        Definition::Path(path) => {
+            path.parent() == Some(config.home().as_path_unlocked())
+                && path.file_stem() == Some("credentials".as_ref())
+        }
+        Definition::Environment(_) | Definition::Cli => false,
+    }
+}
+
+/// Runs `provider` for a single request.
+///
+/// A provider reporting that it has no token for the registry is not an
+/// error, it is up to the caller to decide. All other failures are errors.
+fn run(
+    config: &Config,
+    provider: &PathAndArgs,
+    action: Action,
+    sid: SourceId,
+    registry: Option<&str>,
+    token: Option<&str>,
+) -> CargoResult<Reply> {
+    let name = provider.path.raw_value();
+    let exe = provider.path.clone().resolve_program(config);
+    let request = Request {
+        v: PROTOCOL_VERSION,
+        action,
+        registry: RegistryInfo {
+            index_url: sid.url().as_str(),
+            name: registry.unwrap_or(CRATES_IO_REGISTRY),
+        },
+        token,
+    };
+    let request = serde_json::to_string(&request)?;
+
+    let mut cmd = Command::new(&exe);
+    cmd.args(&provider.args)
+        .env(crate::CARGO_ENV, config.cargo_exe()?)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit());
+    debug!("running credential provider {:?}", cmd);
+    let mut child = cmd
+        .spawn()
+        .chain_err(|| format!("failed to execute credential provider `{}`", name))?;
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        // A provider that exits without reading its input is reported below
+        // based on its exit status and output.
+        if let Err(e) = writeln!(stdin, "{}", request) {
+            debug!("failed to write to credential provider: {}", e);
+        }
+    }
+    let output = child
+        .wait_with_output()
+        .chain_err(|| format!("failed to wait for credential provider `{}`", name))?;
+    if !output.status.success() {
+        bail!(
+            "credential provider `{}` exited with {}",
+            name,
+            output.status
+        );
+    }
+    let response: Response = serde_json::from_slice(&output.stdout)
+        .chain_err(|| format!("credential provider `{}` returned invalid output", name))?;
+    if response.v != PROTOCOL_VERSION {
+        bail!(
+            "credential provider `{}` uses protocol version {}, \
+             but this version of Cargo only supports version {}",
+            name,
+            response.v,
+            PROTOCOL_VERSION
+        );
+    }
+    match response.error {
+        None => Ok(Reply::Done(response.token)),
+        Some(ResponseError {
+            kind: ErrorKind::NotFound,
+            ..
+        }) => Ok(Reply::NotFound),
+        Some(ResponseError {
+            kind: ErrorKind::Other,
+            message,
+        }) => bail!(
+            "credential provider `{}` failed: {}",
+            name,
+            message.as_deref().unwrap_or("unknown error")
+        ),
+    }
+}
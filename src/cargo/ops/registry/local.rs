@@ -0,0 +1,247 @@
+//! Publishing to local registries, the directories of `.crate` files and
+//! index entries read by `local-registry` sources, so that they can be
+//! maintained without a registry service.
+//!
+//! A local registry is the target of `cargo publish --to <path>`, or of
+//! `--index` and `--registry <name>` when the index is a `file://` URL of a
+//! directory which isn't a git repository. Its layout is:
+//!
+//! ```text
+//! index/
+//!     3/f/foo     # one JSON line per version, like in any registry index
+//! foo-0.1.0.crate
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+use crates_io::NewCrate;
+use serde::Serialize;
+use url::Url;
+
+use crate::core::{Package, SourceId};
+use crate::sources::registry::index_file_path;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{paths, Checksum, ChecksumAlgorithm, Config, IntoUrl};
+
+/// An entry of the index, in the same format as the ones of the remote
+/// registries.
+#[derive(Serialize)]
+pub(crate) struct IndexPackage {
+    pub name: String,
+    pub vers: String,
+    pub deps: Vec<IndexDependency>,
+    pub cksum: String,
+    pub features: BTreeMap<String, Vec<String>>,
+    pub yanked: bool,
+    pub links: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rust_version: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct IndexDependency {
+    /// The name of the dependency in the manifest, which differs from the
+    /// name of the package if it was renamed.
+    pub name: String,
+    pub req: String,
+    pub features: Vec<String>,
+    pub optional: bool,
+    pub default_features: bool,
+    pub target: Option<String>,
+    pub kind: String,
+    /// The index of the registry of the dependency, `None` if it's the same
+    /// as the one of the package.
+    pub registry: Option<String>,
+    /// The name of the package, if the dependency was renamed.
+    pub package: Option<String>,
+}
+
+/// Returns the directory of the local registry designated by the `--to`,
+/// `--index` or `--registry` argument, if the target is a local registry,
+/// and the source the dependencies of the package are compared with.
+///
+/// A local registry published to with `--to` usually replaces crates.io, so
+/// dependencies from crates.io are recorded as coming from the same registry.
+pub(super) fn target(
+    config: &Config,
+    to: Option<&Path>,
+    index: Option<&str>,
+    registry: Option<&str>,
+) -> CargoResult<Option<(PathBuf, SourceId)>> {
+    if let Some(to) = to {
+        return Ok(Some((config.cwd().join(to), SourceId::crates_io(config)?)));
+    }
+    // Conflicting `--index` and `--registry` arguments are reported by
+    // `registry()`.
+    let (url, source_id) = match (index, registry) {
+        (Some(index), None) => {
+            let url = index.into_url()?;
+            let source_id = SourceId::for_registry(&url)?;
+            (url, source_id)
+        }
+        (None, Some(registry)) => (
+            config.get_registry_index(registry)?,
+            SourceId::alt_registry(config, registry)?,
+        ),
+        _ => return Ok(None),
+    };
+    Ok(local_registry_path(&url).map(|path| (path, source_id)))
+}
+
+/// Returns the path of the directory at `index`, if it's a `file://` URL of
+/// a directory which isn't a git repository.
+fn local_registry_path(index: &Url) -> Option<PathBuf> {
+    if index.scheme() != "file" {
+        return None;
+    }
+    let path = index.to_file_path().ok()?;
+    if git2::Repository::open(&path).is_ok() {
+        return None;
+    }
+    Some(path)
+}
+
+/// Adds `krate`, whose `.crate` file is `tarball`, to the local registry at
+/// `root`.
+pub(super) fn publish(
+    config: &Config,
+    pkg: &Package,
+    krate: &NewCrate,
+    mut tarball: &File,
+    root: &Path,
+    dry_run: bool,
+) -> CargoResult<()> {
+    let index_path = root.join("index").join(index_file_path(&krate.name));
+    let lines = read_index(&index_path)?;
+    if lines
+        .iter()
+        .any(|line| line.get("vers").and_then(|v| v.as_str()) == Some(&krate.vers))
+    {
+        bail!(
+            "`{}` is already in the local registry at `{}`",
+            pkg.package_id(),
+            root.display()
+        );
+    }
+
+    tarball.seek(SeekFrom::Start(0))?;
+    let cksum = Checksum::compute_file(ChecksumAlgorithm::Sha256, tarball)
+        .chain_err(|| "failed to compute the checksum of the package")?;
+    let deps = krate
+        .deps
+        .iter()
+        .map(|dep| IndexDependency {
+            name: dep
+                .explicit_name_in_toml
+                .clone()
+                .unwrap_or_else(|| dep.name.clone()),
+            req: dep.version_req.clone(),
+            features: dep.features.clone(),
+            optional: dep.optional,
+            default_features: dep.default_features,
+            target: dep.target.clone(),
+            kind: dep.kind.clone(),
+            registry: dep.registry.clone(),
+            package: dep.explicit_name_in_toml.as_ref().map(|_| dep.name.clone()),
+        })
+        .collect();
+    let entry = IndexPackage {
+        name: krate.name.clone(),
+        vers: krate.vers.clone(),
+        deps,
+        cksum: cksum.to_string(),
+        features: krate.features.clone(),
+        yanked: false,
+        links: krate.links.clone(),
+        rust_version: krate.rust_version.clone(),
+    };
+
+    if dry_run {
+        config.shell().warn("aborting upload due to dry run")?;
+        return Ok(());
+    }
+    config.shell().status(
+        "Adding",
+        format!("{} to `{}`", pkg.package_id(), root.display()),
+    )?;
+
+    // The `.crate` file is written first, so that the index never lists a
+    // version which can't be unpacked.
+    let dst = root.join(format!("{}-{}.crate", krate.name, krate.vers));
+    paths::create_dir_all(root)?;
+    tarball.seek(SeekFrom::Start(0))?;
+    let mut file = paths::create(&dst)?;
+    io::copy(&mut tarball, &mut file)
+        .chain_err(|| format!("failed to write `{}`", dst.display()))?;
+
+    paths::create_dir_all(index_path.parent().unwrap())?;
+    let mut file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&index_path)
+        .chain_err(|| format!("failed to open `{}`", index_path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)
+        .chain_err(|| format!("failed to write `{}`", index_path.display()))?;
+    Ok(())
+}
+
+/// Marks the version `version` of `name` in the local registry at `root` as
+/// yanked, or not yanked if `undo` is set.
+pub(super) fn yank(
+    config: &Config,
+    root: &Path,
+    name: &str,
+    version: &str,
+    undo: bool,
+) -> CargoResult<()> {
+    let index_path = root.join("index").join(index_file_path(name));
+    let mut lines = read_index(&index_path)?;
+    let entry = lines
+        .iter_mut()
+        .find(|line| line.get("vers").and_then(|v| v.as_str()) == Some(version));
+    let entry = match entry {
+        Some(entry) => entry,
+        None => bail!(
+            "`{} v{}` is not in the local registry at `{}`",
+            name,
+            version,
+            root.display()
+        ),
+    };
+    let (status, yanked) = if undo {
+        ("Unyank", false)
+    } else {
+        ("Yank", true)
+    };
+    config
+        .shell()
+        .status(status, format!("{}:{}", name, version))?;
+    entry["yanked"] = yanked.into();
+
+    let mut contents = String::new();
+    for line in &lines {
+        contents.push_str(&serde_json::to_string(line)?);
+        contents.push('\n');
+    }
+    paths::write(&index_path, contents)
+}
+
+/// Reads the entries of the index file at `path`, which is empty if the
+/// package was never published.
+fn read_index(path: &Path) -> CargoResult<Vec<serde_json::Value>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    paths::read(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line)
+                .chain_err(|| format!("failed to parse an entry of `{}`", path.display()))
+        })
+        .collect()
+}
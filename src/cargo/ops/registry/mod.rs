@@ -2,6 +2,7 @@ use std::collections::{BTreeMap, HashSet};
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::iter::repeat;
+use std::path::PathBuf;
 use std::str;
 use std::time::Duration;
 use std::{cmp, env};
@@ -11,28 +12,38 @@ use crates_io::{self, NewCrate, NewCrateDependency, Registry};
 use curl::easy::{Easy, InfoType, SslOpt, SslVersion};
 use log::{log, Level};
 use percent_encoding::{percent_encode, NON_ALPHANUMERIC};
+use serde::de::IgnoredAny;
 
 use crate::core::dependency::DepKind;
 use crate::core::manifest::ManifestMetadata;
 use crate::core::source::Source;
 use crate::core::{Package, SourceId, Workspace};
 use crate::ops;
-use crate::sources::{RegistrySource, SourceConfigMap, CRATES_IO_REGISTRY};
-use crate::util::config::{self, Config, SslVersionConfig, SslVersionConfigRange};
+use crate::sources::{SourceConfigMap, CRATES_IO_REGISTRY};
+use crate::util::config::{Config, PathAndArgs, SslVersionConfig, SslVersionConfigRange};
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::important_paths::find_root_manifest_for_wd;
-use crate::util::IntoUrl;
 use crate::util::{paths, validate_package_name};
+use crate::util::{CanonicalUrl, IntoUrl};
 use crate::{drop_print, drop_println, version};
 
+mod auth;
+mod local;
+
+pub(crate) use self::local::{IndexDependency, IndexPackage};
+
 /// Registry settings loaded from config files.
 ///
 /// This is loaded based on the `--registry` flag and the config settings.
 pub struct RegistryConfig {
     /// The index URL. If `None`, use crates.io.
     pub index: Option<String>,
-    /// The authentication token.
+    /// The authentication token stored in the config.
     pub token: Option<String>,
+    /// The external program to fetch and store the token with, set with
+    /// `credential-provider`. If `None`, the token is kept in the
+    /// credentials file.
+    pub credential_provider: Option<PathAndArgs>,
 }
 
 pub struct PublishOpts<'cfg> {
@@ -45,6 +56,9 @@ pub struct PublishOpts<'cfg> {
     pub targets: Vec<String>,
     pub dry_run: bool,
     pub registry: Option<String>,
+    /// The directory of a local registry to publish to, instead of a
+    /// registry service.
+    pub to: Option<PathBuf>,
     pub features: Vec<String>,
     pub all_features: bool,
     pub no_default_features: bool,
@@ -83,15 +97,27 @@ pub fn publish(ws: &Workspace<'_>, opts: &PublishOpts<'_>) -> CargoResult<()> {
         }
     }
 
-    let (mut registry, reg_id) = registry(
+    let local = local::target(
         opts.config,
-        opts.token.clone(),
-        opts.index.clone(),
-        publish_registry,
-        true,
-        !opts.dry_run,
+        opts.to.as_deref(),
+        opts.index.as_deref(),
+        publish_registry.as_deref(),
     )?;
-    verify_dependencies(pkg, &registry, reg_id)?;
+    let (mut registry, reg_id) = match local {
+        Some((_, reg_id)) => (None, reg_id),
+        None => {
+            let (registry, reg_id) = registry(
+                opts.config,
+                opts.token.clone(),
+                opts.index.clone(),
+                publish_registry,
+                true,
+                !opts.dry_run,
+            )?;
+            (Some(registry), reg_id)
+        }
+    };
+    verify_dependencies(pkg, registry.as_ref(), reg_id)?;
 
     // Prepare a tarball, with a non-surpressable warning if metadata
     // is missing since this is being put online.
@@ -112,6 +138,22 @@ pub fn publish(ws: &Workspace<'_>, opts: &PublishOpts<'_>) -> CargoResult<()> {
     )?
     .unwrap();
 
+    let registry = match (registry.as_mut(), local) {
+        (Some(registry), _) => registry,
+        (None, Some((root, _))) => {
+            let krate = new_crate(opts.config, pkg, reg_id)?;
+            return local::publish(
+                opts.config,
+                pkg,
+                &krate,
+                tarball.file(),
+                &root,
+                opts.dry_run,
+            );
+        }
+        (None, None) => unreachable!(),
+    };
+
     // Upload said tarball to the specified destination
     opts.config
         .shell()
@@ -120,7 +162,7 @@ pub fn publish(ws: &Workspace<'_>, opts: &PublishOpts<'_>) -> CargoResult<()> {
         opts.config,
         pkg,
         tarball.file(),
-        &mut registry,
+        registry,
         reg_id,
         opts.dry_run,
     )?;
@@ -128,9 +170,11 @@ pub fn publish(ws: &Workspace<'_>, opts: &PublishOpts<'_>) -> CargoResult<()> {
     Ok(())
 }
 
+/// Checks that the dependencies of `pkg` can be published to `registry_src`,
+/// whose API is `registry` unless it's a local registry.
 fn verify_dependencies(
     pkg: &Package,
-    registry: &Registry,
+    registry: Option<&Registry>,
     registry_src: SourceId,
 ) -> CargoResult<()> {
     for dep in pkg.dependencies().iter() {
@@ -172,7 +216,11 @@ fn verify_dependencies(
             // This extra hostname check is mostly to assist with testing,
             // but also prevents someone using `--index` to specify
             // something that points to crates.io.
-            if registry_src.is_default_registry() || registry.host_is_crates_io() {
+            // Local registries may list dependencies from any registry.
+            let is_crates_io = registry.map_or(false, |registry| {
+                registry_src.is_default_registry() || registry.host_is_crates_io()
+            });
+            if is_crates_io {
                 bail!("crates cannot be published to crates.io with dependencies sourced from other\n\
                        registries. `{}` needs to be published to crates.io before publishing this crate.\n\
                        (crate `{}` is pulled from {})",
@@ -185,14 +233,8 @@ fn verify_dependencies(
     Ok(())
 }
 
-fn transmit(
-    config: &Config,
-    pkg: &Package,
-    tarball: &File,
-    registry: &mut Registry,
-    registry_id: SourceId,
-    dry_run: bool,
-) -> CargoResult<()> {
+/// Describes `pkg` for publishing it to the registry `registry_id`.
+fn new_crate(config: &Config, pkg: &Package, registry_id: SourceId) -> CargoResult<NewCrate> {
     let deps = pkg
         .dependencies()
         .iter()
@@ -247,6 +289,7 @@ fn transmit(
         ref categories,
         ref badges,
         ref links,
+        ref rust_version,
     } = *manifest.metadata();
     let readme_content = readme
         .as_ref()
@@ -261,12 +304,6 @@ fn transmit(
         }
     }
 
-    // Do not upload if performing a dry run
-    if dry_run {
-        config.shell().warn("aborting upload due to dry run")?;
-        return Ok(());
-    }
-
     let summary = pkg.summary();
     let string_features = summary
         .features()
@@ -279,28 +316,45 @@ fn transmit(
         })
         .collect::<BTreeMap<String, Vec<String>>>();
 
-    let publish = registry.publish(
-        &NewCrate {
-            name: pkg.name().to_string(),
-            vers: pkg.version().to_string(),
-            deps,
-            features: string_features,
-            authors: authors.clone(),
-            description: description.clone(),
-            homepage: homepage.clone(),
-            documentation: documentation.clone(),
-            keywords: keywords.clone(),
-            categories: categories.clone(),
-            readme: readme_content,
-            readme_file: readme.clone(),
-            repository: repository.clone(),
-            license: license.clone(),
-            license_file: license_file.clone(),
-            badges: badges.clone(),
-            links: links.clone(),
-        },
-        tarball,
-    );
+    Ok(NewCrate {
+        name: pkg.name().to_string(),
+        vers: pkg.version().to_string(),
+        deps,
+        features: string_features,
+        authors: authors.clone(),
+        description: description.clone(),
+        homepage: homepage.clone(),
+        documentation: documentation.clone(),
+        keywords: keywords.clone(),
+        categories: categories.clone(),
+        readme: readme_content,
+        readme_file: readme.clone(),
+        repository: repository.clone(),
+        license: license.clone(),
+        license_file: license_file.clone(),
+        badges: badges.clone(),
+        links: links.clone(),
+        rust_version: rust_version.clone(),
+    })
+}
+
+fn transmit(
+    config: &Config,
+    pkg: &Package,
+    tarball: &File,
+    registry: &mut Registry,
+    registry_id: SourceId,
+    dry_run: bool,
+) -> CargoResult<()> {
+    let krate = new_crate(config, pkg, registry_id)?;
+
+    // Do not upload if performing a dry run
+    if dry_run {
+        config.shell().warn("aborting upload due to dry run")?;
+        return Ok(());
+    }
+
+    let publish = registry.publish(&krate, tarball);
 
     match publish {
         Ok(warnings) => {
@@ -339,7 +393,8 @@ fn transmit(
     }
 }
 
-/// Returns the index and token from the config file for the given registry.
+/// Returns the index, token and credential provider from the config file for
+/// the given registry.
 ///
 /// `registry` is typically the registry specified on the command-line. If
 /// `None`, `index` is set to `None` to indicate it should use crates.io.
@@ -348,11 +403,11 @@ pub fn registry_configuration(
     registry: Option<String>,
 ) -> CargoResult<RegistryConfig> {
     // `registry.default` is handled in command-line parsing.
-    let (index, token) = match registry {
+    let (index, token) = match &registry {
         Some(registry) => {
-            validate_package_name(&registry, "registry name", "")?;
+            validate_package_name(registry, "registry name", "")?;
             (
-                Some(config.get_registry_index(&registry)?.to_string()),
+                Some(config.get_registry_index(registry)?.to_string()),
                 config
                     .get_string(&format!("registries.{}.token", registry))?
                     .map(|p| p.val),
@@ -365,7 +420,76 @@ pub fn registry_configuration(
         }
     };
 
-    Ok(RegistryConfig { index, token })
+    let credential_provider = auth::credential_provider(config, registry.as_deref())?;
+
+    Ok(RegistryConfig {
+        index,
+        token,
+        credential_provider,
+    })
+}
+
+/// Returns the token to send to the registry at `sid`, whose `config.json`
+/// sets `auth-required`, with every request.
+///
+/// The token is found the same way as for `cargo publish`, for the registry
+/// of `[registries]` (or of the environment) with the same index URL, or for
+/// crates.io.
+pub fn registry_auth_token(config: &Config, sid: SourceId) -> CargoResult<String> {
+    let registry = if sid.is_default_registry() {
+        None
+    } else {
+        let registries = config.get::<Option<BTreeMap<String, IgnoredAny>>>("registries")?;
+        let mut names: Vec<String> = registries
+            .into_iter()
+            .flatten()
+            .map(|(name, _)| name)
+            .collect();
+        // A registry defined only in the environment isn't in the
+        // `[registries]` table, but its name is known when a dependency
+        // refers to it.
+        names.extend(sid.alt_registry_key().map(String::from));
+        let mut found = None;
+        for name in names {
+            if config
+                .get_string(&format!("registries.{}.index", name))?
+                .is_none()
+            {
+                continue;
+            }
+            let index = config.get_registry_index(&name)?;
+            if CanonicalUrl::new(&index)? == *sid.canonical_url() {
+                found = Some(name);
+                break;
+            }
+        }
+        match found {
+            Some(name) => Some(name),
+            None => bail!(
+                "the registry at `{}` requires authentication, but it isn't \
+                 defined in `[registries]`\n\
+                 Tokens can only be set for the registries defined there.",
+                sid.url()
+            ),
+        }
+    };
+    let reg_cfg = registry_configuration(config, registry.clone())?;
+    match auth::auth_token(config, &reg_cfg, sid, registry.as_deref())? {
+        Some(token) => Ok(token),
+        None => {
+            let name = registry.as_deref().unwrap_or(CRATES_IO_REGISTRY);
+            let login = match &registry {
+                Some(registry) => format!("cargo login --registry {}", registry),
+                None => "cargo login".to_string(),
+            };
+            bail!(
+                "the registry `{}` requires authentication, but no token was found for it\n\
+                 Run `{}` to save one.",
+                name,
+                login
+            )
+        }
+    }
 }
 
 /// Returns the `Registry` and `Source` based on command-line and config settings.
@@ -392,11 +516,8 @@ fn registry(
         bail!("both `--index` and `--registry` should not be set at the same time");
     }
     // Parse all configuration options
-    let RegistryConfig {
-        token: token_config,
-        index: index_config,
-    } = registry_configuration(config, registry.clone())?;
-    let opt_index = index_config.as_ref().or_else(|| index.as_ref());
+    let reg_cfg = registry_configuration(config, registry.clone())?;
+    let opt_index = reg_cfg.index.as_ref().or_else(|| index.as_ref());
     let sid = get_source_id(config, opt_index, registry.as_ref())?;
     if !sid.is_remote_registry() {
         bail!(
@@ -407,7 +528,8 @@ fn registry(
     }
     let api_host = {
         let _lock = config.acquire_package_cache_lock()?;
-        let mut src = RegistrySource::remote(sid, &HashSet::new(), config);
+        // Sparse indexes are remote registries too, with their own source.
+        let mut src = sid.load_registry(config, &HashSet::new())?;
         // Only update the index if the config is not available or `force` is set.
         let cfg = src.config();
         let mut updated_cfg = || {
@@ -425,6 +547,16 @@ fn registry(
         cfg.and_then(|cfg| cfg.api)
             .ok_or_else(|| format_err!("{} does not support API commands", sid))?
     };
+    // Only ask an external credential provider for a token if one is
+    // required, as it may prompt the user.
+    let token_config = if index.is_none()
+        && token.is_none()
+        && (validate_token || reg_cfg.credential_provider.is_none())
+    {
+        auth::auth_token(config, &reg_cfg, sid, registry.as_deref())?
+    } else {
+        None
+    };
     let token = match (&index, &token, &token_config) {
         // No token.
         (None, None, None) => {
@@ -673,7 +805,7 @@ pub fn registry_login(
     token: Option<String>,
     reg: Option<String>,
 ) -> CargoResult<()> {
-    let (registry, _) = registry(config, token.clone(), None, reg.clone(), false, false)?;
+    let (registry, sid) = registry(config, token.clone(), None, reg.clone(), false, false)?;
 
     let token = match token {
         Some(token) => token,
@@ -695,18 +827,20 @@ pub fn registry_login(
         }
     };
 
-    let RegistryConfig {
-        token: old_token, ..
-    } = registry_configuration(config, reg.clone())?;
+    let reg_cfg = registry_configuration(config, reg.clone())?;
 
-    if let Some(old_token) = old_token {
-        if old_token == token {
-            config.shell().status("Login", "already logged in")?;
-            return Ok(());
+    // External providers are always asked to store the token, they may keep
+    // it somewhere Cargo can't compare it with.
+    if reg_cfg.credential_provider.is_none() {
+        if let Some(old_token) = &reg_cfg.token {
+            if *old_token == token {
+                config.shell().status("Login", "already logged in")?;
+                return Ok(());
+            }
         }
     }
 
-    config::save_credentials(config, token, reg.clone())?;
+    auth::login(config, &reg_cfg, sid, reg.as_deref(), token)?;
     config.shell().status(
         "Login",
         format!(
@@ -717,6 +851,24 @@ pub fn registry_login(
     Ok(())
 }
 
+pub fn registry_logout(config: &Config, reg: Option<String>) -> CargoResult<()> {
+    let reg_cfg = registry_configuration(config, reg.clone())?;
+    let sid = get_source_id(config, reg_cfg.index.as_ref(), reg.as_ref())?;
+    let reg_name = reg.as_ref().map_or("crates.io", String::as_str);
+    if auth::logout(config, &reg_cfg, sid, reg.as_deref())? {
+        config.shell().status(
+            "Logout",
+            format!("token for `{}` has been removed", reg_name),
+        )?;
+    } else {
+        config.shell().status(
+            "Logout",
+            format!("not currently logged in to `{}`", reg_name),
+        )?;
+    }
+    Ok(())
+}
+
 pub struct OwnersOptions {
     pub krate: Option<String>,
     pub token: Option<String>,
@@ -790,6 +942,7 @@ pub fn yank(
     index: Option<String>,
     undo: bool,
     reg: Option<String>,
+    to: Option<PathBuf>,
 ) -> CargoResult<()> {
     let name = match krate {
         Some(name) => name,
@@ -804,6 +957,11 @@ pub fn yank(
         None => bail!("a version must be specified to yank"),
     };
 
+    if let Some((root, _)) = local::target(config, to.as_deref(), index.as_deref(), reg.as_deref())?
+    {
+        return local::yank(config, &root, &name, &version, undo);
+    }
+
     let (mut registry, _) = registry(config, token, index, reg, true, true)?;
 
     if undo {
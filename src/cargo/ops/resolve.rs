@@ -11,18 +11,26 @@
 //!   providing the most power and flexibility.
 
 use crate::core::compiler::{CompileKind, RustcTargetData};
+use crate::core::dependency::DepKind;
 use crate::core::registry::PackageRegistry;
 use crate::core::resolver::features::{FeatureResolver, ForceAllTargets, ResolvedFeatures};
-use crate::core::resolver::{self, HasDevUnits, Resolve, ResolveOpts};
+use crate::core::resolver::{
+    self, HasDevUnits, IncompatibleRustVersions, Resolve, ResolveError, ResolveOpts,
+};
 use crate::core::summary::Summary;
 use crate::core::Feature;
-use crate::core::{PackageId, PackageIdSpec, PackageSet, Source, SourceId, Workspace};
-use crate::ops;
-use crate::sources::PathSource;
+use crate::core::{Package, PackageId, PackageIdSpec, PackageSet, Source, SourceId, Workspace};
+use crate::ops::tree::{graph, Charset, EdgeKind, Prefix, Target, TreeOptions};
+use crate::ops::{self, Packages};
+use crate::sources::{PathSource, CRATES_IO_REGISTRY};
 use crate::util::errors::{CargoResult, CargoResultExt};
-use crate::util::{profile, CanonicalUrl};
+use crate::util::interning::InternedString;
+use crate::util::machine_message::{self, Message};
+use crate::util::{profile, CanonicalUrl, Config};
 use log::{debug, trace};
-use std::collections::HashSet;
+use semver::Version;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use url::Url;
 
 /// Result for `resolve_ws_with_opts`.
 pub struct WorkspaceResolve<'cfg> {
@@ -152,12 +160,263 @@ pub fn resolve_ws_with_opts<'cfg>(
         force_all_targets,
     )?;
 
-    Ok(WorkspaceResolve {
+    let ws_resolve = WorkspaceResolve {
         pkg_set,
         workspace_resolve: resolve,
         targeted_resolve: resolved_with_overrides,
         resolved_features,
-    })
+    };
+    check_dependency_policy(
+        ws,
+        &ws_resolve,
+        target_data,
+        requested_targets,
+        opts,
+        specs,
+        has_dev_units,
+        force_all_targets,
+    )?;
+    Ok(ws_resolve)
+}
+
+/// A way the dependency graph doesn't comply with the
+/// `[workspace.dependency-policy]` of the workspace.
+enum PolicyViolation {
+    Banned(PackageId),
+    Source(PackageId),
+    /// There are more than `max` semver incompatible versions of `name`.
+    Versions {
+        name: InternedString,
+        ids: Vec<PackageId>,
+        max: usize,
+    },
+}
+
+/// Checks the resolved dependencies against the
+/// `[workspace.dependency-policy]` of the workspace, if it has one.
+///
+/// Every package of the resolve is checked, whatever the platform it's for,
+/// so that the outcome doesn't depend on the host. Errors describe how the
+/// offending packages are depended on, using the `cargo tree` graph of the
+/// requested targets.
+fn check_dependency_policy(
+    ws: &Workspace<'_>,
+    ws_resolve: &WorkspaceResolve<'_>,
+    target_data: &RustcTargetData,
+    requested_targets: &[CompileKind],
+    opts: &ResolveOpts,
+    specs: &[PackageIdSpec],
+    has_dev_units: HasDevUnits,
+    force_all_targets: ForceAllTargets,
+) -> CargoResult<()> {
+    let policy = match ws.dependency_policy() {
+        Some(policy) => policy,
+        None => return Ok(()),
+    };
+    let config = ws.config();
+    let resolve = &ws_resolve.targeted_resolve;
+    let mut ids: Vec<PackageId> = resolve.iter().collect();
+    ids.sort();
+
+    let allowed_registries = match &policy.allow_registries {
+        Some(names) => Some(
+            names
+                .iter()
+                .map(|name| match name.as_str() {
+                    CRATES_IO_REGISTRY => SourceId::crates_io(config),
+                    name => SourceId::alt_registry(config, name),
+                })
+                .collect::<CargoResult<Vec<_>>>()
+                .chain_err(|| "failed to load `workspace.dependency-policy.allow-registries`")?,
+        ),
+        None => None,
+    };
+    let is_allowed_source = |source_id: SourceId| {
+        if source_id.is_registry() {
+            allowed_registries.as_ref().map_or(true, |allowed| {
+                allowed
+                    .iter()
+                    .any(|registry| registry.canonical_url() == source_id.canonical_url())
+            })
+        } else if source_id.is_git() {
+            policy.allow_git.as_ref().map_or(true, |allowed| {
+                allowed
+                    .iter()
+                    .any(|entry| is_allowed_git(source_id.canonical_url(), entry))
+            })
+        } else {
+            true
+        }
+    };
+
+    let mut violations = Vec::new();
+    for &id in &ids {
+        let banned = policy.ban.iter().any(|banned| {
+            banned.name == id.name()
+                && banned
+                    .version
+                    .as_ref()
+                    .map_or(true, |req| req.matches(id.version()))
+        });
+        if banned {
+            violations.push(PolicyViolation::Banned(id));
+        }
+        if !is_allowed_source(id.source_id()) {
+            violations.push(PolicyViolation::Source(id));
+        }
+    }
+    if let Some(max) = policy.max_versions {
+        let mut copies: BTreeMap<InternedString, Vec<PackageId>> = BTreeMap::new();
+        for &id in &ids {
+            let same_name = copies.entry(id.name()).or_default();
+            let key = id.as_activations_key();
+            if !same_name
+                .iter()
+                .any(|other| other.as_activations_key() == key)
+            {
+                same_name.push(id);
+            }
+        }
+        for (name, ids) in copies {
+            if ids.len() > max {
+                violations.push(PolicyViolation::Versions { name, ids, max });
+            }
+        }
+    }
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    // Build the same graph as `cargo tree`, to find the paths from the
+    // members to the offending packages.
+    let package_map: HashMap<PackageId, &Package> = ws_resolve
+        .pkg_set
+        .get_many(ws_resolve.pkg_set.package_ids())?
+        .into_iter()
+        .map(|pkg| (pkg.package_id(), pkg))
+        .collect();
+    let mut edge_kinds: HashSet<EdgeKind> = vec![
+        EdgeKind::Dep(DepKind::Normal),
+        EdgeKind::Dep(DepKind::Build),
+    ]
+    .into_iter()
+    .collect();
+    if has_dev_units == HasDevUnits::Yes {
+        edge_kinds.insert(EdgeKind::Dep(DepKind::Development));
+    }
+    let tree_opts = TreeOptions {
+        features: Vec::new(),
+        no_default_features: false,
+        all_features: false,
+        packages: Packages::Default,
+        target: match force_all_targets {
+            ForceAllTargets::Yes => Target::All,
+            ForceAllTargets::No => Target::Host,
+        },
+        edge_kinds,
+        invert: Vec::new(),
+        prefix: Prefix::None,
+        no_dedupe: false,
+        duplicates: false,
+        charset: Charset::Ascii,
+        format: String::new(),
+        graph_features: false,
+    };
+    let graph = graph::build(
+        ws,
+        resolve,
+        &ws_resolve.resolved_features,
+        specs,
+        &opts.features,
+        target_data,
+        requested_targets,
+        package_map,
+        &tree_opts,
+    )?;
+    let member_ids: Vec<PackageId> = ws.members().map(|pkg| pkg.package_id()).collect();
+    let roots = graph.indexes_from_ids(&member_ids);
+    let dependents = |id: PackageId| {
+        let shortest = graph
+            .indexes_from_ids(&[id])
+            .into_iter()
+            .filter_map(|index| graph.path_from(&roots, index))
+            .min_by_key(|path| path.len());
+        let path: Vec<PackageId> = match shortest {
+            Some(path) => path
+                .into_iter()
+                .rev()
+                .map(|index| graph.package_id_for_index(index))
+                .collect(),
+            // Such as packages only used on other platforms.
+            None => resolve.path_to_top(&id).into_iter().cloned().collect(),
+        };
+        path[1..]
+            .iter()
+            .map(|dep| format!("\n    ... which is depended on by `{}`", dep))
+            .collect::<String>()
+    };
+
+    let mut msg = format!(
+        "the dependency graph violates the dependency policy of the workspace, \
+         set in `{}`",
+        ws.root_manifest().display()
+    );
+    for violation in violations {
+        msg.push_str("\n\n");
+        match violation {
+            PolicyViolation::Banned(id) => {
+                msg.push_str(&format!("package `{}` is banned{}", id, dependents(id)));
+            }
+            PolicyViolation::Source(id) => {
+                msg.push_str(&format!(
+                    "package `{}` comes from a source which is not allowed{}",
+                    id,
+                    dependents(id)
+                ));
+            }
+            PolicyViolation::Versions { name, ids, max } => {
+                msg.push_str(&format!(
+                    "package `{}` has {} semver incompatible versions, \
+                     but at most {} {} allowed",
+                    name,
+                    ids.len(),
+                    max,
+                    if max == 1 { "is" } else { "are" }
+                ));
+                for id in ids {
+                    msg.push_str(&format!("\n\npackage `{}`{}", id, dependents(id)));
+                }
+            }
+        }
+    }
+    anyhow::bail!(msg)
+}
+
+/// Whether the `allow-git` entry `entry` of the dependency policy allows the
+/// git repository at `url`.
+///
+/// An entry is either a host, or a URL which the repository must be at or
+/// under, by whole path segments: `https://github.com/org` allows
+/// `https://github.com/org/repo` but not `https://github.com/org-evil/repo`.
+fn is_allowed_git(url: &CanonicalUrl, entry: &str) -> bool {
+    fn segments(url: &Url) -> Vec<&str> {
+        url.path_segments()
+            .into_iter()
+            .flatten()
+            .filter(|segment| !segment.is_empty())
+            .collect()
+    }
+
+    let url = url.raw_canonicalized_url();
+    let prefix = match entry.parse().ok().and_then(|u| CanonicalUrl::new(&u).ok()) {
+        Some(prefix) => prefix,
+        None => return url.host_str() == Some(entry),
+    };
+    let prefix = prefix.raw_canonicalized_url();
+    url.scheme() == prefix.scheme()
+        && url.host() == prefix.host()
+        && url.port_or_known_default() == prefix.port_or_known_default()
+        && segments(url).starts_with(&segments(prefix))
 }
 
 fn resolve_with_registry<'cfg>(
@@ -346,14 +605,24 @@ pub fn resolve_with_previous<'cfg>(
     };
 
     ws.preload(registry);
-    let mut resolved = resolver::resolve(
+    let rust_version = resolver_rust_version(ws)?;
+    let mut resolved = match resolver::resolve(
         &summaries,
         &replace,
         registry,
         &try_to_use,
+        rust_version.as_ref(),
         Some(ws.config()),
         ws.features().require(Feature::public_dependency()).is_ok(),
-    )?;
+    ) {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            if ws.json_messages() {
+                emit_conflict_report(ws.config(), &e)?;
+            }
+            return Err(e);
+        }
+    };
     resolved.register_used_patches(&registry.patches());
     if register_patches {
         // It would be good if this warning was more targeted and helpful
@@ -374,11 +643,32 @@ pub fn resolve_with_previous<'cfg>(
         }
     }
     if let Some(previous) = previous {
-        resolved.merge_from(previous)?;
+        resolved.merge_from(previous, to_avoid)?;
     }
     Ok(resolved)
 }
 
+/// Returns the Rust version the resolver should prefer dependency versions
+/// for, if the workspace sets `incompatible-rust-versions = "fallback"`.
+///
+/// This is the oldest `rust-version` of the workspace members, or the version
+/// of the active `rustc` if none of them declares one.
+pub fn resolver_rust_version(ws: &Workspace<'_>) -> CargoResult<Option<Version>> {
+    if ws.incompatible_rust_versions() == IncompatibleRustVersions::Allow {
+        return Ok(None);
+    }
+    if let Some(rust_version) = ws.rust_version() {
+        return Ok(Some(rust_version));
+    }
+    // Pre-releases count as the release they lead to, as when building.
+    let rustc = ws.config().load_global_rustc(Some(ws))?;
+    Ok(Some(Version::new(
+        rustc.version.major,
+        rustc.version.minor,
+        rustc.version.patch,
+    )))
+}
+
 /// Read the `paths` configuration variable to discover all path overrides that
 /// have been configured.
 pub fn add_overrides<'a>(
@@ -609,3 +899,23 @@ fn register_previous_locks(
         }
     }
 }
+
+/// Prints the explanation of a resolver conflict as a JSON message, so
+/// tooling can see the competing requirements.
+fn emit_conflict_report(config: &Config, err: &anyhow::Error) -> CargoResult<()> {
+    let report = match err
+        .chain()
+        .find_map(|e| e.downcast_ref::<ResolveError>())
+        .and_then(|e| e.conflict_report())
+    {
+        Some(report) => report,
+        None => return Ok(()),
+    };
+    let msg = machine_message::ResolveConflict {
+        report,
+        explanation: report.to_string(),
+    }
+    .to_json_string();
+    writeln!(config.shell().out(), "{}", msg)?;
+    Ok(())
+}
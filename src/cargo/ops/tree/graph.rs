@@ -8,7 +8,7 @@ use crate::core::resolver::Resolve;
 use crate::core::{FeatureMap, FeatureValue, Package, PackageId, PackageIdSpec, Workspace};
 use crate::util::interning::InternedString;
 use crate::util::CargoResult;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub enum Node {
@@ -148,7 +148,7 @@ impl<'a> Graph<'a> {
         self.package_map[&id]
     }
 
-    fn package_id_for_index(&self, index: usize) -> PackageId {
+    pub fn package_id_for_index(&self, index: usize) -> PackageId {
         match self.nodes[index] {
             Node::Package { package_id, .. } => package_id,
             Node::Feature { .. } => panic!("unexpected feature node"),
@@ -214,6 +214,34 @@ impl<'a> Graph<'a> {
         self.edges = new_edges;
     }
 
+    /// Returns one of the shortest paths from one of the `roots` to the node
+    /// `to`, both included, following edges of any kind.
+    pub fn path_from(&self, roots: &[usize], to: usize) -> Option<Vec<usize>> {
+        let mut parents: HashMap<usize, Option<usize>> =
+            roots.iter().map(|root| (*root, None)).collect();
+        let mut queue: VecDeque<usize> = roots.iter().cloned().collect();
+        while let Some(index) = queue.pop_front() {
+            if index == to {
+                let mut path = vec![index];
+                while let Some(parent) = parents[path.last().unwrap()] {
+                    path.push(parent);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            // Sorted for consistent output.
+            let mut next: Vec<usize> = self.edges[index].0.values().flatten().cloned().collect();
+            next.sort_unstable_by(|a, b| self.nodes[*a].cmp(&self.nodes[*b]));
+            for next_index in next {
+                if !parents.contains_key(&next_index) {
+                    parents.insert(next_index, Some(index));
+                    queue.push_back(next_index);
+                }
+            }
+        }
+        None
+    }
+
     /// Returns a list of nodes that are considered "duplicates" (same package
     /// name, with different versions/features/source/etc.).
     pub fn find_duplicates(&self) -> Vec<usize> {
@@ -1,9 +1,16 @@
+use crate::core::compiler::{CompileKind, CompileTarget, RustcTargetData};
+use crate::core::dependency::DepKind;
+use crate::core::manifest::TargetSourcePath;
+use crate::core::resolver::Resolve;
 use crate::core::shell::Verbosity;
-use crate::core::{GitReference, Workspace};
+use crate::core::{GitReference, Package, PackageId, SourceId, Workspace};
 use crate::ops;
+use crate::ops::registry::{IndexDependency, IndexPackage};
 use crate::sources::path::PathSource;
-use crate::util::Sha256;
+use crate::sources::registry::{crate_cache_path, index_file_path};
+use crate::sources::SourceConfigMap;
 use crate::util::{paths, CargoResult, CargoResultExt, Config};
+use crate::util::{Checksum, ChecksumAlgorithm, Sha256};
 use anyhow::bail;
 use serde::Serialize;
 use std::collections::HashSet;
@@ -16,6 +23,32 @@ pub struct VendorOptions<'a> {
     pub versioned_dirs: bool,
     pub destination: &'a Path,
     pub extra: Vec<PathBuf>,
+    pub format: VendorFormat,
+    /// The target triples to vendor packages for. If empty, packages are
+    /// vendored for all platforms.
+    pub platforms: Vec<String>,
+}
+
+/// How the vendored packages are laid out in the destination directory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VendorFormat {
+    /// A directory source, with the unpacked sources of each package.
+    Directory,
+    /// A local registry, with the `.crate` file of each package and an index.
+    LocalRegistry,
+}
+
+impl VendorFormat {
+    pub fn from_name(name: &str) -> CargoResult<VendorFormat> {
+        match name {
+            "directory" => Ok(VendorFormat::Directory),
+            "local-registry" => Ok(VendorFormat::LocalRegistry),
+            _ => bail!(
+                "unknown vendor format `{}`, expected `directory` or `local-registry`",
+                name
+            ),
+        }
+    }
 }
 
 pub fn vendor(ws: &Workspace<'_>, opts: &VendorOptions<'_>) -> CargoResult<()> {
@@ -52,6 +85,10 @@ enum VendorSource {
     Directory {
         directory: PathBuf,
     },
+    LocalRegistry {
+        #[serde(rename = "local-registry")]
+        local_registry: PathBuf,
+    },
     Registry {
         registry: Option<String>,
         #[serde(rename = "replace-with")]
@@ -83,6 +120,10 @@ fn sync(
     if !opts.no_delete {
         for entry in canonical_destination.read_dir()? {
             let entry = entry?;
+            // Stale index files are removed once the new index is written.
+            if opts.format == VendorFormat::LocalRegistry && entry.file_name() == "index" {
+                continue;
+            }
             if !entry
                 .file_name()
                 .to_str()
@@ -133,6 +174,7 @@ fn sync(
 
     let mut checksums = HashMap::new();
     let mut ids = BTreeMap::new();
+    let mut used = HashSet::new();
 
     // Next up let's actually download all crates and start storing internal
     // tables about them.
@@ -144,6 +186,10 @@ fn sync(
             .get_many(resolve.iter())
             .chain_err(|| "failed to download packages")?;
 
+        if !opts.platforms.is_empty() {
+            used.extend(platform_packages(ws, &resolve, &opts.platforms)?);
+        }
+
         for pkg in resolve.iter() {
             // No need to vendor path crates since they're already in the
             // repository
@@ -162,6 +208,18 @@ fn sync(
         }
     }
 
+    // Packages which aren't built for any of the platforms are still needed to
+    // resolve dependencies, so their manifests are vendored, but not their
+    // sources.
+    let unused = if opts.platforms.is_empty() {
+        HashSet::new()
+    } else {
+        ids.keys()
+            .filter(|id| !used.contains(*id))
+            .cloned()
+            .collect()
+    };
+
     let mut versions = HashMap::new();
     for id in ids.keys() {
         let map = versions.entry(id.name()).or_insert_with(BTreeMap::default);
@@ -182,50 +240,74 @@ fn sync(
     }
 
     let mut sources = BTreeSet::new();
-    for (id, pkg) in ids.iter() {
-        // Next up, copy it to the vendor directory
-        let src = pkg
-            .manifest_path()
-            .parent()
-            .expect("manifest_path should point to a file");
-        let max_version = *versions[&id.name()].iter().rev().next().unwrap().0;
-        let dir_has_version_suffix = opts.versioned_dirs || id.version() != max_version;
-        let dst_name = if dir_has_version_suffix {
-            // Eg vendor/futures-0.1.13
-            format!("{}-{}", id.name(), id.version())
-        } else {
-            // Eg vendor/futures
-            id.name().to_string()
-        };
-
-        sources.insert(id.source_id());
-        let dst = canonical_destination.join(&dst_name);
-        to_remove.remove(&dst);
-        let cksum = dst.join(".cargo-checksum.json");
-        if dir_has_version_suffix && cksum.exists() {
-            // Always re-copy directory without version suffix in case the version changed
-            continue;
-        }
-
-        config.shell().status(
-            "Vendoring",
-            &format!("{} ({}) to {}", id, src.to_string_lossy(), dst.display()),
+    if opts.format == VendorFormat::LocalRegistry {
+        sources.extend(ids.keys().map(|id| id.source_id()));
+        sync_local_registry(
+            config,
+            &ids,
+            &checksums,
+            &unused,
+            canonical_destination,
+            &mut to_remove,
+            opts.no_delete,
         )?;
+    } else {
+        for (id, pkg) in ids.iter() {
+            // Next up, copy it to the vendor directory
+            let src = pkg
+                .manifest_path()
+                .parent()
+                .expect("manifest_path should point to a file");
+            let max_version = *versions[&id.name()].iter().rev().next().unwrap().0;
+            let dir_has_version_suffix = opts.versioned_dirs || id.version() != max_version;
+            let dst_name = if dir_has_version_suffix {
+                // Eg vendor/futures-0.1.13
+                format!("{}-{}", id.name(), id.version())
+            } else {
+                // Eg vendor/futures
+                id.name().to_string()
+            };
+
+            sources.insert(id.source_id());
+            let dst = canonical_destination.join(&dst_name);
+            to_remove.remove(&dst);
+            let cksum = dst.join(".cargo-checksum.json");
+            if unused.contains(id) {
+                let _ = fs::remove_dir_all(&dst);
+                write_stub(pkg, &dst)
+                    .chain_err(|| format!("failed to write the manifest of: {}", id))?;
+                let json = serde_json::json!({
+                    "package": checksums.get(id),
+                    "files": {},
+                });
+                paths::write(&cksum, json.to_string())?;
+                continue;
+            }
+            if dir_has_version_suffix && cksum.exists() && !is_stub(&cksum)? {
+                // Always re-copy directory without version suffix in case the version changed
+                continue;
+            }
 
-        let _ = fs::remove_dir_all(&dst);
-        let pathsource = PathSource::new(src, id.source_id(), config);
-        let paths = pathsource.list_files(pkg)?;
-        let mut map = BTreeMap::new();
-        cp_sources(src, &paths, &dst, &mut map)
-            .chain_err(|| format!("failed to copy over vendored sources for: {}", id))?;
-
-        // Finally, emit the metadata about this package
-        let json = serde_json::json!({
-            "package": checksums.get(id),
-            "files": map,
-        });
-
-        paths::write(&cksum, json.to_string())?;
+            config.shell().status(
+                "Vendoring",
+                &format!("{} ({}) to {}", id, src.to_string_lossy(), dst.display()),
+            )?;
+
+            let _ = fs::remove_dir_all(&dst);
+            let pathsource = PathSource::new(src, id.source_id(), config);
+            let paths = pathsource.list_files(pkg)?;
+            let mut map = BTreeMap::new();
+            cp_sources(src, &paths, &dst, &mut map)
+                .chain_err(|| format!("failed to copy over vendored sources for: {}", id))?;
+
+            // Finally, emit the metadata about this package
+            let json = serde_json::json!({
+                "package": checksums.get(id),
+                "files": map,
+            });
+
+            paths::write(&cksum, json.to_string())?;
+        }
     }
 
     for path in to_remove {
@@ -240,12 +322,15 @@ fn sync(
     let mut config = BTreeMap::new();
 
     let merged_source_name = "vendored-sources";
-    config.insert(
-        merged_source_name.to_string(),
-        VendorSource::Directory {
+    let merged_source = match opts.format {
+        VendorFormat::Directory => VendorSource::Directory {
             directory: opts.destination.to_path_buf(),
         },
-    );
+        VendorFormat::LocalRegistry => VendorSource::LocalRegistry {
+            local_registry: opts.destination.to_path_buf(),
+        },
+    };
+    config.insert(merged_source_name.to_string(), merged_source);
 
     // replace original sources with vendor
     for source_id in sources {
@@ -294,6 +379,246 @@ fn sync(
     Ok(VendorConfig { source: config })
 }
 
+/// Copies the `.crate` file of each package of `ids` to `dst`, and writes
+/// an index listing them, so that `dst` can be used as a local registry.
+///
+/// The packages of `unused` are only listed in the index, which is enough
+/// to resolve dependencies as long as they aren't built.
+///
+/// Only the `.crate` files which changed are copied, and the index files
+/// are only rewritten if their contents changed.
+fn sync_local_registry(
+    config: &Config,
+    ids: &BTreeMap<PackageId, Package>,
+    checksums: &HashMap<PackageId, Option<Option<String>>>,
+    unused: &HashSet<PackageId>,
+    dst: &Path,
+    to_remove: &mut HashSet<PathBuf>,
+    no_delete: bool,
+) -> CargoResult<()> {
+    // With `--respect-source-config`, the `.crate` files were downloaded from
+    // the replacements of the sources.
+    let source_config = SourceConfigMap::new(config)?;
+    let mut index = BTreeMap::new();
+    for (id, pkg) in ids.iter() {
+        if !id.source_id().is_registry() {
+            bail!(
+                "`{}` can't be vendored in the `local-registry` format, \
+                 only packages from registries can",
+                id
+            );
+        }
+        let replaced_id = source_config
+            .load(id.source_id(), &HashSet::new())?
+            .replaced_source_id();
+        let src = crate_cache_path(config, id.with_source_id(replaced_id)).into_path_unlocked();
+        let cksum = match checksums[id].clone().flatten() {
+            Some(cksum) => Checksum::parse(&cksum)?,
+            None => Checksum::compute_path(ChecksumAlgorithm::Sha256, &src)?,
+        };
+
+        if !unused.contains(id) {
+            let crate_file = dst.join(format!("{}-{}.crate", id.name(), id.version()));
+            to_remove.remove(&crate_file);
+            let up_to_date = crate_file.exists()
+                && Checksum::compute_path(cksum.algorithm(), &crate_file)? == cksum;
+            if !up_to_date {
+                config.shell().status(
+                    "Vendoring",
+                    &format!("{} ({}) to {}", id, src.display(), crate_file.display()),
+                )?;
+                paths::copy(&src, &crate_file)
+                    .chain_err(|| format!("failed to copy over the `.crate` file of {}", id))?;
+            }
+        }
+
+        index
+            .entry(id.name())
+            .or_insert_with(Vec::new)
+            .push(index_package(config, pkg, cksum)?);
+    }
+
+    let index_dir = dst.join("index");
+    let mut index_files = HashSet::new();
+    for (name, entries) in index {
+        let path = index_dir.join(index_file_path(&name));
+        let mut contents = String::new();
+        // Versions vendored before are kept with `--no-delete`, like their
+        // `.crate` files.
+        if no_delete && path.exists() {
+            for line in paths::read(&path)?.lines() {
+                let prev: serde_json::Value = serde_json::from_str(line)
+                    .chain_err(|| format!("failed to parse `{}`", path.display()))?;
+                if !entries.iter().any(|entry| prev["vers"] == *entry.vers) {
+                    contents.push_str(line);
+                    contents.push('\n');
+                }
+            }
+        }
+        for entry in entries {
+            contents.push_str(&serde_json::to_string(&entry)?);
+            contents.push('\n');
+        }
+        if paths::read(&path).ok().as_ref() != Some(&contents) {
+            paths::create_dir_all(path.parent().unwrap())?;
+            paths::write(&path, contents)?;
+        }
+        index_files.insert(path);
+    }
+    if !no_delete {
+        remove_stale_index_files(&index_dir, &index_files)?;
+    }
+    Ok(())
+}
+
+/// Describes `pkg`, whose `.crate` file has the checksum `cksum`, like its
+/// registry index does.
+fn index_package(config: &Config, pkg: &Package, cksum: Checksum) -> CargoResult<IndexPackage> {
+    let registry_id = pkg.package_id().source_id();
+    let deps = pkg
+        .dependencies()
+        .iter()
+        .map(|dep| {
+            // In the index, None means "from the same registry" whereas in
+            // Cargo.toml, it means "from crates.io".
+            let dep_registry_id = match dep.registry_id() {
+                Some(id) => id,
+                None => SourceId::crates_io(config)?,
+            };
+            let registry = if dep_registry_id != registry_id {
+                Some(dep_registry_id.url().to_string())
+            } else {
+                None
+            };
+            Ok(IndexDependency {
+                name: dep.name_in_toml().to_string(),
+                req: dep.version_req().to_string(),
+                features: dep.features().iter().map(|s| s.to_string()).collect(),
+                optional: dep.is_optional(),
+                default_features: dep.uses_default_features(),
+                target: dep.platform().map(|s| s.to_string()),
+                kind: match dep.kind() {
+                    DepKind::Normal => "normal",
+                    DepKind::Build => "build",
+                    DepKind::Development => "dev",
+                }
+                .to_string(),
+                registry,
+                package: dep
+                    .explicit_name_in_toml()
+                    .map(|_| dep.package_name().to_string()),
+            })
+        })
+        .collect::<CargoResult<Vec<_>>>()?;
+    let summary = pkg.summary();
+    let features = summary
+        .features()
+        .iter()
+        .map(|(name, values)| {
+            let values = values.iter().map(|v| v.to_string(summary)).collect();
+            (name.to_string(), values)
+        })
+        .collect();
+    Ok(IndexPackage {
+        name: pkg.name().to_string(),
+        vers: pkg.version().to_string(),
+        deps,
+        cksum: cksum.to_string(),
+        features,
+        yanked: false,
+        links: pkg.manifest().links().map(|s| s.to_string()),
+        rust_version: pkg.rust_version().map(|s| s.to_string()),
+    })
+}
+
+/// Removes the files under `dir` which aren't in `keep`, and the directories
+/// left empty.
+fn remove_stale_index_files(dir: &Path, keep: &HashSet<PathBuf>) -> CargoResult<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in dir.read_dir()? {
+        let path = entry?.path();
+        if path.is_dir() {
+            remove_stale_index_files(&path, keep)?;
+            if path.read_dir()?.next().is_none() {
+                paths::remove_dir(&path)?;
+            }
+        } else if !keep.contains(&path) {
+            paths::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the packages of `resolve` which are built when building the
+/// members of `ws` for one of the target triples of `platforms`.
+///
+/// Build scripts and procedural macros are assumed to run on one of
+/// `platforms` too, so the host has to be listed to cross-compile.
+fn platform_packages(
+    ws: &Workspace<'_>,
+    resolve: &Resolve,
+    platforms: &[String],
+) -> CargoResult<HashSet<PackageId>> {
+    let kinds = platforms
+        .iter()
+        .map(|platform| Ok(CompileKind::Target(CompileTarget::new(platform)?)))
+        .collect::<CargoResult<Vec<_>>>()?;
+    let target_data = RustcTargetData::new(ws, &kinds)?;
+
+    let mut visited = HashSet::new();
+    let mut stack = Vec::new();
+    for member in ws.members() {
+        for kind in kinds.iter() {
+            stack.push((member.package_id(), *kind));
+        }
+    }
+    while let Some((id, kind)) = stack.pop() {
+        if !visited.insert((id, kind)) {
+            continue;
+        }
+        for (dep_id, deps) in resolve.deps(id) {
+            // Like in `cargo tree`, dependencies are only followed if their
+            // `target` matches the platform.
+            if deps
+                .iter()
+                .any(|dep| target_data.dep_platform_activated(dep, kind))
+            {
+                stack.push((dep_id, kind));
+            }
+        }
+    }
+    Ok(visited.into_iter().map(|(id, _)| id).collect())
+}
+
+/// Writes the manifest of `pkg` to `dst`, with an empty file for each of its
+/// targets, which is enough to resolve dependencies on it without building
+/// it.
+fn write_stub(pkg: &Package, dst: &Path) -> CargoResult<()> {
+    let src = pkg.root();
+    paths::create_dir_all(dst)?;
+    paths::copy(pkg.manifest_path(), dst.join("Cargo.toml"))?;
+    for target in pkg.targets() {
+        if let TargetSourcePath::Path(path) = target.src_path() {
+            let path = dst.join(path.strip_prefix(src).unwrap_or(path));
+            paths::create_dir_all(path.parent().unwrap())?;
+            paths::write(&path, "")?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether the `.cargo-checksum.json` file at `path` is the one of a
+/// stub written by `write_stub`, which doesn't list any file.
+fn is_stub(path: &Path) -> CargoResult<bool> {
+    let json: serde_json::Value = serde_json::from_str(&paths::read(path)?)
+        .chain_err(|| format!("failed to parse `{}`", path.display()))?;
+    Ok(json["files"]
+        .as_object()
+        .map_or(false, |files| files.is_empty()))
+}
+
 fn cp_sources(
     src: &Path,
     paths: &[PathBuf],
@@ -43,6 +43,9 @@ struct SourceConfigDef {
     tag: OptValue<String>,
     /// The git revision.
     rev: OptValue<String>,
+    /// The key the commits of the index of a registry source are signed
+    /// with, read by `RemoteRegistry` when updating the index.
+    signing_key: OptValue<String>,
 }
 
 /// Configuration for a particular source, found in TOML looking like:
@@ -106,47 +109,11 @@ impl<'cfg> SourceConfigMap<'cfg> {
     ) -> CargoResult<Box<dyn Source + 'cfg>> {
         debug!("loading: {}", id);
 
-        let mut name = match self.id2name.get(&id) {
-            Some(name) => name,
+        let (new_id, name) = match self.find_replacement(id)? {
+            Some(replacement) => replacement,
             None => return Ok(id.load(self.config, yanked_whitelist)?),
         };
-        let mut cfg_loc = "";
-        let orig_name = name;
-        let new_id;
-        loop {
-            let cfg = match self.cfgs.get(name) {
-                Some(cfg) => cfg,
-                None => bail!(
-                    "could not find a configured source with the \
-                     name `{}` when attempting to lookup `{}` \
-                     (configuration in `{}`)",
-                    name,
-                    orig_name,
-                    cfg_loc
-                ),
-            };
-            match &cfg.replace_with {
-                Some((s, c)) => {
-                    name = s;
-                    cfg_loc = c;
-                }
-                None if id == cfg.id => return Ok(id.load(self.config, yanked_whitelist)?),
-                None => {
-                    new_id = cfg.id.with_precise(id.precise().map(|s| s.to_string()));
-                    break;
-                }
-            }
-            debug!("following pointer to {}", name);
-            if name == orig_name {
-                bail!(
-                    "detected a cycle of `replace-with` sources, the source \
-                     `{}` is eventually replaced with itself \
-                     (configuration in `{}`)",
-                    name,
-                    cfg_loc
-                )
-            }
-        }
+        let orig_name = &self.id2name[&id];
 
         let new_src = new_id.load(
             self.config,
@@ -185,6 +152,60 @@ restore the source replacement configuration to continue the build
         Ok(Box::new(ReplacedSource::new(id, new_id, new_src)))
     }
 
+    /// Returns the `SourceId` which `id` is replaced with by the `[source]`
+    /// configuration, or `id` itself if it isn't replaced.
+    pub fn replacement(&self, id: SourceId) -> CargoResult<SourceId> {
+        Ok(match self.find_replacement(id)? {
+            Some((new_id, _)) => new_id,
+            None => id,
+        })
+    }
+
+    /// Follows the `replace-with` chain of `id`, returning the source it ends
+    /// at along with its name, or `None` if `id` isn't replaced.
+    fn find_replacement(&self, id: SourceId) -> CargoResult<Option<(SourceId, &str)>> {
+        let mut name = match self.id2name.get(&id) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        let mut cfg_loc = "";
+        let orig_name = name;
+        loop {
+            let cfg = match self.cfgs.get(name) {
+                Some(cfg) => cfg,
+                None => bail!(
+                    "could not find a configured source with the \
+                     name `{}` when attempting to lookup `{}` \
+                     (configuration in `{}`)",
+                    name,
+                    orig_name,
+                    cfg_loc
+                ),
+            };
+            match &cfg.replace_with {
+                Some((s, c)) => {
+                    name = s;
+                    cfg_loc = c;
+                }
+                None if id == cfg.id => return Ok(None),
+                None => {
+                    let new_id = cfg.id.with_precise(id.precise().map(|s| s.to_string()));
+                    return Ok(Some((new_id, name)));
+                }
+            }
+            debug!("following pointer to {}", name);
+            if name == orig_name {
+                bail!(
+                    "detected a cycle of `replace-with` sources, the source \
+                     `{}` is eventually replaced with itself \
+                     (configuration in `{}`)",
+                    name,
+                    cfg_loc
+                )
+            }
+        }
+    }
+
     fn add(&mut self, name: &str, cfg: SourceConfig) -> CargoResult<()> {
         if let Some(old_name) = self.id2name.insert(cfg.id, name.to_string()) {
             // The user is allowed to redefine the built-in crates-io
@@ -205,6 +226,14 @@ restore the source replacement configuration to continue the build
 
     fn add_config(&mut self, name: String, def: SourceConfigDef) -> CargoResult<()> {
         let mut srcs = Vec::new();
+        if let (None, Some(val)) = (&def.registry, &def.signing_key) {
+            bail!(
+                "source definition `source.{}` specifies `signing-key`, \
+                 but that requires a `registry` key to be specified (in {})",
+                name,
+                val.definition
+            );
+        }
         if let Some(registry) = def.registry {
             let url = url(&registry, &format!("source.{}.registry", name))?;
             srcs.push(SourceId::for_registry(&url)?);
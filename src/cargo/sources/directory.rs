@@ -9,7 +9,7 @@ use crate::core::{Dependency, Package, PackageId, Source, SourceId, Summary};
 use crate::sources::PathSource;
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::paths;
-use crate::util::{Config, Sha256};
+use crate::util::{self, Config};
 
 pub struct DirectorySource<'cfg> {
     source_id: SourceId,
@@ -170,11 +170,11 @@ impl<'cfg> Source for DirectorySource<'cfg> {
 
         for (file, cksum) in cksum.files.iter() {
             let file = pkg.root().join(file);
-            let actual = Sha256::new()
-                .update_path(&file)
-                .chain_err(|| format!("failed to calculate checksum of: {}", file.display()))?
-                .finish_hex();
-            if &*actual != cksum {
+            let expected = util::Checksum::parse(cksum)
+                .chain_err(|| format!("invalid checksum of: {}", file.display()))?;
+            let actual = util::Checksum::compute_path(expected.algorithm(), &file)
+                .chain_err(|| format!("failed to calculate checksum of: {}", file.display()))?;
+            if actual != expected {
                 anyhow::bail!(
                     "the listed checksum of `{}` has changed:\n\
                      expected: {}\n\
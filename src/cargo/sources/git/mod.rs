@@ -1,4 +1,7 @@
 pub use self::source::GitSource;
-pub use self::utils::{fetch, GitCheckout, GitDatabase, GitRemote};
+pub use self::utils::{
+    fetch, fetch_with_authorization, is_auth_failure, verify_commit_signature, GitCheckout,
+    GitDatabase, GitRemote,
+};
 mod source;
 mod utils;
@@ -9,6 +9,7 @@ use crate::util::Config;
 use anyhow::Context;
 use log::trace;
 use std::fmt::{self, Debug, Formatter};
+use std::path::PathBuf;
 use url::Url;
 
 pub struct GitSource<'cfg> {
@@ -112,6 +113,61 @@ impl<'cfg> Source for GitSource<'cfg> {
     }
 
     fn update(&mut self) -> CargoResult<()> {
+        self.checkout()?;
+        self.path_source.as_mut().unwrap().update()
+    }
+
+    fn download(&mut self, id: PackageId) -> CargoResult<MaybePackage> {
+        trace!(
+            "getting packages for package ID `{}` from `{:?}`",
+            id,
+            self.remote
+        );
+        self.path_source
+            .as_mut()
+            .expect("BUG: `update()` must be called before `get()`")
+            .download(id)
+    }
+
+    fn finish_download(&mut self, _id: PackageId, _data: Vec<u8>) -> CargoResult<Package> {
+        panic!("no download should have started")
+    }
+
+    fn fingerprint(&self, _pkg: &Package) -> CargoResult<String> {
+        Ok(self.locked_rev.as_ref().unwrap().to_string())
+    }
+
+    fn describe(&self) -> String {
+        format!("Git repository {}", self.source_id)
+    }
+
+    fn add_to_yanked_whitelist(&mut self, _pkgs: &[PackageId]) {}
+
+    fn is_yanked(&mut self, _pkg: PackageId) -> CargoResult<bool> {
+        Ok(false)
+    }
+}
+
+impl<'cfg> GitSource<'cfg> {
+    /// Fetches the repository if needed, and checks out the locked revision,
+    /// or the one the reference points to, returning the directory it's in.
+    ///
+    /// This is what `update()` does, without reading the packages, for
+    /// repositories which are used for something else than packages.
+    pub fn checkout(&mut self) -> CargoResult<PathBuf> {
+        Ok(self.checkout_inner(true)?.unwrap())
+    }
+
+    /// Like `checkout`, but never fetches: an unlocked reference is resolved
+    /// in the repository as it was last fetched, as with `--offline`.
+    ///
+    /// Returns `None` if the repository hasn't been fetched yet, or doesn't
+    /// have the locked revision.
+    pub fn checkout_cached(&mut self) -> CargoResult<Option<PathBuf>> {
+        self.checkout_inner(false)
+    }
+
+    fn checkout_inner(&mut self, fetch: bool) -> CargoResult<Option<PathBuf>> {
         let git_path = self.config.git_path();
         let git_path = self.config.assert_package_cache_locked(&git_path);
         let db_path = git_path.join("db").join(&self.ident);
@@ -125,7 +181,7 @@ impl<'cfg> Source for GitSource<'cfg> {
             // If we're in offline mode, we're not locked, and we have a
             // database, then try to resolve our reference with the preexisting
             // repository.
-            (None, Some(db)) if self.config.offline() => {
+            (None, Some(db)) if self.config.offline() || !fetch => {
                 let rev = db
                     .resolve(&self.manifest_reference, None)
                     .with_context(|| {
@@ -140,6 +196,9 @@ impl<'cfg> Source for GitSource<'cfg> {
             // situation that we have a locked revision but the database
             // doesn't have it.
             (locked_rev, db) => {
+                if !fetch {
+                    return Ok(None);
+                }
                 if self.config.offline() {
                     anyhow::bail!(
                         "can't checkout from '{}': you are in the offline mode (--offline)",
@@ -182,37 +241,7 @@ impl<'cfg> Source for GitSource<'cfg> {
 
         self.path_source = Some(path_source);
         self.locked_rev = Some(actual_rev);
-        self.path_source.as_mut().unwrap().update()
-    }
-
-    fn download(&mut self, id: PackageId) -> CargoResult<MaybePackage> {
-        trace!(
-            "getting packages for package ID `{}` from `{:?}`",
-            id,
-            self.remote
-        );
-        self.path_source
-            .as_mut()
-            .expect("BUG: `update()` must be called before `get()`")
-            .download(id)
-    }
-
-    fn finish_download(&mut self, _id: PackageId, _data: Vec<u8>) -> CargoResult<Package> {
-        panic!("no download should have started")
-    }
-
-    fn fingerprint(&self, _pkg: &Package) -> CargoResult<String> {
-        Ok(self.locked_rev.as_ref().unwrap().to_string())
-    }
-
-    fn describe(&self) -> String {
-        format!("Git repository {}", self.source_id)
-    }
-
-    fn add_to_yanked_whitelist(&mut self, _pkgs: &[PackageId]) {}
-
-    fn is_yanked(&mut self, _pkg: PackageId) -> CargoResult<bool> {
-        Ok(false)
+        Ok(Some(checkout_path))
     }
 }
 
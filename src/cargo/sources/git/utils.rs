@@ -119,8 +119,9 @@ use serde::ser;
 use serde::Serialize;
 use std::env;
 use std::fmt;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 use url::Url;
 
 fn serialize_str<T, S>(t: &T, s: S) -> Result<S::Ok, S::Error>
@@ -860,6 +861,33 @@ pub fn fetch(
     url: &str,
     reference: &GitReference,
     config: &Config,
+) -> CargoResult<()> {
+    fetch_inner(repo, url, reference, config, None, true)
+}
+
+/// Like `fetch`, sending `authorization` as the `Authorization` header of
+/// the HTTP requests, for the indexes of registries which require
+/// authentication.
+///
+/// When `git` is used for the fetch, it isn't allowed to prompt for
+/// credentials, see `is_auth_failure`.
+pub fn fetch_with_authorization(
+    repo: &mut git2::Repository,
+    url: &str,
+    reference: &GitReference,
+    config: &Config,
+    authorization: Option<&str>,
+) -> CargoResult<()> {
+    fetch_inner(repo, url, reference, config, authorization, false)
+}
+
+fn fetch_inner(
+    repo: &mut git2::Repository,
+    url: &str,
+    reference: &GitReference,
+    config: &Config,
+    authorization: Option<&str>,
+    prompt: bool,
 ) -> CargoResult<()> {
     if config.frozen() {
         anyhow::bail!(
@@ -922,8 +950,13 @@ pub fn fetch(
     // flavors of authentication possible while also still giving us all the
     // speed and portability of using `libgit2`.
     if let Some(true) = config.net_config()?.git_fetch_with_cli {
-        return fetch_with_cli(repo, url, &refspecs, tags, config);
+        return fetch_with_cli(repo, url, &refspecs, tags, authorization, prompt, config);
     }
+    let headers: Vec<String> = authorization
+        .iter()
+        .map(|authorization| format!("Authorization: {}", authorization))
+        .collect();
+    let headers: Vec<&str> = headers.iter().map(|h| h.as_str()).collect();
 
     debug!("doing a fetch for {}", url);
     let git_config = git2::Config::open_default()?;
@@ -931,6 +964,7 @@ pub fn fetch(
         if tags {
             opts.download_tags(git2::AutotagOption::All);
         }
+        opts.custom_headers(&headers);
         // The `fetch` operation here may fail spuriously due to a corrupt
         // repository. It could also fail, however, for a whole slew of other
         // reasons (aka network related reasons). We want Cargo to automatically
@@ -970,14 +1004,80 @@ pub fn fetch(
     })
 }
 
+/// Returns whether `err`, returned by `fetch_with_authorization`, is the
+/// server refusing access to the repository without credentials.
+///
+/// For libgit2, that's an HTTP 401 or 403 response. The messages of `git`
+/// are translated, so they can't be relied upon. Instead, `git` is given a
+/// credential helper which only records that it was run, as `git` only asks
+/// for credentials when the server refused the request without them (or
+/// with the ones it was given).
+pub fn is_auth_failure(err: &anyhow::Error) -> bool {
+    if err.downcast_ref::<CredentialsRequested>().is_some() {
+        return true;
+    }
+    err.chain().any(|e| {
+        if let Some(e) = e.downcast_ref::<git2::Error>() {
+            return e.code() == git2::ErrorCode::Auth
+                || (e.class() == ErrorClass::Http
+                    && (e.message().contains("401") || e.message().contains("403")));
+        }
+        e.to_string()
+            .starts_with("failed to authenticate when downloading repository")
+    })
+}
+
+/// The error of a `git fetch` during which `git` asked for credentials.
+#[derive(Debug)]
+struct CredentialsRequested;
+
+impl fmt::Display for CredentialsRequested {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the server requested credentials")
+    }
+}
+
 fn fetch_with_cli(
     repo: &mut git2::Repository,
     url: &str,
     refspecs: &[String],
     tags: bool,
+    authorization: Option<&str>,
+    prompt: bool,
     config: &Config,
 ) -> CargoResult<()> {
     let mut cmd = process("git");
+    // Passed through the environment rather than with `-c` so that the token
+    // doesn't show up in the command line.
+    let mut git_config = Vec::new();
+    if let Some(authorization) = authorization {
+        git_config.push((
+            "http.extraHeader",
+            format!("Authorization: {}", authorization),
+        ));
+    }
+    // `git` runs the credential helpers to get credentials when the server
+    // refuses a request, and to erase them when the server refuses the ones
+    // they gave, so the last helper is always run in either case.
+    let credentials_requested = repo.path().join("cargo-credentials-requested");
+    if !prompt {
+        cmd.env("GIT_TERMINAL_PROMPT", "0");
+        if credentials_requested.exists() {
+            paths::remove_file(&credentials_requested)?;
+        }
+        cmd.env("CARGO_CREDENTIALS_REQUESTED", &credentials_requested);
+        git_config.push((
+            "credential.helper",
+            "!f() { : > \"$CARGO_CREDENTIALS_REQUESTED\"; }; f".to_string(),
+        ));
+    }
+    if !git_config.is_empty() {
+        cmd.env("GIT_CONFIG_COUNT", git_config.len().to_string());
+        for (i, (key, value)) in git_config.iter().enumerate() {
+            cmd.env(&format!("GIT_CONFIG_KEY_{}", i), key)
+                .env(&format!("GIT_CONFIG_VALUE_{}", i), value);
+        }
+    }
     cmd.arg("fetch");
     if tags {
         cmd.arg("--tags");
@@ -1001,7 +1101,87 @@ fn fetch_with_cli(
     config
         .shell()
         .verbose(|s| s.status("Running", &cmd.to_string()))?;
-    cmd.exec_with_output()?;
+    if let Err(e) = cmd.exec_with_output() {
+        if !prompt && credentials_requested.exists() {
+            return Err(e.context(CredentialsRequested));
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Checks that `commit` was signed with `public_key`, an OpenSSH public key
+/// such as `ssh-ed25519 AAAA...`.
+///
+/// Like `git verify-commit` does for SSH signatures, the check is done by
+/// `ssh-keygen -Y verify`, so OpenSSH 8.0 or newer needs to be installed.
+pub fn verify_commit_signature(
+    repo: &git2::Repository,
+    commit: git2::Oid,
+    public_key: &str,
+    config: &Config,
+) -> CargoResult<()> {
+    let (signature, signed_data) = match repo.extract_signature(&commit, None) {
+        Ok(signature) => signature,
+        Err(e) if e.code() == git2::ErrorCode::NotFound => {
+            anyhow::bail!("commit `{}` is not signed", commit)
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let signature = signature.as_str().unwrap_or("");
+    if !signature.starts_with("-----BEGIN SSH SIGNATURE-----") {
+        anyhow::bail!("commit `{}` is not signed with an SSH key", commit);
+    }
+
+    let dir = tempfile::Builder::new()
+        .prefix("cargo-signature")
+        .tempdir()?;
+    let allowed_signers = dir.path().join("allowed_signers");
+    paths::write(&allowed_signers, format!("cargo {}\n", public_key.trim()))?;
+    let signature_path = dir.path().join("signature");
+    paths::write(&signature_path, signature)?;
+
+    let mut cmd = process("ssh-keygen");
+    cmd.arg("-Y")
+        .arg("verify")
+        .arg("-f")
+        .arg(&allowed_signers)
+        .arg("-I")
+        .arg("cargo")
+        .arg("-n")
+        .arg("git")
+        .arg("-s")
+        .arg(&signature_path);
+    config
+        .shell()
+        .verbose(|s| s.status("Running", &cmd.to_string()))?;
+    let run = || -> std::io::Result<std::process::Output> {
+        let mut child = cmd
+            .build_command()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        child.stdin.take().unwrap().write_all(&signed_data)?;
+        child.wait_with_output()
+    };
+    let output = run().chain_err(|| format!("failed to run `{}`", cmd))?;
+    if !output.status.success() {
+        // `ssh-keygen` explains why on stdout or stderr depending on the
+        // version.
+        let mut msg = format!(
+            "the signature of commit `{}` wasn't made with the key `{}`",
+            commit,
+            public_key.trim()
+        );
+        for out in &[&output.stdout, &output.stderr] {
+            let out = String::from_utf8_lossy(out);
+            if !out.trim().is_empty() {
+                msg.push_str(&format!("\n{}", out.trim_end()));
+            }
+        }
+        anyhow::bail!(msg);
+    }
     Ok(())
 }
 
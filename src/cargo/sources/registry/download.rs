@@ -0,0 +1,158 @@
+//! Shared download logic between git-based and HTTP-based remote registries.
+//!
+//! Both kinds of remote registries describe where to download `.crate` files
+//! from in their `config.json`, and both keep downloaded files in the same
+//! cache directory layout, so the functions here are parameterized over just
+//! the cache location.
+
+use crate::core::PackageId;
+use crate::sources::registry::{
+    MaybeLock, RegistryConfig, CRATE_TEMPLATE, LOWER_PREFIX_TEMPLATE, PREFIX_TEMPLATE,
+    VERSION_TEMPLATE,
+};
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::{Checksum, Config, Filesystem};
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File, OpenOptions};
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::path::Path;
+
+pub(super) fn make_crate_prefix(name: &str) -> String {
+    match name.len() {
+        1 => String::from("1"),
+        2 => String::from("2"),
+        3 => format!("3/{}", &name[..1]),
+        _ => format!("{}/{}", &name[0..2], &name[2..4]),
+    }
+}
+
+pub(super) fn filename(pkg: PackageId) -> String {
+    format!("{}-{}.crate", pkg.name(), pkg.version())
+}
+
+/// Returns a `MaybeLock` for the `.crate` file of `pkg`, which is either
+/// already present in `cache_path` or needs to be downloaded from the
+/// location described by the registry's `config.json`.
+///
+/// `auth_token` is only called if the download requires authentication.
+pub(super) fn download(
+    cache_path: &Filesystem,
+    config: &Config,
+    pkg: PackageId,
+    _checksum: &str,
+    registry_config: RegistryConfig,
+    auth_token: &dyn Fn() -> CargoResult<String>,
+) -> CargoResult<MaybeLock> {
+    let filename = filename(pkg);
+
+    // Attempt to open an read-only copy first to avoid an exclusive write
+    // lock and also work with read-only filesystems. Note that we check the
+    // length of the file like below to handle interrupted downloads.
+    //
+    // If this fails then we fall through to the exclusive path where we may
+    // have to redownload the file.
+    let path = cache_path.join(&filename);
+    let path = config.assert_package_cache_locked(&path);
+    if let Ok(dst) = File::open(&path) {
+        let meta = dst.metadata()?;
+        if meta.len() > 0 {
+            return Ok(MaybeLock::Ready(dst));
+        }
+    }
+
+    let mut url = registry_config.dl;
+    if !url.contains(CRATE_TEMPLATE)
+        && !url.contains(VERSION_TEMPLATE)
+        && !url.contains(PREFIX_TEMPLATE)
+        && !url.contains(LOWER_PREFIX_TEMPLATE)
+    {
+        write!(url, "/{}/{}/download", CRATE_TEMPLATE, VERSION_TEMPLATE).unwrap();
+    }
+    let prefix = make_crate_prefix(&*pkg.name());
+    let url = url
+        .replace(CRATE_TEMPLATE, &*pkg.name())
+        .replace(VERSION_TEMPLATE, &pkg.version().to_string())
+        .replace(PREFIX_TEMPLATE, &prefix)
+        .replace(LOWER_PREFIX_TEMPLATE, &prefix.to_lowercase());
+
+    let authorization = if registry_config.auth_required {
+        Some(auth_token()?)
+    } else {
+        None
+    };
+
+    Ok(MaybeLock::Download {
+        url,
+        descriptor: pkg.to_string(),
+        authorization,
+    })
+}
+
+/// Verifies the checksum of a freshly downloaded `.crate` file and writes it
+/// into `cache_path`.
+pub(super) fn finish_download(
+    cache_path: &Filesystem,
+    config: &Config,
+    pkg: PackageId,
+    checksum: &str,
+    data: &[u8],
+) -> CargoResult<File> {
+    // Verify what we just downloaded
+    let expected = Checksum::parse(checksum)
+        .chain_err(|| format!("failed to verify the checksum of `{}`", pkg))?;
+    if Checksum::compute(expected.algorithm(), data) != expected {
+        anyhow::bail!("failed to verify the checksum of `{}`", pkg)
+    }
+
+    let filename = filename(pkg);
+    cache_path.create_dir()?;
+    let path = cache_path.join(&filename);
+    let path = config.assert_package_cache_locked(&path);
+    let mut dst = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .write(true)
+        .open(&path)
+        .chain_err(|| format!("failed to open `{}`", path.display()))?;
+    let meta = dst.metadata()?;
+    if meta.len() > 0 {
+        return Ok(dst);
+    }
+
+    dst.write_all(data)?;
+    dst.seek(SeekFrom::Start(0))?;
+    Ok(dst)
+}
+
+/// Returns whether the `.crate` file for `pkg` is already in `cache_path`.
+pub(super) fn is_crate_downloaded(
+    cache_path: &Filesystem,
+    config: &Config,
+    pkg: PackageId,
+) -> bool {
+    let filename = filename(pkg);
+    let path = Path::new(&filename);
+
+    let path = cache_path.join(path);
+    let path = config.assert_package_cache_locked(&path);
+    if let Ok(meta) = fs::metadata(path) {
+        return meta.len() > 0;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::make_crate_prefix;
+
+    #[test]
+    fn crate_prefix() {
+        assert_eq!(make_crate_prefix("a"), "1");
+        assert_eq!(make_crate_prefix("ab"), "2");
+        assert_eq!(make_crate_prefix("abc"), "3/a");
+        assert_eq!(make_crate_prefix("Abc"), "3/A");
+        assert_eq!(make_crate_prefix("AbCd"), "Ab/Cd");
+        assert_eq!(make_crate_prefix("aBcDe"), "aB/cD");
+    }
+}
@@ -0,0 +1,505 @@
+//! Access to a registry index over plain HTTP(S), one file at a time.
+//!
+//! A sparse registry serves the files of its index (see `registry/mod.rs` for
+//! their layout) at the same relative paths under the index URL. Instead of
+//! cloning the whole index like `RemoteRegistry`, we download only the files
+//! for crates that are actually queried and keep a copy of each under the
+//! index directory in `$CARGO_HOME`.
+//!
+//! Every copy on disk starts with a single line recording the `ETag` or
+//! `Last-Modified` header the file was served with, followed by the file
+//! contents as served. That line is used both to revalidate the copy with a
+//! conditional request and as the version of the file for the `.cache`
+//! summaries maintained by `RegistryIndex`.
+//!
+//! Copies are only revalidated once `update_index` has been called during
+//! this session, mirroring how the git index is only fetched when an update
+//! is requested. Files that aren't on disk yet are always fetched, unless the
+//! network isn't available, in which case they're considered missing.
+
+use crate::core::{PackageId, SourceId};
+use crate::ops;
+use crate::sources::registry::download;
+use crate::sources::registry::remote::configured_signing_key;
+use crate::sources::registry::MaybeLock;
+use crate::sources::registry::{RegistryConfig, RegistryData};
+use crate::util::errors::{CargoResult, CargoResultExt, HttpNot200};
+use crate::util::interning::InternedString;
+use crate::util::network::Retry;
+use crate::util::paths;
+use crate::util::{Config, Filesystem};
+use curl::easy::{Easy, HttpVersion, List};
+use curl::multi::{EasyHandle, Multi};
+use lazycell::LazyCell;
+use log::{debug, trace};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const ETAG: &str = "etag";
+const LAST_MODIFIED: &str = "last-modified";
+
+pub struct HttpRegistry<'cfg> {
+    index_path: Filesystem,
+    cache_path: Filesystem,
+    source_id: SourceId,
+    config: &'cfg Config,
+    /// The index URL with the `sparse+` prefix removed and a trailing slash
+    /// guaranteed, so that relative paths can just be appended to it.
+    url: String,
+    /// Whether `update_index` has been called, meaning that index files
+    /// already on disk have to be revalidated with the server before use.
+    requested_update: bool,
+    /// Index files which have already been brought up to date during this
+    /// session, and so can be read from disk without asking the server.
+    fresh: HashSet<String>,
+    multi: LazyCell<Multi>,
+    /// Whether the registry requires authentication, which is known from its
+    /// `config.json` or from the server refusing a request without a token.
+    auth_required: Cell<bool>,
+    auth_token: LazyCell<String>,
+}
+
+/// An in-flight request for a single index file.
+struct Fetch<'cfg> {
+    path: String,
+    url: String,
+    data: Arc<Mutex<Vec<u8>>>,
+    /// The `ETag` or `Last-Modified` header line sent back by the server.
+    version: Arc<Mutex<Option<String>>>,
+    /// Whether the token of the registry is sent with the request.
+    authorized: bool,
+    retry: Retry<'cfg>,
+}
+
+impl<'cfg> HttpRegistry<'cfg> {
+    pub fn new(source_id: SourceId, config: &'cfg Config, name: &str) -> HttpRegistry<'cfg> {
+        let url = source_id.url().as_str();
+        let mut url = url.trim_start_matches("sparse+").to_string();
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        HttpRegistry {
+            index_path: config.registry_index_path().join(name),
+            cache_path: config.registry_cache_path().join(name),
+            source_id,
+            config,
+            url,
+            requested_update: false,
+            fresh: HashSet::new(),
+            multi: LazyCell::new(),
+            auth_required: Cell::new(false),
+            auth_token: LazyCell::new(),
+        }
+    }
+
+    /// Makes sure all of `paths` are up to date on disk, downloading all of
+    /// those that need it in parallel.
+    fn ensure_fresh(&mut self, root: &Path, paths: &[&str]) -> CargoResult<()> {
+        let mut needed = Vec::new();
+        for &path in paths {
+            if self.fresh.contains(path) {
+                continue;
+            }
+            let on_disk = root.join(path).exists();
+            if !self.config.network_allowed() || (on_disk && !self.requested_update) {
+                trace!("using {} from disk without revalidating", path);
+                self.fresh.insert(path.to_string());
+                continue;
+            }
+            if !needed.contains(&path) {
+                needed.push(path);
+            }
+        }
+        if needed.is_empty() {
+            return Ok(());
+        }
+        self.fetch(root, &needed)?;
+        self.fresh.extend(needed.into_iter().map(|p| p.to_string()));
+        Ok(())
+    }
+
+    fn multi(&self) -> CargoResult<&Multi> {
+        self.multi.try_borrow_with(|| {
+            let mut multi = Multi::new();
+            let multiplexing = self.config.http_config()?.multiplexing.unwrap_or(true);
+            multi
+                .pipelining(false, multiplexing)
+                .chain_err(|| "failed to enable multiplexing/pipelining in curl")?;
+            multi.set_max_host_connections(2)?;
+            Ok(multi)
+        })
+    }
+
+    /// Returns the token to send along with every request, for registries
+    /// which require authentication.
+    fn auth_token(&self) -> CargoResult<String> {
+        self.auth_token
+            .try_borrow_with(|| ops::registry_auth_token(self.config, self.source_id))
+            .map(|token| token.clone())
+    }
+
+    /// Downloads all of `paths` from the server, writing them to disk under
+    /// `root`.
+    fn fetch(&self, root: &Path, paths: &[&str]) -> CargoResult<()> {
+        debug!("fetching {} index files from {}", paths.len(), self.url);
+        if !self.auth_required.get() {
+            let config = read_config(&root.join("config.json"));
+            self.auth_required
+                .set(config.map_or(false, |config| config.auth_required));
+        }
+        let multi = self.multi()?;
+        let mut pending: HashMap<usize, (Fetch<'cfg>, EasyHandle)> = HashMap::new();
+        for (token, &path) in paths.iter().enumerate() {
+            let fetch = Fetch {
+                path: path.to_string(),
+                url: format!("{}{}", self.url, path),
+                data: Arc::new(Mutex::new(Vec::new())),
+                version: Arc::new(Mutex::new(None)),
+                authorized: self.auth_required.get(),
+                retry: Retry::new(self.config)?,
+            };
+            let handle = self.start_fetch(root, &fetch)?;
+            let mut handle = multi.add(handle)?;
+            handle.set_token(token)?;
+            pending.insert(token, (fetch, handle));
+        }
+
+        while !pending.is_empty() {
+            multi
+                .perform()
+                .chain_err(|| "failed to perform http requests")?;
+            let mut results = Vec::new();
+            multi.messages(|msg| {
+                let token = msg.token().expect("failed to read token");
+                if let Some(result) = msg.result_for(&pending[&token].1) {
+                    results.push((token, result));
+                }
+            });
+
+            if results.is_empty() {
+                let timeout = multi.get_timeout()?.unwrap_or_else(|| Duration::new(5, 0));
+                multi
+                    .wait(&mut [], timeout)
+                    .chain_err(|| "failed to wait on curl `Multi`")?;
+                continue;
+            }
+
+            for (token, result) in results {
+                let (mut fetch, handle) = pending.remove(&token).unwrap();
+                let handle = multi.remove(handle)?;
+                let url = fetch.url.clone();
+
+                // The server tells us that it requires authentication if
+                // we didn't know it yet, so try again with the token.
+                if result.is_ok() && handle.response_code()? == 401 && !fetch.authorized {
+                    debug!("{} requires authentication", url);
+                    self.auth_required.set(true);
+                    fetch.authorized = true;
+                    fetch.data.lock().unwrap().clear();
+                    *fetch.version.lock().unwrap() = None;
+                    let mut handle = multi.add(self.start_fetch(root, &fetch)?)?;
+                    handle.set_token(token)?;
+                    pending.insert(token, (fetch, handle));
+                    continue;
+                }
+
+                let finished = fetch
+                    .retry
+                    .r#try(|| {
+                        let code = match result {
+                            Ok(()) => handle.response_code()?,
+                            // A missing file with a `file://` index URL.
+                            Err(ref e) if e.is_file_couldnt_read_file() => 404,
+                            Err(e) => return Err(e.into()),
+                        };
+                        match code {
+                            // `file://` URLs don't have a response code.
+                            0 | 200 | 304 | 404 | 410 => Ok(code),
+                            code => Err(HttpNot200 {
+                                code,
+                                url: url.clone(),
+                            }
+                            .into()),
+                        }
+                    })
+                    .chain_err(|| format!("failed to fetch `{}`", url))?;
+                match finished {
+                    Some(code) => self.finish_fetch(root, &fetch, code)?,
+                    None => {
+                        fetch.data.lock().unwrap().clear();
+                        *fetch.version.lock().unwrap() = None;
+                        let mut handle = multi.add(handle)?;
+                        handle.set_token(token)?;
+                        pending.insert(token, (fetch, handle));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates the curl handle to download `fetch`, sending along the
+    /// version of the copy we already have, if any, so the server can reply
+    /// with `304 Not Modified` when it's still current.
+    fn start_fetch(&self, root: &Path, fetch: &Fetch<'cfg>) -> CargoResult<Easy> {
+        trace!("fetching {}", fetch.url);
+        let mut handle = ops::http_handle(self.config)?;
+        handle.get(true)?;
+        handle.url(&fetch.url)?;
+        handle.follow_location(true)?;
+
+        // Like package downloads, prefer HTTP/2 so all of these requests can
+        // share one connection, but fall back gracefully with libcurls that
+        // weren't built with support for it.
+        if self.config.http_config()?.multiplexing.unwrap_or(true) {
+            if let Err(e) = handle.http_version(HttpVersion::V2) {
+                debug!("ignoring libcurl HTTP2 error: {}", e);
+            }
+            if let Err(e) = handle.pipewait(true) {
+                debug!("ignoring libcurl pipewait error: {}", e);
+            }
+        } else {
+            handle.http_version(HttpVersion::V11)?;
+        }
+
+        let mut headers = List::new();
+        match read_version(&root.join(&fetch.path)) {
+            Some(v) if v.starts_with(ETAG) => {
+                headers.append(&format!("If-None-Match: {}", &v[ETAG.len() + 2..]))?
+            }
+            Some(v) if v.starts_with(LAST_MODIFIED) => headers.append(&format!(
+                "If-Modified-Since: {}",
+                &v[LAST_MODIFIED.len() + 2..]
+            ))?,
+            _ => {}
+        }
+        if fetch.authorized {
+            headers.append(&format!("Authorization: {}", self.auth_token()?))?;
+        }
+        handle.http_headers(headers)?;
+
+        let data = fetch.data.clone();
+        handle.write_function(move |buf| {
+            data.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        })?;
+        let version = fetch.version.clone();
+        handle.header_function(move |buf| {
+            if let Ok(line) = str::from_utf8(buf) {
+                let mut parts = line.splitn(2, ':');
+                let name = parts.next().unwrap().trim().to_ascii_lowercase();
+                let value = parts.next().unwrap_or("").trim();
+                let mut version = version.lock().unwrap();
+                match name.as_str() {
+                    ETAG => *version = Some(format!("{}: {}", ETAG, value)),
+                    // An `ETag` is a better validator, so prefer it.
+                    LAST_MODIFIED if version.is_none() => {
+                        *version = Some(format!("{}: {}", LAST_MODIFIED, value))
+                    }
+                    _ => {}
+                }
+            }
+            true
+        })?;
+        Ok(handle)
+    }
+
+    fn finish_fetch(&self, root: &Path, fetch: &Fetch<'cfg>, code: u32) -> CargoResult<()> {
+        let dst = root.join(&fetch.path);
+        match code {
+            304 => {
+                debug!("index file {} is up to date", fetch.path);
+            }
+            404 | 410 => {
+                debug!("index file {} does not exist", fetch.path);
+                if dst.exists() {
+                    paths::remove_file(&dst)?;
+                }
+            }
+            _ => {
+                debug!("index file {} downloaded", fetch.path);
+                let version = fetch.version.lock().unwrap().take().unwrap_or_default();
+                let data = fetch.data.lock().unwrap();
+                let mut contents = Vec::with_capacity(version.len() + 1 + data.len());
+                contents.extend_from_slice(version.as_bytes());
+                contents.push(b'\n');
+                contents.extend_from_slice(&data);
+                paths::create_dir_all(dst.parent().unwrap())?;
+                paths::write(&dst, contents)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads the copy of `config.json` at `path`, if there is one.
+fn read_config(path: &Path) -> Option<RegistryConfig> {
+    let contents = paths::read_bytes(path).ok()?;
+    let start = memchr::memchr(b'\n', &contents).map_or(contents.len(), |i| i + 1);
+    serde_json::from_slice(&contents[start..]).ok()
+}
+
+/// Reads the version line of the copy of an index file at `path`.
+///
+/// Returns `None` if there is no copy, or if the server didn't send any
+/// version information along with it.
+fn read_version(path: &Path) -> Option<String> {
+    let mut line = String::new();
+    BufReader::new(File::open(path).ok()?)
+        .read_line(&mut line)
+        .ok()?;
+    let line = line.trim_end();
+    if line.is_empty() {
+        None
+    } else {
+        Some(line.to_string())
+    }
+}
+
+impl<'cfg> RegistryData for HttpRegistry<'cfg> {
+    fn prepare(&self) -> CargoResult<()> {
+        Ok(())
+    }
+
+    fn index_path(&self) -> &Filesystem {
+        &self.index_path
+    }
+
+    fn assert_index_locked<'a>(&self, path: &'a Filesystem) -> &'a Path {
+        self.config.assert_package_cache_locked(path)
+    }
+
+    fn current_version(&self) -> Option<InternedString> {
+        // Files in a sparse index are versioned individually, see
+        // `file_version`.
+        None
+    }
+
+    fn file_version(&mut self, root: &Path, path: &Path) -> CargoResult<Option<InternedString>> {
+        let path = path.to_str().expect("index paths are UTF-8");
+        self.ensure_fresh(root, &[path])?;
+        Ok(read_version(&root.join(path)).map(|v| InternedString::new(&v)))
+    }
+
+    fn supports_prefetch(&self) -> bool {
+        true
+    }
+
+    fn prefetch(&mut self, root: &Path, paths: &[String]) -> CargoResult<()> {
+        let paths = paths.iter().map(|p| p.as_str()).collect::<Vec<_>>();
+        self.ensure_fresh(root, &paths)
+    }
+
+    fn load(
+        &self,
+        root: &Path,
+        path: &Path,
+        data: &mut dyn FnMut(&[u8]) -> CargoResult<()>,
+    ) -> CargoResult<()> {
+        // The file has been brought up to date by `file_version` already, so
+        // all that's left is to read it back, skipping the version line. A
+        // missing file means the crate doesn't exist in this registry.
+        let path = root.join(path);
+        let contents = match fs::read(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => {
+                return Err(anyhow::Error::from(e))
+                    .chain_err(|| format!("failed to read `{}`", path.display()))
+            }
+        };
+        let start = memchr::memchr(b'\n', &contents).map_or(contents.len(), |i| i + 1);
+        data(&contents[start..])
+    }
+
+    fn config(&mut self) -> CargoResult<Option<RegistryConfig>> {
+        debug!("loading config");
+        let path = self
+            .config
+            .assert_package_cache_locked(&self.index_path)
+            .to_path_buf();
+        self.ensure_fresh(&path, &["config.json"])?;
+        let mut config = None;
+        self.load(&path, Path::new("config.json"), &mut |json| {
+            config = Some(serde_json::from_slice(json)?);
+            Ok(())
+        })?;
+        trace!("config loaded");
+        Ok(config)
+    }
+
+    fn update_index(&mut self) -> CargoResult<()> {
+        // The files of a sparse index aren't signed, unlike the commits of a
+        // git index, so a signing key can't be honored.
+        if configured_signing_key(self.config, self.source_id)?.is_some() {
+            anyhow::bail!(
+                "a `signing-key` is set for the sparse registry index `{}`, but \
+                 only git indexes can be verified",
+                self.source_id.url()
+            );
+        }
+        if !self.config.network_allowed() {
+            return Ok(());
+        }
+        if self.config.cli_unstable().no_index_update {
+            return Ok(());
+        }
+        // Like with git indexes, only revalidate files once per session.
+        if self.config.updated_sources().contains(&self.source_id) {
+            return Ok(());
+        }
+
+        debug!("updating the index");
+
+        // Weed out any problems with the HTTP configuration before we try to
+        // fetch anything, as `RemoteRegistry` does.
+        self.config.http()?;
+
+        // Nothing is actually downloaded here. Instead, every index file will
+        // be revalidated with the server the first time it's needed.
+        self.config
+            .shell()
+            .status("Updating", self.source_id.display_index())?;
+        self.requested_update = true;
+        self.fresh.clear();
+        self.config.updated_sources().insert(self.source_id);
+        Ok(())
+    }
+
+    fn download(&mut self, pkg: PackageId, checksum: &str) -> CargoResult<MaybeLock> {
+        let registry_config = match self.config()? {
+            Some(config) => config,
+            None => anyhow::bail!(
+                "the {} does not contain a `config.json` file",
+                self.source_id.display_index()
+            ),
+        };
+        download::download(
+            &self.cache_path,
+            self.config,
+            pkg,
+            checksum,
+            registry_config,
+            &|| self.auth_token(),
+        )
+    }
+
+    fn finish_download(
+        &mut self,
+        pkg: PackageId,
+        checksum: &str,
+        data: &[u8],
+    ) -> CargoResult<File> {
+        download::finish_download(&self.cache_path, self.config, pkg, checksum, data)
+    }
+
+    fn is_crate_downloaded(&self, pkg: PackageId) -> bool {
+        download::is_crate_downloaded(&self.cache_path, self.config, pkg)
+    }
+}
@@ -66,7 +66,7 @@
 //! details like invalidating caches and whatnot which are handled below, but
 //! hopefully those are more obvious inline in the code itself.
 
-use crate::core::dependency::Dependency;
+use crate::core::dependency::{DepKind, Dependency};
 use crate::core::{PackageId, SourceId, Summary};
 use crate::sources::registry::{RegistryData, RegistryPackage};
 use crate::util::interning::InternedString;
@@ -168,6 +168,9 @@ pub struct RegistryIndex<'cfg> {
     source_id: SourceId,
     path: Filesystem,
     summaries_cache: HashMap<InternedString, Summaries>,
+    /// Names of packages whose dependency graph has already been walked by
+    /// `prefetch`.
+    prefetched: HashSet<InternedString>,
     config: &'cfg Config,
 }
 
@@ -236,6 +239,7 @@ impl<'cfg> RegistryIndex<'cfg> {
             source_id,
             path: path.clone(),
             summaries_cache: HashMap::new(),
+            prefetched: HashSet::new(),
             config,
         }
     }
@@ -315,29 +319,25 @@ impl<'cfg> RegistryIndex<'cfg> {
         // let root = self.config.assert_package_cache_locked(&self.path);
         let root = load.assert_index_locked(&self.path);
         let cache_root = root.join(".cache");
-        let index_version = load.current_version();
-
-        // See module comment in `registry/mod.rs` for why this is structured
-        // the way it is.
-        let fs_name = name
-            .chars()
-            .flat_map(|c| c.to_lowercase())
-            .collect::<String>();
-        let raw_path = match fs_name.len() {
-            1 => format!("1/{}", fs_name),
-            2 => format!("2/{}", fs_name),
-            3 => format!("3/{}/{}", &fs_name[..1], fs_name),
-            _ => format!("{}/{}/{}", &fs_name[0..2], &fs_name[2..4], fs_name),
-        };
+        let raw_path = index_file_path(&name);
 
         // Attempt to handle misspellings by searching for a chain of related
         // names to the original `raw_path` name. Only return summaries
         // associated with the first hit, however. The resolver will later
         // reject any candidates that have the wrong name, and with this it'll
         // along the way produce helpful "did you mean?" suggestions.
-        for path in UncanonicalizedIter::new(&raw_path).take(1024) {
+        let paths = UncanonicalizedIter::new(&raw_path)
+            .take(1024)
+            .collect::<Vec<_>>();
+        for (i, path) in paths.iter().enumerate() {
+            // The spelling as provided has usually been fetched by `prefetch`
+            // already. If it doesn't exist, fetch all other spellings in one
+            // batch rather than one after the other. Those reported missing
+            // by the server aren't requested again below.
+            if i == 1 && load.supports_prefetch() {
+                load.prefetch(root, &paths[1..])?;
+            }
             let summaries = Summaries::parse(
-                index_version.as_deref(),
                 root,
                 &cache_root,
                 path.as_ref(),
@@ -364,6 +364,7 @@ impl<'cfg> RegistryIndex<'cfg> {
         yanked_whitelist: &HashSet<PackageId>,
         f: &mut dyn FnMut(Summary),
     ) -> CargoResult<()> {
+        self.prefetch(dep, load)?;
         if self.config.offline()
             && self.query_inner_with_online(dep, load, yanked_whitelist, f, false)? != 0
         {
@@ -381,6 +382,56 @@ impl<'cfg> RegistryIndex<'cfg> {
         Ok(())
     }
 
+    /// Walks the graph of packages in this registry that `dep` may depend on,
+    /// asking `load` to fetch the index files for each level of the graph in
+    /// one batch.
+    ///
+    /// This is purely an optimization for registries which fetch index files
+    /// individually over the network: without it every file would be fetched
+    /// serially as the resolver happens to ask for it. The walk is somewhat
+    /// pessimistic as it follows optional and platform-specific dependencies
+    /// of every matching version, but it never follows dev-dependencies since
+    /// those of a registry package are never built.
+    fn prefetch(&mut self, dep: &Dependency, load: &mut dyn RegistryData) -> CargoResult<()> {
+        if !load.supports_prefetch() || self.prefetched.contains(&dep.package_name()) {
+            return Ok(());
+        }
+        let source_id = self.source_id;
+        let root = load.assert_index_locked(&self.path).to_path_buf();
+        let mut seen = HashSet::new();
+        let mut pending = vec![(dep.package_name(), dep.version_req().clone())];
+        while !pending.is_empty() {
+            let mut paths = pending
+                .iter()
+                .filter(|(name, _)| !self.summaries_cache.contains_key(name))
+                .map(|(name, _)| index_file_path(name))
+                .collect::<Vec<_>>();
+            paths.sort();
+            paths.dedup();
+            load.prefetch(&root, &paths)?;
+
+            let mut next = Vec::new();
+            for (name, req) in pending {
+                self.prefetched.insert(name);
+                let summaries = self.summaries(name, &req, load)?.filter(|s| !s.yanked);
+                for summary in summaries {
+                    for dep in summary.summary.dependencies() {
+                        if dep.source_id() != source_id || dep.kind() == DepKind::Development {
+                            continue;
+                        }
+                        let key = (dep.package_name(), dep.version_req().to_string());
+                        if seen.insert(key) {
+                            next.push((dep.package_name(), dep.version_req().clone()));
+                        }
+                    }
+                }
+            }
+            next.retain(|(name, _)| !self.prefetched.contains(name));
+            pending = next;
+        }
+        Ok(())
+    }
+
     fn query_inner_with_online(
         &mut self,
         dep: &Dependency,
@@ -457,9 +508,6 @@ impl Summaries {
     /// for `relative` from the underlying index (aka typically libgit2 with
     /// crates.io) and then parse everything in there.
     ///
-    /// * `index_version` - a version string to describe the current state of
-    ///   the index which for remote registries is the current git sha and
-    ///   for local registries is not available.
     /// * `root` - this is the root argument passed to `load`
     /// * `cache_root` - this is the root on the filesystem itself of where to
     ///   store cache files.
@@ -470,7 +518,6 @@ impl Summaries {
     /// * `load` - the actual index implementation which may be very slow to
     ///   call. We avoid this if we can.
     pub fn parse(
-        index_version: Option<&str>,
         root: &Path,
         cache_root: &Path,
         relative: &Path,
@@ -478,6 +525,13 @@ impl Summaries {
         load: &mut dyn RegistryData,
         config: &Config,
     ) -> CargoResult<Option<Summaries>> {
+        // The version of the index file describes whether any cache we have
+        // for it is still fresh. For git-based registries this is the sha of
+        // the commit we're looking at, and for local registries it's not
+        // available at all.
+        let index_version = load.file_version(root, relative)?;
+        let index_version = index_version.as_deref();
+
         // First up, attempt to load the cache. This could fail for all manner
         // of reasons, but consider all of them non-fatal and just log their
         // occurrence in case anyone is debugging anything.
@@ -716,6 +770,7 @@ impl IndexSummary {
             features,
             yanked,
             links,
+            rust_version,
         } = serde_json::from_slice(line)?;
         log::trace!("json parsed registry {}/{}", name, vers);
         let pkgid = PackageId::new(name, &vers, source_id)?;
@@ -726,6 +781,7 @@ impl IndexSummary {
         let namespaced_features = false;
         let mut summary = Summary::new(pkgid, deps, &features, links, namespaced_features)?;
         summary.set_checksum(cksum);
+        summary.set_rust_version(rust_version);
         Ok(IndexSummary {
             summary,
             yanked: yanked.unwrap_or(false),
@@ -733,6 +789,24 @@ impl IndexSummary {
     }
 }
 
+/// Returns the path of the index file for the package `name`, relative to
+/// the root of the index.
+///
+/// See module comment in `registry/mod.rs` for why this is structured the
+/// way it is.
+pub fn index_file_path(name: &str) -> String {
+    let fs_name = name
+        .chars()
+        .flat_map(|c| c.to_lowercase())
+        .collect::<String>();
+    match fs_name.len() {
+        1 => format!("1/{}", fs_name),
+        2 => format!("2/{}", fs_name),
+        3 => format!("3/{}/{}", &fs_name[..1], fs_name),
+        _ => format!("{}/{}/{}", &fs_name[0..2], &fs_name[2..4], fs_name),
+    }
+}
+
 fn split<'a>(haystack: &'a [u8], needle: u8) -> impl Iterator<Item = &'a [u8]> + 'a {
     struct Split<'a> {
         haystack: &'a [u8],
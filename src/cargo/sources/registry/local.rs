@@ -1,9 +1,9 @@
 use crate::core::PackageId;
 use crate::sources::registry::{MaybeLock, RegistryConfig, RegistryData};
-use crate::util::errors::CargoResult;
+use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::interning::InternedString;
 use crate::util::paths;
-use crate::util::{Config, Filesystem, Sha256};
+use crate::util::{Checksum, Config, Filesystem};
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::SeekFrom;
@@ -99,8 +99,9 @@ impl<'cfg> RegistryData for LocalRegistry<'cfg> {
 
         // We don't actually need to download anything per-se, we just need to
         // verify the checksum matches the .crate file itself.
-        let actual = Sha256::new().update_file(&crate_file)?.finish_hex();
-        if actual != checksum {
+        let expected = Checksum::parse(checksum)
+            .chain_err(|| format!("failed to verify the checksum of `{}`", pkg))?;
+        if Checksum::compute_file(expected.algorithm(), &crate_file)? != expected {
             anyhow::bail!("failed to verify the checksum of `{}`", pkg)
         }
 
@@ -49,6 +49,21 @@
 //!   the end of a file (the exact format is described later). This means that
 //!   the commits for an index are quite small and easily applied/compressible.
 //!
+//! ## Sparse registries
+//!
+//! Cloning and updating a git repository containing every crate ever
+//! published gets slow as the index grows, even though a single build
+//! usually only needs a small fraction of it. As an alternative, a registry
+//! can serve the very same files over plain HTTP(S). Such registries are
+//! spelled with a `sparse+` prefix in front of the index URL, for example
+//! `sparse+https://example.com/index/`.
+//!
+//! Cargo then fetches only the files for the crates it actually needs, many
+//! of them in parallel, and keeps a copy of each on disk. Each copy remembers
+//! the `ETag` or `Last-Modified` header it was served with, so refreshing it
+//! is a cheap conditional request which usually comes back `304 Not
+//! Modified`. See `http_remote.rs` for the details.
+//!
 //! ## The format of the Index
 //!
 //! The index is a store for the list of versions for all packages known, so its
@@ -162,14 +177,14 @@ use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::collections::HashSet;
 use std::fs::{File, OpenOptions};
-use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::io::{self, Read, Take, Write};
+use std::path::{Component, Path, PathBuf};
 
 use flate2::read::GzDecoder;
 use log::debug;
 use semver::{Version, VersionReq};
 use serde::Deserialize;
-use tar::Archive;
+use tar::{Archive, EntryType};
 
 use crate::core::dependency::{DepKind, Dependency};
 use crate::core::source::MaybePackage;
@@ -182,6 +197,12 @@ use crate::util::into_url::IntoUrl;
 use crate::util::{restricted_names, CargoResult, Config, Filesystem};
 
 const PACKAGE_SOURCE_LOCK: &str = ".cargo-ok";
+/// The default of `registry.max-unpack-size`, the most bytes a `.crate` file
+/// may unpack to, which guards against decompression bombs.
+const DEFAULT_MAX_UNPACK_SIZE: u64 = 512 * 1024 * 1024;
+/// The default of `registry.max-unpack-entries`, the most files and
+/// directories a `.crate` file may contain.
+const DEFAULT_MAX_UNPACK_ENTRIES: u64 = 100_000;
 pub const CRATES_IO_INDEX: &str = "https://github.com/rust-lang/crates.io-index";
 pub const CRATES_IO_REGISTRY: &str = "crates-io";
 const CRATE_TEMPLATE: &str = "{crate}";
@@ -222,6 +243,20 @@ pub struct RegistryConfig {
     /// operations like yanks, owner modifications, publish new crates, etc.
     /// If this is None, the registry does not support API commands.
     pub api: Option<String>,
+
+    /// The OpenSSH public key which the commits of a git index are signed
+    /// with, unless one is set in the `.cargo/config` of the user.
+    ///
+    /// This is only trusted when read from the index as of the previous
+    /// update, so that a compromised mirror can't replace it.
+    #[serde(rename = "signing-key")]
+    pub signing_key: Option<String>,
+
+    /// Whether the registry requires a token for every request, including
+    /// fetching the index and downloading `.crate` files, rather than only
+    /// for API commands like `cargo publish`.
+    #[serde(rename = "auth-required", default)]
+    pub auth_required: bool,
 }
 
 /// A single line in the index representing a single version of a package.
@@ -243,6 +278,11 @@ pub struct RegistryPackage<'a> {
     /// Added early 2018 (see https://github.com/rust-lang/cargo/pull/4978),
     /// can be `None` if published before then.
     links: Option<InternedString>,
+    /// The `rust-version` field of the package's manifest, the oldest Rust
+    /// version it supports.
+    ///
+    /// `None` if the package doesn't declare one.
+    rust_version: Option<InternedString>,
 }
 
 #[test]
@@ -389,23 +429,64 @@ pub trait RegistryData {
     }
     fn assert_index_locked<'a>(&self, path: &'a Filesystem) -> &'a Path;
     fn current_version(&self) -> Option<InternedString>;
+
+    /// Returns the version of the index file at `path`, which is used to
+    /// decide whether a `.cache` entry previously written for it is still
+    /// valid.
+    ///
+    /// Registries which are versioned as a whole (such as a git index) just
+    /// return `current_version`. Registries which fetch files individually
+    /// make sure `path` is up to date here before `load` is called for it.
+    fn file_version(&mut self, _root: &Path, _path: &Path) -> CargoResult<Option<InternedString>> {
+        Ok(self.current_version())
+    }
+
+    /// Returns whether this registry can make use of `prefetch`.
+    fn supports_prefetch(&self) -> bool {
+        false
+    }
+
+    /// Brings the given index files up to date ahead of time, ideally all in
+    /// parallel, so that later calls to `file_version` and `load` for them
+    /// don't need to touch the network.
+    fn prefetch(&mut self, _root: &Path, _paths: &[String]) -> CargoResult<()> {
+        Ok(())
+    }
 }
 
 pub enum MaybeLock {
     Ready(File),
-    Download { url: String, descriptor: String },
+    Download {
+        url: String,
+        descriptor: String,
+        /// The `Authorization` header to send along with the request.
+        authorization: Option<String>,
+    },
 }
 
+mod download;
+mod http_remote;
 mod index;
 mod local;
 mod remote;
 
+pub use self::index::index_file_path;
+
 fn short_name(id: SourceId) -> String {
     let hash = hex::short_hash(&id);
     let ident = id.url().host_str().unwrap_or("").to_string();
     format!("{}-{}", ident, hash)
 }
 
+/// Returns where the `.crate` file of `pkg`, which comes from a remote
+/// registry, is kept once downloaded.
+pub fn crate_cache_path(config: &Config, pkg: PackageId) -> Filesystem {
+    config
+        .registry_cache_path()
+        .join(short_name(pkg.source_id()))
+        .join(download::filename(pkg))
+}
+
 impl<'cfg> RegistrySource<'cfg> {
     pub fn remote(
         source_id: SourceId,
@@ -417,6 +498,16 @@ impl<'cfg> RegistrySource<'cfg> {
         RegistrySource::new(source_id, config, &name, Box::new(ops), yanked_whitelist)
     }
 
+    pub fn http(
+        source_id: SourceId,
+        yanked_whitelist: &HashSet<PackageId>,
+        config: &'cfg Config,
+    ) -> RegistrySource<'cfg> {
+        let name = short_name(source_id);
+        let ops = http_remote::HttpRegistry::new(source_id, config, &name);
+        RegistrySource::new(source_id, config, &name, Box::new(ops), yanked_whitelist)
+    }
+
     pub fn local(
         source_id: SourceId,
         path: &Path,
@@ -478,10 +569,26 @@ impl<'cfg> RegistrySource<'cfg> {
             .open(&path)
             .chain_err(|| format!("failed to open `{}`", path.display()))?;
 
-        let gz = GzDecoder::new(tarball);
+        let max_size = self
+            .config
+            .get::<Option<u64>>("registry.max-unpack-size")?
+            .unwrap_or(DEFAULT_MAX_UNPACK_SIZE);
+        let max_entries = self
+            .config
+            .get::<Option<u64>>("registry.max-unpack-entries")?
+            .unwrap_or(DEFAULT_MAX_UNPACK_ENTRIES);
+
+        // The sizes of the entries are checked below, but the decompressed
+        // stream is limited as well so that nothing else in it, such as
+        // extended headers, can grow unbounded. It's twice as large to leave
+        // room for the headers of packages with many small files.
+        let max_stream_size = max_size.saturating_mul(2);
+        let gz = LimitErrorReader::new(GzDecoder::new(tarball), max_stream_size);
         let mut tar = Archive::new(gz);
         let prefix = unpack_dir.file_name().unwrap();
         let parent = unpack_dir.parent().unwrap();
+        let mut size = 0u64;
+        let mut entries = 0u64;
         for entry in tar.entries()? {
             let mut entry = entry.chain_err(|| "failed to iterate over archive")?;
             let entry_path = entry
@@ -495,7 +602,11 @@ impl<'cfg> RegistrySource<'cfg> {
             // itself should never generate a tarball that hits this error, and
             // crates.io should also block uploads with these sorts of tarballs,
             // but be extra sure by adding a check here as well.
-            if !entry_path.starts_with(prefix) {
+            let escapes = entry_path.components().any(|c| match c {
+                Component::Normal(_) | Component::CurDir => false,
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => true,
+            });
+            if escapes || !entry_path.starts_with(prefix) {
                 anyhow::bail!(
                     "invalid tarball downloaded, contains \
                      a file at {:?} which isn't under {:?}",
@@ -503,6 +614,45 @@ impl<'cfg> RegistrySource<'cfg> {
                     prefix
                 )
             }
+            // Links could point outside of the package directory, and no
+            // other kind of special file belongs in a package.
+            let kind = match entry.header().entry_type() {
+                EntryType::Regular | EntryType::Directory => None,
+                EntryType::XGlobalHeader => continue,
+                EntryType::Symlink => Some("a symlink"),
+                EntryType::Link => Some("a hard link"),
+                _ => Some("a special file"),
+            };
+            if let Some(kind) = kind {
+                anyhow::bail!(
+                    "invalid tarball downloaded, contains {} at `{}`, \
+                     only files and directories are allowed",
+                    kind,
+                    entry_path.display()
+                )
+            }
+
+            entries += 1;
+            if entries > max_entries {
+                anyhow::bail!(
+                    "invalid tarball downloaded, contains more than {} entries \
+                     (the limit was reached at `{}`)\n\
+                     The limit is set by the `registry.max-unpack-entries` config value.",
+                    max_entries,
+                    entry_path.display()
+                )
+            }
+            size = size.saturating_add(entry.size());
+            if size > max_size {
+                anyhow::bail!(
+                    "invalid tarball downloaded, unpacks to more than {} bytes \
+                     (the limit was reached at `{}`)\n\
+                     The limit is set by the `registry.max-unpack-size` config value.",
+                    max_size,
+                    entry_path.display()
+                )
+            }
+
             // Unpacking failed
             let mut result = entry.unpack_in(parent).map_err(anyhow::Error::from);
             if cfg!(windows) && restricted_names::is_windows_reserved_path(&entry_path) {
@@ -630,9 +780,15 @@ impl<'cfg> Source for RegistrySource<'cfg> {
         let hash = self.index.hash(package, &mut *self.ops)?;
         match self.ops.download(package, hash)? {
             MaybeLock::Ready(file) => self.get_pkg(package, &file).map(MaybePackage::Ready),
-            MaybeLock::Download { url, descriptor } => {
-                Ok(MaybePackage::Download { url, descriptor })
-            }
+            MaybeLock::Download {
+                url,
+                descriptor,
+                authorization,
+            } => Ok(MaybePackage::Download {
+                url,
+                descriptor,
+                authorization,
+            }),
         }
     }
 
@@ -661,3 +817,37 @@ impl<'cfg> Source for RegistrySource<'cfg> {
         self.index.is_yanked(pkg, &mut *self.ops)
     }
 }
+
+/// A reader which fails once more than `limit` bytes were read, instead of
+/// stopping there like `Read::take`.
+struct LimitErrorReader<R> {
+    inner: Take<R>,
+    limit: u64,
+}
+
+impl<R: Read> LimitErrorReader<R> {
+    fn new(r: R, limit: u64) -> LimitErrorReader<R> {
+        LimitErrorReader {
+            // One more byte is allowed, to tell if the limit is exceeded.
+            inner: r.take(limit.saturating_add(1)),
+            limit,
+        }
+    }
+}
+
+impl<R: Read> Read for LimitErrorReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if self.inner.limit() == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "the tarball decompresses to more than {} bytes\n\
+                     The limit is twice the `registry.max-unpack-size` config value.",
+                    self.limit
+                ),
+            ));
+        }
+        Ok(n)
+    }
+}
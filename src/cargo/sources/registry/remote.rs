@@ -1,34 +1,23 @@
 use crate::core::{GitReference, PackageId, SourceId};
+use crate::ops;
 use crate::sources::git;
+use crate::sources::registry::download;
 use crate::sources::registry::MaybeLock;
-use crate::sources::registry::{
-    RegistryConfig, RegistryData, CRATE_TEMPLATE, LOWER_PREFIX_TEMPLATE, PREFIX_TEMPLATE,
-    VERSION_TEMPLATE,
-};
+use crate::sources::registry::{RegistryConfig, RegistryData};
 use crate::util::errors::{CargoResult, CargoResultExt};
 use crate::util::interning::InternedString;
 use crate::util::paths;
-use crate::util::{Config, Filesystem, Sha256};
+use crate::util::{CanonicalUrl, Config, Filesystem, IntoUrl};
 use lazycell::LazyCell;
 use log::{debug, trace};
+use serde::de::IgnoredAny;
 use std::cell::{Cell, Ref, RefCell};
-use std::fmt::Write as FmtWrite;
-use std::fs::{self, File, OpenOptions};
-use std::io::prelude::*;
-use std::io::SeekFrom;
+use std::collections::BTreeMap;
+use std::fs::File;
 use std::mem;
 use std::path::Path;
 use std::str;
 
-fn make_crate_prefix(name: &str) -> String {
-    match name.len() {
-        1 => String::from("1"),
-        2 => String::from("2"),
-        3 => format!("3/{}", &name[..1]),
-        _ => format!("{}/{}", &name[0..2], &name[2..4]),
-    }
-}
-
 pub struct RemoteRegistry<'cfg> {
     index_path: Filesystem,
     cache_path: Filesystem,
@@ -39,6 +28,7 @@ pub struct RemoteRegistry<'cfg> {
     repo: LazyCell<git2::Repository>,
     head: Cell<Option<git2::Oid>>,
     current_sha: Cell<Option<InternedString>>,
+    auth_token: LazyCell<String>,
 }
 
 impl<'cfg> RemoteRegistry<'cfg> {
@@ -54,6 +44,7 @@ impl<'cfg> RemoteRegistry<'cfg> {
             repo: LazyCell::new(),
             head: Cell::new(None),
             current_sha: Cell::new(None),
+            auth_token: LazyCell::new(),
         }
     }
 
@@ -136,13 +127,134 @@ impl<'cfg> RemoteRegistry<'cfg> {
         Ok(Ref::map(self.tree.borrow(), |s| s.as_ref().unwrap()))
     }
 
-    fn filename(&self, pkg: PackageId) -> String {
-        format!("{}-{}.crate", pkg.name(), pkg.version())
+    /// Returns the token to send along with every request, for registries
+    /// which require authentication.
+    fn auth_token(&self) -> CargoResult<String> {
+        self.auth_token
+            .try_borrow_with(|| ops::registry_auth_token(self.config, self.source_id))
+            .map(|token| token.clone())
     }
 }
 
 const LAST_UPDATED_FILE: &str = ".last-updated";
 
+/// Returns the key set in the config for the index of `source_id`, from
+/// `registry.signing-key` for crates.io, or the `signing-key` of the
+/// `[registries]` or `[source]` entry with the same URL.
+pub(super) fn configured_signing_key(
+    config: &Config,
+    source_id: SourceId,
+) -> CargoResult<Option<String>> {
+    if source_id.is_default_registry() {
+        return config.get::<Option<String>>("registry.signing-key");
+    }
+    let table_names = |key: &str| -> CargoResult<Vec<String>> {
+        let table = config.get::<Option<BTreeMap<String, IgnoredAny>>>(key)?;
+        Ok(table.into_iter().flatten().map(|(name, _)| name).collect())
+    };
+    // A registry defined only in the environment isn't in the `[registries]`
+    // table, but its name is known when a dependency refers to it.
+    let mut registries = table_names("registries")?;
+    registries.extend(source_id.alt_registry_key().map(String::from));
+    for name in registries {
+        let key = config.get::<Option<String>>(&format!("registries.{}.signing-key", name))?;
+        if let Some(key) = key {
+            let url = config.get_registry_index(&name)?;
+            if CanonicalUrl::new(&url)? == *source_id.canonical_url() {
+                return Ok(Some(key));
+            }
+        }
+    }
+    for name in table_names("source")? {
+        if let Some(key) = config.get::<Option<String>>(&format!("source.{}.signing-key", name))? {
+            let url = config.get_string(&format!("source.{}.registry", name))?;
+            if let Some(url) = url {
+                if CanonicalUrl::new(&url.val.into_url()?)? == *source_id.canonical_url() {
+                    return Ok(Some(key));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Reads the `config.json` of the index as of `commit`.
+fn config_at(repo: &git2::Repository, commit: git2::Oid) -> CargoResult<Option<RegistryConfig>> {
+    let commit = repo.find_commit(commit)?;
+    let entry = match commit.tree()?.get_path(Path::new("config.json")) {
+        Ok(entry) => entry,
+        Err(_) => return Ok(None),
+    };
+    let object = entry.to_object(repo)?;
+    let blob = object
+        .as_blob()
+        .ok_or_else(|| anyhow::format_err!("`config.json` is not a blob in the git repo"))?;
+    Ok(Some(serde_json::from_slice(blob.content())?))
+}
+
+/// Checks that the fetched version of the index is signed with the key of
+/// the registry, if it has one.
+///
+/// The key set in the config is used if there is one. Otherwise, it is taken
+/// from the `config.json` of the index as of `previous`, the version which
+/// was verified last time. When the index is fetched for the first time,
+/// nothing can vouch for the key in the fetched `config.json`, so it is
+/// trusted as is, with a warning, and only checked from the next fetch on.
+fn verify_index(
+    repo: &git2::Repository,
+    git_ref: &GitReference,
+    previous: Option<git2::Oid>,
+    source_id: SourceId,
+    config: &Config,
+) -> CargoResult<()> {
+    let head = git_ref.resolve(repo, None)?;
+    if previous == Some(head) {
+        return Ok(());
+    }
+    let key = match (configured_signing_key(config, source_id)?, previous) {
+        (Some(key), _) => key,
+        (None, Some(previous)) => match config_at(repo, previous)?.and_then(|c| c.signing_key) {
+            Some(key) => key,
+            None => return Ok(()),
+        },
+        (None, None) => {
+            if let Some(key) = config_at(repo, head)?.and_then(|c| c.signing_key) {
+                config.shell().warn(format!(
+                    "trusting the signing key `{}` of the {} on first use, \
+                     set `signing-key` in the config of the registry to pin it instead",
+                    key,
+                    source_id.display_index()
+                ))?;
+            }
+            return Ok(());
+        }
+    };
+    git::verify_commit_signature(repo, head, &key, config)
+}
+
+/// Returns the refs fetched from the remote, pointing to the fetched
+/// versions of the index.
+fn remote_refs(repo: &git2::Repository) -> CargoResult<Vec<(String, git2::Oid)>> {
+    let mut refs = Vec::new();
+    for reference in repo.references_glob("refs/remotes/origin/*")? {
+        let reference = reference?;
+        if let (Some(name), Some(oid)) = (reference.name(), reference.target()) {
+            refs.push((name.to_string(), oid));
+        }
+    }
+    Ok(refs)
+}
+
+fn restore_remote_refs(repo: &git2::Repository, refs: &[(String, git2::Oid)]) -> CargoResult<()> {
+    for reference in repo.references_glob("refs/remotes/origin/*")? {
+        reference?.delete()?;
+    }
+    for (name, oid) in refs {
+        repo.reference(name, *oid, true, "cargo: restore the verified index")?;
+    }
+    Ok(())
+}
+
 impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
     fn prepare(&self) -> CargoResult<()> {
         self.repo()?; // create intermediate dirs and initialize the repo
@@ -235,9 +347,59 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
         // Fetch the latest version of our `index_git_ref` into the index
         // checkout.
         let url = self.source_id.url();
+        let previous = self.index_git_ref.resolve(self.repo()?, None).ok();
+
+        // Whether the registry requires authentication is declared in the
+        // index itself, so it's only known once it was fetched. Until then,
+        // git has to be set up to authenticate on its own.
+        let auth_required = previous
+            .and_then(|previous| config_at(self.repo().ok()?, previous).ok()?)
+            .map_or(false, |config| config.auth_required);
+        let authorization = if auth_required {
+            Some(self.auth_token()?)
+        } else {
+            None
+        };
+
+        let (config, source_id) = (self.config, self.source_id);
         let repo = self.repo.borrow_mut().unwrap();
-        git::fetch(repo, url.as_str(), &self.index_git_ref, self.config)
-            .chain_err(|| format!("failed to fetch `{}`", url))?;
+        let saved_refs = remote_refs(repo)?;
+        let index_git_ref = &self.index_git_ref;
+        let mut fetch = |authorization: Option<&str>| {
+            git::fetch_with_authorization(repo, url.as_str(), index_git_ref, config, authorization)
+        };
+        let mut fetched = fetch(authorization.as_deref());
+        // The first fetch of the index, or the first one since it started to
+        // require authentication, is done without a token. If the server
+        // refuses it, try once more with the token of the registry, or tell
+        // the user how to provide one.
+        if let Err(e) = fetched {
+            fetched = if authorization.is_none() && git::is_auth_failure(&e) {
+                match ops::registry_auth_token(config, source_id) {
+                    Ok(token) => {
+                        debug!("retrying the fetch of the index with the registry token");
+                        fetch(Some(&token))
+                    }
+                    Err(token_err) => Err(e.context(format!("{:#}", token_err))),
+                }
+            } else {
+                Err(e)
+            };
+        }
+        fetched.chain_err(|| format!("failed to fetch `{}`", url))?;
+        let verified = verify_index(
+            repo,
+            &self.index_git_ref,
+            previous,
+            self.source_id,
+            self.config,
+        );
+        if let Err(e) = verified {
+            // Go back to the last verified version of the index, so that the
+            // fetched one isn't used later on, such as when offline.
+            restore_remote_refs(repo, &saved_refs)?;
+            return Err(e.context("failed to verify the signature of the index"));
+        }
         self.config.updated_sources().insert(self.source_id);
 
         // Create a dummy file to record the mtime for when we updated the
@@ -247,44 +409,16 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
         Ok(())
     }
 
-    fn download(&mut self, pkg: PackageId, _checksum: &str) -> CargoResult<MaybeLock> {
-        let filename = self.filename(pkg);
-
-        // Attempt to open an read-only copy first to avoid an exclusive write
-        // lock and also work with read-only filesystems. Note that we check the
-        // length of the file like below to handle interrupted downloads.
-        //
-        // If this fails then we fall through to the exclusive path where we may
-        // have to redownload the file.
-        let path = self.cache_path.join(&filename);
-        let path = self.config.assert_package_cache_locked(&path);
-        if let Ok(dst) = File::open(&path) {
-            let meta = dst.metadata()?;
-            if meta.len() > 0 {
-                return Ok(MaybeLock::Ready(dst));
-            }
-        }
-
-        let config = self.config()?.unwrap();
-        let mut url = config.dl;
-        if !url.contains(CRATE_TEMPLATE)
-            && !url.contains(VERSION_TEMPLATE)
-            && !url.contains(PREFIX_TEMPLATE)
-            && !url.contains(LOWER_PREFIX_TEMPLATE)
-        {
-            write!(url, "/{}/{}/download", CRATE_TEMPLATE, VERSION_TEMPLATE).unwrap();
-        }
-        let prefix = make_crate_prefix(&*pkg.name());
-        let url = url
-            .replace(CRATE_TEMPLATE, &*pkg.name())
-            .replace(VERSION_TEMPLATE, &pkg.version().to_string())
-            .replace(PREFIX_TEMPLATE, &prefix)
-            .replace(LOWER_PREFIX_TEMPLATE, &prefix.to_lowercase());
-
-        Ok(MaybeLock::Download {
-            url,
-            descriptor: pkg.to_string(),
-        })
+    fn download(&mut self, pkg: PackageId, checksum: &str) -> CargoResult<MaybeLock> {
+        let registry_config = self.config()?.unwrap();
+        download::download(
+            &self.cache_path,
+            self.config,
+            pkg,
+            checksum,
+            registry_config,
+            &|| self.auth_token(),
+        )
     }
 
     fn finish_download(
@@ -293,42 +427,11 @@ impl<'cfg> RegistryData for RemoteRegistry<'cfg> {
         checksum: &str,
         data: &[u8],
     ) -> CargoResult<File> {
-        // Verify what we just downloaded
-        let actual = Sha256::new().update(data).finish_hex();
-        if actual != checksum {
-            anyhow::bail!("failed to verify the checksum of `{}`", pkg)
-        }
-
-        let filename = self.filename(pkg);
-        self.cache_path.create_dir()?;
-        let path = self.cache_path.join(&filename);
-        let path = self.config.assert_package_cache_locked(&path);
-        let mut dst = OpenOptions::new()
-            .create(true)
-            .read(true)
-            .write(true)
-            .open(&path)
-            .chain_err(|| format!("failed to open `{}`", path.display()))?;
-        let meta = dst.metadata()?;
-        if meta.len() > 0 {
-            return Ok(dst);
-        }
-
-        dst.write_all(data)?;
-        dst.seek(SeekFrom::Start(0))?;
-        Ok(dst)
+        download::finish_download(&self.cache_path, self.config, pkg, checksum, data)
     }
 
     fn is_crate_downloaded(&self, pkg: PackageId) -> bool {
-        let filename = format!("{}-{}.crate", pkg.name(), pkg.version());
-        let path = Path::new(&filename);
-
-        let path = self.cache_path.join(path);
-        let path = self.config.assert_package_cache_locked(&path);
-        if let Ok(meta) = fs::metadata(path) {
-            return meta.len() > 0;
-        }
-        false
+        download::is_crate_downloaded(&self.cache_path, self.config, pkg)
     }
 }
 
@@ -338,18 +441,3 @@ impl<'cfg> Drop for RemoteRegistry<'cfg> {
         self.tree.borrow_mut().take();
     }
 }
-
-#[cfg(test)]
-mod tests {
-    use super::make_crate_prefix;
-
-    #[test]
-    fn crate_prefix() {
-        assert_eq!(make_crate_prefix("a"), "1");
-        assert_eq!(make_crate_prefix("ab"), "2");
-        assert_eq!(make_crate_prefix("abc"), "3/a");
-        assert_eq!(make_crate_prefix("Abc"), "3/A");
-        assert_eq!(make_crate_prefix("AbCd"), "Ab/Cd");
-        assert_eq!(make_crate_prefix("aBcDe"), "aB/cD");
-    }
-}
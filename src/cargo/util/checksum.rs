@@ -0,0 +1,200 @@
+//! Checksums of packages and files, as listed in the `cksum` field of the
+//! registry index, the `checksum` entries of `Cargo.lock` and the
+//! `.cargo-checksum.json` files of directory sources.
+//!
+//! A checksum is the hex digest of the data, prefixed with the name of the
+//! algorithm used to compute it, such as `sha512:8a3f…`. Checksums used to
+//! always be SHA-256 and had no prefix, so a bare digest is still SHA-256.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crypto_hash::Algorithm;
+
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::paths;
+
+/// The algorithms which checksums may be computed with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl ChecksumAlgorithm {
+    pub fn from_name(name: &str) -> CargoResult<ChecksumAlgorithm> {
+        match name {
+            "sha256" => Ok(ChecksumAlgorithm::Sha256),
+            "sha512" => Ok(ChecksumAlgorithm::Sha512),
+            "blake3" => Ok(ChecksumAlgorithm::Blake3),
+            _ => anyhow::bail!(
+                "unsupported checksum algorithm `{}`, expected `sha256`, `sha512` or `blake3`",
+                name
+            ),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Sha512 => "sha512",
+            ChecksumAlgorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// The length of the digests of the algorithm, in bytes.
+    fn len(self) -> usize {
+        match self {
+            ChecksumAlgorithm::Sha256 | ChecksumAlgorithm::Blake3 => 32,
+            ChecksumAlgorithm::Sha512 => 64,
+        }
+    }
+
+    fn hasher(self) -> Hasher {
+        match self {
+            ChecksumAlgorithm::Sha256 => Hasher::Sha(crypto_hash::Hasher::new(Algorithm::SHA256)),
+            ChecksumAlgorithm::Sha512 => Hasher::Sha(crypto_hash::Hasher::new(Algorithm::SHA512)),
+            ChecksumAlgorithm::Blake3 => Hasher::Blake3(blake3::Hasher::new()),
+        }
+    }
+}
+
+/// `crypto-hash` only has the SHA family, BLAKE3 has its own crate.
+enum Hasher {
+    Sha(crypto_hash::Hasher),
+    Blake3(blake3::Hasher),
+}
+
+impl Hasher {
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha(hasher) => {
+                let _ = hasher.write_all(data);
+            }
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha(mut hasher) => hasher.finish(),
+            Hasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Checksum {
+    algorithm: ChecksumAlgorithm,
+    digest: Vec<u8>,
+}
+
+impl Checksum {
+    pub fn parse(s: &str) -> CargoResult<Checksum> {
+        let mut parts = s.splitn(2, ':');
+        let (algorithm, digest) = match (parts.next(), parts.next()) {
+            (Some(name), Some(digest)) => (ChecksumAlgorithm::from_name(name)?, digest),
+            _ => (ChecksumAlgorithm::Sha256, s),
+        };
+        let digest = hex::decode(digest)
+            .ok()
+            .filter(|digest| digest.len() == algorithm.len())
+            .ok_or_else(|| {
+                anyhow::format_err!(
+                    "invalid checksum `{}`, expected {} hex digits",
+                    s,
+                    algorithm.len() * 2
+                )
+            })?;
+        Ok(Checksum { algorithm, digest })
+    }
+
+    pub fn algorithm(&self) -> ChecksumAlgorithm {
+        self.algorithm
+    }
+
+    pub fn compute(algorithm: ChecksumAlgorithm, data: &[u8]) -> Checksum {
+        let mut hasher = algorithm.hasher();
+        hasher.update(data);
+        Checksum {
+            algorithm,
+            digest: hasher.finish(),
+        }
+    }
+
+    pub fn compute_file(algorithm: ChecksumAlgorithm, mut file: &File) -> io::Result<Checksum> {
+        let mut hasher = algorithm.hasher();
+        let mut buf = [0; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+        Ok(Checksum {
+            algorithm,
+            digest: hasher.finish(),
+        })
+    }
+
+    pub fn compute_path(algorithm: ChecksumAlgorithm, path: &Path) -> CargoResult<Checksum> {
+        let file = paths::open(path)?;
+        Checksum::compute_file(algorithm, &file)
+            .chain_err(|| format!("failed to read `{}`", path.display()))
+    }
+}
+
+/// SHA-256 checksums are written without a prefix, so that they can still be
+/// read by older versions of Cargo.
+impl fmt::Display for Checksum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.algorithm != ChecksumAlgorithm::Sha256 {
+            write!(f, "{}:", self.algorithm.name())?;
+        }
+        write!(f, "{}", hex::encode(&self.digest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Checksum, ChecksumAlgorithm};
+
+    #[test]
+    fn parse() {
+        let sha256 = Checksum::compute(ChecksumAlgorithm::Sha256, b"hello");
+        let bare = sha256.to_string();
+        assert_eq!(bare.len(), 64);
+        assert_eq!(Checksum::parse(&bare).unwrap(), sha256);
+        assert_eq!(
+            Checksum::parse(&format!("sha256:{}", bare)).unwrap(),
+            sha256
+        );
+
+        let sha512 = Checksum::compute(ChecksumAlgorithm::Sha512, b"hello");
+        assert!(sha512.to_string().starts_with("sha512:"));
+        assert_eq!(Checksum::parse(&sha512.to_string()).unwrap(), sha512);
+
+        let blake3 = Checksum::compute(ChecksumAlgorithm::Blake3, b"hello");
+        assert_eq!(
+            blake3.to_string(),
+            "blake3:ea8f163db38682925e4491c5e58d4bb3506ef8c14eb78a86e908c5624a67200f"
+        );
+        assert_eq!(Checksum::parse(&blake3.to_string()).unwrap(), blake3);
+
+        for bad in &[
+            "",
+            "abcd",
+            &format!("sha512:{}", bare),
+            &format!("md5:{}", bare),
+            &format!("g{}", &bare[1..]),
+        ] {
+            assert!(Checksum::parse(bad).is_err(), "{:?} parsed", bad);
+        }
+    }
+}
@@ -156,6 +156,13 @@ pub trait AppExt: Sized {
         self._arg(multi_opt("message-format", "FMT", "Error format"))
     }
 
+    fn arg_explain_rebuilds(self) -> Self {
+        self._arg(opt(
+            "explain-rebuilds",
+            "Print why each unit which isn't up to date is rebuilt",
+        ))
+    }
+
     fn arg_build_plan(self) -> Self {
         self._arg(opt(
             "build-plan",
@@ -163,6 +170,13 @@ pub trait AppExt: Sized {
         ))
     }
 
+    fn arg_ignore_rust_version(self) -> Self {
+        self._arg(opt(
+            "ignore-rust-version",
+            "Ignore `rust-version` specification in packages",
+        ))
+    }
+
     fn arg_unit_graph(self) -> Self {
         self._arg(opt("unit-graph", "Output build graph in JSON (unstable)").hidden(true))
     }
@@ -301,6 +315,12 @@ pub trait ArgMatchesExt {
         if config.cli_unstable().avoid_dev_deps {
             ws.set_require_optional_deps(false);
         }
+        let json_messages = self
+            ._values_of("message-format")
+            .iter()
+            .flat_map(|fmt| fmt.split(','))
+            .any(|fmt| fmt.to_ascii_lowercase().starts_with("json"));
+        ws.set_json_messages(json_messages);
         if ws.is_virtual() && !ws.allows_unstable_package_features() {
             // --all-features is actually honored. In general, workspaces and
             // feature flags are a bit of a mess right now.
@@ -459,6 +479,7 @@ pub trait ArgMatchesExt {
         build_config.requested_profile = self.get_profile_name(config, "dev", profile_checking)?;
         build_config.build_plan = self._is_present("build-plan");
         build_config.unit_graph = self._is_present("unit-graph");
+        build_config.explain_rebuilds = self._is_present("explain-rebuilds");
         if build_config.build_plan {
             config
                 .cli_unstable()
@@ -492,6 +513,7 @@ pub trait ArgMatchesExt {
             target_rustc_args: None,
             local_rustdoc_args: None,
             rustdoc_document_private_items: false,
+            honor_rust_version: !self._is_present("ignore-rust-version"),
         };
 
         if let Some(ws) = workspace {
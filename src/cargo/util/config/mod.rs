@@ -1576,7 +1576,17 @@ pub fn homedir(cwd: &Path) -> Option<PathBuf> {
     ::home::cargo_home_with_cwd(cwd).ok()
 }
 
-pub fn save_credentials(cfg: &Config, token: String, registry: Option<String>) -> CargoResult<()> {
+/// Saves `token` in the credentials file as the token for `registry`, or for
+/// crates.io if `registry` is `None`.
+///
+/// If `token` is `None`, the existing token is removed instead.
+///
+/// Returns whether the credentials file had a token for `registry` before.
+pub fn save_credentials(
+    cfg: &Config,
+    token: Option<String>,
+    registry: Option<String>,
+) -> CargoResult<bool> {
     // If 'credentials.toml' exists, we should write to that, otherwise
     // use the legacy 'credentials'. There's no need to print the warning
     // here, because it would already be printed at load time.
@@ -1595,25 +1605,6 @@ pub fn save_credentials(cfg: &Config, token: String, registry: Option<String>) -
             .open_rw(filename, cfg, "credentials' config file")?
     };
 
-    let (key, mut value) = {
-        let key = "token".to_string();
-        let value = ConfigValue::String(token, Definition::Path(file.path().to_path_buf()));
-        let mut map = HashMap::new();
-        map.insert(key, value);
-        let table = CV::Table(map, Definition::Path(file.path().to_path_buf()));
-
-        if let Some(registry) = registry.clone() {
-            let mut map = HashMap::new();
-            map.insert(registry, table);
-            (
-                "registries".into(),
-                CV::Table(map, Definition::Path(file.path().to_path_buf())),
-            )
-        } else {
-            ("registry".into(), table)
-        }
-    };
-
     let mut contents = String::new();
     file.read_to_string(&mut contents).chain_err(|| {
         format!(
@@ -1633,13 +1624,20 @@ pub fn save_credentials(cfg: &Config, token: String, registry: Option<String>) -
             .insert("registry".into(), map.into());
     }
 
-    if registry.is_some() {
-        if let Some(table) = toml.as_table_mut().unwrap().remove("registries") {
-            let v = CV::from_toml(Definition::Path(file.path().to_path_buf()), table)?;
-            value.merge(v, false)?;
-        }
+    let table = match &registry {
+        Some(registry) => toml
+            .get_mut("registries")
+            .and_then(|registries| registries.get_mut(registry)),
+        None => toml.get_mut("registry"),
+    };
+    let had_token = match table {
+        Some(toml::Value::Table(table)) if token.is_none() => table.remove("token").is_some(),
+        Some(table) => table.get("token").is_some(),
+        None => false,
+    };
+    if let Some(token) = token {
+        insert_token(&mut toml, token, registry, file.path())?;
     }
-    toml.as_table_mut().unwrap().insert(key, value.into_toml());
 
     let contents = toml.to_string();
     file.seek(SeekFrom::Start(0))?;
@@ -1649,7 +1647,42 @@ pub fn save_credentials(cfg: &Config, token: String, registry: Option<String>) -
     set_permissions(file.file(), 0o600)
         .chain_err(|| format!("failed to set permissions of `{}`", file.path().display()))?;
 
-    return Ok(());
+    return Ok(had_token);
+
+    fn insert_token(
+        toml: &mut toml::Value,
+        token: String,
+        registry: Option<String>,
+        path: &Path,
+    ) -> CargoResult<()> {
+        let (key, mut value) = {
+            let key = "token".to_string();
+            let value = ConfigValue::String(token, Definition::Path(path.to_path_buf()));
+            let mut map = HashMap::new();
+            map.insert(key, value);
+            let table = CV::Table(map, Definition::Path(path.to_path_buf()));
+
+            if let Some(registry) = registry.clone() {
+                let mut map = HashMap::new();
+                map.insert(registry, table);
+                (
+                    "registries".into(),
+                    CV::Table(map, Definition::Path(path.to_path_buf())),
+                )
+            } else {
+                ("registry".into(), table)
+            }
+        };
+
+        if registry.is_some() {
+            if let Some(table) = toml.as_table_mut().unwrap().remove("registries") {
+                let v = CV::from_toml(Definition::Path(path.to_path_buf()), table)?;
+                value.merge(v, false)?;
+            }
+        }
+        toml.as_table_mut().unwrap().insert(key, value.into_toml());
+        Ok(())
+    }
 
     #[cfg(unix)]
     fn set_permissions(file: &File, mode: u32) -> CargoResult<()> {
@@ -1745,6 +1778,10 @@ pub struct CargoBuildConfig {
     pub rustc: Option<PathBuf>,
     pub rustdoc: Option<PathBuf>,
     pub out_dir: Option<ConfigRelativePath>,
+    pub shared_cache: Option<ConfigRelativePath>,
+    pub shared_cache_max_size: Option<u64>,
+    pub remote_cache: Option<String>,
+    pub remote_cache_mode: Option<String>,
 }
 
 /// A type to deserialize a list of strings from a toml file.
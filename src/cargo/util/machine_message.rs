@@ -4,6 +4,7 @@ use serde::ser;
 use serde::Serialize;
 use serde_json::{self, json, value::RawValue};
 
+use crate::core::resolver::ConflictReport;
 use crate::core::{compiler::CompileMode, PackageId, Target};
 
 pub trait Message: ser::Serialize {
@@ -91,6 +92,32 @@ impl<'a> Message for TimingInfo<'a> {
     }
 }
 
+#[derive(Serialize)]
+pub struct RebuildReason<'a> {
+    pub package_id: PackageId,
+    pub target: &'a Target,
+    pub mode: CompileMode,
+    /// A short name of the reason, such as `file-changed`.
+    pub kind: &'static str,
+    /// The rendered explanation of the reason.
+    pub message: String,
+    /// The unit whose rebuild caused this one, if any.
+    pub triggered_by: Option<RebuildTrigger<'a>>,
+}
+
+#[derive(Serialize)]
+pub struct RebuildTrigger<'a> {
+    pub package_id: PackageId,
+    pub target: &'a Target,
+    pub mode: CompileMode,
+}
+
+impl<'a> Message for RebuildReason<'a> {
+    fn reason(&self) -> &str {
+        "compiler-rebuild-reason"
+    }
+}
+
 #[derive(Serialize)]
 pub struct BuildFinished {
     pub success: bool,
@@ -101,3 +128,17 @@ impl Message for BuildFinished {
         "build-finished"
     }
 }
+
+#[derive(Serialize)]
+pub struct ResolveConflict<'a> {
+    #[serde(flatten)]
+    pub report: &'a ConflictReport,
+    /// The rendered explanation of the conflict.
+    pub explanation: String,
+}
+
+impl<'a> Message for ResolveConflict<'a> {
+    fn reason(&self) -> &str {
+        "resolve-conflict"
+    }
+}
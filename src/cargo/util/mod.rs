@@ -1,6 +1,7 @@
 use std::time::Duration;
 
 pub use self::canonical_url::CanonicalUrl;
+pub use self::checksum::{Checksum, ChecksumAlgorithm};
 pub use self::config::{homedir, Config, ConfigValue};
 pub use self::dependency_queue::DependencyQueue;
 pub use self::diagnostic_server::RustfixDiagnosticServer;
@@ -32,6 +33,7 @@ pub use self::workspace::{
 };
 
 mod canonical_url;
+mod checksum;
 pub mod command_prelude;
 pub mod config;
 pub mod cpu;
@@ -60,8 +62,10 @@ mod read2;
 pub mod restricted_names;
 pub mod rustc;
 mod sha256;
+pub mod spdx;
 pub mod to_semver;
 pub mod toml;
+pub mod toml_mut;
 mod vcs;
 mod workspace;
 
@@ -0,0 +1,251 @@
+//! Parsing of the SPDX license expressions used in the `license` field of
+//! manifests, such as `MIT OR Apache-2.0`.
+//!
+//! Only the syntax is checked: identifiers aren't compared against the SPDX
+//! license list, so that new or custom (`LicenseRef-`) licenses are accepted.
+//! As crates.io used to allow it, `/` is accepted as an alias for `OR`.
+
+use std::fmt;
+
+use crate::util::errors::CargoResult;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LicenseExpr {
+    License(License),
+    And(Vec<LicenseExpr>),
+    Or(Vec<LicenseExpr>),
+}
+
+/// A single license of an expression, such as `GPL-2.0+` or
+/// `GPL-2.0 WITH Classpath-exception-2.0`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct License {
+    pub id: String,
+    /// Whether the identifier was followed by `+`, for "this version or any
+    /// later one".
+    pub or_later: bool,
+    pub exception: Option<String>,
+}
+
+impl LicenseExpr {
+    pub fn parse(s: &str) -> CargoResult<LicenseExpr> {
+        let mut parser = Parser {
+            tokens: tokenize(s),
+            pos: 0,
+        };
+        let expr = parser.parse_or()?;
+        match parser.next() {
+            None => Ok(expr),
+            Some(token) => anyhow::bail!("unexpected `{}`", token),
+        }
+    }
+
+    /// Returns the licenses appearing in the expression, in order.
+    pub fn licenses(&self) -> Vec<&License> {
+        match self {
+            LicenseExpr::License(license) => vec![license],
+            LicenseExpr::And(exprs) | LicenseExpr::Or(exprs) => {
+                exprs.iter().flat_map(|expr| expr.licenses()).collect()
+            }
+        }
+    }
+
+    /// Returns whether the expression can be complied with using only the
+    /// licenses for which `accept` returns `true`.
+    pub fn is_satisfied_by(&self, accept: &dyn Fn(&License) -> bool) -> bool {
+        match self {
+            LicenseExpr::License(license) => accept(license),
+            LicenseExpr::And(exprs) => exprs.iter().all(|expr| expr.is_satisfied_by(accept)),
+            LicenseExpr::Or(exprs) => exprs.iter().any(|expr| expr.is_satisfied_by(accept)),
+        }
+    }
+}
+
+impl fmt::Display for License {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)?;
+        if self.or_later {
+            write!(f, "+")?;
+        }
+        if let Some(exception) = &self.exception {
+            write!(f, " WITH {}", exception)?;
+        }
+        Ok(())
+    }
+}
+
+fn tokenize(s: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in s.char_indices() {
+        if c.is_whitespace() || c == '(' || c == ')' || c == '/' {
+            if let Some(start) = start.take() {
+                tokens.push(&s[start..i]);
+            }
+            if !c.is_whitespace() {
+                tokens.push(&s[i..i + 1]);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(start) = start {
+        tokens.push(&s[start..]);
+    }
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: Vec<&'a str>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> CargoResult<LicenseExpr> {
+        let mut exprs = vec![self.parse_and()?];
+        while let Some("OR") | Some("/") = self.peek() {
+            self.next();
+            exprs.push(self.parse_and()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            LicenseExpr::Or(exprs)
+        })
+    }
+
+    fn parse_and(&mut self) -> CargoResult<LicenseExpr> {
+        let mut exprs = vec![self.parse_primary()?];
+        while let Some("AND") = self.peek() {
+            self.next();
+            exprs.push(self.parse_primary()?);
+        }
+        Ok(if exprs.len() == 1 {
+            exprs.pop().unwrap()
+        } else {
+            LicenseExpr::And(exprs)
+        })
+    }
+
+    fn parse_primary(&mut self) -> CargoResult<LicenseExpr> {
+        match self.next() {
+            Some("(") => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(")") => Ok(expr),
+                    Some(token) => anyhow::bail!("expected `)`, found `{}`", token),
+                    None => anyhow::bail!("expected `)`, found the end of the expression"),
+                }
+            }
+            Some(token) => {
+                let (id, or_later) = match token.strip_suffix('+') {
+                    Some(id) => (id, true),
+                    None => (token, false),
+                };
+                check_identifier(id, token)?;
+                let exception = match self.peek() {
+                    Some("WITH") => {
+                        self.next();
+                        let exception = self.next().ok_or_else(|| {
+                            anyhow::format_err!("expected an exception after `WITH`")
+                        })?;
+                        check_identifier(exception, exception)?;
+                        Some(exception.to_string())
+                    }
+                    _ => None,
+                };
+                Ok(LicenseExpr::License(License {
+                    id: id.to_string(),
+                    or_later,
+                    exception,
+                }))
+            }
+            None => anyhow::bail!("expected a license, found the end of the expression"),
+        }
+    }
+}
+
+fn check_identifier(id: &str, token: &str) -> CargoResult<()> {
+    let is_operator = ["AND", "OR", "WITH", "(", ")", "/"].contains(&id);
+    let is_valid = id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+    if id.is_empty() || is_operator || !is_valid {
+        anyhow::bail!("expected a license, found `{}`", token);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{License, LicenseExpr};
+
+    fn license(id: &str) -> LicenseExpr {
+        LicenseExpr::License(License {
+            id: id.to_string(),
+            or_later: false,
+            exception: None,
+        })
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(LicenseExpr::parse("MIT").unwrap(), license("MIT"));
+        assert_eq!(
+            LicenseExpr::parse("MIT OR Apache-2.0").unwrap(),
+            LicenseExpr::Or(vec![license("MIT"), license("Apache-2.0")])
+        );
+        assert_eq!(
+            LicenseExpr::parse("MIT/Apache-2.0").unwrap(),
+            LicenseExpr::parse("MIT OR Apache-2.0").unwrap()
+        );
+        assert_eq!(
+            LicenseExpr::parse("(MIT OR Apache-2.0) AND Zlib OR BSD-3-Clause").unwrap(),
+            LicenseExpr::Or(vec![
+                LicenseExpr::And(vec![
+                    LicenseExpr::Or(vec![license("MIT"), license("Apache-2.0")]),
+                    license("Zlib"),
+                ]),
+                license("BSD-3-Clause"),
+            ])
+        );
+        let gpl = LicenseExpr::parse("GPL-2.0+ WITH Classpath-exception-2.0").unwrap();
+        assert_eq!(
+            gpl.licenses()[0].to_string(),
+            "GPL-2.0+ WITH Classpath-exception-2.0"
+        );
+
+        for bad in &[
+            "",
+            "MIT OR",
+            "(MIT",
+            "MIT)",
+            "MIT or Apache-2.0",
+            "MIT WITH",
+            "M!T",
+        ] {
+            assert!(LicenseExpr::parse(bad).is_err(), "{:?} parsed", bad);
+        }
+    }
+
+    #[test]
+    fn is_satisfied_by() {
+        let expr = LicenseExpr::parse("(MIT OR Apache-2.0) AND Zlib").unwrap();
+        let accept = |ids: &'static [&'static str]| {
+            move |license: &License| ids.contains(&license.id.as_str())
+        };
+        assert!(expr.is_satisfied_by(&accept(&["MIT", "Zlib"])));
+        assert!(expr.is_satisfied_by(&accept(&["Apache-2.0", "Zlib"])));
+        assert!(!expr.is_satisfied_by(&accept(&["MIT", "Apache-2.0"])));
+    }
+}
@@ -6,6 +6,7 @@ use std::str;
 
 use anyhow::{anyhow, bail};
 use cargo_platform::Platform;
+use lazycell::LazyCell;
 use log::{debug, trace};
 use semver::{self, VersionReq};
 use serde::de;
@@ -16,10 +17,11 @@ use url::Url;
 use crate::core::dependency::DepKind;
 use crate::core::manifest::{ManifestMetadata, TargetSourcePath, Warnings};
 use crate::core::profiles::Strip;
-use crate::core::resolver::ResolveBehavior;
+use crate::core::resolver::{IncompatibleRustVersions, ResolveBehavior};
+use crate::core::{BannedDependency, DependencyPolicy, GitReference, PackageIdSpec, SourceId};
 use crate::core::{Dependency, Manifest, PackageId, Summary, Target};
 use crate::core::{Edition, EitherManifest, Feature, Features, VirtualManifest, Workspace};
-use crate::core::{GitReference, PackageIdSpec, SourceId, WorkspaceConfig, WorkspaceRootConfig};
+use crate::core::{WorkspaceConfig, WorkspaceRootConfig};
 use crate::sources::{CRATES_IO_INDEX, CRATES_IO_REGISTRY};
 use crate::util::errors::{CargoResult, CargoResultExt, ManifestError};
 use crate::util::interning::InternedString;
@@ -241,9 +243,12 @@ pub struct DetailedTomlDependency {
     default_features2: Option<bool>,
     package: Option<String>,
     public: Option<bool>,
+    /// Whether to inherit this dependency from `[workspace.dependencies]`,
+    /// in which case only `features` and `optional` may also be given.
+    workspace: Option<bool>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct TomlManifest {
     cargo_features: Option<Vec<String>>,
@@ -781,16 +786,18 @@ impl<'de> de::Deserialize<'de> for VecStringOrBool {
 /// tables.
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct TomlProject {
-    edition: Option<String>,
+    edition: Option<MaybeWorkspace<String>>,
+    #[serde(rename = "rust-version")]
+    rust_version: Option<MaybeWorkspace<String>>,
     name: InternedString,
-    version: semver::Version,
-    authors: Option<Vec<String>>,
+    version: MaybeWorkspace<semver::Version>,
+    authors: Option<MaybeWorkspace<Vec<String>>>,
     build: Option<StringOrBool>,
     metabuild: Option<StringOrVec>,
     links: Option<String>,
     exclude: Option<Vec<String>>,
     include: Option<Vec<String>>,
-    publish: Option<VecStringOrBool>,
+    publish: Option<MaybeWorkspace<VecStringOrBool>>,
     #[serde(rename = "publish-lockfile")]
     publish_lockfile: Option<bool>,
     workspace: Option<String>,
@@ -806,21 +813,21 @@ pub struct TomlProject {
     default_run: Option<String>,
 
     // Package metadata.
-    description: Option<String>,
-    homepage: Option<String>,
-    documentation: Option<String>,
+    description: Option<MaybeWorkspace<String>>,
+    homepage: Option<MaybeWorkspace<String>>,
+    documentation: Option<MaybeWorkspace<String>>,
     readme: Option<StringOrBool>,
-    keywords: Option<Vec<String>>,
-    categories: Option<Vec<String>>,
-    license: Option<String>,
+    keywords: Option<MaybeWorkspace<Vec<String>>>,
+    categories: Option<MaybeWorkspace<Vec<String>>>,
+    license: Option<MaybeWorkspace<String>>,
     #[serde(rename = "license-file")]
     license_file: Option<String>,
-    repository: Option<String>,
+    repository: Option<MaybeWorkspace<String>>,
     metadata: Option<toml::Value>,
     resolver: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct TomlWorkspace {
     members: Option<Vec<String>>,
     #[serde(rename = "default-members")]
@@ -828,12 +835,308 @@ pub struct TomlWorkspace {
     exclude: Option<Vec<String>>,
     metadata: Option<toml::Value>,
     resolver: Option<String>,
+    #[serde(rename = "incompatible-rust-versions")]
+    incompatible_rust_versions: Option<String>,
+    #[serde(rename = "dependency-policy")]
+    dependency_policy: Option<TomlDependencyPolicy>,
+
+    // Values members can inherit.
+    package: Option<TomlWorkspacePackage>,
+    dependencies: Option<BTreeMap<String, TomlDependency>>,
+}
+
+impl TomlWorkspace {
+    /// Whether this workspace has values for members to inherit.
+    fn has_inheritable(&self) -> bool {
+        self.package.is_some() || self.dependencies.is_some()
+    }
+
+    fn incompatible_rust_versions(
+        &self,
+        features: &Features,
+    ) -> CargoResult<Option<IncompatibleRustVersions>> {
+        match &self.incompatible_rust_versions {
+            Some(value) => {
+                features.require(Feature::msrv_policy())?;
+                Ok(Some(IncompatibleRustVersions::from_manifest(value)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn dependency_policy(&self, features: &Features) -> CargoResult<Option<DependencyPolicy>> {
+        let policy = match &self.dependency_policy {
+            Some(policy) => policy,
+            None => return Ok(None),
+        };
+        features.require(Feature::dependency_policy())?;
+        let mut ban = Vec::new();
+        for banned in policy.ban.iter().flatten() {
+            let version = match &banned.version {
+                Some(req) => Some(VersionReq::parse(req).chain_err(|| {
+                    format!(
+                        "failed to parse the version requirement `{}` of the \
+                         banned dependency `{}`",
+                        req, banned.name
+                    )
+                })?),
+                None => None,
+            };
+            ban.push(BannedDependency {
+                name: InternedString::new(&banned.name),
+                version,
+            });
+        }
+        if policy.max_versions == Some(0) {
+            bail!("`workspace.dependency-policy.max-versions` must be at least 1");
+        }
+        Ok(Some(DependencyPolicy {
+            ban,
+            allow_registries: policy.allow_registries.clone(),
+            allow_git: policy.allow_git.clone(),
+            max_versions: policy.max_versions,
+        }))
+    }
+}
+
+/// Represents the `[workspace.dependency-policy]` section, checked against
+/// the resolved dependencies of the workspace.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct TomlDependencyPolicy {
+    ban: Option<Vec<TomlBannedDependency>>,
+    allow_registries: Option<Vec<String>>,
+    allow_git: Option<Vec<String>>,
+    max_versions: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TomlBannedDependency {
+    name: String,
+    version: Option<String>,
+}
+
+/// Represents the `[workspace.package]` section, the fields of `[package]`
+/// which members can inherit with `field.workspace = true`.
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct TomlWorkspacePackage {
+    version: Option<semver::Version>,
+    authors: Option<Vec<String>>,
+    description: Option<String>,
+    homepage: Option<String>,
+    documentation: Option<String>,
+    keywords: Option<Vec<String>>,
+    categories: Option<Vec<String>>,
+    license: Option<String>,
+    repository: Option<String>,
+    publish: Option<VecStringOrBool>,
+    edition: Option<String>,
+    #[serde(rename = "rust-version")]
+    rust_version: Option<String>,
+}
+
+/// A field of `[package]` which is either given directly, or inherited from
+/// `[workspace.package]` with `field.workspace = true`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum MaybeWorkspace<T> {
+    Defined(T),
+    Workspace(TomlWorkspaceField),
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TomlWorkspaceField {
+    workspace: bool,
+}
+
+impl<'de, T: de::Deserialize<'de>> de::Deserialize<'de> for MaybeWorkspace<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        // Only the `{ workspace = true }` form is a table, unless `T` is
+        // itself one, which none of the inheritable fields are.
+        let value = toml::Value::deserialize(deserializer)?;
+        if value.is_table() {
+            TomlWorkspaceField::deserialize(value)
+                .map(MaybeWorkspace::Workspace)
+                .map_err(de::Error::custom)
+        } else {
+            T::deserialize(value)
+                .map(MaybeWorkspace::Defined)
+                .map_err(de::Error::custom)
+        }
+    }
+}
+
+impl<T> MaybeWorkspace<T> {
+    /// Returns the value of the field, calling `get_ws_value` to look it up
+    /// in the workspace if it is inherited.
+    fn resolve(
+        self,
+        label: &str,
+        features: &Features,
+        get_ws_value: impl FnOnce() -> CargoResult<T>,
+    ) -> CargoResult<T> {
+        match self {
+            MaybeWorkspace::Defined(value) => Ok(value),
+            MaybeWorkspace::Workspace(TomlWorkspaceField { workspace: true }) => {
+                features
+                    .require(Feature::workspace_inheritance())
+                    .chain_err(|| format!("failed to inherit `{}` from the workspace", label))?;
+                get_ws_value()
+            }
+            MaybeWorkspace::Workspace(TomlWorkspaceField { workspace: false }) => {
+                bail!("`{}.workspace` cannot be false", label)
+            }
+        }
+    }
+}
+
+/// Resolves the optional field `field`, replacing it with the inherited value
+/// if needed, and returns its value.
+fn inherit_field<T: Clone>(
+    field: &mut Option<MaybeWorkspace<T>>,
+    label: &str,
+    features: &Features,
+    get_ws_value: impl FnOnce() -> CargoResult<T>,
+) -> CargoResult<Option<T>> {
+    let value = match field.take() {
+        Some(field) => Some(field.resolve(label, features, get_ws_value)?),
+        None => None,
+    };
+    *field = value.clone().map(MaybeWorkspace::Defined);
+    Ok(value)
+}
+
+/// The values a workspace root makes available to its members through
+/// `[workspace.package]` and `[workspace.dependencies]`.
+struct InheritableFields {
+    ws_root: PathBuf,
+    package: TomlWorkspacePackage,
+    dependencies: BTreeMap<String, TomlDependency>,
+}
+
+impl InheritableFields {
+    fn new(ws_root: &Path, ws: &TomlWorkspace) -> InheritableFields {
+        InheritableFields {
+            ws_root: ws_root.to_path_buf(),
+            package: ws.package.clone().unwrap_or_default(),
+            dependencies: ws.dependencies.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Returns the field `label` of `[workspace.package]`, read with `get`.
+    fn package_field<T: Clone>(
+        &self,
+        label: &str,
+        get: impl FnOnce(&TomlWorkspacePackage) -> &Option<T>,
+    ) -> CargoResult<T> {
+        get(&self.package).clone().ok_or_else(|| {
+            anyhow!(
+                "`{}` was inherited, but `workspace.package.{}` is not defined in `{}`",
+                label,
+                label,
+                self.ws_root.join("Cargo.toml").display()
+            )
+        })
+    }
+
+    /// Returns the entry for `name` in `[workspace.dependencies]`, with its
+    /// path resolved against the workspace root and made absolute, so that it
+    /// doesn't depend on where the member is.
+    fn dependency(&self, name: &str) -> CargoResult<DetailedTomlDependency> {
+        let dep = match self.dependencies.get(name) {
+            Some(TomlDependency::Simple(version)) => DetailedTomlDependency {
+                version: Some(version.clone()),
+                ..Default::default()
+            },
+            Some(TomlDependency::Detailed(dep)) => dep.clone(),
+            None => bail!(
+                "`dependency.{}` was inherited, but `workspace.dependencies.{}` \
+                 is not defined in `{}`",
+                name,
+                name,
+                self.ws_root.join("Cargo.toml").display()
+            ),
+        };
+        if dep.workspace.is_some() {
+            bail!(
+                "`workspace.dependencies.{}` cannot itself be inherited from the workspace",
+                name
+            );
+        }
+        if dep.optional.is_some() {
+            bail!(
+                "`workspace.dependencies.{}` cannot be optional, \
+                 members can set `optional` when they inherit it",
+                name
+            );
+        }
+        let path = dep.path.map(|path| {
+            paths::normalize_path(&self.ws_root.join(path))
+                .to_string_lossy()
+                .into_owned()
+        });
+        Ok(DetailedTomlDependency { path, ..dep })
+    }
 }
 
-impl TomlProject {
-    pub fn to_package_id(&self, source_id: SourceId) -> CargoResult<PackageId> {
-        PackageId::new(self.name, self.version.clone(), source_id)
+/// Finds the root of the workspace the package in `package_root` belongs to,
+/// following the same rules as `Workspace::find_root`, and returns its
+/// directory and `[workspace]` table.
+///
+/// This only parses the root manifest instead of loading it as a package,
+/// since it is called while the member itself is being loaded.
+fn find_workspace_root(
+    package_root: &Path,
+    root_link: Option<&str>,
+    config: &Config,
+) -> CargoResult<Option<(PathBuf, TomlWorkspace)>> {
+    let read_toml = |manifest_path: &Path| -> CargoResult<TomlManifest> {
+        let contents = paths::read(manifest_path)?;
+        let toml = parse(&contents, manifest_path, config)?;
+        toml.try_into()
+            .chain_err(|| format!("failed to parse manifest at `{}`", manifest_path.display()))
+    };
+
+    if let Some(root_link) = root_link {
+        let root = paths::normalize_path(&package_root.join(root_link));
+        let toml = read_toml(&root.join("Cargo.toml"))?;
+        return Ok(toml.workspace.map(|ws| (root, ws)));
+    }
+
+    let manifest_path = package_root.join("Cargo.toml");
+    for path in paths::ancestors(&manifest_path).skip(2) {
+        if path.ends_with("target/package") {
+            break;
+        }
+        let ances_manifest_path = path.join("Cargo.toml");
+        if !ances_manifest_path.exists() {
+            continue;
+        }
+        let toml = read_toml(&ances_manifest_path)?;
+        if let Some(ws) = toml.workspace {
+            let root_config = WorkspaceRootConfig::new(
+                path,
+                &ws.members,
+                &ws.default_members,
+                &ws.exclude,
+                &ws.metadata,
+                None,
+                None,
+            );
+            if !root_config.is_excluded(&manifest_path) {
+                return Ok(Some((path.to_path_buf(), ws)));
+            }
+        } else if let Some(project) = toml.package.as_ref().or_else(|| toml.project.as_ref()) {
+            if let Some(root_link) = &project.workspace {
+                return find_workspace_root(path, Some(root_link), config);
+            }
+        }
     }
+    Ok(None)
 }
 
 struct Context<'a, 'b> {
@@ -1002,6 +1305,24 @@ impl TomlManifest {
         }
     }
 
+    /// Returns the values the workspace this package belongs to makes
+    /// available for inheritance.
+    fn inheritable_fields(
+        &self,
+        package_root: &Path,
+        config: &Config,
+    ) -> CargoResult<InheritableFields> {
+        if let Some(ws) = &self.workspace {
+            return Ok(InheritableFields::new(package_root, ws));
+        }
+        let project = self.project.as_ref().or_else(|| self.package.as_ref());
+        let root_link = project.and_then(|p| p.workspace.as_deref());
+        match find_workspace_root(package_root, root_link, config)? {
+            Some((ws_root, ws)) => Ok(InheritableFields::new(&ws_root, &ws)),
+            None => bail!("failed to find a workspace root to inherit from"),
+        }
+    }
+
     pub fn to_real_manifest(
         me: &Rc<TomlManifest>,
         source_id: SourceId,
@@ -1017,9 +1338,83 @@ impl TomlManifest {
         let cargo_features = me.cargo_features.as_ref().unwrap_or(&empty);
         let features = Features::new(cargo_features, &mut warnings)?;
 
-        let project = me.project.as_ref().or_else(|| me.package.as_ref());
+        if me
+            .workspace
+            .as_ref()
+            .map_or(false, |ws| ws.has_inheritable())
+        {
+            features.require(Feature::workspace_inheritance())?;
+        }
+
+        // Replace the fields and dependencies inherited from the workspace
+        // with their values. The resolved manifest is the one kept as the
+        // `original` of the `Manifest`, so that a packaged manifest gets the
+        // actual values.
+        let inheritable = LazyCell::new();
+        let get_ws = || inheritable.try_borrow_with(|| me.inheritable_fields(package_root, config));
+        let mut resolved = (**me).clone();
+        let project = resolved.project.as_mut().or(resolved.package.as_mut());
         let project = project.ok_or_else(|| anyhow!("no `package` section found"))?;
 
+        let version = project.version.clone().resolve("version", &features, || {
+            get_ws()?.package_field("version", |p| &p.version)
+        })?;
+        project.version = MaybeWorkspace::Defined(version.clone());
+        let edition = inherit_field(&mut project.edition, "edition", &features, || {
+            get_ws()?.package_field("edition", |p| &p.edition)
+        })?;
+        let rust_version =
+            inherit_field(&mut project.rust_version, "rust-version", &features, || {
+                get_ws()?.package_field("rust-version", |p| &p.rust_version)
+            })?;
+        let authors = inherit_field(&mut project.authors, "authors", &features, || {
+            get_ws()?.package_field("authors", |p| &p.authors)
+        })?;
+        let description =
+            inherit_field(&mut project.description, "description", &features, || {
+                get_ws()?.package_field("description", |p| &p.description)
+            })?;
+        let homepage = inherit_field(&mut project.homepage, "homepage", &features, || {
+            get_ws()?.package_field("homepage", |p| &p.homepage)
+        })?;
+        let documentation = inherit_field(
+            &mut project.documentation,
+            "documentation",
+            &features,
+            || get_ws()?.package_field("documentation", |p| &p.documentation),
+        )?;
+        let keywords = inherit_field(&mut project.keywords, "keywords", &features, || {
+            get_ws()?.package_field("keywords", |p| &p.keywords)
+        })?;
+        let categories = inherit_field(&mut project.categories, "categories", &features, || {
+            get_ws()?.package_field("categories", |p| &p.categories)
+        })?;
+        let license = inherit_field(&mut project.license, "license", &features, || {
+            get_ws()?.package_field("license", |p| &p.license)
+        })?;
+        let repository = inherit_field(&mut project.repository, "repository", &features, || {
+            get_ws()?.package_field("repository", |p| &p.repository)
+        })?;
+        let publish = inherit_field(&mut project.publish, "publish", &features, || {
+            get_ws()?.package_field("publish", |p| &p.publish)
+        })?;
+
+        inherit_dependencies(&mut resolved.dependencies, &features, &get_ws)?;
+        inherit_dependencies(&mut resolved.dev_dependencies, &features, &get_ws)?;
+        inherit_dependencies(&mut resolved.dev_dependencies2, &features, &get_ws)?;
+        inherit_dependencies(&mut resolved.build_dependencies, &features, &get_ws)?;
+        inherit_dependencies(&mut resolved.build_dependencies2, &features, &get_ws)?;
+        for platform in resolved.target.iter_mut().flat_map(|t| t.values_mut()) {
+            inherit_dependencies(&mut platform.dependencies, &features, &get_ws)?;
+            inherit_dependencies(&mut platform.dev_dependencies, &features, &get_ws)?;
+            inherit_dependencies(&mut platform.dev_dependencies2, &features, &get_ws)?;
+            inherit_dependencies(&mut platform.build_dependencies, &features, &get_ws)?;
+            inherit_dependencies(&mut platform.build_dependencies2, &features, &get_ws)?;
+        }
+
+        let me = &Rc::new(resolved);
+        let project = me.project.as_ref().or_else(|| me.package.as_ref()).unwrap();
+
         let package_name = project.name.trim();
         if package_name.is_empty() {
             bail!("package name cannot be an empty string")
@@ -1027,9 +1422,9 @@ impl TomlManifest {
 
         validate_package_name(package_name, "package name", "")?;
 
-        let pkgid = project.to_package_id(source_id)?;
+        let pkgid = PackageId::new(project.name, version, source_id)?;
 
-        let edition = if let Some(ref edition) = project.edition {
+        let edition = if let Some(ref edition) = edition {
             features
                 .require(Feature::edition())
                 .chain_err(|| "editions are unstable")?;
@@ -1040,6 +1435,10 @@ impl TomlManifest {
             Edition::Edition2015
         };
 
+        if let Some(rust_version) = &rust_version {
+            validate_rust_version(rust_version)?;
+        }
+
         if project.metabuild.is_some() {
             features.require(Feature::metabuild())?;
         }
@@ -1203,27 +1602,29 @@ impl TomlManifest {
                     .collect()
             })
             .unwrap_or_else(BTreeMap::new);
-        let summary = Summary::new(
+        let mut summary = Summary::new(
             pkgid,
             deps,
             &summary_features,
             project.links.as_deref(),
             project.namespaced_features.unwrap_or(false),
         )?;
+        summary.set_rust_version(rust_version.as_deref().map(InternedString::new));
 
         let metadata = ManifestMetadata {
-            description: project.description.clone(),
-            homepage: project.homepage.clone(),
-            documentation: project.documentation.clone(),
+            description,
+            homepage,
+            documentation,
             readme: readme_for_project(package_root, project),
-            authors: project.authors.clone().unwrap_or_default(),
-            license: project.license.clone(),
+            authors: authors.unwrap_or_default(),
+            license: license.clone(),
             license_file: project.license_file.clone(),
-            repository: project.repository.clone(),
-            keywords: project.keywords.clone().unwrap_or_default(),
-            categories: project.categories.clone().unwrap_or_default(),
+            repository,
+            keywords: keywords.unwrap_or_default(),
+            categories: categories.unwrap_or_default(),
             badges: me.badges.clone().unwrap_or_default(),
             links: project.links.clone(),
+            rust_version,
         };
 
         let workspace_config = match (me.workspace.as_ref(), project.workspace.as_ref()) {
@@ -1233,6 +1634,8 @@ impl TomlManifest {
                 &config.default_members,
                 &config.exclude,
                 &config.metadata,
+                config.incompatible_rust_versions(&features)?,
+                config.dependency_policy(&features)?,
             )),
             (None, root) => WorkspaceConfig::Member {
                 root: root.cloned(),
@@ -1246,7 +1649,7 @@ impl TomlManifest {
         if let Some(profiles) = &profiles {
             profiles.validate(&features, &mut warnings)?;
         }
-        let publish = match project.publish {
+        let publish = match publish {
             Some(VecStringOrBool::VecString(ref vecstring)) => Some(vecstring.clone()),
             Some(VecStringOrBool::Bool(false)) => Some(vec![]),
             None | Some(VecStringOrBool::Bool(true)) => None,
@@ -1308,7 +1711,7 @@ impl TomlManifest {
             project.metabuild.clone().map(|sov| sov.0),
             resolve_behavior,
         );
-        if project.license_file.is_some() && project.license.is_some() {
+        if project.license_file.is_some() && license.is_some() {
             manifest.warnings_mut().add_warning(
                 "only one of `license` or \
                  `license-file` is necessary"
@@ -1405,6 +1808,13 @@ impl TomlManifest {
         {
             features.require(Feature::resolver())?;
         }
+        if me
+            .workspace
+            .as_ref()
+            .map_or(false, |ws| ws.has_inheritable())
+        {
+            features.require(Feature::workspace_inheritance())?;
+        }
         let resolve_behavior = me
             .workspace
             .as_ref()
@@ -1418,6 +1828,8 @@ impl TomlManifest {
                 &config.default_members,
                 &config.exclude,
                 &config.metadata,
+                config.incompatible_rust_versions(&features)?,
+                config.dependency_policy(&features)?,
             )),
             None => {
                 bail!("virtual manifests must be configured with [workspace]");
@@ -1528,6 +1940,20 @@ impl TomlManifest {
     }
 }
 
+/// Checks that `rust-version` is a plain version such as `1.44` or `1.44.1`,
+/// without any operator or pre-release.
+fn validate_rust_version(rust_version: &str) -> CargoResult<()> {
+    let parts: Vec<_> = rust_version.split('.').collect();
+    let is_number = |part: &&str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    if parts.len() < 2 || parts.len() > 3 || !parts.iter().all(is_number) {
+        bail!(
+            "`rust-version` must be a version like \"1.44\" or \"1.44.1\", found `{}`",
+            rust_version
+        );
+    }
+    Ok(())
+}
+
 /// Returns the name of the README file for a `TomlProject`.
 fn readme_for_project(package_root: &Path, project: &TomlProject) -> Option<String> {
     match &project.readme {
@@ -1592,6 +2018,76 @@ impl TomlDependency {
             TomlDependency::Simple(..) => true,
         }
     }
+
+    /// Replaces a `{ workspace = true }` dependency with its entry in
+    /// `[workspace.dependencies]`, adding the features it enables and
+    /// whether it is optional.
+    fn inherit<'a>(
+        self,
+        name: &str,
+        features: &Features,
+        get_ws: impl FnOnce() -> CargoResult<&'a InheritableFields>,
+    ) -> CargoResult<TomlDependency> {
+        let member = match self {
+            TomlDependency::Detailed(d) if d.workspace.is_some() => d,
+            dep => return Ok(dep),
+        };
+        features
+            .require(Feature::workspace_inheritance())
+            .chain_err(|| format!("failed to inherit dependency `{}` from the workspace", name))?;
+        if member.workspace == Some(false) {
+            bail!("`workspace` cannot be false for dependency `{}`", name);
+        }
+        let other_keys = [
+            ("version", member.version.is_some()),
+            ("registry", member.registry.is_some()),
+            ("registry-index", member.registry_index.is_some()),
+            ("path", member.path.is_some()),
+            ("git", member.git.is_some()),
+            ("branch", member.branch.is_some()),
+            ("tag", member.tag.is_some()),
+            ("rev", member.rev.is_some()),
+            ("default-features", member.default_features.is_some()),
+            ("default_features", member.default_features2.is_some()),
+            ("package", member.package.is_some()),
+            ("public", member.public.is_some()),
+        ];
+        if let Some((key, _)) = other_keys.iter().find(|(_, is_set)| *is_set) {
+            bail!(
+                "dependency `{}` is inherited from the workspace and cannot specify `{}`, \
+                 only `features` and `optional` can be used with `workspace = true`",
+                name,
+                key
+            );
+        }
+
+        let mut dep = get_ws()?.dependency(name)?;
+        if let Some(member_features) = member.features {
+            let dep_features = dep.features.get_or_insert_with(Vec::new);
+            for feature in member_features {
+                if !dep_features.contains(&feature) {
+                    dep_features.push(feature);
+                }
+            }
+        }
+        dep.optional = member.optional;
+        Ok(TomlDependency::Detailed(dep))
+    }
+}
+
+/// Resolves the inherited dependencies of a dependency table.
+fn inherit_dependencies<'a>(
+    deps: &mut Option<BTreeMap<String, TomlDependency>>,
+    features: &Features,
+    get_ws: &impl Fn() -> CargoResult<&'a InheritableFields>,
+) -> CargoResult<()> {
+    if let Some(deps) = deps {
+        for (name, dep) in deps.iter_mut() {
+            let resolved = dep.clone().inherit(name, features, get_ws)?;
+            *dep = resolved;
+        }
+    }
+    Ok(())
 }
 
 impl DetailedTomlDependency {
@@ -1601,6 +2097,15 @@ impl DetailedTomlDependency {
         cx: &mut Context<'_, '_>,
         kind: Option<DepKind>,
     ) -> CargoResult<Dependency> {
+        if self.workspace.is_some() {
+            // Inherited dependencies are resolved before getting here, so
+            // this is a table where they aren't supported.
+            bail!(
+                "dependency ({}) specifies `workspace`, which can only be used \
+                 in the dependency tables of a package",
+                name_in_toml
+            );
+        }
         if self.version.is_none() && self.path.is_none() && self.git.is_none() {
             let msg = format!(
                 "dependency ({}) specified without \
@@ -1829,7 +2334,7 @@ impl ser::Serialize for PathValue {
 }
 
 /// Corresponds to a `target` entry, but `TomlTarget` is already used.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 struct TomlPlatform {
     dependencies: Option<BTreeMap<String, TomlDependency>>,
     #[serde(rename = "build-dependencies")]
@@ -0,0 +1,444 @@
+//! A dependency entry as written in a manifest.
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::bail;
+use toml_edit::{Item, TableLike, Value};
+
+use crate::util::errors::CargoResult;
+use crate::util::paths;
+
+/// Where a dependency comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DependencySource {
+    /// A registry, either crates.io or the one named by
+    /// `Dependency::registry`.
+    Registry,
+    /// A local directory, as an absolute path.
+    Path(PathBuf),
+    /// A git repository.
+    Git {
+        url: String,
+        branch: Option<String>,
+        tag: Option<String>,
+        rev: Option<String>,
+    },
+    /// The entry of the same name in `[workspace.dependencies]`, written as
+    /// `workspace = true`.
+    Workspace,
+}
+
+/// A dependency entry as it appears in one of the dependency tables of a
+/// manifest, as opposed to `core::Dependency` which is the resolved form
+/// used by the rest of Cargo.
+///
+/// Fields which are `None` are not written, and are left untouched when
+/// updating an existing entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    /// The name of the package.
+    pub name: String,
+    /// The name the dependency is known as in the manifest, if different from
+    /// the package name.
+    pub rename: Option<String>,
+    /// The version requirement.
+    pub version: Option<String>,
+    pub source: DependencySource,
+    /// The name of the registry, `None` for crates.io.
+    pub registry: Option<String>,
+    pub features: Option<Vec<String>>,
+    pub default_features: Option<bool>,
+    pub optional: Option<bool>,
+}
+
+/// Keys of a dependency entry which describe its source, and are replaced
+/// as a whole when the source changes.
+const SOURCE_KEYS: &[&str] = &["version", "path", "git", "branch", "tag", "rev", "registry"];
+
+impl Dependency {
+    /// Creates a dependency on `name` from crates.io with no requirement.
+    pub fn new(name: &str) -> Dependency {
+        Dependency {
+            name: name.to_string(),
+            rename: None,
+            version: None,
+            source: DependencySource::Registry,
+            registry: None,
+            features: None,
+            default_features: None,
+            optional: None,
+        }
+    }
+
+    /// The key of this dependency in its dependency table.
+    pub fn toml_key(&self) -> &str {
+        self.rename.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Parses the entry `key = item` of a dependency table.
+    ///
+    /// Relative paths are resolved against `crate_root`, the directory of the
+    /// manifest.
+    pub fn from_toml(crate_root: &Path, key: &str, item: &Item) -> CargoResult<Dependency> {
+        if let Some(version) = item.as_str() {
+            let mut dep = Dependency::new(key);
+            dep.version = Some(version.to_string());
+            return Ok(dep);
+        }
+        let table = match item.as_table_like() {
+            Some(table) => table,
+            None => bail!(
+                "dependency `{}` is specified as a {}, expected a string or a table",
+                key,
+                item.type_name()
+            ),
+        };
+
+        let string = |name: &str| -> CargoResult<Option<String>> {
+            match table.get(name) {
+                None => Ok(None),
+                Some(item) => match item.as_str() {
+                    Some(s) => Ok(Some(s.to_string())),
+                    None => bail!(
+                        "`{}` of dependency `{}` must be a string, found {}",
+                        name,
+                        key,
+                        item.type_name()
+                    ),
+                },
+            }
+        };
+        let boolean = |name: &str| -> CargoResult<Option<bool>> {
+            match table.get(name) {
+                None => Ok(None),
+                Some(item) => match item.as_bool() {
+                    Some(b) => Ok(Some(b)),
+                    None => bail!(
+                        "`{}` of dependency `{}` must be a boolean, found {}",
+                        name,
+                        key,
+                        item.type_name()
+                    ),
+                },
+            }
+        };
+
+        let (name, rename) = match string("package")? {
+            Some(package) => (package, Some(key.to_string())),
+            None => (key.to_string(), None),
+        };
+        let source = if boolean("workspace")? == Some(true) {
+            DependencySource::Workspace
+        } else if let Some(path) = string("path")? {
+            DependencySource::Path(paths::normalize_path(&crate_root.join(path)))
+        } else if let Some(url) = string("git")? {
+            DependencySource::Git {
+                url,
+                branch: string("branch")?,
+                tag: string("tag")?,
+                rev: string("rev")?,
+            }
+        } else {
+            DependencySource::Registry
+        };
+        let features = match table.get("features") {
+            None => None,
+            Some(item) => {
+                let array = match item.as_array() {
+                    Some(array) => array,
+                    None => bail!(
+                        "`features` of dependency `{}` must be an array, found {}",
+                        key,
+                        item.type_name()
+                    ),
+                };
+                Some(
+                    array
+                        .iter()
+                        .filter_map(|v| v.as_str().map(String::from))
+                        .collect(),
+                )
+            }
+        };
+        let default_features = match boolean("default-features")? {
+            Some(b) => Some(b),
+            None => boolean("default_features")?,
+        };
+
+        Ok(Dependency {
+            name,
+            rename,
+            version: string("version")?,
+            source,
+            registry: string("registry")?,
+            features,
+            default_features,
+            optional: boolean("optional")?,
+        })
+    }
+
+    /// Creates a new entry for this dependency, using the short
+    /// `name = "version"` form when possible.
+    pub fn to_toml(&self, crate_root: &Path) -> Item {
+        if let (DependencySource::Registry, Some(version), true) =
+            (&self.source, &self.version, self.is_plain())
+        {
+            return toml_edit::value(version.as_str());
+        }
+        let mut table = toml_edit::InlineTable::default();
+        self.update_table(crate_root, &mut table);
+        toml_edit::value(Value::InlineTable(table))
+    }
+
+    /// Updates the existing entry `item` to match this dependency.
+    ///
+    /// The source keys are replaced, features are merged with the existing
+    /// ones, and every other key (and the formatting of the entry) is kept.
+    pub fn update_toml(&self, crate_root: &Path, item: &mut Item) {
+        if let Some(value) = item.as_value().filter(|v| v.is_str()) {
+            // A `name = "version"` entry has nothing to keep but its
+            // formatting.
+            let decor = value.decor().clone();
+            *item = self.to_toml(crate_root);
+            if let Some(value) = item.as_value_mut() {
+                *value.decor_mut() = decor;
+            }
+            return;
+        }
+        if let Some(table) = item.as_inline_table_mut() {
+            self.update_table(crate_root, table);
+            // Keys added to the end go after the space before the closing
+            // `}`, which belongs after the last key instead.
+            let len = table.len();
+            for (_, value) in table.iter_mut().take(len.saturating_sub(1)) {
+                let decor = value.decor_mut();
+                if decor.suffix().map_or(false, |s| s.trim().is_empty()) {
+                    decor.set_suffix("");
+                }
+            }
+        } else if let Some(table) = item.as_table_like_mut() {
+            self.update_table(crate_root, table);
+        }
+    }
+
+    /// Whether this dependency has nothing but a version requirement.
+    fn is_plain(&self) -> bool {
+        self.rename.is_none()
+            && self.registry.is_none()
+            && self.features.as_ref().map_or(true, |f| f.is_empty())
+            && self.default_features.unwrap_or(true)
+            && !self.optional.unwrap_or(false)
+    }
+
+    fn update_table(&self, crate_root: &Path, table: &mut dyn TableLike) {
+        match &self.rename {
+            Some(_) => set(table, "package", Value::from(self.name.as_str())),
+            None => {
+                table.remove("package");
+            }
+        }
+
+        // Only rewrite the source keys if the source actually changed, so
+        // that an unrelated update doesn't reorder them.
+        let mut source = Vec::new();
+        if let Some(version) = &self.version {
+            source.push(("version", version.clone()));
+        }
+        match &self.source {
+            DependencySource::Registry | DependencySource::Workspace => {}
+            DependencySource::Path(path) => {
+                source.push(("path", relative_path(crate_root, path)));
+            }
+            DependencySource::Git {
+                url,
+                branch,
+                tag,
+                rev,
+            } => {
+                source.push(("git", url.clone()));
+                let refs = [("branch", branch), ("tag", tag), ("rev", rev)];
+                for (key, value) in refs.iter() {
+                    if let Some(value) = value {
+                        source.push((key, value.clone()));
+                    }
+                }
+            }
+        }
+        if let Some(registry) = &self.registry {
+            source.push(("registry", registry.clone()));
+        }
+        for key in SOURCE_KEYS {
+            match source.iter().find(|(k, _)| k == key) {
+                Some((_, value)) => {
+                    if table.get(key).and_then(|i| i.as_str()) != Some(value.as_str()) {
+                        set(table, key, Value::from(value.as_str()));
+                    }
+                }
+                None => {
+                    table.remove(key);
+                }
+            }
+        }
+        if self.source == DependencySource::Workspace {
+            if table.get("workspace").and_then(|i| i.as_bool()) != Some(true) {
+                set(table, "workspace", Value::from(true));
+            }
+        } else {
+            table.remove("workspace");
+        }
+
+        if let Some(default_features) = self.default_features {
+            table.remove("default_features");
+            set(table, "default-features", Value::from(default_features));
+        }
+        if let Some(features) = &self.features {
+            let existing = table.get_mut("features").and_then(|i| i.as_array_mut());
+            match existing {
+                Some(array) => {
+                    for feature in features {
+                        if !array.iter().any(|v| v.as_str() == Some(feature.as_str())) {
+                            array.push(feature.as_str());
+                        }
+                    }
+                }
+                None if !features.is_empty() => {
+                    let array: toml_edit::Array = features.iter().map(String::as_str).collect();
+                    set(table, "features", Value::Array(array));
+                }
+                None => {}
+            }
+        }
+        if let Some(optional) = self.optional {
+            if optional {
+                set(table, "optional", Value::from(true));
+            } else {
+                table.remove("optional");
+            }
+        }
+    }
+}
+
+/// Sets `key` to `value`, keeping the formatting around an existing value.
+fn set(table: &mut dyn TableLike, key: &str, mut value: Value) {
+    match table.get_mut(key).and_then(|i| i.as_value_mut()) {
+        Some(existing) => {
+            *value.decor_mut() = existing.decor().clone();
+            *existing = value;
+        }
+        None => {
+            table.insert(key, toml_edit::value(value));
+        }
+    }
+}
+
+/// Returns `path` relative to `base`, with `/` as the separator so that the
+/// manifest is the same on every platform.
+///
+/// Both paths must be absolute. If they don't share a root (such as
+/// different drives on Windows), `path` is returned unchanged.
+fn relative_path(base: &Path, path: &Path) -> String {
+    let (base, path) = (paths::normalize_path(base), paths::normalize_path(path));
+    let base: Vec<_> = base.components().collect();
+    let path: Vec<_> = path.components().collect();
+    if base.first() != path.first() {
+        return path
+            .iter()
+            .collect::<PathBuf>()
+            .to_string_lossy()
+            .into_owned();
+    }
+    let common = base.iter().zip(&path).take_while(|(a, b)| a == b).count();
+    let mut parts = Vec::new();
+    for _ in &base[common..] {
+        parts.push("..".to_string());
+    }
+    for component in &path[common..] {
+        if let Component::Normal(part) = component {
+            parts.push(part.to_string_lossy().into_owned());
+        }
+    }
+    if parts.is_empty() {
+        ".".to_string()
+    } else {
+        parts.join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{relative_path, Dependency, DependencySource};
+    use std::path::{Path, PathBuf};
+
+    fn root() -> PathBuf {
+        if cfg!(windows) {
+            PathBuf::from("C:\\work\\foo")
+        } else {
+            PathBuf::from("/work/foo")
+        }
+    }
+
+    fn round_trip(toml: &str) -> String {
+        let mut doc: toml_edit::Document = toml.parse().unwrap();
+        let (key, item) = doc.as_table().iter().next().unwrap();
+        let dep = Dependency::from_toml(&root(), key, item).unwrap();
+        let key = key.to_string();
+        dep.update_toml(&root(), &mut doc[&key]);
+        doc.to_string()
+    }
+
+    #[test]
+    fn relative_paths() {
+        let root = root();
+        assert_eq!(relative_path(&root, &root.join("bar")), "bar");
+        assert_eq!(relative_path(&root, &root.join("../bar/baz")), "../bar/baz");
+        assert_eq!(relative_path(&root, &root), ".");
+    }
+
+    #[test]
+    fn parse_and_rewrite_is_lossless() {
+        for toml in &[
+            "foo = \"1.0\" # pinned\n",
+            "foo = { version = \"1.0\", features = [\"a\"] } # comment\n",
+            "bar = { package = \"foo\", path = \"../bar\", optional = true }\n",
+            "foo = { git = \"https://example.com/foo\", branch = \"main\" }\n",
+            "foo = { workspace = true, features = [\"a\"] }\n",
+        ] {
+            assert_eq!(round_trip(toml), *toml);
+        }
+    }
+
+    #[test]
+    fn workspace_dependency() {
+        let mut doc: toml_edit::Document = "foo = { workspace = true }\n".parse().unwrap();
+        let dep = Dependency::from_toml(&root(), "foo", &doc["foo"]).unwrap();
+        assert_eq!(dep.source, DependencySource::Workspace);
+        assert_eq!(dep.version, None);
+
+        let mut dep = Dependency::new("foo");
+        dep.source = DependencySource::Workspace;
+        dep.optional = Some(true);
+        dep.update_toml(&root(), &mut doc["foo"]);
+        assert_eq!(
+            doc.to_string(),
+            "foo = { workspace = true, optional = true }\n"
+        );
+        assert_eq!(
+            dep.to_toml(&root()).to_string(),
+            "{ workspace = true, optional = true }"
+        );
+    }
+
+    #[test]
+    fn simple_dependency_is_a_string() {
+        let mut dep = Dependency::new("foo");
+        dep.version = Some("1.0".to_string());
+        assert_eq!(dep.to_toml(Path::new("/")).to_string(), "\"1.0\"");
+
+        dep.features = Some(vec!["a".to_string()]);
+        dep.source = DependencySource::Path(root().join("../bar"));
+        assert_eq!(
+            dep.to_toml(&root()).to_string(),
+            "{ version = \"1.0\", path = \"../bar\", features = [\"a\"] }"
+        );
+    }
+}
@@ -0,0 +1,400 @@
+//! A manifest as an editable TOML document.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, format_err};
+use toml_edit::{Document, Item, TableLike};
+
+use super::dependency::Dependency;
+use crate::core::dependency::DepKind;
+use crate::util::errors::{CargoResult, CargoResultExt};
+use crate::util::paths;
+
+/// One of the dependency tables of a manifest, such as
+/// `[dev-dependencies]` or `[target.'cfg(unix)'.dependencies]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DepTable {
+    kind: DepKind,
+    target: Option<String>,
+}
+
+impl DepTable {
+    /// The `[dependencies]` table.
+    pub fn new() -> DepTable {
+        DepTable {
+            kind: DepKind::Normal,
+            target: None,
+        }
+    }
+
+    pub fn set_kind(mut self, kind: DepKind) -> DepTable {
+        self.kind = kind;
+        self
+    }
+
+    pub fn set_target(mut self, target: impl Into<String>) -> DepTable {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn kind(&self) -> DepKind {
+        self.kind
+    }
+
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    /// The name of the table for this kind of dependency.
+    pub fn kind_table(&self) -> &'static str {
+        match self.kind {
+            DepKind::Normal => "dependencies",
+            DepKind::Development => "dev-dependencies",
+            DepKind::Build => "build-dependencies",
+        }
+    }
+
+    /// The older spelling of `kind_table`, which Cargo still accepts.
+    fn legacy_kind_table(&self) -> &'static str {
+        match self.kind {
+            DepKind::Normal => "dependencies",
+            DepKind::Development => "dev_dependencies",
+            DepKind::Build => "build_dependencies",
+        }
+    }
+
+    /// The path of keys to the table, starting from the root of the
+    /// manifest.
+    fn path<'a>(&'a self, kind_table: &'a str) -> Vec<&'a str> {
+        match &self.target {
+            Some(target) => vec!["target", target, kind_table],
+            None => vec![kind_table],
+        }
+    }
+}
+
+impl Default for DepTable {
+    fn default() -> DepTable {
+        DepTable::new()
+    }
+}
+
+impl fmt::Display for DepTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.target {
+            Some(target) => write!(f, "{} for target `{}`", self.kind_table(), target),
+            None => write!(f, "{}", self.kind_table()),
+        }
+    }
+}
+
+/// A manifest parsed into a TOML document which keeps comments, ordering
+/// and formatting, so that it can be edited and written back without
+/// otherwise changing it.
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    pub data: Document,
+}
+
+impl Manifest {
+    /// Returns the `package.name` of the manifest.
+    pub fn package_name(&self) -> CargoResult<&str> {
+        ["package", "project"]
+            .iter()
+            .filter_map(|key| self.data.get(key))
+            .find_map(|package| package.get("name").and_then(|name| name.as_str()))
+            .ok_or_else(|| format_err!("manifest has no `package.name`"))
+    }
+
+    /// Returns the table `table`, if the manifest has it.
+    pub fn get_table(&self, table: &DepTable) -> Option<&dyn TableLike> {
+        self.find_table(table, table.kind_table())
+            .or_else(|| self.find_table(table, table.legacy_kind_table()))
+    }
+
+    fn find_table(&self, table: &DepTable, kind_table: &str) -> Option<&dyn TableLike> {
+        let mut item = self.data.as_item();
+        for key in table.path(kind_table) {
+            item = item.get(key)?;
+        }
+        item.as_table_like()
+    }
+
+    /// Returns the table `table`, creating it if the manifest doesn't have
+    /// it yet.
+    pub fn get_or_insert_table(&mut self, table: &DepTable) -> CargoResult<&mut dyn TableLike> {
+        let kind_table = if self.find_table(table, table.kind_table()).is_none()
+            && self.find_table(table, table.legacy_kind_table()).is_some()
+        {
+            table.legacy_kind_table()
+        } else {
+            table.kind_table()
+        };
+        let path = table.path(kind_table);
+        let mut item = self.data.as_item_mut();
+        for (i, key) in path.iter().enumerate() {
+            let parent = match item.as_table_like_mut() {
+                Some(parent) => parent,
+                None => bail!("`{}` is not a table", path[..i].join(".")),
+            };
+            item = parent.entry(key).or_insert_with(|| {
+                let mut new = toml_edit::Table::new();
+                // Only the dependency table itself gets a header, the
+                // tables it is nested in are implied by it.
+                new.set_implicit(i + 1 < path.len());
+                Item::Table(new)
+            });
+        }
+        item.as_table_like_mut()
+            .ok_or_else(|| format_err!("`{}` is not a table", path.join(".")))
+    }
+
+    /// Returns every dependency table of the manifest, including the ones for
+    /// specific targets.
+    pub fn get_tables(&self) -> Vec<(DepTable, &dyn TableLike)> {
+        let kinds = [DepKind::Normal, DepKind::Development, DepKind::Build];
+        let mut tables = Vec::new();
+        for kind in kinds.iter() {
+            let table = DepTable::new().set_kind(*kind);
+            if let Some(t) = self.get_table(&table) {
+                tables.push((table, t));
+            }
+        }
+        let targets = self.data.get("target").and_then(|t| t.as_table_like());
+        for (target, _) in targets.into_iter().flat_map(|t| t.iter()) {
+            for kind in kinds.iter() {
+                let table = DepTable::new().set_kind(*kind).set_target(target);
+                if let Some(t) = self.get_table(&table) {
+                    tables.push((table, t));
+                }
+            }
+        }
+        tables
+    }
+}
+
+impl FromStr for Manifest {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> CargoResult<Manifest> {
+        Ok(Manifest {
+            data: input.parse()?,
+        })
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.data.fmt(f)
+    }
+}
+
+/// A `Manifest` read from a `Cargo.toml` on disk.
+#[derive(Debug, Clone)]
+pub struct LocalManifest {
+    pub path: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Deref for LocalManifest {
+    type Target = Manifest;
+
+    fn deref(&self) -> &Manifest {
+        &self.manifest
+    }
+}
+
+impl DerefMut for LocalManifest {
+    fn deref_mut(&mut self) -> &mut Manifest {
+        &mut self.manifest
+    }
+}
+
+impl LocalManifest {
+    /// Reads the manifest at `path`.
+    pub fn try_new(path: &Path) -> CargoResult<LocalManifest> {
+        let contents = paths::read(path)?;
+        let manifest = contents
+            .parse()
+            .chain_err(|| format!("failed to parse manifest at `{}`", path.display()))?;
+        Ok(LocalManifest {
+            path: path.to_path_buf(),
+            manifest,
+        })
+    }
+
+    /// Writes the manifest back to disk.
+    pub fn write(&self) -> CargoResult<()> {
+        paths::write(&self.path, self.manifest.to_string().as_bytes())
+    }
+
+    /// The directory containing the manifest, which relative paths in it are
+    /// relative to.
+    pub fn crate_root(&self) -> &Path {
+        self.path.parent().unwrap()
+    }
+
+    /// Returns the dependency with the key `key` in `table`, if any.
+    pub fn get_dependency(&self, table: &DepTable, key: &str) -> Option<CargoResult<Dependency>> {
+        let item = self.get_table(table)?.get(key)?;
+        Some(Dependency::from_toml(self.crate_root(), key, item))
+    }
+
+    /// Adds `dep` to `table`, or updates the existing entry for it.
+    ///
+    /// If the entries of the table are sorted, they are kept sorted.
+    pub fn insert_into_table(&mut self, table: &DepTable, dep: &Dependency) -> CargoResult<()> {
+        let crate_root = self.crate_root().to_path_buf();
+        let t = self.manifest.get_or_insert_table(table)?;
+        match t.get_mut(dep.toml_key()) {
+            Some(item) => dep.update_toml(&crate_root, item),
+            None => {
+                let keys: Vec<_> = t.iter().map(|(key, _)| key.to_string()).collect();
+                let was_sorted = keys.windows(2).all(|w| w[0] <= w[1]);
+                t.insert(dep.toml_key(), dep.to_toml(&crate_root));
+                if was_sorted {
+                    t.sort_values();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the dependency with the key `key` from `table`.
+    pub fn remove_from_table(&mut self, table: &DepTable, key: &str) -> CargoResult<()> {
+        let removed = match self.manifest.get_table(table) {
+            Some(_) => self
+                .manifest
+                .get_or_insert_table(table)?
+                .remove(key)
+                .is_some(),
+            None => false,
+        };
+        if !removed {
+            bail!("the dependency `{}` could not be found in `{}`", key, table);
+        }
+        Ok(())
+    }
+
+    /// Removes the entries of `[features]` which enable the dependency `key`,
+    /// or one of its features, unless it is still a dependency in another
+    /// table.
+    pub fn remove_from_features(&mut self, key: &str) {
+        let still_used = self
+            .get_tables()
+            .iter()
+            .any(|(table, t)| table.kind() != DepKind::Development && t.contains_key(key));
+        if still_used {
+            return;
+        }
+        let features = self
+            .manifest
+            .data
+            .get_mut("features")
+            .and_then(|f| f.as_table_like_mut());
+        let dep_feature = format!("{}/", key);
+        for (_, values) in features.into_iter().flat_map(|f| f.iter_mut()) {
+            let array = match values.as_array_mut() {
+                Some(array) => array,
+                None => continue,
+            };
+            let mut i = 0;
+            while i < array.len() {
+                let enables_dep = array
+                    .get(i)
+                    .and_then(|v| v.as_str())
+                    .map_or(false, |v| v == key || v.starts_with(&dep_feature));
+                if !enables_dep {
+                    i += 1;
+                    continue;
+                }
+                let removed = array.remove(i);
+                // The first value has no space before it.
+                if i == 0 {
+                    if let (Some(first), Some(prefix)) =
+                        (array.get_mut(0), removed.decor().prefix())
+                    {
+                        first.decor_mut().set_prefix(prefix);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DepTable, LocalManifest, Manifest};
+    use crate::core::dependency::DepKind;
+    use crate::util::toml_mut::Dependency;
+    use std::path::PathBuf;
+
+    fn local(toml: &str) -> LocalManifest {
+        LocalManifest {
+            path: PathBuf::from("/work/foo/Cargo.toml"),
+            manifest: toml.parse::<Manifest>().unwrap(),
+        }
+    }
+
+    fn dep(name: &str, version: &str) -> Dependency {
+        let mut dep = Dependency::new(name);
+        dep.version = Some(version.to_string());
+        dep
+    }
+
+    #[test]
+    fn insert_keeps_formatting() {
+        let mut manifest = local(
+            r#"# The manifest.
+[package]
+name = "foo"
+
+[dependencies]
+# Comment about a.
+a = "1.0"  # trailing
+c = { version = "0.1", features = ["x"] }
+"#,
+        );
+        manifest
+            .insert_into_table(&DepTable::new(), &dep("b", "2.0"))
+            .unwrap();
+        let mut c = dep("c", "0.2");
+        c.features = Some(vec!["y".to_string()]);
+        manifest.insert_into_table(&DepTable::new(), &c).unwrap();
+        assert_eq!(
+            manifest.to_string(),
+            r#"# The manifest.
+[package]
+name = "foo"
+
+[dependencies]
+# Comment about a.
+a = "1.0"  # trailing
+b = "2.0"
+c = { version = "0.2", features = ["x", "y"] }
+"#
+        );
+    }
+
+    #[test]
+    fn insert_into_new_target_table() {
+        let mut manifest = local("[package]\nname = \"foo\"\n");
+        let table = DepTable::new()
+            .set_kind(DepKind::Development)
+            .set_target("cfg(unix)");
+        manifest
+            .insert_into_table(&table, &dep("a", "1.0"))
+            .unwrap();
+        assert_eq!(
+            manifest.to_string(),
+            "[package]\nname = \"foo\"\n\n[target.\"cfg(unix)\".dev-dependencies]\na = \"1.0\"\n"
+        );
+        assert_eq!(manifest.get_tables()[0].0, table);
+
+        manifest.remove_from_table(&table, "a").unwrap();
+        assert!(manifest.remove_from_table(&table, "a").is_err());
+    }
+}
@@ -0,0 +1,13 @@
+//! Format-preserving editing of manifests.
+//!
+//! `util::toml` deserializes a manifest into `TomlManifest`, which drops
+//! comments, ordering and formatting. Commands which change a user's
+//! `Cargo.toml`, such as `cargo add`, instead go through the types here,
+//! which edit the TOML document in place so that only the parts that
+//! actually change are touched.
+
+pub use self::dependency::{Dependency, DependencySource};
+pub use self::manifest::{DepTable, LocalManifest, Manifest};
+
+mod dependency;
+mod manifest;
@@ -808,6 +808,7 @@ fn alt_reg_metadata() {
                         "features": {},
                         "manifest_path": "[..]/foo/Cargo.toml",
                         "metadata": null,
+                        "rust_version": null,
                         "publish": null,
                         "authors": [],
                         "categories": [],
@@ -862,6 +863,7 @@ fn alt_reg_metadata() {
                         "features": {},
                         "manifest_path": "[..]/altdep-0.0.1/Cargo.toml",
                         "metadata": null,
+                        "rust_version": null,
                         "publish": null,
                         "authors": [],
                         "categories": [],
@@ -884,6 +886,7 @@ fn alt_reg_metadata() {
                         "features": {},
                         "manifest_path": "[..]/altdep2-0.0.1/Cargo.toml",
                         "metadata": null,
+                        "rust_version": null,
                         "publish": null,
                         "authors": [],
                         "categories": [],
@@ -906,6 +909,7 @@ fn alt_reg_metadata() {
                         "features": {},
                         "manifest_path": "[..]/bar-0.0.1/Cargo.toml",
                         "metadata": null,
+                        "rust_version": null,
                         "publish": null,
                         "authors": [],
                         "categories": [],
@@ -953,6 +957,7 @@ fn alt_reg_metadata() {
                         "features": {},
                         "manifest_path": "[..]/foo/Cargo.toml",
                         "metadata": null,
+                        "rust_version": null,
                         "publish": null,
                         "authors": [],
                         "categories": [],
@@ -988,6 +993,7 @@ fn alt_reg_metadata() {
                         "features": {},
                         "manifest_path": "[..]/iodep-0.0.1/Cargo.toml",
                         "metadata": null,
+                        "rust_version": null,
                         "publish": null,
                         "authors": [],
                         "categories": [],
@@ -1081,6 +1087,7 @@ fn unknown_registry() {
                   "features": {},
                   "manifest_path": "[..]",
                   "metadata": null,
+                  "rust_version": null,
                   "publish": null,
                   "authors": [],
                   "categories": [],
@@ -1103,6 +1110,7 @@ fn unknown_registry() {
                   "features": {},
                   "manifest_path": "[..]",
                   "metadata": null,
+                  "rust_version": null,
                   "publish": null,
                   "authors": [],
                   "categories": [],
@@ -1138,6 +1146,7 @@ fn unknown_registry() {
                   "features": {},
                   "manifest_path": "[..]/foo/Cargo.toml",
                   "metadata": null,
+                  "rust_version": null,
                   "publish": null,
                   "authors": [],
                   "categories": [],
@@ -0,0 +1,261 @@
+//! Tests for the `cargo audit` command.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{git, paths, project, Project};
+
+const BAR_ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-2020-0001"
+package = "bar"
+date = "2020-01-01"
+url = "https://example.com/RUSTSEC-2020-0001"
+
+[versions]
+patched = [">= 1.2.0"]
+unaffected = ["< 0.5.0"]
+```
+
+# Memory corruption in bar
+
+Something something.
+"#;
+
+fn publish() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("baz", "0.1.0").publish();
+    Package::new("qux", "0.2.0").publish();
+}
+
+fn workspace(audit_config: &str) -> Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "1.0"
+            baz = "0.1"
+            qux = "0.2"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(".cargo/config", &format!("[audit]\n{}", audit_config))
+        .file("advisory-db/crates/bar/RUSTSEC-2020-0001.md", BAR_ADVISORY)
+        .file(
+            "advisory-db/crates/bar/RUSTSEC-2020-0004.md",
+            r#"```toml
+[advisory]
+id = "RUSTSEC-2020-0004"
+package = "bar"
+withdrawn = "2020-03-01"
+```
+
+# Not actually an issue
+"#,
+        )
+        .file(
+            "advisory-db/crates/baz/RUSTSEC-2020-0002.toml",
+            r#"
+            [advisory]
+            id = "RUSTSEC-2020-0002"
+            package = "baz"
+            title = "baz is unmaintained"
+            informational = "unmaintained"
+            "#,
+        )
+        .file(
+            "advisory-db/crates/qux/RUSTSEC-2020-0003.md",
+            r#"```toml
+[advisory]
+id = "RUSTSEC-2020-0003"
+package = "qux"
+
+[versions]
+patched = ["^0.1.3", ">= 0.2.0"]
+```
+
+# Fixed already
+"#,
+        )
+        .build()
+}
+
+#[cargo_test]
+fn reports_vulnerabilities() {
+    publish();
+    let p = workspace("database = \"advisory-db\"");
+    p.cargo("generate-lockfile").run();
+
+    p.cargo("audit")
+        .with_status(101)
+        .with_stdout(
+            "\
+RUSTSEC-2020-0001: Memory corruption in bar
+  kind:     vulnerability
+  package:  bar v1.0.0
+  date:     2020-01-01
+  url:      https://example.com/RUSTSEC-2020-0001
+  solution: upgrade to >=1.2.0
+
+RUSTSEC-2020-0002: baz is unmaintained
+  kind:     unmaintained
+  package:  baz v0.1.0
+  solution: no patched version available
+
+",
+        )
+        .with_stderr("[ERROR] 1 vulnerability found")
+        .run();
+
+    p.cargo("audit --ignore RUSTSEC-2020-0001")
+        .with_stdout_contains("RUSTSEC-2020-0002: baz is unmaintained")
+        .with_stdout_does_not_contain("[..]RUSTSEC-2020-0001[..]")
+        .with_stderr("")
+        .run();
+
+    Package::new("bar", "1.2.0").publish();
+    p.cargo("update -p bar").run();
+    p.cargo("audit -q --ignore RUSTSEC-2020-0002")
+        .with_stdout("")
+        .with_stderr("")
+        .run();
+    p.cargo("audit --ignore RUSTSEC-2020-0002")
+        .with_stderr("[NOTE] no vulnerable packages found")
+        .run();
+}
+
+#[cargo_test]
+fn json_output() {
+    publish();
+    let p = workspace("database = \"advisory-db\"\nignore = [\"RUSTSEC-2020-0002\"]");
+    p.cargo("generate-lockfile").run();
+
+    p.cargo("audit --format json")
+        .with_status(101)
+        .with_json(
+            r#"
+            {
+              "version": 1,
+              "vulnerabilities": [
+                {
+                  "id": "RUSTSEC-2020-0001",
+                  "package": "bar 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                  "title": "Memory corruption in bar",
+                  "date": "2020-01-01",
+                  "url": "https://example.com/RUSTSEC-2020-0001",
+                  "informational": null,
+                  "patched": [">=1.2.0"]
+                }
+              ],
+              "warnings": []
+            }
+            "#,
+        )
+        .run();
+}
+
+#[cargo_test]
+fn git_database_works_offline() {
+    publish();
+    let db = git::repo(&paths::root().join("advisory-db"))
+        .file("crates/bar/RUSTSEC-2020-0001.md", BAR_ADVISORY)
+        .build();
+    let url = db.url();
+    let p = workspace(&format!("database-url = \"{}\"", url));
+    p.cargo("generate-lockfile").run();
+
+    p.cargo("audit")
+        .with_status(101)
+        .with_stdout_contains("RUSTSEC-2020-0001: Memory corruption in bar")
+        .with_stderr(&format!(
+            "\
+[UPDATING] git repository `{}`
+[ERROR] 1 vulnerability found
+",
+            url
+        ))
+        .run();
+
+    p.cargo("audit --offline")
+        .with_status(101)
+        .with_stdout_contains("RUSTSEC-2020-0001: Memory corruption in bar")
+        .with_stderr("[ERROR] 1 vulnerability found")
+        .run();
+}
+
+#[cargo_test]
+fn build_policy_uses_fetched_git_database() {
+    publish();
+    let db = git::repo(&paths::root().join("advisory-db"))
+        .file("crates/bar/RUSTSEC-2020-0001.md", BAR_ADVISORY)
+        .build();
+    let url = db.url();
+    let p = workspace(&format!("database-url = \"{}\"\nbuild = \"deny\"", url));
+
+    // Compiling doesn't fetch the database.
+    p.cargo("build")
+        .with_stderr_contains(
+            "\
+[WARNING] the advisory database has not been fetched yet, so dependencies \
+were not checked against it
+Run `cargo audit` or `cargo update` to fetch it.
+",
+        )
+        .with_stderr_does_not_contain("[UPDATING] git repository[..]")
+        .run();
+
+    // `cargo update` does, and then compiling uses that copy.
+    p.cargo("update")
+        .with_stderr_contains(&format!("[UPDATING] git repository `{}`", url))
+        .run();
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains("[WARNING] package `bar v1.0.0` is affected by RUSTSEC-2020-0001[..]")
+        .with_stderr_does_not_contain("[UPDATING] git repository[..]")
+        .run();
+}
+
+#[cargo_test]
+fn build_policy() {
+    publish();
+    let p = workspace("database = \"advisory-db\"\nbuild = \"deny\"");
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] [..]
+[DOWNLOADED] [..]
+[DOWNLOADED] [..]
+[WARNING] package `bar v1.0.0` is affected by RUSTSEC-2020-0001: \
+Memory corruption in bar (upgrade to >=1.2.0)
+[ERROR] refusing to build packages with vulnerabilities, as `audit.build` is `deny`
+Upgrade the affected packages, or add the advisories to `audit.ignore`.
+",
+        )
+        .run();
+
+    p.change_file(
+        ".cargo/config",
+        "[audit]\ndatabase = \"advisory-db\"\nbuild = \"warn\"",
+    );
+    p.cargo("test")
+        .with_stderr_contains("[WARNING] package `bar v1.0.0` is affected by RUSTSEC-2020-0001[..]")
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+
+    p.change_file(".cargo/config", "[audit]\nbuild = \"sometimes\"");
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] invalid value for `audit.build`: `sometimes`, \
+             expected `allow`, `warn` or `deny`",
+        )
+        .run();
+}
@@ -1078,18 +1078,15 @@ fn incompatible_dependencies() {
         .with_status(101)
         .with_stderr_contains(
             "\
-error: failed to select a version for `bad`.
-    ... required by package `qux v0.1.0`
-    ... which is depended on by `foo v0.0.1 ([..])`
-versions that meet the requirements `>=1.0.1` are: 1.0.2, 1.0.1
-
-all possible versions conflict with previously selected packages.
+error: failed to select a version for `bad`
 
-  previously selected package `bad v1.0.0`
-    ... which is depended on by `baz v0.1.0`
+because `bad >=1.0.1` is required by package `qux v0.1.0`
     ... which is depended on by `foo v0.0.1 ([..])`
-
-failed to select a version for `bad` which could resolve this conflict",
+and the versions matching it are 1.0.2, 1.0.1
+and `bad v1.0.0` was selected, as `bad =1.0.0` is required by package `baz v0.1.0`
+    ... which is depended on by `foo v0.0.1 ([..])`
+and only one semver compatible version of a package can be selected
+so no version of `bad` can be selected",
         )
         .run();
 }
@@ -1124,21 +1121,72 @@ fn incompatible_dependencies_with_multi_semver() {
         .with_status(101)
         .with_stderr_contains(
             "\
-error: failed to select a version for `bad`.
-    ... required by package `foo v0.0.1 ([..])`
-versions that meet the requirements `>=1.0.1, <=2.0.0` are: 2.0.0, 1.0.1
-
-all possible versions conflict with previously selected packages.
+error: failed to select a version for `bad`
 
-  previously selected package `bad v2.0.1`
-    ... which is depended on by `baz v0.1.0`
+because `bad >=1.0.1, <=2.0.0` is required by package `foo v0.0.1 ([..])`
+and the versions matching it are 2.0.0, 1.0.1
+and `bad v2.0.1` was selected, as `bad >=2.0.1` is required by package `baz v0.1.0`
     ... which is depended on by `foo v0.0.1 ([..])`
-
-  previously selected package `bad v1.0.0`
-    ... which is depended on by `bar v0.1.0`
+and `bad v1.0.0` was selected, as `bad =1.0.0` is required by package `bar v0.1.0`
     ... which is depended on by `foo v0.0.1 ([..])`
+and only one semver compatible version of a package can be selected
+so no version of `bad` can be selected",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn incompatible_dependencies_json_report() {
+    Package::new("bad", "1.0.0").publish();
+    Package::new("bad", "1.0.1").publish();
+    Package::new("bar", "0.1.0").dep("bad", "=1.0.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
 
-failed to select a version for `bad` which could resolve this conflict",
+            [dependencies]
+            bar = "0.1.0"
+            bad = "=1.0.1"
+        "#,
+        )
+        .file("src/main.rs", "fn main(){}")
+        .build();
+
+    p.cargo("build --message-format=json")
+        .with_status(101)
+        .with_json(
+            r#"
+            {
+                "reason": "resolve-conflict",
+                "package": "bad",
+                "requirement": {
+                    "dependent": "bar 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                    "req": "=1.0.0",
+                    "depended_on_by": ["foo 0.0.1 (path+file:///[..]/foo)"]
+                },
+                "candidates": ["1.0.0"],
+                "conflicts": [
+                    {
+                        "selected": "bad 1.0.1 (registry+https://github.com/rust-lang/crates.io-index)",
+                        "depended_on_by": ["foo 0.0.1 (path+file:///[..]/foo)"],
+                        "required_by": [
+                            {
+                                "dependent": "foo 0.0.1 (path+file:///[..]/foo)",
+                                "req": "=1.0.1",
+                                "depended_on_by": []
+                            }
+                        ],
+                        "reason": "semver"
+                    }
+                ],
+                "explanation": "{...}"
+            }
+            "#,
         )
         .run();
 }
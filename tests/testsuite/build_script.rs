@@ -380,14 +380,13 @@ fn links_duplicates() {
 
     p.cargo("build").with_status(101)
                        .with_stderr("\
-error: failed to select a version for `a-sys`.
-    ... required by package `foo v0.5.0 ([..])`
-versions that meet the requirements `*` are: 0.5.0
+error: failed to select a version for `a-sys`
 
-the package `a-sys` links to the native library `a`, but it conflicts with a previous package which links to `a` as well:
-package `foo v0.5.0 ([..])`
-
-failed to select a version for `a-sys` which could resolve this conflict
+because `a-sys *` is required by package `foo v0.5.0 ([..])`
+and the versions matching it are 0.5.0
+and the native library `a` is also linked to by package `foo v0.5.0 ([..])`
+and only one package may link to a native library
+so no version of `a-sys` can be selected
 ").run();
 }
 
@@ -498,15 +497,14 @@ fn links_duplicates_deep_dependency() {
 
     p.cargo("build").with_status(101)
                        .with_stderr("\
-error: failed to select a version for `a-sys`.
-    ... required by package `a v0.5.0 ([..])`
-    ... which is depended on by `foo v0.5.0 ([..])`
-versions that meet the requirements `*` are: 0.5.0
-
-the package `a-sys` links to the native library `a`, but it conflicts with a previous package which links to `a` as well:
-package `foo v0.5.0 ([..])`
+error: failed to select a version for `a-sys`
 
-failed to select a version for `a-sys` which could resolve this conflict
+because `a-sys *` is required by package `a v0.5.0 ([..])`
+    ... which is depended on by `foo v0.5.0 ([..])`
+and the versions matching it are 0.5.0
+and the native library `a` is also linked to by package `foo v0.5.0 ([..])`
+and only one package may link to a native library
+so no version of `a-sys` can be selected
 ").run();
 }
 
@@ -3577,14 +3575,13 @@ fn links_duplicates_with_cycle() {
 
     p.cargo("build").with_status(101)
                        .with_stderr("\
-error: failed to select a version for `a`.
-    ... required by package `foo v0.5.0 ([..])`
-versions that meet the requirements `*` are: 0.5.0
-
-the package `a` links to the native library `a`, but it conflicts with a previous package which links to `a` as well:
-package `foo v0.5.0 ([..])`
+error: failed to select a version for `a`
 
-failed to select a version for `a` which could resolve this conflict
+because `a *` is required by package `foo v0.5.0 ([..])`
+and the versions matching it are 0.5.0
+and the native library `a` is also linked to by package `foo v0.5.0 ([..])`
+and only one package may link to a native library
+so no version of `a` can be selected
 ").run();
 }
 
@@ -4034,3 +4031,195 @@ Caused by:
     // Restore permissions so that the directory can be deleted.
     fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
 }
+
+#[cargo_test]
+fn rerun_if_changed_directory_is_recursive() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-changed=schema");
+                }
+            "#,
+        )
+        .file("schema/a/b.proto", "")
+        .file("schema/c.proto", "")
+        .build();
+
+    p.cargo("build").run();
+
+    p.cargo("build -v")
+        .with_stderr(
+            "\
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    sleep_ms(1000);
+    p.change_file("schema/a/b.proto", "message A {}");
+    p.cargo("build -v")
+        .with_stderr_contains("[RUNNING] `[..]/build-script-build`")
+        .run();
+}
+
+#[cargo_test]
+fn rerun_if_changed_glob() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-changed=schema/**/*.proto");
+                }
+            "#,
+        )
+        .file("schema/a/b.proto", "")
+        .file("schema/a/notes.txt", "")
+        .build();
+
+    p.cargo("build").run();
+
+    // Files not matching the pattern are ignored.
+    sleep_ms(1000);
+    p.change_file("schema/a/notes.txt", "todo");
+    p.cargo("build -v")
+        .with_stderr(
+            "\
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    sleep_ms(1000);
+    p.change_file("schema/a/b.proto", "message A {}");
+    p.cargo("build -v")
+        .with_stderr_contains("[RUNNING] `[..]/build-script-build`")
+        .run();
+}
+
+#[cargo_test]
+fn rerun_if_changed_invalid_glob() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-changed=schema/[.proto");
+                }
+            "#,
+        )
+        .build();
+
+    p.cargo("build")
+        .with_stderr_contains(
+            "[WARNING] invalid glob pattern `schema/[.proto` in `rerun-if-changed`, \
+             checking it as a path instead: [..]",
+        )
+        .run();
+
+    // It's taken as a path, which is missing, so the script always runs.
+    p.cargo("build -v")
+        .with_stderr_contains("[RUNNING] `[..]/build-script-build`")
+        .run();
+
+    // Until it exists.
+    p.change_file("schema/[.proto", "");
+    p.cargo("build").run();
+    p.cargo("build -v")
+        .with_stderr(
+            "\
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn rerun_if_changed_path_with_glob_characters() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-changed=data[1].json");
+                }
+            "#,
+        )
+        .file("data[1].json", "{}")
+        .file("data1.json", "{}")
+        .build();
+
+    p.cargo("build").run();
+
+    // The path exists, so it isn't taken as a pattern matching `data1.json`.
+    sleep_ms(1000);
+    p.change_file("data1.json", "[]");
+    p.cargo("build -v")
+        .with_stderr(
+            "\
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    sleep_ms(1000);
+    p.change_file("data[1].json", "[]");
+    p.cargo("build -v")
+        .with_stderr_contains("[RUNNING] `[..]/build-script-build`")
+        .run();
+}
+
+#[cargo_test]
+fn rerun_if_changed_package_root_skips_target_and_vcs() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-changed=.");
+                }
+            "#,
+        )
+        .file(".git/HEAD", "ref: refs/heads/master")
+        .build();
+
+    p.cargo("build").run();
+
+    // The outputs of the build, in `target`, are newer than the last run.
+    p.cargo("build -v")
+        .with_stderr(
+            "\
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    sleep_ms(1000);
+    p.change_file(".git/HEAD", "ref: refs/heads/other");
+    p.cargo("build -v")
+        .with_stderr(
+            "\
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    sleep_ms(1000);
+    p.change_file("data.txt", "");
+    p.cargo("build -v")
+        .with_stderr_contains("[RUNNING] `[..]/build-script-build`")
+        .run();
+}
@@ -0,0 +1,362 @@
+//! Tests for the `cargo add` command.
+
+use cargo_test_support::git;
+use cargo_test_support::registry::{self, Package};
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn adds_latest_version() {
+    registry::init();
+    Package::new("bar", "0.1.0").publish();
+    Package::new("bar", "0.2.0").publish();
+    Package::new("bar", "0.3.0-alpha.1").publish();
+    Package::new("bar", "0.2.1").yanked(true).publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                # The manifest of foo.
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                authors = []
+
+                [dependencies] # sorted
+                # Pinned because of a bug.
+                aaa = { path = "aaa" }  # trailing
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("aaa/Cargo.toml", &basic_manifest("aaa", "0.1.0"))
+        .file("aaa/src/lib.rs", "")
+        .build();
+
+    p.cargo("add bar")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] bar v0.2.0 to dependencies
+",
+        )
+        .run();
+    assert_eq!(
+        p.read_file("Cargo.toml"),
+        r#"
+                # The manifest of foo.
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                authors = []
+
+                [dependencies] # sorted
+                # Pinned because of a bug.
+                aaa = { path = "aaa" }  # trailing
+bar = "0.2.0"
+            "#
+    );
+
+    p.cargo("build")
+        .with_stderr_contains("[COMPILING] bar v0.2.0")
+        .run();
+}
+
+#[cargo_test]
+fn version_requirement_features_and_rename() {
+    registry::init();
+    Package::new("bar", "0.1.0")
+        .feature("f1", &[])
+        .feature("f2", &[])
+        .publish();
+    Package::new("bar", "0.2.0").publish();
+
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("add bar@0.1 --features f1,f2 --rename baz --optional")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] bar v0.1 as baz to dependencies
+",
+        )
+        .run();
+    assert!(p.read_file("Cargo.toml").contains(
+        "\n[dependencies]\n\
+         baz = { package = \"bar\", version = \"0.1\", features = [\"f1\", \"f2\"], optional = true }\n"
+    ));
+
+    p.cargo("add bar@0.1 --features missing")
+        .with_status(101)
+        .with_stderr_contains("[ERROR] `bar` v0.1.0 does not have a feature named `missing`")
+        .run();
+}
+
+#[cargo_test]
+fn updates_existing_dependency() {
+    registry::init();
+    Package::new("bar", "0.1.0")
+        .feature("f1", &[])
+        .feature("f2", &[])
+        .publish();
+    Package::new("bar", "0.1.1")
+        .feature("f1", &[])
+        .feature("f2", &[])
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                authors = []
+
+                [dependencies]
+                bar = { version = "0.1.0", features = ["f1"] } # keep this
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    // The existing requirement is kept, and features are merged.
+    p.cargo("add bar --features f2")
+        .with_stderr_contains("[ADDING] bar v0.1.0 to dependencies")
+        .run();
+    assert!(p
+        .read_file("Cargo.toml")
+        .contains(r#"bar = { version = "0.1.0", features = ["f1", "f2"] } # keep this"#));
+}
+
+#[cargo_test]
+fn keeps_existing_source_and_rename() {
+    registry::init();
+    Package::new("bar", "0.1.0").feature("f1", &[]).publish();
+    Package::new("bar", "0.2.0").feature("f1", &[]).publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                authors = []
+
+                [dependencies]
+                baz = { package = "bar", version = "0.1" }
+                qux = { path = "qux" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("qux/Cargo.toml", &basic_manifest("qux", "0.5.0"))
+        .file("qux/src/lib.rs", "")
+        .build();
+
+    p.cargo("add baz --features f1")
+        .with_stderr_contains("[ADDING] bar v0.1 as baz to dependencies")
+        .run();
+    p.cargo("add qux@0.5")
+        .with_stderr("[ADDING] qux (local) to dependencies")
+        .run();
+    p.cargo("add bar --rename qux")
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] dependency `qux` is taken from a local path, use `--path`, `--git` or \
+             `--registry` to say where `bar` should be taken from",
+        )
+        .run();
+    assert!(p.read_file("Cargo.toml").contains(
+        r#"
+                baz = { package = "bar", version = "0.1", features = ["f1"] }
+                qux = { path = "qux", version = "0.5" }
+"#
+    ));
+    p.cargo("check").run();
+}
+
+#[cargo_test]
+fn dependency_tables() {
+    registry::init();
+    Package::new("bar", "1.0.0").publish();
+
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("add bar --dev").run();
+    p.cargo("add bar --build --target cfg(unix)")
+        .with_stderr_contains("[ADDING] bar v1.0.0 to build-dependencies for target `cfg(unix)`")
+        .run();
+    let manifest = p.read_file("Cargo.toml");
+    assert!(manifest.contains("\n[dev-dependencies]\nbar = \"1.0.0\"\n"));
+    assert!(manifest.contains("\n[target.\"cfg(unix)\".build-dependencies]\nbar = \"1.0.0\"\n"));
+
+    p.cargo("add bar --dev --build")
+        .with_status(1)
+        .with_stderr_contains("[..]cannot be used with[..]")
+        .run();
+}
+
+#[cargo_test]
+fn path_dependency() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file("../baz/Cargo.toml", &basic_manifest("baz", "0.5.0"))
+        .file("../baz/src/lib.rs", "")
+        .build();
+
+    p.cargo("add --path ../baz")
+        .with_stderr("[ADDING] baz (local) to dependencies")
+        .run();
+    assert!(p
+        .read_file("Cargo.toml")
+        .contains("\n[dependencies]\nbaz = { path = \"../baz\" }\n"));
+
+    p.cargo("add qux --path ../baz")
+        .with_status(101)
+        .with_stderr("[ERROR] the package at `[..]baz` is `baz`, not `qux`")
+        .run();
+}
+
+#[cargo_test]
+fn git_dependency() {
+    let git_project = git::new("bar", |project| {
+        project
+            .file("Cargo.toml", &basic_manifest("bar", "0.5.0"))
+            .file("src/lib.rs", "")
+    });
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo(&format!(
+        "add bar --git {} --branch master",
+        git_project.url()
+    ))
+    .with_stderr("[ADDING] bar (git) to dependencies")
+    .run();
+    assert!(p.read_file("Cargo.toml").contains(&format!(
+        "\n[dependencies]\nbar = {{ git = \"{}\", branch = \"master\" }}\n",
+        git_project.url()
+    )));
+    p.cargo("build")
+        .with_stderr_contains("[COMPILING] bar v0.5.0 ([..])")
+        .run();
+
+    p.cargo("add --tag v1 baz")
+        .with_status(1)
+        .with_stderr_contains("[..]--git <URL>[..]")
+        .run();
+}
+
+#[cargo_test]
+fn alternative_registry() {
+    registry::init();
+    Package::new("bar", "0.0.1").alternative(true).publish();
+
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("add bar --registry alternative").run();
+    assert!(p
+        .read_file("Cargo.toml")
+        .contains("\n[dependencies]\nbar = { version = \"0.0.1\", registry = \"alternative\" }\n"));
+}
+
+#[cargo_test]
+fn missing_crate() {
+    registry::init();
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project().file("src/lib.rs", "").build();
+
+    p.cargo("add baz")
+        .with_status(101)
+        .with_stderr_contains("[ERROR] could not find `baz` in registry `[..]`")
+        .run();
+    p.cargo("add bar@2")
+        .with_status(101)
+        .with_stderr_contains("[ERROR] could not find `bar` in registry `[..]` with version `2`")
+        .run();
+    // Nothing was written.
+    assert!(!p.read_file("Cargo.toml").contains("[dependencies]"));
+}
+
+#[cargo_test]
+fn workspace_member() {
+    registry::init();
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [workspace]
+                members = ["a", "b"]
+            "#,
+        )
+        .file("a/Cargo.toml", &basic_manifest("a", "0.1.0"))
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file("b/src/lib.rs", "")
+        .build();
+
+    p.cargo("add bar")
+        .with_status(101)
+        .with_stderr_contains("[ERROR] manifest path `[..]` is a virtual manifest[..]")
+        .run();
+    p.cargo("add bar -p b").run();
+    assert!(p.read_file("b/Cargo.toml").contains("bar = \"0.1.0\""));
+    assert!(!p.read_file("a/Cargo.toml").contains("bar"));
+}
+
+#[cargo_test]
+fn inherited_dependency() {
+    registry::init();
+    Package::new("bar", "0.1.0").feature("f1", &[]).publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                cargo-features = ["workspace-inheritance"]
+
+                [workspace]
+                members = ["a"]
+
+                [workspace.dependencies]
+                bar = "0.1"
+            "#,
+        )
+        .file(
+            "a/Cargo.toml",
+            r#"
+                cargo-features = ["workspace-inheritance"]
+
+                [package]
+                name = "a"
+                version = "0.1.0"
+                authors = []
+
+                [dependencies]
+                bar = { workspace = true }
+            "#,
+        )
+        .file("a/src/lib.rs", "")
+        .build();
+
+    p.cargo("add bar@0.1 -p a")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] dependency `bar` is inherited from the workspace, \
+             only `--features` and `--optional` can be used to change it",
+        )
+        .run();
+
+    // The entry stays inherited, and only gets the new features.
+    p.cargo("add bar -p a --features f1")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[ADDING] bar (workspace) to dependencies")
+        .run();
+    assert!(p
+        .read_file("a/Cargo.toml")
+        .contains(r#"bar = { workspace = true, features = ["f1"] }"#));
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+}
@@ -85,6 +85,7 @@ fn list_aliases_with_descriptions() {
         .with_stdout_contains("    b                    alias: build")
         .with_stdout_contains("    c                    alias: check")
         .with_stdout_contains("    r                    alias: run")
+        .with_stdout_contains("    rm                   alias: remove")
         .with_stdout_contains("    t                    alias: test")
         .run();
 }
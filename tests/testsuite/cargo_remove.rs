@@ -0,0 +1,152 @@
+//! Tests for the `cargo remove` command.
+
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn removes_dependencies() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                authors = []
+
+                [dependencies]
+                # The first one.
+                bar = "0.1"
+                baz = { version = "0.2", features = ["x"] }
+                qux = "0.3"  # keep
+
+                [dev-dependencies]
+                bar = "0.1"
+
+                [target.'cfg(unix)'.dependencies]
+                bar = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("remove bar baz")
+        .with_stderr(
+            "\
+[REMOVING] bar from dependencies
+[REMOVING] baz from dependencies
+",
+        )
+        .run();
+    p.cargo("rm --dev bar")
+        .with_stderr("[REMOVING] bar from dev-dependencies")
+        .run();
+    p.cargo("rm --target cfg(unix) bar").run();
+    assert_eq!(
+        p.read_file("Cargo.toml"),
+        r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                authors = []
+
+                [dependencies]
+                qux = "0.3"  # keep
+
+                [dev-dependencies]
+
+                [target.'cfg(unix)'.dependencies]
+            "#
+    );
+}
+
+#[cargo_test]
+fn removes_feature_references() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                authors = []
+
+                [features]
+                default = ["bar", "qux"]
+                extra = ["bar/std", "baz/std"]
+
+                [dependencies]
+                bar = { path = "bar", optional = true }
+                baz = { path = "baz", optional = true }
+                qux = { path = "qux", optional = true }
+
+                [target.'cfg(unix)'.dependencies]
+                baz = { path = "baz", optional = true }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.1.0"))
+        .file("baz/src/lib.rs", "")
+        .file("qux/Cargo.toml", &basic_manifest("qux", "0.1.0"))
+        .file("qux/src/lib.rs", "")
+        .build();
+
+    // `baz` is still a dependency for unix, so `baz/std` stays.
+    p.cargo("remove bar baz").run();
+    assert_eq!(
+        p.read_file("Cargo.toml"),
+        r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+                authors = []
+
+                [features]
+                default = ["qux"]
+                extra = ["baz/std"]
+
+                [dependencies]
+                qux = { path = "qux", optional = true }
+
+                [target.'cfg(unix)'.dependencies]
+                baz = { path = "baz", optional = true }
+            "#
+    );
+}
+
+#[cargo_test]
+fn missing_dependency() {
+    let manifest = r#"
+        [package]
+        name = "foo"
+        version = "0.1.0"
+        authors = []
+
+        [dependencies]
+        bar = "0.1"
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("remove bar baz")
+        .with_status(101)
+        .with_stderr(
+            "\
+[REMOVING] bar from dependencies
+[REMOVING] baz from dependencies
+[ERROR] the dependency `baz` could not be found in `dependencies`
+",
+        )
+        .run();
+    p.cargo("remove --build bar")
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] the dependency `bar` could not be found in `build-dependencies`",
+        )
+        .run();
+    // Nothing was written.
+    assert_eq!(p.read_file("Cargo.toml"), manifest);
+}
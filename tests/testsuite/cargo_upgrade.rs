@@ -0,0 +1,185 @@
+//! Tests for the `cargo upgrade` command.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project};
+
+#[cargo_test]
+fn upgrades_to_latest_compatible() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("baz", "0.1.0").feature("f", &[]).publish();
+    Package::new("qux", "0.3.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "1.0"  # keep this
+            baz = { version = "~0.1.0", features = ["f"] }
+            qux = "=0.3.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile").run();
+
+    Package::new("bar", "1.2.3").publish();
+    Package::new("bar", "2.0.0").publish();
+    Package::new("baz", "0.1.5").feature("f", &[]).publish();
+    Package::new("baz", "0.2.0").feature("f", &[]).publish();
+    Package::new("qux", "0.3.1").publish();
+
+    p.cargo("upgrade")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPGRADING] bar 1.0 -> 1.2 in foo (dependencies)
+[UPGRADING] baz ~0.1.0 -> ~0.1.5 in foo (dependencies)
+[UPDATING] bar v1.0.0 -> v1.2.3
+[UPDATING] baz v0.1.0 -> v0.1.5
+",
+        )
+        .run();
+    assert!(p.read_file("Cargo.toml").contains(
+        r#"
+            bar = "1.2"  # keep this
+            baz = { version = "~0.1.5", features = ["f"] }
+            qux = "=0.3.0"
+"#
+    ));
+    let lock = p.read_lockfile();
+    assert!(lock.contains("name = \"bar\"\nversion = \"1.2.3\""));
+    assert!(lock.contains("name = \"qux\"\nversion = \"0.3.0\""));
+
+    p.cargo("upgrade")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+note: all dependency requirements are up to date
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn upgrades_incompatible_across_the_workspace() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("baz", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [workspace]
+            members = ["a", "b"]
+
+            [workspace.dependencies]
+            baz = "0.1"
+            "#,
+        )
+        .file(
+            "a/Cargo.toml",
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [package]
+            name = "a"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "1.0"
+            baz.workspace = true
+            "#,
+        )
+        .file("a/src/lib.rs", "")
+        .file(
+            "b/Cargo.toml",
+            r#"
+            [package]
+            name = "b"
+            version = "0.1.0"
+            authors = []
+
+            [dev-dependencies]
+            bar = { version = "1.0", optional = false }
+            "#,
+        )
+        .file("b/src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    Package::new("bar", "2.1.0").publish();
+    Package::new("baz", "0.2.0").publish();
+
+    p.cargo("upgrade --incompatible -p bar --dry-run")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPGRADING] bar 1.0 -> 2.1 in a (dependencies)
+[UPGRADING] bar 1.0 -> 2.1 in b (dev-dependencies)
+[WARNING] aborting upgrade due to dry run
+",
+        )
+        .run();
+    assert!(p.read_file("a/Cargo.toml").contains("bar = \"1.0\""));
+
+    p.cargo("upgrade --incompatible")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPGRADING] bar 1.0 -> 2.1 in a (dependencies)
+[UPGRADING] bar 1.0 -> 2.1 in b (dev-dependencies)
+[UPGRADING] baz 0.1 -> 0.2 in workspace.dependencies
+[UPDATING] bar v1.0.0 -> v2.1.0
+[UPDATING] baz v0.1.0 -> v0.2.0
+",
+        )
+        .run();
+    let a = p.read_file("a/Cargo.toml");
+    assert!(a.contains("bar = \"2.1\"") && a.contains("baz.workspace = true"));
+    assert!(p
+        .read_file("b/Cargo.toml")
+        .contains("bar = { version = \"2.1\", optional = false }"));
+    assert!(p.read_file("Cargo.toml").contains("baz = \"0.2\""));
+}
+
+#[cargo_test]
+fn unknown_dependency() {
+    Package::new("bar", "1.0.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "1.0"
+            baz = { path = "baz" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file("baz/Cargo.toml", &basic_manifest("baz", "0.1.0"))
+        .file("baz/src/lib.rs", "")
+        .build();
+
+    p.cargo("upgrade -p baz")
+        .with_status(101)
+        .with_stderr("[ERROR] the registry dependency `baz` could not be found in the workspace")
+        .run();
+}
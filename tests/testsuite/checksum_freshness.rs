@@ -0,0 +1,167 @@
+//! Tests for `-Z checksum-freshness`.
+
+use cargo_test_support::{project, Project};
+use filetime::FileTime;
+
+/// Sets the mtime of the file at `path` in `p`, relative to now.
+fn set_mtime(p: &Project, path: &str, seconds_from_now: i64) {
+    let now = FileTime::now();
+    let mtime = FileTime::from_unix_time(now.unix_seconds() + seconds_from_now, 0);
+    filetime::set_file_mtime(p.root().join(path), mtime).unwrap();
+}
+
+#[cargo_test]
+fn touched_but_unchanged_source_is_fresh() {
+    let p = project()
+        .file("src/lib.rs", "mod a;")
+        .file("src/a.rs", "")
+        .build();
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    set_mtime(&p, "src/a.rs", 10);
+    p.cargo("build -Zchecksum-freshness -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    // Without the flag, the mtimes are compared.
+    p.cargo("build -v")
+        .with_stderr_contains("[RUNNING] `rustc --crate-name foo [..]")
+        .run();
+}
+
+#[cargo_test]
+fn touched_source_is_only_rehashed_once() {
+    let p = project().file("src/lib.rs", "").build();
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    set_mtime(&p, "src/lib.rs", 10);
+    p.cargo("build -Zchecksum-freshness")
+        .env("CARGO_LOG", "cargo::core::compiler::fingerprint=debug")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[..]rehashed [..]src/lib.rs[..]")
+        .run();
+
+    // The new mtime was recorded along with the checksum.
+    p.cargo("build -Zchecksum-freshness")
+        .env("CARGO_LOG", "cargo::core::compiler::fingerprint=debug")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_does_not_contain("[..]rehashed[..]")
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+    p.cargo("build -Zchecksum-freshness -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[FRESH] foo v0.0.1 ([CWD])")
+        .run();
+}
+
+#[cargo_test]
+fn changed_source_with_older_mtime_is_rebuilt() {
+    let p = project().file("src/lib.rs", "").build();
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    // As if the source tree was restored from a cache.
+    p.change_file("src/lib.rs", "pub fn f() {}");
+    set_mtime(&p, "src/lib.rs", -3600);
+    p.cargo("build -Zchecksum-freshness --explain-rebuilds")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[DIRTY] foo v0.0.1 ([CWD]) (lib): the contents of the file `[CWD]/src/lib.rs` \
+    have changed: modified at [..]
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn rerun_if_changed_contents() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-changed=data.txt");
+                }
+            "#,
+        )
+        .file("data.txt", "1")
+        .build();
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    set_mtime(&p, "data.txt", 10);
+    p.cargo("build -Zchecksum-freshness -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[FRESH] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.change_file("data.txt", "2");
+    set_mtime(&p, "data.txt", -7200);
+    p.cargo("build -Zchecksum-freshness -v")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `[..]/build-script-build`")
+        .run();
+}
+
+#[cargo_test]
+fn rerun_if_changed_new_file_with_older_mtime() {
+    let p = project()
+        .file("src/lib.rs", "")
+        .file(
+            "build.rs",
+            r#"
+                fn main() {
+                    println!("cargo:rerun-if-changed=schema");
+                }
+            "#,
+        )
+        .file("schema/a.proto", "")
+        .build();
+    p.cargo("build -Zchecksum-freshness")
+        .masquerade_as_nightly_cargo()
+        .run();
+
+    // As if the source tree was restored from a cache.
+    p.change_file("schema/b.proto", "");
+    set_mtime(&p, "schema/b.proto", -3600);
+    set_mtime(&p, "schema", -3600);
+    p.cargo("build -Zchecksum-freshness --explain-rebuilds")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains(
+            "[DIRTY] foo v0.0.1 ([CWD]) (custom-build): \
+             the file `[CWD]/schema/b.proto` was added",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn requires_nightly() {
+    let p = project().file("src/lib.rs", "").build();
+    p.cargo("build -Zchecksum-freshness")
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] the `-Z` flag is only accepted on the nightly channel of Cargo[..]",
+        )
+        .run();
+}
@@ -0,0 +1,262 @@
+//! Tests for registry tokens stored by credential providers.
+
+use cargo_test_support::paths;
+use cargo_test_support::registry::{self, Package};
+use cargo_test_support::{basic_manifest, cargo_process, project, Project};
+use std::fs;
+
+/// Builds a credential provider which appends every request it receives to
+/// `requests.log` in the project root, and whose behavior is picked by its
+/// first argument:
+///
+/// * `ok`: hands out the token `sekrit` and accepts everything else.
+/// * `empty`: reports that it has no token.
+/// * `fail`: reports an error.
+/// * `v2`: replies with an unsupported protocol version.
+fn build_provider() -> Project {
+    let provider = project()
+        .at("provider")
+        .file("Cargo.toml", &basic_manifest("test-provider", "1.0.0"))
+        .file(
+            "src/main.rs",
+            r##"
+                use std::fs::OpenOptions;
+                use std::io::{Read, Write};
+
+                fn main() {
+                    let mut request = String::new();
+                    std::io::stdin().read_to_string(&mut request).unwrap();
+                    let log = std::env::current_exe().unwrap()
+                        .parent().unwrap()
+                        .join("../../requests.log");
+                    OpenOptions::new().create(true).append(true).open(log).unwrap()
+                        .write_all(request.as_bytes()).unwrap();
+
+                    let mode = std::env::args().nth(1).unwrap();
+                    let response = match (mode.as_str(), request.contains(r#""action":"get""#)) {
+                        ("ok", true) => r#"{"v":1,"token":"sekrit"}"#,
+                        ("ok", false) => r#"{"v":1}"#,
+                        ("empty", _) => r#"{"v":1,"error":{"kind":"not-found"}}"#,
+                        ("fail", _) => r#"{"v":1,"error":{"kind":"other","message":"keyring is locked"}}"#,
+                        _ => r#"{"v":2}"#,
+                    };
+                    println!("{}", response);
+                }
+            "##,
+        )
+        .build();
+    provider.cargo("build").run();
+
+    // Start without any tokens in the credentials file.
+    registry::init();
+    fs::remove_file(paths::home().join(".cargo/credentials")).unwrap();
+    provider
+}
+
+/// Configures `provider` for the `alternative` registry, in a config file
+/// next to the project so it can be switched out within a test.
+fn configure(provider: &Project, mode: &str) {
+    let config = paths::root().join(".cargo/config");
+    fs::create_dir_all(config.parent().unwrap()).unwrap();
+    fs::write(
+        &config,
+        format!(
+            r#"
+                [registries.alternative]
+                credential-provider = ["{}", "{}"]
+            "#,
+            provider
+                .bin("test-provider")
+                .display()
+                .to_string()
+                .replace('\\', "\\\\"),
+            mode
+        ),
+    )
+    .unwrap();
+}
+
+fn requests(provider: &Project) -> String {
+    fs::read_to_string(provider.root().join("requests.log")).unwrap_or_default()
+}
+
+fn publisher() -> Project {
+    Package::new("bar", "0.0.1").alternative(true).publish();
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+            license = "MIT"
+            description = "foo"
+        "#,
+        )
+        .file("src/lib.rs", "")
+        .build()
+}
+
+#[cargo_test]
+fn requires_nightly_flag() {
+    let provider = build_provider();
+    configure(&provider, "ok");
+    let p = publisher();
+
+    p.cargo("publish --no-verify --registry alternative")
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] no upload token found, please run `cargo login` or pass `--token`",
+        )
+        .run();
+    assert_eq!(requests(&provider), "");
+}
+
+#[cargo_test]
+fn publish_gets_token() {
+    let provider = build_provider();
+    configure(&provider, "ok");
+    let p = publisher();
+
+    p.cargo("publish --no-verify --registry alternative -Z credential-provider")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[UPLOADING] foo v0.1.0 ([CWD])")
+        .run();
+
+    let requests = requests(&provider);
+    assert_eq!(requests.lines().count(), 1);
+    assert!(requests.starts_with(r#"{"v":1,"action":"get","registry":{"index-url":"file:///"#));
+    assert!(requests.ends_with("\"name\":\"alternative\"}}\n"));
+}
+
+#[cargo_test]
+fn missing_token() {
+    let provider = build_provider();
+    configure(&provider, "empty");
+    let p = publisher();
+
+    p.cargo("publish --no-verify --registry alternative -Z credential-provider")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] no upload token found, please run `cargo login` or pass `--token`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn provider_errors() {
+    let provider = build_provider();
+    configure(&provider, "fail");
+    let p = publisher();
+
+    p.cargo("publish --no-verify --registry alternative -Z credential-provider")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] credential provider `[..]test-provider[..]` failed: keyring is locked",
+        )
+        .run();
+
+    configure(&provider, "v2");
+    p.cargo("publish --no-verify --registry alternative -Z credential-provider")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] credential provider `[..]` uses protocol version 2, \
+             but this version of Cargo only supports version 1",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn login_and_logout() {
+    let provider = build_provider();
+    configure(&provider, "ok");
+
+    cargo_process("login --registry alternative -Z credential-provider abcdef")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[LOGIN] token for `alternative` saved
+",
+        )
+        .run();
+    assert!(!paths::home().join(".cargo/credentials").exists());
+
+    cargo_process("logout --registry alternative -Z credential-provider -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[LOGOUT] token for `alternative` has been removed")
+        .run();
+
+    let requests = requests(&provider);
+    let requests: Vec<_> = requests.lines().collect();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0].starts_with(r#"{"v":1,"action":"store","#));
+    assert!(requests[0].ends_with(r#""token":"abcdef"}"#));
+    assert!(requests[1].starts_with(r#"{"v":1,"action":"erase","#));
+}
+
+#[cargo_test]
+fn plaintext_logout() {
+    registry::init();
+    let credentials = paths::home().join(".cargo/credentials");
+
+    cargo_process("logout --registry alternative")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "[ERROR] the `cargo logout` command is unstable, \
+             pass `-Z unstable-options` to enable it",
+        )
+        .run();
+
+    cargo_process("logout --registry alternative -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[LOGOUT] token for `alternative` has been removed")
+        .run();
+    let contents = fs::read_to_string(&credentials).unwrap();
+    assert!(!contents.contains("[registries.alternative]\ntoken"));
+    // The crates.io token is untouched.
+    assert!(contents.contains("[registry]\ntoken = \"api-token\""));
+
+    cargo_process("logout --registry alternative -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[LOGOUT] not currently logged in to `alternative`")
+        .run();
+
+    // The built-in provider can also be selected explicitly.
+    let config = paths::root().join(".cargo/config");
+    fs::create_dir_all(config.parent().unwrap()).unwrap();
+    fs::write(
+        &config,
+        "[registry]\ncredential-provider = \"cargo:plaintext\"\n",
+    )
+    .unwrap();
+    cargo_process("logout -Z unstable-options -Z credential-provider")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[LOGOUT] token for `crates.io` has been removed")
+        .run();
+    assert!(!fs::read_to_string(&credentials).unwrap().contains("token"));
+}
+
+#[cargo_test]
+fn plaintext_logout_token_from_env() {
+    registry::init();
+    let credentials = paths::home().join(".cargo/credentials");
+    fs::write(&credentials, "").unwrap();
+
+    cargo_process("logout -Z unstable-options")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_REGISTRY_TOKEN", "from-env")
+        .with_stderr(
+            "\
+[WARNING] the token for `crates.io` is still set by environment variable \
+`CARGO_REGISTRY_TOKEN`, it has to be removed from there
+[LOGOUT] not currently logged in to `crates.io`
+",
+        )
+        .run();
+}
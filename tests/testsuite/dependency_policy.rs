@@ -0,0 +1,331 @@
+//! Tests for the `[workspace.dependency-policy]` table.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, git, path2url, paths, project};
+
+#[cargo_test]
+fn dependency_policy_requires_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [workspace.dependency-policy]
+            max-versions = 1
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  feature `dependency-policy` is required
+
+  consider adding `cargo-features = [\"dependency-policy\"]` to the manifest
+",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+        cargo-features = ["dependency-policy"]
+
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [workspace.dependency-policy]
+        max-versions = 0
+        "#,
+    );
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `workspace.dependency-policy.max-versions` must be at least 1
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn banned_dependency() {
+    Package::new("baz", "0.1.5").publish();
+    Package::new("baz", "0.2.0").publish();
+    Package::new("bar", "1.0.0").dep("baz", "0.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["dependency-policy"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "1.0"
+
+            [workspace.dependency-policy]
+            ban = [{ name = "baz", version = "<0.2" }]
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] [..]
+[DOWNLOADED] [..]
+[ERROR] the dependency graph violates the dependency policy of the workspace, \
+set in `[CWD]/Cargo.toml`
+
+package `baz v0.1.5` is banned
+    ... which is depended on by `bar v1.0.0`
+    ... which is depended on by `foo v0.0.1 ([CWD])`
+",
+        )
+        .run();
+
+    // Other versions are allowed.
+    Package::new("bar", "1.0.1").dep("baz", "0.2").publish();
+    p.cargo("update").masquerade_as_nightly_cargo().run();
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+}
+
+#[cargo_test]
+fn allowed_sources() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("bar", "1.0.0").alternative(true).publish();
+    let git_project = git::new("baz", |project| {
+        project
+            .file("Cargo.toml", &basic_manifest("baz", "0.5.0"))
+            .file("src/lib.rs", "")
+    });
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                cargo-features = ["dependency-policy"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                bar = {{ version = "1.0", registry = "alternative" }}
+                baz = {{ git = '{}' }}
+                local = {{ path = "local" }}
+
+                [workspace.dependency-policy]
+                allow-registries = ["crates-io"]
+                allow-git = ["https://github.com/"]
+                "#,
+                git_project.url()
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file("local/Cargo.toml", &basic_manifest("local", "0.1.0"))
+        .file("local/src/lib.rs", "")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] the dependency graph violates the dependency policy of the workspace, \
+set in `[CWD]/Cargo.toml`
+
+package `bar v1.0.0 (registry `[..]alternative-registry`)` comes from a source \
+which is not allowed
+    ... which is depended on by `foo v0.0.1 ([CWD])`
+
+package `baz v0.5.0 ([..]baz#[..])` comes from a source which is not allowed
+    ... which is depended on by `foo v0.0.1 ([CWD])`
+",
+        )
+        .run();
+
+    // Entries may also be URL prefixes, and registries the name of one in
+    // `[registries]`.
+    p.change_file(
+        "Cargo.toml",
+        &format!(
+            r#"
+            cargo-features = ["dependency-policy"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = {{ version = "1.0", registry = "alternative" }}
+            baz = {{ git = '{}' }}
+            local = {{ path = "local" }}
+
+            [workspace.dependency-policy]
+            allow-registries = ["alternative"]
+            allow-git = ["{}"]
+            "#,
+            git_project.url(),
+            git_project.url()
+        ),
+    );
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+}
+
+#[cargo_test]
+fn allowed_git_prefix_is_whole_path_segments() {
+    let baz = git::repo(&paths::root().join("org/baz"))
+        .file("Cargo.toml", &basic_manifest("baz", "0.5.0"))
+        .file("src/lib.rs", "")
+        .build();
+    let qux = git::repo(&paths::root().join("org-evil/qux"))
+        .file("Cargo.toml", &basic_manifest("qux", "0.5.0"))
+        .file("src/lib.rs", "")
+        .build();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                cargo-features = ["dependency-policy"]
+
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+
+                [dependencies]
+                baz = {{ git = '{}' }}
+                qux = {{ git = '{}' }}
+
+                [workspace.dependency-policy]
+                allow-git = ["{}"]
+                "#,
+                baz.url(),
+                qux.url(),
+                path2url(paths::root().join("org"))
+            ),
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] the dependency graph violates the dependency policy of the workspace, \
+set in `[CWD]/Cargo.toml`
+
+package `qux v0.5.0 ([..]org-evil/qux#[..])` comes from a source which is not allowed
+    ... which is depended on by `foo v0.0.1 ([CWD])`
+",
+        )
+        .with_stderr_does_not_contain("package `baz [..]")
+        .run();
+}
+
+#[cargo_test]
+fn max_versions() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("bar", "1.1.0").publish();
+    Package::new("bar", "2.0.0").publish();
+    Package::new("baz", "1.0.0").dep("bar", "2.0").publish();
+    Package::new("qux", "1.0.0").dep("bar", "1.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["dependency-policy"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "1.0"
+            baz = "1.0"
+            qux = "1.0"
+
+            [workspace.dependency-policy]
+            max-versions = 1
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    // Semver compatible versions are unified, so there are only two copies.
+    p.cargo("check")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] the dependency graph violates the dependency policy of the workspace, \
+set in `[CWD]/Cargo.toml`
+
+package `bar` has 2 semver incompatible versions, but at most 1 is allowed
+
+package `bar v1.1.0`
+    ... which is depended on by `foo v0.0.1 ([CWD])`
+
+package `bar v2.0.0`
+    ... which is depended on by `baz v1.0.0`
+    ... which is depended on by `foo v0.0.1 ([CWD])`
+",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+        cargo-features = ["dependency-policy"]
+
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = "1.0"
+        baz = "1.0"
+        qux = "1.0"
+
+        [workspace.dependency-policy]
+        max-versions = 2
+        "#,
+    );
+    p.cargo("check").masquerade_as_nightly_cargo().run();
+}
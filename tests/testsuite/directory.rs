@@ -4,12 +4,13 @@ use std::collections::HashMap;
 use std::fs;
 use std::str;
 
+use cargo::util::ChecksumAlgorithm;
 use serde::Serialize;
 
 use cargo_test_support::cargo_process;
 use cargo_test_support::git;
 use cargo_test_support::paths;
-use cargo_test_support::registry::{cksum, Package};
+use cargo_test_support::registry::Package;
 use cargo_test_support::{basic_manifest, project, t, ProjectBuilder};
 
 fn setup() {
@@ -30,6 +31,7 @@ fn setup() {
 struct VendorPackage {
     p: Option<ProjectBuilder>,
     cksum: Checksum,
+    algorithm: ChecksumAlgorithm,
 }
 
 #[derive(Serialize)]
@@ -46,14 +48,20 @@ impl VendorPackage {
                 package: Some(String::new()),
                 files: HashMap::new(),
             },
+            algorithm: ChecksumAlgorithm::Sha256,
         }
     }
 
     fn file(&mut self, name: &str, contents: &str) -> &mut VendorPackage {
         self.p = Some(self.p.take().unwrap().file(name, contents));
-        self.cksum
-            .files
-            .insert(name.to_string(), cksum(contents.as_bytes()));
+        let cksum = cargo::util::Checksum::compute(self.algorithm, contents.as_bytes());
+        self.cksum.files.insert(name.to_string(), cksum.to_string());
+        self
+    }
+
+    /// Sets the algorithm of the checksums of the files added afterwards.
+    fn checksum_algorithm(&mut self, algorithm: ChecksumAlgorithm) -> &mut VendorPackage {
+        self.algorithm = algorithm;
         self
     }
 
@@ -479,6 +487,51 @@ the source
         .run();
 }
 
+#[cargo_test]
+fn sha512_file_checksum() {
+    setup();
+
+    VendorPackage::new("bar")
+        .checksum_algorithm(ChecksumAlgorithm::Sha512)
+        .file("Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("src/lib.rs", "")
+        .build();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build").run();
+
+    t!(fs::write(
+        paths::root().join("index/bar/src/lib.rs"),
+        "fn bar() -> u32 { 0 }"
+    ));
+    p.cargo("clean").run();
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+error: the listed checksum of `[..]lib.rs` has changed:
+expected: sha512:[..]
+actual:   sha512:[..]
+",
+        )
+        .run();
+}
+
 #[cargo_test]
 fn only_dot_files_ok() {
     setup();
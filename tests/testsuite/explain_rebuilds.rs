@@ -0,0 +1,170 @@
+//! Tests for the `--explain-rebuilds` flag.
+
+use cargo_test_support::paths::CargoPathExt;
+use cargo_test_support::{basic_manifest, project, Project};
+
+/// A package `foo` depending on the path package `bar`.
+fn foo_and_bar() -> Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+
+                [dependencies]
+                bar = { path = "bar" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() { bar::bar(); }")
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.0.1"))
+        .file("bar/src/lib.rs", "pub fn bar() {}")
+        .build()
+}
+
+#[cargo_test]
+fn explains_first_build() {
+    let p = project().file("src/lib.rs", "").build();
+    p.cargo("build --explain-rebuilds")
+        .with_stderr(
+            "\
+[DIRTY] foo v0.0.1 ([CWD]) (lib): no previous build with the same \
+    profile, features and compiler was found
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    p.cargo("build --explain-rebuilds")
+        .with_stderr("[FINISHED] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn explains_changed_file_and_cascade() {
+    let p = foo_and_bar();
+    p.cargo("build").run();
+    p.root().move_into_the_past();
+    p.root().join("target").move_into_the_past();
+
+    p.change_file("bar/src/lib.rs", "pub fn bar() { }");
+    p.cargo("build --explain-rebuilds")
+        .with_stderr(
+            "\
+[DIRTY] bar v0.0.1 ([CWD]/bar) (lib): the file `[CWD]/bar/src/lib.rs` has changed: \
+    modified at [..], after the last build at [..]
+[COMPILING] bar v0.0.1 ([CWD]/bar)
+[DIRTY] foo v0.0.1 ([CWD]) (bin \"foo\"): the dependency `bar` is rebuilt, \
+    triggered by bar v0.0.1 ([CWD]/bar) (lib)
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn explains_changed_rustflags() {
+    let p = project().file("src/lib.rs", "").build();
+    p.cargo("build").run();
+    p.cargo("build --explain-rebuilds")
+        .env("RUSTFLAGS", "-C debug-assertions")
+        .with_stderr(
+            "\
+[DIRTY] foo v0.0.1 ([CWD]) (lib): RUSTFLAGS has changed: \
+    previously [], now [\"-C\", \"debug-assertions\"]
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn explains_changed_env_var() {
+    let p = project()
+        .file(
+            "src/lib.rs",
+            r#"pub const VAR: Option<&str> = option_env!("EXPLAIN_VAR");"#,
+        )
+        .build();
+    p.cargo("build").run();
+    p.cargo("build --explain-rebuilds")
+        .env("EXPLAIN_VAR", "1")
+        .with_stderr(
+            "\
+[DIRTY] foo v0.0.1 ([CWD]) (lib): the environment variable `EXPLAIN_VAR` has changed: \
+    previously None, now Some(\"1\")
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn explains_changed_profile() {
+    let p = project().file("src/lib.rs", "").build();
+    p.cargo("build").run();
+    p.change_file(
+        "Cargo.toml",
+        r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [profile.dev]
+            opt-level = 1
+        "#,
+    );
+    p.cargo("build --explain-rebuilds")
+        .with_stderr(
+            "\
+[DIRTY] foo v0.0.1 ([CWD]) (lib): no previous build with the same \
+    profile, features and compiler was found
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn json_message() {
+    let p = foo_and_bar();
+    p.cargo("build").run();
+    p.root().move_into_the_past();
+    p.root().join("target").move_into_the_past();
+
+    p.change_file("bar/src/lib.rs", "pub fn bar() { }");
+    p.cargo("build --explain-rebuilds --message-format=json")
+        .with_json_contains_unordered(
+            r#"
+                {
+                    "reason": "compiler-rebuild-reason",
+                    "package_id": "bar 0.0.1 ([..])",
+                    "target": "{...}",
+                    "mode": "build",
+                    "kind": "file-changed",
+                    "message": "the file `[..]lib.rs` has changed: [..]",
+                    "triggered_by": null
+                }
+
+                {
+                    "reason": "compiler-rebuild-reason",
+                    "package_id": "foo 0.0.1 ([..])",
+                    "target": "{...}",
+                    "mode": "build",
+                    "kind": "dependency-rebuilt",
+                    "message": "the dependency `bar` is rebuilt",
+                    "triggered_by": {
+                        "package_id": "bar 0.0.1 ([..])",
+                        "target": "{...}",
+                        "mode": "build"
+                    }
+                }
+            "#,
+        )
+        .run();
+}
@@ -129,14 +129,13 @@ fn invalid4() {
         .with_status(101)
         .with_stderr(
             "\
-error: failed to select a version for `bar`.
-    ... required by package `foo v0.0.1 ([..])`
-versions that meet the requirements `*` are: 0.0.1
+error: failed to select a version for `bar`
 
-the package `foo` depends on `bar`, with features: `bar` but `bar` does not have these features.
-
-
-failed to select a version for `bar` which could resolve this conflict",
+because `bar *` is required by package `foo v0.0.1 ([..])`
+and the versions matching it are 0.0.1
+and the features `bar` of `bar` are enabled by package `foo v0.0.1 ([..])`
+and none of the versions has them
+so no version of `bar` can be selected",
         )
         .run();
 
@@ -333,15 +332,13 @@ fn invalid10() {
         .build();
 
     p.cargo("build").with_stderr("\
-error: failed to select a version for `bar`.
-    ... required by package `foo v0.0.1 ([..])`
-versions that meet the requirements `*` are: 0.0.1
-
-the package `foo` depends on `bar`, with features: `baz` but `bar` does not have these features.
- It has a required dependency with that name, but only optional dependencies can be used as features.
-
+error: failed to select a version for `bar`
 
-failed to select a version for `bar` which could resolve this conflict
+because `bar *` is required by package `foo v0.0.1 ([..])`
+and the versions matching it are 0.0.1
+and `baz` of `bar` is enabled as a feature by package `foo v0.0.1 ([..])`
+and it is a required dependency of every version, but only optional dependencies can be used as features
+so no version of `bar` can be selected
 ").with_status(101)
         .run();
 }
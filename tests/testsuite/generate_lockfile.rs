@@ -218,3 +218,145 @@ fn duplicate_entries_in_lockfile() {
         )
         .run();
 }
+
+#[cargo_test]
+fn merge_conflicts() {
+    Package::new("bar", "1.0.0").publish();
+    Package::new("qux", "1.0.0").publish();
+    Package::new("baz", "0.1.0").publish();
+
+    let manifest = r#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+
+        [dependencies]
+        bar = "1.0"
+        qux = "1.0"
+        baz = "0.1"
+    "#;
+    let p = project()
+        .file("Cargo.toml", manifest)
+        .file("src/lib.rs", "")
+        .build();
+    p.cargo("generate-lockfile").run();
+    let ours = p.read_lockfile();
+
+    // The other side updates `bar` and no longer depends on `baz`.
+    Package::new("bar", "1.1.0").publish();
+    Package::new("qux", "1.0.1").publish();
+    p.change_file("Cargo.toml", &manifest.replace("baz = \"0.1\"", ""));
+    p.cargo("update -p bar").run();
+    let theirs = p.read_lockfile();
+    assert!(theirs.contains("\"bar\"\nversion = \"1.1.0\""));
+    p.change_file("Cargo.toml", manifest);
+
+    let conflicted = |ours: &str, theirs: &str| {
+        format!("<<<<<<< HEAD\n{}=======\n{}>>>>>>> other\n", ours, theirs)
+    };
+    p.change_file("Cargo.lock", &conflicted(&ours, &theirs));
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse lock file at: [..]Cargo.lock
+
+Caused by:
+  the lock file has unresolved merge conflicts
+  Run `cargo generate-lockfile --merge-conflicts` to merge them, \
+keeping the versions locked on either side.
+",
+        )
+        .run();
+
+    p.cargo("generate-lockfile --merge-conflicts")
+        .with_stderr_contains("[MERGING] conflicting changes to Cargo.lock")
+        .run();
+    let merged = p.read_lockfile();
+    assert!(!merged.contains("<<<<<<<"));
+    assert!(merged.contains("\"bar\"\nversion = \"1.1.0\""));
+    assert!(merged.contains("\"qux\"\nversion = \"1.0.0\""));
+    assert!(merged.contains("\"baz\"\nversion = \"0.1.0\""));
+
+    // Which side is which doesn't matter.
+    p.change_file("Cargo.lock", &conflicted(&theirs, &ours));
+    p.cargo("generate-lockfile --merge-conflicts").run();
+    assert_eq!(p.read_lockfile(), merged);
+    p.cargo("build --locked").run();
+}
+
+#[cargo_test]
+fn merge_conflicts_malformed() {
+    let p = project().file("src/lib.rs", "").build();
+    p.cargo("generate-lockfile").run();
+    let lock = p.read_lockfile();
+    p.change_file("Cargo.lock", &format!("<<<<<<< HEAD\n{}=======\n", lock));
+    p.cargo("generate-lockfile --merge-conflicts")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse lock file at: [..]Cargo.lock
+
+Caused by:
+  unterminated merge conflict
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn resolve_conflict_json_report() {
+    Package::new("bad", "1.0.0").publish();
+    Package::new("bad", "1.0.1").publish();
+    Package::new("bar", "0.1.0").dep("bad", "=1.0.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+
+            [dependencies]
+            bar = "0.1.0"
+            bad = "=1.0.1"
+        "#,
+        )
+        .file("src/main.rs", "fn main(){}")
+        .build();
+
+    let report = r#"
+        {
+            "reason": "resolve-conflict",
+            "package": "bad",
+            "requirement": {
+                "dependent": "bar 0.1.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                "req": "=1.0.0",
+                "depended_on_by": ["foo 0.0.1 (path+file:///[..]/foo)"]
+            },
+            "candidates": ["1.0.0"],
+            "conflicts": "{...}",
+            "explanation": "{...}"
+        }
+    "#;
+    p.cargo("generate-lockfile --message-format=json")
+        .with_status(101)
+        .with_json(report)
+        .run();
+    p.cargo("tree --message-format=json")
+        .with_status(101)
+        .with_json(report)
+        .run();
+    p.cargo("metadata --format-version=1")
+        .with_status(101)
+        .with_json(report)
+        .run();
+
+    // Without `--message-format=json` only the error is printed.
+    p.cargo("tree")
+        .with_status(101)
+        .with_stdout("")
+        .with_stderr_contains("error: failed to select a version for `bad`")
+        .run();
+}
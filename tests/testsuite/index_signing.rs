@@ -0,0 +1,307 @@
+//! Tests for verifying the signatures of registry indexes.
+
+use cargo_test_support::registry::{self, IndexSigningKey, Package};
+use cargo_test_support::{git, project, Project};
+use std::fs;
+
+fn alt_project(signing_key: Option<&IndexSigningKey>) -> Project {
+    // Creates the registries, so that the project config is merged with the
+    // one defining them.
+    registry::init();
+    let config = match signing_key {
+        Some(key) => format!(
+            "[registries.alternative]\nsigning-key = '{}'\n",
+            key.public_key()
+        ),
+        None => String::new(),
+    };
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { version = "0.1", registry = "alternative" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file(".cargo/config", &config)
+        .build()
+}
+
+/// Commits a new `config.json` to the alternative registry, with the given
+/// `signing-key`.
+fn write_index_config(signing_key: Option<&IndexSigningKey>) {
+    let mut config = serde_json::json!({
+        "dl": registry::alt_dl_url(),
+        "api": registry::alt_api_url().to_string(),
+    });
+    if let Some(key) = signing_key {
+        config["signing-key"] = key.public_key().into();
+    }
+    let path = registry::alt_registry_path();
+    fs::write(path.join("config.json"), config.to_string()).unwrap();
+    let repo = git2::Repository::open(&path).unwrap();
+    git::add(&repo);
+    git::commit(&repo);
+}
+
+#[cargo_test]
+fn signed_index() {
+    if !IndexSigningKey::is_available() {
+        return;
+    }
+    let key = IndexSigningKey::generate("registry");
+    let p = alt_project(Some(&key));
+    Package::new("bar", "0.1.0").alternative(true).publish();
+    key.sign_index(&registry::alt_registry_path());
+
+    p.cargo("generate-lockfile").run();
+
+    // Unsigned commits are rejected, and the previous version of the index
+    // is kept.
+    Package::new("bar", "0.1.1").alternative(true).publish();
+    p.cargo("update")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] failed to get `bar` as a dependency of package `foo v0.0.1 ([CWD])`
+
+Caused by:
+  failed to load source for dependency `bar`
+
+Caused by:
+  Unable to update registry `[..]alternative-registry`
+
+Caused by:
+  failed to verify the signature of the index
+
+Caused by:
+  commit `[..]` is not signed
+",
+        )
+        .run();
+    // The fetched version wasn't kept, so it's verified again.
+    p.cargo("update")
+        .with_status(101)
+        .with_stderr_contains("  commit `[..]` is not signed")
+        .run();
+    assert!(p.read_lockfile().contains("version = \"0.1.0\""));
+
+    key.sign_index(&registry::alt_registry_path());
+    p.cargo("update")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATING] bar v0.1.0 ([..]) -> v0.1.1
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn wrong_signing_key() {
+    if !IndexSigningKey::is_available() {
+        return;
+    }
+    let key = IndexSigningKey::generate("registry");
+    let other_key = IndexSigningKey::generate("mirror");
+    let p = alt_project(Some(&key));
+    Package::new("bar", "0.1.0").alternative(true).publish();
+    other_key.sign_index(&registry::alt_registry_path());
+
+    p.cargo("generate-lockfile")
+        .with_status(101)
+        .with_stderr(&format!(
+            "\
+[UPDATING] `[..]` index
+[ERROR] failed to get `bar` as a dependency of package `foo v0.0.1 ([CWD])`
+
+Caused by:
+  failed to load source for dependency `bar`
+
+Caused by:
+  Unable to update registry `[..]alternative-registry`
+
+Caused by:
+  failed to verify the signature of the index
+
+Caused by:
+  the signature of commit `[..]` wasn't made with the key `{}`
+  Could not verify signature.
+",
+            key.public_key()
+        ))
+        .run();
+}
+
+#[cargo_test]
+fn signing_key_in_index_config() {
+    if !IndexSigningKey::is_available() {
+        return;
+    }
+    let key = IndexSigningKey::generate("registry");
+    let p = alt_project(None);
+    Package::new("bar", "0.1.0").alternative(true).publish();
+    write_index_config(Some(&key));
+    key.sign_index(&registry::alt_registry_path());
+
+    // The key is trusted when the index is fetched for the first time.
+    p.cargo("generate-lockfile")
+        .with_stderr_contains(&format!(
+            "[WARNING] trusting the signing key `{}` of the `[..]alternative-registry` index \
+             on first use, set `signing-key` in the config of the registry to pin it instead",
+            key.public_key()
+        ))
+        .run();
+
+    // Afterwards, the key of the previous version of the index is used, so
+    // it can't be removed by an unsigned commit.
+    Package::new("bar", "0.1.1").alternative(true).publish();
+    write_index_config(None);
+    p.cargo("update")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] failed to get `bar` as a dependency of package `foo v0.0.1 ([CWD])`
+
+Caused by:
+  failed to load source for dependency `bar`
+
+Caused by:
+  Unable to update registry `[..]alternative-registry`
+
+Caused by:
+  failed to verify the signature of the index
+
+Caused by:
+  commit `[..]` is not signed
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn signing_key_of_source() {
+    if !IndexSigningKey::is_available() {
+        return;
+    }
+    // The test registry replaces crates.io with `source.dummy-registry`, like
+    // a mirror would.
+    let key = IndexSigningKey::generate("registry");
+    Package::new("bar", "0.1.0").publish();
+    key.sign_index(&registry::registry_path());
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            ".cargo/config",
+            &format!(
+                "[source.dummy-registry]\nsigning-key = '{}'\n",
+                key.public_key()
+            ),
+        )
+        .build();
+
+    p.cargo("generate-lockfile").run();
+
+    Package::new("bar", "0.1.1").publish();
+    p.cargo("update")
+        .with_status(101)
+        .with_stderr_contains("  commit `[..]` is not signed")
+        .run();
+}
+
+#[cargo_test]
+fn signing_key_of_registry_from_env() {
+    if !IndexSigningKey::is_available() {
+        return;
+    }
+    // The registry is only defined in the environment, so it isn't in the
+    // `[registries]` table.
+    let key = IndexSigningKey::generate("registry");
+    let p = alt_project(None);
+    Package::new("bar", "0.1.0").alternative(true).publish();
+    p.change_file(
+        "Cargo.toml",
+        r#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [dependencies]
+        bar = { version = "0.1", registry = "from-env" }
+        "#,
+    );
+
+    p.cargo("generate-lockfile")
+        .env(
+            "CARGO_REGISTRIES_FROM_ENV_INDEX",
+            registry::alt_registry_url().as_str(),
+        )
+        .env("CARGO_REGISTRIES_FROM_ENV_SIGNING_KEY", key.public_key())
+        .with_status(101)
+        .with_stderr_contains("  commit `[..]` is not signed")
+        .run();
+
+    key.sign_index(&registry::alt_registry_path());
+    p.cargo("generate-lockfile")
+        .env(
+            "CARGO_REGISTRIES_FROM_ENV_INDEX",
+            registry::alt_registry_url().as_str(),
+        )
+        .env("CARGO_REGISTRIES_FROM_ENV_SIGNING_KEY", key.public_key())
+        .run();
+}
+
+#[cargo_test]
+fn signing_key_of_sparse_registry() {
+    let _server = registry::init_sparse();
+    Package::new("bar", "0.1.0").alternative(true).publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { version = "0.1", registry = "alternative" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            ".cargo/config",
+            "[registries.alternative]\nsigning-key = 'ssh-ed25519 AAAA'\n",
+        )
+        .build();
+
+    p.cargo("generate-lockfile -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  a `signing-key` is set for the sparse registry index \
+             `sparse+http://[..]/alternative-registry`, but only git indexes can be verified",
+        )
+        .run();
+}
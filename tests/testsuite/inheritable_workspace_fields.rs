@@ -0,0 +1,537 @@
+//! Tests for inheriting package fields and dependencies from the workspace.
+
+use cargo_test_support::publish::validate_crate_contents;
+use cargo_test_support::registry::{self, Package};
+use cargo_test_support::{basic_lib_manifest, basic_manifest, git, project};
+use std::fs::File;
+
+#[cargo_test]
+fn requires_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [workspace]
+            members = ["bar"]
+
+            [workspace.package]
+            version = "1.2.3"
+            "#,
+        )
+        .file("bar/Cargo.toml", &basic_manifest("bar", "0.1.0"))
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]/foo/Cargo.toml`
+
+Caused by:
+  feature `workspace-inheritance` is required
+
+  consider adding `cargo-features = [\"workspace-inheritance\"]` to the manifest
+",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+        [workspace]
+        members = ["bar"]
+        "#,
+    );
+    p.change_file(
+        "bar/Cargo.toml",
+        r#"
+        [package]
+        name = "bar"
+        version = { workspace = true }
+        authors = []
+        "#,
+    );
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]/foo/bar/Cargo.toml`
+
+Caused by:
+  failed to inherit `version` from the workspace
+
+Caused by:
+  feature `workspace-inheritance` is required
+
+  consider adding `cargo-features = [\"workspace-inheritance\"]` to the manifest
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn inherit_package_fields() {
+    registry::init();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [workspace]
+            members = ["bar"]
+
+            [workspace.package]
+            version = "1.2.3"
+            authors = ["Rustaceans"]
+            description = "This is a crate"
+            documentation = "https://www.rust-lang.org/learn"
+            homepage = "https://www.rust-lang.org"
+            repository = "https://github.com/example/example"
+            license = "MIT"
+            keywords = ["cli"]
+            categories = ["development-tools"]
+            publish = true
+            edition = "2018"
+            rust-version = "1.40"
+            "#,
+        )
+        .file(
+            "bar/Cargo.toml",
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [package]
+            name = "bar"
+            version = { workspace = true }
+            authors = { workspace = true }
+            description = { workspace = true }
+            documentation = { workspace = true }
+            homepage = { workspace = true }
+            repository = { workspace = true }
+            license = { workspace = true }
+            keywords = { workspace = true }
+            categories = { workspace = true }
+            publish = { workspace = true }
+            edition = { workspace = true }
+            rust-version = { workspace = true }
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] bar v1.2.3 ([CWD]/bar)
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    p.cargo("package --no-verify")
+        .cwd("bar")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let f = File::open(&p.root().join("target/package/bar-1.2.3.crate")).unwrap();
+    let rewritten_toml = format!(
+        r#"{}
+cargo-features = ["workspace-inheritance"]
+
+[package]
+edition = "2018"
+rust-version = "1.40"
+name = "bar"
+version = "1.2.3"
+authors = ["Rustaceans"]
+publish = true
+description = "This is a crate"
+homepage = "https://www.rust-lang.org"
+documentation = "https://www.rust-lang.org/learn"
+keywords = ["cli"]
+categories = ["development-tools"]
+license = "MIT"
+repository = "https://github.com/example/example"
+"#,
+        cargo::core::package::MANIFEST_PREAMBLE,
+    );
+    validate_crate_contents(
+        f,
+        "bar-1.2.3.crate",
+        &["Cargo.toml", "Cargo.toml.orig", "src/lib.rs"],
+        &[("Cargo.toml", &rewritten_toml)],
+    );
+}
+
+#[cargo_test]
+fn inherit_own_workspace_fields() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [package]
+            name = "foo"
+            version = { workspace = true }
+            authors = []
+            license.workspace = true
+
+            [workspace]
+
+            [workspace.package]
+            version = "0.5.0"
+            license = "MIT OR Apache-2.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] foo v0.5.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn inherit_dependencies() {
+    Package::new("dep", "0.1.0")
+        .feature("a", &[])
+        .feature("b", &[])
+        .file("src/lib.rs", "")
+        .publish();
+    Package::new("dep", "0.1.2").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [workspace]
+            members = ["bar"]
+
+            [workspace.dependencies]
+            dep = { version = "=0.1.0", features = ["a"] }
+            baz = { path = "baz", version = "0.5.0" }
+            "#,
+        )
+        .file(
+            "bar/Cargo.toml",
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [package]
+            name = "bar"
+            version = "0.2.0"
+            authors = []
+
+            [dependencies]
+            dep = { workspace = true, features = ["b"], optional = true }
+
+            [dev-dependencies]
+            baz = { workspace = true }
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .file("baz/Cargo.toml", &basic_lib_manifest("baz"))
+        .file("baz/src/lib.rs", "")
+        .build();
+
+    p.cargo("build --features dep")
+        .cwd("bar")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] dep v0.1.0 ([..])
+[COMPILING] dep v0.1.0
+[COMPILING] bar v0.2.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    p.cargo("test --no-run")
+        .cwd("bar")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[COMPILING] baz v0.5.0 ([..]/foo/baz)")
+        .run();
+
+    p.cargo("package --no-verify")
+        .cwd("bar")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let f = File::open(&p.root().join("target/package/bar-0.2.0.crate")).unwrap();
+    let rewritten_toml = format!(
+        r#"{}
+cargo-features = ["workspace-inheritance"]
+
+[package]
+name = "bar"
+version = "0.2.0"
+authors = []
+[dependencies.dep]
+version = "=0.1.0"
+features = ["a", "b"]
+optional = true
+[dev-dependencies.baz]
+version = "0.5.0"
+"#,
+        cargo::core::package::MANIFEST_PREAMBLE,
+    );
+    validate_crate_contents(
+        f,
+        "bar-0.2.0.crate",
+        &["Cargo.toml", "Cargo.toml.orig", "src/lib.rs"],
+        &[("Cargo.toml", &rewritten_toml)],
+    );
+}
+
+#[cargo_test]
+fn inherit_from_workspace_pointer() {
+    let git_project = git::new("dep", |project| {
+        project
+            .file("Cargo.toml", &basic_lib_manifest("dep"))
+            .file("src/lib.rs", "")
+    });
+
+    let p = project()
+        .file(
+            "ws/Cargo.toml",
+            &format!(
+                r#"
+                cargo-features = ["workspace-inheritance"]
+
+                [workspace]
+                members = ["../bar"]
+
+                [workspace.package]
+                version = "3.0.0"
+
+                [workspace.dependencies]
+                dep = {{ git = '{}' }}
+                "#,
+                git_project.url()
+            ),
+        )
+        .file(
+            "bar/Cargo.toml",
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [package]
+            name = "bar"
+            version = { workspace = true }
+            authors = []
+            workspace = "../ws"
+
+            [dependencies]
+            dep = { workspace = true }
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .cwd("bar")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] git repository `[..]`
+[COMPILING] dep v0.5.0 ([..])
+[COMPILING] bar v3.0.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn inherit_path_dependency_in_nested_member() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [workspace]
+            members = ["crates/nested/bar"]
+
+            [workspace.dependencies]
+            baz = { path = "libs/baz" }
+            "#,
+        )
+        .file(
+            "crates/nested/bar/Cargo.toml",
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [package]
+            name = "bar"
+            version = "0.2.0"
+            authors = []
+
+            [dependencies]
+            baz = { workspace = true }
+            "#,
+        )
+        .file("crates/nested/bar/src/lib.rs", "extern crate baz;")
+        .file("libs/baz/Cargo.toml", &basic_lib_manifest("baz"))
+        .file("libs/baz/src/lib.rs", "")
+        .build();
+
+    // The path is relative to the workspace root, not to the member.
+    p.cargo("build")
+        .cwd("crates/nested/bar")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] baz v0.5.0 ([ROOT]/foo/libs/baz)
+[COMPILING] bar v0.2.0 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn errors() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [workspace]
+            members = ["bar"]
+
+            [workspace.package]
+            version = "1.0.0"
+
+            [workspace.dependencies]
+            dep = { path = "dep" }
+            "#,
+        )
+        .file(
+            "bar/Cargo.toml",
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+            license = { workspace = true }
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]/foo/bar/Cargo.toml`
+
+Caused by:
+  `license` was inherited, but `workspace.package.license` is not defined in `[..]/foo/Cargo.toml`
+",
+        )
+        .run();
+
+    let bar_manifest = |dep: &str| {
+        format!(
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [package]
+            name = "bar"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            {}
+            "#,
+            dep
+        )
+    };
+
+    p.change_file(
+        "bar/Cargo.toml",
+        &bar_manifest("other = { workspace = true }"),
+    );
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  `dependency.other` was inherited, but `workspace.dependencies.other` \
+             is not defined in `[..]/foo/Cargo.toml`",
+        )
+        .run();
+
+    p.change_file(
+        "bar/Cargo.toml",
+        &bar_manifest("dep = { workspace = true, version = \"1.0\" }"),
+    );
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  dependency `dep` is inherited from the workspace and cannot specify `version`, \
+             only `features` and `optional` can be used with `workspace = true`",
+        )
+        .run();
+
+    p.change_file(
+        "bar/Cargo.toml",
+        &bar_manifest("dep = { workspace = false }"),
+    );
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("  `workspace` cannot be false for dependency `dep`")
+        .run();
+}
+
+#[cargo_test]
+fn no_workspace_root() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["workspace-inheritance"]
+
+            [package]
+            name = "foo"
+            version = { workspace = true }
+            authors = []
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]/foo/Cargo.toml`
+
+Caused by:
+  failed to find a workspace root to inherit from
+",
+        )
+        .run();
+}
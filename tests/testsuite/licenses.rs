@@ -0,0 +1,216 @@
+//! Tests for the `cargo licenses` command and the `[licenses]` policy.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{project, Project};
+
+fn publish(name: &str, license: &str) {
+    Package::new(name, "1.0.0")
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                [package]
+                name = "{}"
+                version = "1.0.0"
+                authors = []
+                {}
+                "#,
+                name, license
+            ),
+        )
+        .file("src/lib.rs", "")
+        .publish();
+}
+
+fn workspace(config: &str) -> Project {
+    publish("bar", "license = \"MIT OR Apache-2.0\"");
+    publish("baz", "license = \"MIT\"");
+    publish("qux", "license-file = \"LICENSE.txt\"");
+    publish("quux", "");
+    publish("corge", "license = \"MIT or Apache-2.0\"");
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+            license = "MIT"
+
+            [dependencies]
+            bar = "1.0"
+            baz = "1.0"
+            qux = "1.0"
+            quux = "1.0"
+            corge = "1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .file(".cargo/config", config)
+        .build()
+}
+
+#[cargo_test]
+fn text_report() {
+    let p = workspace("");
+    p.cargo("licenses")
+        .with_stdout(
+            "\
+Apache-2.0 (1)
+    bar v1.0.0
+MIT (3)
+    bar v1.0.0
+    baz v1.0.0
+    foo v0.1.0 ([CWD])
+License file only (1)
+    qux v1.0.0 (LICENSE.txt)
+Invalid license expression (1)
+    corge v1.0.0 (`MIT or Apache-2.0`)
+No license (1)
+    quux v1.0.0
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn csv_and_json() {
+    let p = workspace("");
+    p.cargo("licenses --format csv")
+        .with_stdout(
+            "\
+name,version,source,license,license_file,violation
+bar,1.0.0,registry `https://github.com/rust-lang/crates.io-index`,MIT OR Apache-2.0,,
+baz,1.0.0,registry `https://github.com/rust-lang/crates.io-index`,MIT,,
+corge,1.0.0,registry `https://github.com/rust-lang/crates.io-index`,MIT or Apache-2.0,,
+foo,0.1.0,[..],MIT,,
+quux,1.0.0,registry `https://github.com/rust-lang/crates.io-index`,,,
+qux,1.0.0,registry `https://github.com/rust-lang/crates.io-index`,,LICENSE.txt,
+",
+        )
+        .run();
+
+    p.change_file(
+        ".cargo/config",
+        "[licenses]\nallow = [\"MIT\"]\nignore = [\"qux\", \"quux\"]",
+    );
+    p.cargo("licenses --format json")
+        .with_status(101)
+        .with_json(
+            r#"
+            {
+              "version": 1,
+              "packages": [
+                {
+                  "id": "bar 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                  "license": "MIT OR Apache-2.0",
+                  "license_file": null,
+                  "licenses": ["MIT", "Apache-2.0"],
+                  "error": null,
+                  "violation": null
+                },
+                {
+                  "id": "baz 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                  "license": "MIT",
+                  "license_file": null,
+                  "licenses": ["MIT"],
+                  "error": null,
+                  "violation": null
+                },
+                {
+                  "id": "corge 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                  "license": "MIT or Apache-2.0",
+                  "license_file": null,
+                  "licenses": [],
+                  "error": "unexpected `or`",
+                  "violation": "its license `MIT or Apache-2.0` is not a valid SPDX expression"
+                },
+                {
+                  "id": "foo 0.1.0 (path+file://[..])",
+                  "license": "MIT",
+                  "license_file": null,
+                  "licenses": ["MIT"],
+                  "error": null,
+                  "violation": null
+                },
+                {
+                  "id": "quux 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                  "license": null,
+                  "license_file": null,
+                  "licenses": [],
+                  "error": null,
+                  "violation": null
+                },
+                {
+                  "id": "qux 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)",
+                  "license": null,
+                  "license_file": "LICENSE.txt",
+                  "licenses": [],
+                  "error": null,
+                  "violation": null
+                }
+              ]
+            }
+            "#,
+        )
+        .run();
+}
+
+#[cargo_test]
+fn policy() {
+    let p = workspace("[licenses]\nallow = [\"MIT\"]\ndeny = [\"GPL-3.0\"]");
+    p.cargo("licenses -q")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the license policy is violated by 3 packages:
+  corge v1.0.0: its license `MIT or Apache-2.0` is not a valid SPDX expression
+  quux v1.0.0: it has no license
+  qux v1.0.0: it only has a license file (`LICENSE.txt`), \
+add it to `licenses.ignore` once it was reviewed
+",
+        )
+        .run();
+
+    // `bar` can be used under MIT instead.
+    p.change_file(
+        ".cargo/config",
+        "[licenses]\ndeny = [\"Apache-2.0\"]\nignore = [\"corge\", \"qux\", \"quux\"]",
+    );
+    p.cargo("licenses -q").run();
+    p.change_file(
+        ".cargo/config",
+        "[licenses]\ndeny = [\"MIT\"]\nignore = [\"corge\", \"qux\", \"quux\"]",
+    );
+    p.cargo("licenses -q")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] the license policy is violated by 1 package:
+  baz v1.0.0: its license `MIT` is not allowed
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn policy_is_enforced_when_compiling() {
+    let p = workspace("[licenses]\nallow = [\"MIT\"]\nignore = [\"corge\", \"qux\"]");
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] package `quux v1.0.0` doesn't comply with the license policy: it has no license
+Run `cargo licenses` for the licenses of all the dependencies.
+",
+        )
+        .with_stderr_does_not_contain("[COMPILING] [..]")
+        .run();
+
+    p.change_file(
+        ".cargo/config",
+        "[licenses]\nallow = [\"MIT\"]\nignore = [\"corge\", \"qux\", \"quux\"]",
+    );
+    p.cargo("build").run();
+}
@@ -1,7 +1,7 @@
 //! Tests for local-registry sources.
 
 use cargo_test_support::paths::{self, CargoPathExt};
-use cargo_test_support::registry::{registry_path, Package};
+use cargo_test_support::registry::{cksum, registry_path, Package};
 use cargo_test_support::{basic_manifest, project, t};
 use std::fs;
 
@@ -488,3 +488,208 @@ fn crates_io_registry_url_is_optional() {
     p.cargo("build").with_stderr("[FINISHED] [..]").run();
     p.cargo("test").run();
 }
+
+#[cargo_test]
+fn publish_to_local_registry() {
+    setup();
+    let bar = project()
+        .at("bar")
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "bar"
+            homepage = "bar"
+
+            [features]
+            std = []
+        "#,
+        )
+        .file("src/lib.rs", "pub fn bar() {}")
+        .build();
+    bar.cargo("publish --no-verify --to ../registry")
+        .with_stderr(
+            "\
+[PACKAGING] bar v0.0.1 ([CWD])
+[ADDING] bar v0.0.1 ([CWD]) to `[..]registry`
+",
+        )
+        .run();
+
+    // Dependencies are resolved against the local registry, which replaces
+    // crates.io.
+    let foo = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+            license = "MIT"
+            description = "foo"
+            homepage = "foo"
+
+            [dependencies]
+            baz = { version = "0.0.1", package = "bar", features = ["std"] }
+        "#,
+        )
+        .file("src/lib.rs", "pub fn foo() { baz::bar(); }")
+        .build();
+    foo.cargo("publish --to ../registry")
+        .with_stderr(
+            "\
+[PACKAGING] foo v0.1.0 ([CWD])
+[VERIFYING] foo v0.1.0 ([CWD])
+[UNPACKING] bar v0.0.1 ([..])
+[COMPILING] bar v0.0.1
+[COMPILING] foo v0.1.0 ([CWD]/target/package/foo-0.1.0)
+[FINISHED] [..]
+[ADDING] foo v0.1.0 ([CWD]) to `[..]registry`
+",
+        )
+        .run();
+
+    let index = fs::read_to_string(registry_path().join("index/3/b/bar")).unwrap();
+    let entry: serde_json::Value = serde_json::from_str(&index).unwrap();
+    let tarball = fs::read(registry_path().join("bar-0.0.1.crate")).unwrap();
+    assert_eq!(entry["cksum"], cksum(&tarball));
+    assert_eq!(entry["features"], serde_json::json!({"std": []}));
+    assert_eq!(entry["yanked"], false);
+    let index = fs::read_to_string(registry_path().join("index/3/f/foo")).unwrap();
+    let entry: serde_json::Value = serde_json::from_str(&index).unwrap();
+    assert_eq!(
+        entry["deps"],
+        serde_json::json!([{
+            "name": "baz",
+            "req": "^0.0.1",
+            "features": ["std"],
+            "optional": false,
+            "default_features": true,
+            "target": null,
+            "kind": "normal",
+            "registry": null,
+            "package": "bar",
+        }])
+    );
+
+    let p = project()
+        .at("consumer")
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "consumer"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            foo = "0.1"
+        "#,
+        )
+        .file("src/lib.rs", "pub fn consumer() { foo::foo(); }")
+        .build();
+    p.cargo("build")
+        .with_stderr(
+            "\
+[UNPACKING] foo v0.1.0 ([..])
+[COMPILING] bar v0.0.1
+[COMPILING] foo v0.1.0
+[COMPILING] consumer v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    bar.cargo("publish --no-verify --to ../registry")
+        .with_status(101)
+        .with_stderr(
+            "\
+[PACKAGING] bar v0.0.1 ([CWD])
+[ERROR] `bar v0.0.1 ([CWD])` is already in the local registry at `[..]registry`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn yank_from_local_registry() {
+    setup();
+    Package::new("bar", "0.0.1").local(true).publish();
+    Package::new("bar", "0.0.2").local(true).publish();
+    let p = project().build();
+
+    p.cargo("yank --vers 0.0.1 bar --to ../registry")
+        .with_stderr("[..]Yank bar:0.0.1")
+        .run();
+    let yanked = |vers: &str| {
+        let index = fs::read_to_string(registry_path().join("index/3/b/bar")).unwrap();
+        index
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .find(|entry| entry["vers"] == vers)
+            .unwrap()["yanked"]
+            .as_bool()
+            .unwrap()
+    };
+    assert!(yanked("0.0.1"));
+    assert!(!yanked("0.0.2"));
+
+    p.cargo("yank --undo --vers 0.0.1 bar --to ../registry")
+        .with_stderr("[..]Unyank bar:0.0.1")
+        .run();
+    assert!(!yanked("0.0.1"));
+
+    p.cargo("yank --vers 0.1.0 bar --to ../registry")
+        .with_status(101)
+        .with_stderr("[ERROR] `bar v0.1.0` is not in the local registry at `[..]registry`")
+        .run();
+}
+
+#[cargo_test]
+fn publish_to_local_registry_in_registries() {
+    let index = paths::root().join("local-index");
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+            homepage = "foo"
+            publish = ["local"]
+        "#,
+        )
+        .file("src/lib.rs", "")
+        .file(
+            ".cargo/config",
+            &format!(
+                "[registries.local]\nindex = '{}'\n",
+                url::Url::from_file_path(&index).unwrap()
+            ),
+        )
+        .build();
+
+    p.cargo("publish --no-verify")
+        .with_stderr(
+            "\
+[NOTE] Found `local` as only allowed registry. Publishing to it automatically.
+[PACKAGING] foo v0.0.1 ([CWD])
+[ADDING] foo v0.0.1 ([CWD]) to `[ROOT]/local-index`
+",
+        )
+        .run();
+    assert!(index.join("foo-0.0.1.crate").is_file());
+    assert!(index.join("index/3/f/foo").is_file());
+
+    p.cargo("yank --vers 0.0.1 --registry local")
+        .with_stderr("[..]Yank foo:0.0.1")
+        .run();
+}
@@ -13,6 +13,7 @@ extern crate cargo_test_macro;
 
 mod advanced_env;
 mod alt_registry;
+mod audit;
 mod bad_config;
 mod bad_manifest_path;
 mod bench;
@@ -21,12 +22,16 @@ mod build_plan;
 mod build_script;
 mod build_script_env;
 mod cache_messages;
+mod cargo_add;
 mod cargo_alias_config;
 mod cargo_command;
 mod cargo_features;
+mod cargo_remove;
 mod cargo_targets;
+mod cargo_upgrade;
 mod cfg;
 mod check;
+mod checksum_freshness;
 mod clean;
 mod collisions;
 mod concurrent;
@@ -34,15 +39,18 @@ mod config;
 mod config_cli;
 mod config_include;
 mod corrupt_git;
+mod credential_provider;
 mod cross_compile;
 mod cross_publish;
 mod custom_target;
 mod death;
 mod dep_info;
+mod dependency_policy;
 mod directory;
 mod doc;
 mod edition;
 mod error;
+mod explain_rebuilds;
 mod features;
 mod features2;
 mod fetch;
@@ -53,10 +61,13 @@ mod git;
 mod git_auth;
 mod git_gc;
 mod help;
+mod index_signing;
+mod inheritable_workspace_fields;
 mod init;
 mod install;
 mod install_upgrade;
 mod jobserver;
+mod licenses;
 mod list_targets;
 mod local_registry;
 mod locate_project;
@@ -74,6 +85,7 @@ mod net_config;
 mod new;
 mod offline;
 mod out_dir;
+mod outdated;
 mod owner;
 mod package;
 mod package_features;
@@ -93,17 +105,21 @@ mod publish;
 mod publish_lockfile;
 mod read_manifest;
 mod registry;
+mod registry_auth;
 mod rename_deps;
 mod replace;
 mod required_features;
 mod run;
+mod rust_version;
 mod rustc;
 mod rustc_info_cache;
 mod rustdoc;
 mod rustdoc_extern_html;
 mod rustdocflags;
 mod rustflags;
+mod sparse_registry;
 mod search;
+mod shared_cache;
 mod shell_quoting;
 mod standard_lib;
 mod test;
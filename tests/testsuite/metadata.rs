@@ -51,6 +51,7 @@ fn cargo_metadata_simple() {
                 "features": {},
                 "manifest_path": "[..]Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null
             }
         ],
@@ -145,6 +146,7 @@ crate-type = ["lib", "staticlib"]
                 "features": {},
                 "manifest_path": "[..]Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null
             }
         ],
@@ -233,6 +235,7 @@ optional_feat = []
                 },
                 "manifest_path": "[..]Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null
             }
         ],
@@ -320,6 +323,7 @@ fn cargo_metadata_with_deps_and_version() {
                 "links": null,
                 "manifest_path": "[..]Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null,
                 "name": "bar",
                 "readme": null,
@@ -356,6 +360,7 @@ fn cargo_metadata_with_deps_and_version() {
                 "links": null,
                 "manifest_path": "[..]Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null,
                 "name": "baz",
                 "readme": null,
@@ -417,6 +422,7 @@ fn cargo_metadata_with_deps_and_version() {
                 "links": null,
                 "manifest_path": "[..]Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null,
                 "name": "foo",
                 "readme": null,
@@ -453,6 +459,7 @@ fn cargo_metadata_with_deps_and_version() {
                 "links": null,
                 "manifest_path": "[..]Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null,
                 "name": "foobar",
                 "readme": null,
@@ -616,6 +623,7 @@ name = "ex"
                 "features": {},
                 "manifest_path": "[..]Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null
             }
         ],
@@ -705,6 +713,7 @@ crate-type = ["rlib", "dylib"]
                 "features": {},
                 "manifest_path": "[..]Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null
             }
         ],
@@ -792,6 +801,7 @@ fn workspace_metadata() {
                 "features": {},
                 "manifest_path": "[..]bar/Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null
             },
             {
@@ -826,6 +836,7 @@ fn workspace_metadata() {
                 "features": {},
                 "manifest_path": "[..]baz/Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null
             }
         ],
@@ -915,6 +926,7 @@ fn workspace_metadata_no_deps() {
                 "features": {},
                 "manifest_path": "[..]bar/Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null
             },
             {
@@ -949,6 +961,7 @@ fn workspace_metadata_no_deps() {
                 "features": {},
                 "manifest_path": "[..]baz/Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null
             }
         ],
@@ -1009,6 +1022,7 @@ const MANIFEST_OUTPUT: &str = r#"
         "features":{},
         "manifest_path":"[..]Cargo.toml",
         "metadata": null,
+        "rust_version": null,
         "publish": null,
         "readme": null,
         "repository": null
@@ -1197,6 +1211,7 @@ fn package_metadata() {
                         "baz": "quux"
                     }
                 },
+                "rust_version": null,
                 "publish": null
             }
         ],
@@ -1267,6 +1282,7 @@ fn package_publish() {
                 "features": {},
                 "manifest_path": "[..]foo/Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": ["my-registry"]
             }
         ],
@@ -1322,6 +1338,7 @@ fn cargo_metadata_path_to_cargo_toml_project() {
                 "links": null,
                 "manifest_path": "[..]Cargo.toml",
                 "metadata": null,
+                "rust_version": null,
                 "publish": null,
                 "name": "bar",
                 "readme": null,
@@ -1405,6 +1422,7 @@ fn package_edition_2018() {
                     "links": null,
                     "manifest_path": "[..]Cargo.toml",
                     "metadata": null,
+                    "rust_version": null,
                     "publish": null,
                     "name": "foo",
                     "readme": null,
@@ -1492,6 +1510,7 @@ fn target_edition_2018() {
                     "links": null,
                     "manifest_path": "[..]Cargo.toml",
                     "metadata": null,
+                    "rust_version": null,
                     "publish": null,
                     "name": "foo",
                     "readme": null,
@@ -1593,6 +1612,7 @@ fn rename_dependency() {
             "links": null,
             "manifest_path": "[..]",
             "metadata": null,
+            "rust_version": null,
             "publish": null,
             "name": "bar",
             "readme": null,
@@ -1629,6 +1649,7 @@ fn rename_dependency() {
             "links": null,
             "manifest_path": "[..]",
             "metadata": null,
+            "rust_version": null,
             "publish": null,
             "name": "bar",
             "readme": null,
@@ -1690,6 +1711,7 @@ fn rename_dependency() {
             "links": null,
             "manifest_path": "[..]",
             "metadata": null,
+            "rust_version": null,
             "publish": null,
             "name": "foo",
             "readme": null,
@@ -1807,6 +1829,7 @@ fn metadata_links() {
       "links": "a",
       "manifest_path": "[..]/foo/Cargo.toml",
       "metadata": null,
+      "rust_version": null,
       "publish": null,
       "name": "foo",
       "readme": null,
@@ -1930,6 +1953,7 @@ fn deps_with_bin_only() {
       "features": {},
       "manifest_path": "[..]/foo/Cargo.toml",
       "metadata": null,
+      "rust_version": null,
       "publish": null,
       "authors": [],
       "categories": [],
@@ -2030,6 +2054,7 @@ fn filter_platform() {
       "features": {},
       "manifest_path": "[..]/alt-dep-0.0.1/Cargo.toml",
       "metadata": null,
+      "rust_version": null,
       "publish": null,
       "authors": [],
       "categories": [],
@@ -2069,6 +2094,7 @@ fn filter_platform() {
       "features": {},
       "manifest_path": "[..]/cfg-dep-0.0.1/Cargo.toml",
       "metadata": null,
+      "rust_version": null,
       "publish": null,
       "authors": [],
       "categories": [],
@@ -2108,6 +2134,7 @@ fn filter_platform() {
       "features": {},
       "manifest_path": "[..]/host-dep-0.0.1/Cargo.toml",
       "metadata": null,
+      "rust_version": null,
       "publish": null,
       "authors": [],
       "categories": [],
@@ -2147,6 +2174,7 @@ fn filter_platform() {
       "features": {},
       "manifest_path": "[..]/normal-dep-0.0.1/Cargo.toml",
       "metadata": null,
+      "rust_version": null,
       "publish": null,
       "authors": [],
       "categories": [],
@@ -2235,6 +2263,7 @@ fn filter_platform() {
       "features": {},
       "manifest_path": "[..]/foo/Cargo.toml",
       "metadata": null,
+      "rust_version": null,
       "publish": null,
       "authors": [],
       "categories": [],
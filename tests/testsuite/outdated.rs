@@ -0,0 +1,176 @@
+//! Tests for the `cargo outdated` command.
+
+use cargo_test_support::registry::Package;
+use cargo_test_support::{basic_manifest, project};
+
+fn publish_old_versions() {
+    Package::new("baz", "0.1.0").publish();
+    Package::new("bar", "1.0.0").dep("baz", "0.1").publish();
+    Package::new("qux", "0.5.0").publish();
+}
+
+fn publish_new_versions() {
+    Package::new("baz", "0.1.1").publish();
+    Package::new("baz", "0.2.0-alpha.1").publish();
+    Package::new("bar", "1.1.0").dep("baz", "0.1").publish();
+    Package::new("bar", "2.0.0").dep("baz", "0.1").publish();
+    Package::new("bar", "2.1.0").yanked(true).publish();
+}
+
+fn workspace() -> cargo_test_support::Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            bar = "1.0"
+            qux = "0.5"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build()
+}
+
+#[cargo_test]
+fn reports_upgrades() {
+    publish_old_versions();
+    let p = workspace();
+    p.cargo("generate-lockfile").run();
+    publish_new_versions();
+
+    p.cargo("outdated")
+        .with_stdout(
+            "\
+Name  Locked  Compatible  Latest  Kind
+bar   1.0.0   1.1.0       2.0.0   direct
+baz   0.1.0   0.1.1       0.1.1   transitive
+",
+        )
+        .with_stderr("[UPDATING] `[..]` index")
+        .run();
+
+    p.cargo("outdated --root-deps-only")
+        .with_stdout(
+            "\
+Name  Locked  Compatible  Latest  Kind
+bar   1.0.0   1.1.0       2.0.0   direct
+",
+        )
+        .run();
+
+    // The lock file is left alone.
+    assert!(p
+        .read_lockfile()
+        .contains("name = \"bar\"\nversion = \"1.0.0\""));
+
+    p.cargo("update").run();
+    p.cargo("outdated")
+        .with_stdout(
+            "\
+Name  Locked  Compatible  Latest  Kind
+bar   1.1.0   1.1.0       2.0.0   direct
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn json_output() {
+    publish_old_versions();
+    let p = workspace();
+    p.cargo("generate-lockfile").run();
+    publish_new_versions();
+
+    p.cargo("outdated --format json")
+        .with_json(
+            r#"
+            {
+              "version": 1,
+              "packages": [
+                {
+                  "name": "bar",
+                  "source": "registry+https://github.com/rust-lang/crates.io-index",
+                  "locked": "1.0.0",
+                  "compatible": "1.1.0",
+                  "latest": "2.0.0",
+                  "kind": "direct",
+                  "dependents": ["foo 0.1.0 (path+file://[..])"]
+                },
+                {
+                  "name": "baz",
+                  "source": "registry+https://github.com/rust-lang/crates.io-index",
+                  "locked": "0.1.0",
+                  "compatible": "0.1.1",
+                  "latest": "0.1.1",
+                  "kind": "transitive",
+                  "dependents": ["bar 1.0.0 (registry+https://github.com/rust-lang/crates.io-index)"]
+                }
+              ]
+            }
+            "#,
+        )
+        .run();
+}
+
+#[cargo_test]
+fn offline_uses_cached_index() {
+    publish_old_versions();
+    let p = workspace();
+    p.cargo("generate-lockfile").run();
+    publish_new_versions();
+    p.cargo("outdated").run();
+
+    // Versions published after the index was last fetched aren't known, but
+    // versions which were never downloaded are.
+    Package::new("bar", "3.0.0").publish();
+    p.cargo("outdated --offline")
+        .with_stdout(
+            "\
+Name  Locked  Compatible  Latest  Kind
+bar   1.0.0   1.1.0       2.0.0   direct
+baz   0.1.0   0.1.1       0.1.1   transitive
+",
+        )
+        .with_stderr("")
+        .run();
+}
+
+#[cargo_test]
+fn up_to_date() {
+    publish_old_versions();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [workspace]
+            members = ["a", "b"]
+            "#,
+        )
+        .file(
+            "a/Cargo.toml",
+            r#"
+            [package]
+            name = "a"
+            version = "0.1.0"
+            authors = []
+
+            [dependencies]
+            qux = "0.5"
+            b = { path = "../b" }
+            "#,
+        )
+        .file("a/src/lib.rs", "")
+        .file("b/Cargo.toml", &basic_manifest("b", "0.1.0"))
+        .file("b/src/lib.rs", "")
+        .build();
+
+    p.cargo("outdated")
+        .with_stdout("")
+        .with_stderr_contains("note: all dependencies are up to date")
+        .run();
+}
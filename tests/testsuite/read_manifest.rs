@@ -35,6 +35,7 @@ fn manifest_output(readme_value: &str) -> String {
     "features":{{}},
     "manifest_path":"[..]Cargo.toml",
     "metadata": null,
+    "rust_version": null,
     "publish": null
 }}"#,
         readme_value
@@ -1,6 +1,7 @@
 //! Tests for normal registry dependencies.
 
 use cargo::util::paths::remove_dir_all;
+use cargo::util::{Checksum, ChecksumAlgorithm};
 use cargo_test_support::cargo_process;
 use cargo_test_support::git;
 use cargo_test_support::paths::{self, CargoPathExt};
@@ -285,6 +286,157 @@ Caused by:
         .run();
 }
 
+/// Replaces the checksum of every version of the package at `path` in the
+/// index of the test registry.
+fn rewrite_cksum(path: &str, from: &str, to: &str) {
+    let path = registry_path().join(path);
+    let contents = fs::read_to_string(&path).unwrap();
+    fs::write(&path, contents.replace(from, to)).unwrap();
+    let repo = git2::Repository::open(registry_path()).unwrap();
+    git::add(&repo);
+    git::commit(&repo);
+}
+
+#[cargo_test]
+fn sha512_cksum() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+            bad-cksum = "0.1.0"
+        "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    Package::new("bar", "0.1.0")
+        .checksum_algorithm(ChecksumAlgorithm::Sha512)
+        .publish();
+    let mut pkg = Package::new("bad-cksum", "0.1.0");
+    pkg.checksum_algorithm(ChecksumAlgorithm::Sha512).publish();
+    t!(File::create(&pkg.archive_dst()));
+
+    p.cargo("fetch")
+        .with_status(101)
+        .with_stderr_contains(
+            "  failed to verify the checksum of `bad-cksum v0.1.0 (registry `[ROOT][..]`)`",
+        )
+        .run();
+    let lockfile = p.read_lockfile();
+    assert!(lockfile.contains("checksum = \"sha512:"));
+
+    // Other algorithms are rejected, rather than skipping the verification.
+    let cksum = Package::new("bad-cksum", "0.1.1").publish();
+    rewrite_cksum("ba/d-/bad-cksum", &cksum, &format!("md5:{}", cksum));
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml")
+            .replace("bad-cksum = \"0.1.0\"", "bad-cksum = \"0.1.1\""),
+    );
+    p.cargo("fetch")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+Caused by:
+  failed to verify the checksum of `bad-cksum v0.1.1 (registry `[ROOT][..]`)`
+
+Caused by:
+  unsupported checksum algorithm `md5`, expected `sha256`, `sha512` or `blake3`
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn cksum_algorithm_upgrade() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1.0"
+        "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    let pkg = Package::new("bar", "0.1.0");
+    let sha256 = pkg.publish();
+    p.cargo("generate-lockfile").run();
+
+    // The registry moves to SHA-512, which changes the checksums of all the
+    // packages, so they can't be compared with the ones of the lock file.
+    let archive = fs::read(pkg.archive_dst()).unwrap();
+    let sha512 = Checksum::compute(ChecksumAlgorithm::Sha512, &archive).to_string();
+    rewrite_cksum("3/b/bar", &sha256, &sha512);
+    // A new dependency makes Cargo fetch the index again.
+    Package::new("baz", "0.1.0").publish();
+    p.change_file(
+        "Cargo.toml",
+        &p.read_file("Cargo.toml")
+            .replace("bar = \"0.1.0\"", "bar = \"0.1.0\"\nbaz = \"0.1.0\""),
+    );
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] checksum for `bar v0.1.0` changed from a `sha256` checksum to a `sha512` \
+checksum between lock files
+",
+        )
+        .with_stderr_contains(
+            "\
+if the source is trusted, run `cargo update -p bar:0.1.0 --precise 0.1.0` to accept \
+the new checksum of this package, or `cargo update` to accept the new checksums of \
+all packages
+",
+        )
+        .run();
+    assert!(p.read_lockfile().contains(&sha256));
+
+    // The new checksums are only accepted on purpose, here by updating the
+    // package to the version it's already locked to.
+    p.cargo("update -p bar:0.1.0 --precise 0.1.0")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ADDING] baz v0.1.0
+",
+        )
+        .run();
+    assert!(p.read_lockfile().contains(&sha512));
+    p.cargo("build").run();
+
+    // Whatever the algorithm.
+    let blake3 = Checksum::compute(ChecksumAlgorithm::Blake3, &archive).to_string();
+    rewrite_cksum("3/b/bar", &sha512, &blake3);
+    Package::new("baz", "0.1.1").publish();
+    p.cargo("update -p baz")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+[ERROR] checksum for `bar v0.1.0` changed from a `sha512` checksum to a `blake3` \
+checksum between lock files
+",
+        )
+        .run();
+    p.cargo("update").run();
+    assert!(p.read_lockfile().contains(&blake3));
+    p.cargo("build").run();
+}
+
 #[cargo_test]
 fn update_registry() {
     Package::new("init", "0.0.1").publish();
@@ -1915,6 +2067,108 @@ Caused by:
         .run();
 }
 
+#[cargo_test]
+fn links_in_packages_rejected() {
+    Package::new("foo", "0.2.0")
+        .symlink("src/secret.rs", "/etc/passwd")
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "fo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            foo = "0.2"
+        "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] [..]
+[DOWNLOADING] crates ...
+[DOWNLOADED] [..]
+error: failed to download [..]
+
+Caused by:
+  failed to unpack package `foo v0.2.0 ([..])`
+
+Caused by:
+  invalid tarball downloaded, contains a symlink at `foo-0.2.0/src/secret.rs`, \
+only files and directories are allowed
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn unpack_limits() {
+    Package::new("foo", "0.2.0")
+        .file("src/lib.rs", &"// padding\n".repeat(100))
+        .file("src/a.rs", "")
+        .file("src/b.rs", "")
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "fo"
+            version = "0.5.0"
+            authors = []
+
+            [dependencies]
+            foo = "0.2"
+        "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("fetch")
+        .env("CARGO_REGISTRY_MAX_UNPACK_SIZE", "1000")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] [..]
+[DOWNLOADING] crates ...
+[DOWNLOADED] [..]
+error: failed to download [..]
+
+Caused by:
+  failed to unpack package `foo v0.2.0 ([..])`
+
+Caused by:
+  invalid tarball downloaded, unpacks to more than 1000 bytes \
+(the limit was reached at `foo-0.2.0/src/lib.rs`)
+  The limit is set by the `registry.max-unpack-size` config value.
+",
+        )
+        .run();
+
+    p.cargo("fetch")
+        .env("CARGO_REGISTRY_MAX_UNPACK_ENTRIES", "3")
+        .with_status(101)
+        .with_stderr_contains(
+            "  invalid tarball downloaded, contains more than 3 entries \
+(the limit was reached at `foo-0.2.0/src/b.rs`)",
+        )
+        .with_stderr_contains(
+            "  The limit is set by the `registry.max-unpack-entries` config value.",
+        )
+        .run();
+
+    p.cargo("fetch").run();
+}
+
 #[cargo_test]
 fn git_init_templatedir_missing() {
     Package::new("foo", "0.2.0").dep("bar", "*").publish();
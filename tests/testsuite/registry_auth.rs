@@ -0,0 +1,250 @@
+//! Tests for registries which require authentication for every request.
+
+use cargo_test_support::registry::{self, HttpServer, Package};
+use cargo_test_support::{git, paths, project, Project};
+use std::fs;
+
+const TOKEN: &str = "sekrit";
+
+fn make_project() -> Project {
+    project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { version = "0.1", registry = "alternative" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build()
+}
+
+/// Makes the `alternative` registry require authentication, downloading
+/// `.crate` files from `server`.
+fn write_index_config(server: &HttpServer) {
+    let config = serde_json::json!({
+        "dl": format!("{}/alt_dl/{{crate}}/{{version}}/{{crate}}-{{version}}.crate", server.url()),
+        "api": registry::alt_api_url().to_string(),
+        "auth-required": true,
+    });
+    let path = registry::alt_registry_path();
+    fs::write(path.join("config.json"), config.to_string()).unwrap();
+    let repo = git2::Repository::open(&path).unwrap();
+    git::add(&repo);
+    git::commit(&repo);
+}
+
+#[cargo_test]
+fn git_index_auth_required() {
+    registry::init();
+    let server = HttpServer::with_token(TOKEN);
+    Package::new("bar", "0.1.0").alternative(true).publish();
+    write_index_config(&server);
+    let p = make_project();
+
+    p.cargo("fetch")
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[ERROR] failed to download `bar v0.1.0 ([..])`
+
+Caused by:
+  unable to get packages from source
+
+Caused by:
+  the registry `alternative` requires authentication, but no token was found for it
+  Run `cargo login --registry alternative` to save one.
+",
+        )
+        .run();
+
+    p.cargo("fetch")
+        .env("CARGO_REGISTRIES_ALTERNATIVE_TOKEN", TOKEN)
+        .with_stderr(
+            "\
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.1.0 (registry `[..]`)
+",
+        )
+        .run();
+    assert_eq!(server.requests(), ["/alt_dl/bar/0.1.0/bar-0.1.0.crate 200"]);
+}
+
+#[cargo_test]
+fn sparse_index_auth_required() {
+    registry::init();
+    let server = HttpServer::with_token(TOKEN);
+    Package::new("bar", "0.1.0").alternative(true).publish();
+    write_index_config(&server);
+    fs::write(
+        paths::home().join(".cargo/config"),
+        format!(
+            "[registries.alternative]\nindex = 'sparse+{}/alternative-registry'\n",
+            server.url()
+        ),
+    )
+    .unwrap();
+    let p = make_project();
+
+    p.cargo("fetch -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+Caused by:
+  the registry `alternative` requires authentication, but no token was found for it
+  Run `cargo login --registry alternative` to save one.
+",
+        )
+        .run();
+
+    // The server refuses the first request, then Cargo knows from the
+    // `config.json` that the token has to be sent with every request.
+    p.cargo("fetch -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_REGISTRIES_ALTERNATIVE_TOKEN", TOKEN)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.1.0 (registry `[..]`)
+",
+        )
+        .run();
+    assert_eq!(
+        server.requests()[1..],
+        [
+            "/alternative-registry/3/b/bar 401",
+            "/alternative-registry/3/b/bar 200",
+            "/alternative-registry/config.json 200",
+            "/alt_dl/bar/0.1.0/bar-0.1.0.crate 200",
+        ]
+    );
+
+    p.cargo("update -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .env("CARGO_REGISTRIES_ALTERNATIVE_TOKEN", "wrong")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+Caused by:
+  failed to get 200 response from `[..]/alternative-registry/3/b/bar`, got 401
+",
+        )
+        .run();
+}
+
+/// Prepares the index of the `alternative` registry to be served with git's
+/// "dumb" HTTP protocol.
+fn serve_index_with_git() {
+    cargo_test_support::process("git")
+        .arg("update-server-info")
+        .cwd(registry::alt_registry_path())
+        .exec_with_output()
+        .unwrap();
+}
+
+#[cargo_test]
+fn git_index_auth_required_first_clone() {
+    registry::init();
+    let server = HttpServer::with_token(TOKEN);
+    Package::new("bar", "0.1.0").alternative(true).publish();
+    write_index_config(&server);
+    serve_index_with_git();
+    fs::write(
+        paths::home().join(".cargo/config"),
+        format!(
+            "[net]\n\
+             git-fetch-with-cli = true\n\
+             [registries.alternative]\n\
+             index = '{}/alternative-registry/.git'\n",
+            server.url()
+        ),
+    )
+    .unwrap();
+    let p = make_project();
+
+    // The index wasn't fetched yet, so Cargo can't know that it requires
+    // authentication until the server refuses the first request.
+    p.cargo("fetch")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+Caused by:
+  the registry `alternative` requires authentication, but no token was found for it
+  Run `cargo login --registry alternative` to save one.
+",
+        )
+        .run();
+
+    p.cargo("fetch")
+        .env("CARGO_REGISTRIES_ALTERNATIVE_TOKEN", TOKEN)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.1.0 (registry `[..]`)
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn git_index_auth_required_first_clone_of_env_registry() {
+    registry::init();
+    let server = HttpServer::with_token(TOKEN);
+    Package::new("bar", "0.1.0").alternative(true).publish();
+    write_index_config(&server);
+    serve_index_with_git();
+    // The registry is only defined in the environment.
+    fs::write(
+        paths::home().join(".cargo/config"),
+        "[net]\ngit-fetch-with-cli = true\n",
+    )
+    .unwrap();
+    let p = make_project();
+
+    p.cargo("fetch")
+        .env(
+            "CARGO_REGISTRIES_ALTERNATIVE_INDEX",
+            format!("{}/alternative-registry/.git", server.url()),
+        )
+        .env("CARGO_REGISTRIES_ALTERNATIVE_TOKEN", TOKEN)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.1.0 (registry `[..]`)
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn git_index_fetch_failure_is_not_an_auth_failure() {
+    registry::init();
+    fs::write(
+        paths::home().join(".cargo/config"),
+        "[net]\n\
+         git-fetch-with-cli = true\n\
+         retry = 0\n\
+         [registries.alternative]\n\
+         index = 'http://127.0.0.1:1/alternative-registry'\n",
+    )
+    .unwrap();
+    let p = make_project();
+
+    // Nothing asked for credentials, so this isn't reported as missing a
+    // token, even though `git` failed with its usual status of 128.
+    p.cargo("fetch")
+        .with_status(101)
+        .with_stderr_does_not_contain("[..]requires authentication[..]")
+        .with_stderr_contains("[..]process didn't exit successfully: `git fetch[..]")
+        .run();
+}
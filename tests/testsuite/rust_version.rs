@@ -0,0 +1,470 @@
+//! Tests for the `rust-version` manifest field.
+
+use cargo_test_support::registry::{self, Package};
+use cargo_test_support::{project, publish};
+
+#[cargo_test]
+fn rust_version_satisfied() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            rust-version = "1.1.1"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build").run();
+    p.cargo("build --ignore-rust-version").run();
+}
+
+#[cargo_test]
+fn rust_version_bad_values() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            rust-version = "^1.43"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  `rust-version` must be a version like \"1.44\" or \"1.44.1\", found `^1.43`
+",
+        )
+        .run();
+
+    for bad in &["1", "1.43-beta.1", "1.43.0.1", "1..2"] {
+        p.change_file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                [package]
+                name = "foo"
+                version = "0.0.1"
+                authors = []
+                rust-version = "{}"
+                "#,
+                bad
+            ),
+        );
+        p.cargo("build")
+            .with_status(101)
+            .with_stderr_contains(&format!(
+                "  `rust-version` must be a version like \"1.44\" or \"1.44.1\", found `{}`",
+                bad
+            ))
+            .run();
+    }
+}
+
+#[cargo_test]
+fn rust_version_too_high() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            rust-version = "1.9876.0"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] package `foo v0.0.1 ([..])` cannot be built because it requires \
+rustc 1.9876.0 or newer, while the currently active rustc version is [..]
+",
+        )
+        .run();
+    p.cargo("check --ignore-rust-version").run();
+}
+
+#[cargo_test]
+fn rust_version_dependency_fails() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { path = "bar" }
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .file(
+            "bar/Cargo.toml",
+            r#"
+            [package]
+            name = "bar"
+            version = "0.0.1"
+            authors = []
+            rust-version = "1.9876"
+            "#,
+        )
+        .file("bar/src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] package `bar v0.0.1 ([..]/foo/bar)` cannot be built because it requires \
+rustc 1.9876 or newer, while the currently active rustc version is [..]
+",
+        )
+        .run();
+    p.cargo("build --ignore-rust-version").run();
+}
+
+#[cargo_test]
+fn rust_version_in_metadata() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            rust-version = "1.44"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    let output = p
+        .cargo("metadata --no-deps --format-version 1")
+        .exec_with_output()
+        .unwrap();
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(metadata["packages"][0]["rust_version"], "1.44");
+}
+
+#[cargo_test]
+fn rust_version_is_published() {
+    registry::init();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            license = "MIT"
+            description = "foo"
+            rust-version = "1.44"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("publish --no-verify --token sekrit").run();
+
+    publish::validate_upload(
+        r#"
+        {
+          "authors": [],
+          "badges": {},
+          "categories": [],
+          "deps": [],
+          "description": "foo",
+          "documentation": null,
+          "features": {},
+          "homepage": null,
+          "keywords": [],
+          "license": "MIT",
+          "license_file": null,
+          "links": null,
+          "name": "foo",
+          "readme": null,
+          "readme_file": null,
+          "repository": null,
+          "rust_version": "1.44",
+          "vers": "0.0.1"
+        }
+        "#,
+        "foo-0.0.1.crate",
+        &["Cargo.lock", "Cargo.toml", "Cargo.toml.orig", "src/main.rs"],
+    );
+}
+
+#[cargo_test]
+fn resolve_with_incompatible_rust_versions_fallback() {
+    Package::new("bar", "1.0.0").rust_version("1.40").publish();
+    Package::new("bar", "1.1.0")
+        .rust_version("1.9876")
+        .publish();
+    Package::new("bar", "2.0.0")
+        .rust_version("1.9876")
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["msrv-policy"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            rust-version = "1.45"
+
+            [dependencies]
+            bar = "1.0"
+
+            [workspace]
+            incompatible-rust-versions = "fallback"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("generate-lockfile")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[HOLDING] bar v1.0.0 back, v1.1.0 requires rustc 1.9876
+",
+        )
+        .run();
+    assert!(p
+        .read_lockfile()
+        .contains("name = \"bar\"\nversion = \"1.0.0\""));
+
+    p.cargo("update")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[HOLDING] bar v1.0.0 back, v1.1.0 requires rustc 1.9876
+",
+        )
+        .run();
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[COMPILING] bar v1.0.0")
+        .run();
+
+    // Without the setting, the newest version is picked.
+    p.change_file(
+        "Cargo.toml",
+        r#"
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+        rust-version = "1.45"
+
+        [dependencies]
+        bar = "1.0"
+        "#,
+    );
+    p.cargo("update")
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATING] bar v1.0.0 -> v1.1.0
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn held_back_only_reports_the_fallback() {
+    Package::new("bar", "1.0.0").rust_version("1.40").publish();
+    Package::new("bar", "1.1.0").rust_version("1.40").publish();
+    Package::new("bar", "1.2.0")
+        .rust_version("1.9876")
+        .publish();
+    Package::new("baz", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["msrv-policy"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+            rust-version = "1.45"
+
+            [dependencies]
+            bar = "1.0"
+            baz = "0.1"
+
+            [workspace]
+            incompatible-rust-versions = "fallback"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("generate-lockfile")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[HOLDING] bar v1.1.0 back, v1.2.0 requires rustc 1.9876
+",
+        )
+        .run();
+
+    // An older version than the newest compatible one isn't held back by
+    // the fallback.
+    p.cargo("update -p bar --precise 1.0.0")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATING] bar v1.1.0 -> v1.0.0
+",
+        )
+        .run();
+
+    // Neither is a version kept by the lock file.
+    Package::new("baz", "0.1.1").publish();
+    p.cargo("update -p baz")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATING] baz v0.1.0 -> v0.1.1
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn resolve_falls_back_to_incompatible_versions() {
+    Package::new("bar", "1.0.0")
+        .rust_version("1.9876")
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            cargo-features = ["msrv-policy"]
+
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "1.0"
+
+            [workspace]
+            incompatible-rust-versions = "fallback"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    // Nothing is compatible with the active rustc, so bar is picked anyway
+    // and only fails when building.
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v1.0.0 ([..])
+[ERROR] package `bar v1.0.0` cannot be built because it requires rustc 1.9876 or newer, \
+while the currently active rustc version is [..]
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn incompatible_rust_versions_requires_feature() {
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [package]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [workspace]
+            incompatible-rust-versions = "fallback"
+            "#,
+        )
+        .file("src/main.rs", "fn main() {}")
+        .build();
+
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr(
+            "\
+[ERROR] failed to parse manifest at `[..]`
+
+Caused by:
+  feature `msrv-policy` is required
+
+  consider adding `cargo-features = [\"msrv-policy\"]` to the manifest
+",
+        )
+        .run();
+
+    p.change_file(
+        "Cargo.toml",
+        r#"
+        cargo-features = ["msrv-policy"]
+
+        [package]
+        name = "foo"
+        version = "0.0.1"
+        authors = []
+
+        [workspace]
+        incompatible-rust-versions = "newest"
+        "#,
+    );
+    p.cargo("build")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "  `incompatible-rust-versions` setting `newest` is not valid, \
+             valid options are \"allow\" and \"fallback\"",
+        )
+        .run();
+}
@@ -0,0 +1,295 @@
+//! Tests for the `-Z shared-cache` artifact cache.
+
+use cargo_test_support::paths;
+use cargo_test_support::registry::{HttpServer, Package};
+use cargo_test_support::{basic_manifest, project, Project};
+use std::fs;
+use std::net::TcpListener;
+use std::path::PathBuf;
+
+fn cache_dir() -> PathBuf {
+    paths::root().join("shared-cache")
+}
+
+/// A workspace at `name` depending on `bar`, `baz` and the path package
+/// `local`, where `baz` has a build script.
+fn workspace(name: &str) -> Project {
+    project()
+        .at(name)
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "0.1.0"
+                baz = "0.1.0"
+                local = { path = "local" }
+            "#,
+        )
+        .file(
+            "src/main.rs",
+            "fn main() { println!(\"{} {} {}\", bar::bar(), baz::baz(), local::local()); }",
+        )
+        .file("local/Cargo.toml", &basic_manifest("local", "0.1.0"))
+        .file("local/src/lib.rs", "pub fn local() -> u32 { 3 }")
+        .build()
+}
+
+fn publish_deps() {
+    Package::new("bar", "0.1.0")
+        .file("src/lib.rs", "pub fn bar() -> u32 { 1 }")
+        .publish();
+    Package::new("baz", "0.1.0")
+        .file("build.rs", "fn main() {}")
+        .file("src/lib.rs", "pub fn baz() -> u32 { 2 }")
+        .publish();
+}
+
+fn entries() -> Vec<String> {
+    let mut entries: Vec<String> = fs::read_dir(cache_dir().join("entries"))
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    entries.sort();
+    entries
+}
+
+#[cargo_test]
+fn restores_registry_packages() {
+    publish_deps();
+    let a = workspace("a");
+    a.cargo("run -Zshared-cache")
+        .env("CARGO_BUILD_SHARED_CACHE", cache_dir())
+        .masquerade_as_nightly_cargo()
+        .with_stdout("1 2 3")
+        .run();
+
+    // `bar` and the build script of `baz` are cached, but `baz` depends on
+    // the output of its build script, and `local` is a path package.
+    let entries = entries();
+    assert_eq!(entries.len(), 2, "{:?}", entries);
+    assert!(entries[0].starts_with("bar-"));
+    assert!(entries[1].starts_with("baz-"));
+
+    let b = workspace("b");
+    b.cargo("run -Zshared-cache -v")
+        .env("CARGO_BUILD_SHARED_CACHE", cache_dir())
+        .masquerade_as_nightly_cargo()
+        .with_stdout("1 2 3")
+        .with_stderr_contains("[..]Restored bar v0.1.0 (lib) from the shared cache")
+        .with_stderr_contains("[..]Restored baz v0.1.0 (custom-build) from the shared cache")
+        .with_stderr_contains("[RUNNING] `[..]build-script-build`")
+        .with_stderr_contains("[RUNNING] `rustc --crate-name baz [..]")
+        .with_stderr_contains("[RUNNING] `rustc --crate-name local [..]")
+        .with_stderr_does_not_contain("[..]--crate-name bar[..]")
+        .with_stderr_does_not_contain("[..]--crate-name build_script_build[..]")
+        .run();
+
+    // The restored outputs are up to date.
+    b.cargo("build -Zshared-cache -v")
+        .env("CARGO_BUILD_SHARED_CACHE", cache_dir())
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[FRESH] bar v0.1.0")
+        .with_stderr_contains("[FRESH] baz v0.1.0")
+        .with_stderr_does_not_contain("[..]Restored[..]")
+        .with_stderr_does_not_contain("[..]rustc --crate-name[..]")
+        .run();
+}
+
+#[cargo_test]
+fn different_profiles_are_different_entries() {
+    Package::new("bar", "0.1.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                bar = "0.1.0"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Zshared-cache")
+        .env("CARGO_BUILD_SHARED_CACHE", cache_dir())
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("build -Zshared-cache --release -v")
+        .env("CARGO_BUILD_SHARED_CACHE", cache_dir())
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[RUNNING] `rustc --crate-name bar [..]")
+        .run();
+    assert_eq!(entries().len(), 2);
+}
+
+#[cargo_test]
+fn evicts_entries_beyond_max_size() {
+    publish_deps();
+    workspace("a")
+        .cargo("build -Zshared-cache")
+        .env("CARGO_BUILD_SHARED_CACHE", cache_dir())
+        .env("CARGO_BUILD_SHARED_CACHE_MAX_SIZE", "1")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(entries().is_empty());
+}
+
+#[cargo_test]
+fn requires_unstable_flag() {
+    publish_deps();
+    workspace("a")
+        .cargo("build")
+        .env("CARGO_BUILD_SHARED_CACHE", cache_dir())
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert!(!cache_dir().exists());
+}
+
+/// Builds `p` with the remote cache at `url`, and a shared cache of its own.
+fn build_with_remote(p: &Project, url: &str, mode: &str) -> cargo_test_support::Execs {
+    let mut execs = p.cargo("build -Zshared-cache -v");
+    execs
+        .env("CARGO_BUILD_SHARED_CACHE", p.root().join("shared-cache"))
+        .env("CARGO_BUILD_REMOTE_CACHE", url)
+        .env("CARGO_BUILD_REMOTE_CACHE_MODE", mode)
+        .masquerade_as_nightly_cargo();
+    execs
+}
+
+#[cargo_test]
+fn remote_cache() {
+    publish_deps();
+    let server = HttpServer::new();
+    let url = format!("{}/remote-cache", server.url());
+
+    build_with_remote(&workspace("a"), &url, "read-write")
+        .with_stderr_contains("[RUNNING] `rustc --crate-name bar [..]")
+        .run();
+    let mut uploads: Vec<_> = server
+        .requests()
+        .into_iter()
+        .filter(|r| r.ends_with(" 201"))
+        .map(|r| {
+            let name = r
+                .trim_start_matches("/remote-cache/")
+                .trim_end_matches(" 201");
+            let (pkg, _) = name.split_at(name.find('-').unwrap());
+            let (_, ext) = name.split_at(name.find('.').unwrap());
+            format!("{}{}", pkg, ext)
+        })
+        .collect();
+    uploads.sort();
+    assert_eq!(
+        uploads,
+        ["bar.cksum", "bar.tar.gz", "baz.cksum", "baz.tar.gz",]
+    );
+
+    // Another machine, with an empty shared cache.
+    let b = workspace("b");
+    build_with_remote(&b, &url, "read-only")
+        .with_stderr_contains("[..]Restored bar v0.1.0 (lib) from the remote cache")
+        .with_stderr_contains("[..]Restored baz v0.1.0 (custom-build) from the remote cache")
+        .with_stderr_does_not_contain("[..]--crate-name bar[..]")
+        .run();
+    b.process(&b.bin("foo")).with_stdout("1 2 3").run();
+    // Nothing is uploaded by read-only builds.
+    assert_eq!(
+        server
+            .requests()
+            .iter()
+            .filter(|r| r.ends_with(" 201"))
+            .count(),
+        4
+    );
+
+    // The entries are now in the shared cache of `b`.
+    b.cargo("clean").run();
+    build_with_remote(&b, &url, "read-only")
+        .with_stderr_contains("[..]Restored bar v0.1.0 (lib) from the shared cache")
+        .run();
+}
+
+#[cargo_test]
+fn remote_cache_other_cargo_home() {
+    publish_deps();
+    let server = HttpServer::new();
+    let url = format!("{}/remote-cache", server.url());
+    build_with_remote(&workspace("a"), &url, "read-write").run();
+
+    // Another machine, whose registry sources are somewhere else.
+    let home = paths::root().join("other-home");
+    fs::create_dir_all(&home).unwrap();
+    fs::copy(paths::home().join(".cargo/config"), home.join("config")).unwrap();
+    build_with_remote(&workspace("b"), &url, "read-only")
+        .env("CARGO_HOME", &home)
+        .with_stderr_contains("[..]Restored bar v0.1.0 (lib) from the remote cache")
+        .with_stderr_contains("[..]Restored baz v0.1.0 (custom-build) from the remote cache")
+        .with_stderr_does_not_contain("[..]--crate-name bar[..]")
+        .run();
+}
+
+#[cargo_test]
+fn remote_cache_checksum_mismatch() {
+    publish_deps();
+    let server = HttpServer::new();
+    let url = format!("{}/remote-cache", server.url());
+    build_with_remote(&workspace("a"), &url, "read-write").run();
+
+    let remote = paths::root().join("remote-cache");
+    for entry in fs::read_dir(&remote).unwrap() {
+        let path = entry.unwrap().path();
+        if path.to_str().unwrap().ends_with(".tar.gz") {
+            fs::write(&path, "corrupt").unwrap();
+        }
+    }
+
+    build_with_remote(&workspace("b"), &url, "read-only")
+        .with_stderr_contains(
+            "[WARNING] ignoring the entry `bar-[..]` of the remote cache at `[..]`: \
+             checksum mismatch, expected `[..]` but the tarball has `[..]`",
+        )
+        .with_stderr_contains("[RUNNING] `rustc --crate-name bar [..]")
+        .with_stderr_does_not_contain("[..]Restored[..]")
+        .run();
+}
+
+#[cargo_test]
+fn remote_cache_unreachable() {
+    publish_deps();
+    // A port nothing listens on.
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let url = format!("http://{}/remote-cache", addr);
+
+    build_with_remote(&workspace("a"), &url, "read-write")
+        .with_stderr_contains(
+            "[WARNING] the remote cache at `http://[..]/remote-cache` is unavailable, \
+             it won't be used for the rest of the build: [..]",
+        )
+        .with_stderr_contains("[RUNNING] `rustc --crate-name bar [..]")
+        .with_stderr_contains("[FINISHED] [..]")
+        .run();
+}
+
+#[cargo_test]
+fn remote_cache_requires_shared_cache() {
+    publish_deps();
+    workspace("a")
+        .cargo("build -Zshared-cache")
+        .env("CARGO_BUILD_REMOTE_CACHE", "http://127.0.0.1:1")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains(
+            "[ERROR] `build.remote-cache` requires `build.shared-cache` to be set",
+        )
+        .run();
+}
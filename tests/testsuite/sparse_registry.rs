@@ -0,0 +1,383 @@
+//! Tests for sparse registries, whose index is fetched over HTTP.
+
+use cargo_test_support::project;
+use cargo_test_support::publish::validate_alt_upload;
+use cargo_test_support::registry::{self, HttpServer, Package};
+
+fn requests_for(server: &HttpServer, path: &str) -> Vec<String> {
+    server
+        .requests()
+        .into_iter()
+        .filter(|r| r.starts_with(path))
+        .collect()
+}
+
+#[cargo_test]
+fn requires_nightly_flag() {
+    let _server = registry::init_sparse();
+    Package::new("bar", "0.0.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build")
+        .with_status(101)
+        .with_stderr_contains(
+            "[..]usage of sparse registries requires `-Z sparse-registry`, \
+             found index `sparse+http://[..]/registry`",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn simple() {
+    let server = registry::init_sparse();
+    Package::new("bar", "0.0.1").publish();
+    Package::new("unused", "0.0.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#,
+        )
+        .file("src/lib.rs", "extern crate bar;")
+        .build();
+
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[DOWNLOADING] crates ...
+[DOWNLOADED] bar v0.0.1 (registry `[..]`)
+[COMPILING] bar v0.0.1
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+
+    // Only the files that were needed have been fetched.
+    assert_eq!(
+        server.requests(),
+        ["/registry/3/b/bar 200", "/registry/config.json 200"]
+    );
+
+    // A second build with a lock file doesn't need to talk to the server.
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[FINISHED] [..]")
+        .run();
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[cargo_test]
+fn update_revalidates_index_files() {
+    let server = registry::init_sparse();
+    Package::new("bar", "0.1.0").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("generate-lockfile -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .run();
+    assert_eq!(
+        requests_for(&server, "/registry/3"),
+        ["/registry/3/b/bar 200"]
+    );
+
+    // Nothing changed, so the server confirms the copy we have.
+    p.cargo("update -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr("[UPDATING] `[..]` index")
+        .run();
+    assert_eq!(
+        requests_for(&server, "/registry/3"),
+        ["/registry/3/b/bar 200", "/registry/3/b/bar 304"]
+    );
+
+    Package::new("bar", "0.1.1").publish();
+    p.cargo("update -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[UPDATING] `[..]` index
+[UPDATING] bar v0.1.0 -> v0.1.1
+",
+        )
+        .run();
+    assert_eq!(
+        requests_for(&server, "/registry/3"),
+        [
+            "/registry/3/b/bar 200",
+            "/registry/3/b/bar 304",
+            "/registry/3/b/bar 200"
+        ]
+    );
+}
+
+#[cargo_test]
+fn transitive_dependencies() {
+    let server = registry::init_sparse();
+    Package::new("baz", "0.1.0").publish();
+    Package::new("baz", "0.2.0").publish();
+    Package::new("dev-only", "0.1.0").publish();
+    Package::new("bar", "0.1.0")
+        .dep("baz", "0.2")
+        .dev_dep("dev-only", "0.1")
+        .publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.1"
+        "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[COMPILING] baz v0.2.0")
+        .run();
+
+    let mut requests = requests_for(&server, "/registry/3");
+    requests.sort();
+    assert_eq!(requests, ["/registry/3/b/bar 200", "/registry/3/b/baz 200"]);
+}
+
+#[cargo_test]
+fn missing_crate() {
+    let _server = registry::init_sparse();
+    Package::new("bar", "0.0.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            baz = "0.0.1"
+        "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[ERROR] no matching package named `baz` found")
+        .run();
+}
+
+#[cargo_test]
+fn misspelled_crate_fetches_each_spelling_once() {
+    let server = registry::init_sparse();
+    Package::new("my-dep-x", "0.0.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            my_dep_x = "0.0.1"
+        "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_status(101)
+        .with_stderr_contains("[..]perhaps you meant: my-dep-x")
+        .run();
+
+    // Every spelling is fetched exactly once.
+    let mut requests = requests_for(&server, "/registry/my");
+    requests.sort();
+    assert_eq!(
+        requests,
+        [
+            "/registry/my/-d/my-dep-x 200",
+            "/registry/my/-d/my-dep_x 404",
+            "/registry/my/-d/my_dep-x 404",
+            "/registry/my/-d/my_dep_x 404",
+            "/registry/my/_d/my-dep-x 404",
+            "/registry/my/_d/my-dep_x 404",
+            "/registry/my/_d/my_dep-x 404",
+            "/registry/my/_d/my_dep_x 404",
+        ]
+    );
+}
+
+#[cargo_test]
+fn offline_uses_cached_index() {
+    let server = registry::init_sparse();
+    Package::new("bar", "0.0.1").publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = "0.0.1"
+        "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("fetch -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .run();
+    let fetched = server.requests().len();
+
+    p.cargo("generate-lockfile --offline -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .run();
+    p.cargo("build --offline -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr(
+            "\
+[COMPILING] bar v0.0.1
+[COMPILING] foo v0.0.1 ([CWD])
+[FINISHED] [..]
+",
+        )
+        .run();
+    assert_eq!(server.requests().len(), fetched);
+}
+
+#[cargo_test]
+fn alternative_registry() {
+    let server = registry::init_sparse();
+    Package::new("bar", "0.0.1").alternative(true).publish();
+
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+            [project]
+            name = "foo"
+            version = "0.0.1"
+            authors = []
+
+            [dependencies]
+            bar = { version = "0.0.1", registry = "alternative" }
+        "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("build -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[COMPILING] bar v0.0.1 (registry `[..]`)")
+        .run();
+    assert_eq!(
+        requests_for(&server, "/alternative-registry/3"),
+        ["/alternative-registry/3/b/bar 200"]
+    );
+
+    let lock = p.read_lockfile();
+    assert!(lock.contains("source = \"registry+sparse+http://"));
+}
+
+#[cargo_test]
+fn publish_to_alternative_registry() {
+    let server = registry::init_sparse();
+    Package::new("bar", "0.0.1").alternative(true).publish();
+    let p = project().file("src/main.rs", "fn main() {}").build();
+
+    // The `api` of the registry is read from the `config.json` of the sparse
+    // index, not from a git clone of it.
+    p.cargo("publish --registry alternative --token TOKEN -Zsparse-registry")
+        .masquerade_as_nightly_cargo()
+        .with_stderr_contains("[UPLOADING] foo v0.0.1 ([CWD])")
+        .run();
+    assert_eq!(
+        requests_for(&server, "/alternative-registry/config.json"),
+        [
+            "/alternative-registry/config.json 200",
+            "/alternative-registry/config.json 304"
+        ]
+    );
+
+    validate_alt_upload(
+        r#"{
+            "authors": [],
+            "badges": {},
+            "categories": [],
+            "deps": [],
+            "description": null,
+            "documentation": null,
+            "features": {},
+            "homepage": null,
+            "keywords": [],
+            "license": null,
+            "license_file": null,
+            "links": null,
+            "name": "foo",
+            "readme": null,
+            "readme_file": null,
+            "repository": null,
+            "vers": "0.0.1"
+        }"#,
+        "foo-0.0.1.crate",
+        &["Cargo.lock", "Cargo.toml", "Cargo.toml.orig", "src/main.rs"],
+    );
+}
@@ -457,6 +457,7 @@ fn update_precise_first_run() {
       "links": null,
       "manifest_path": "[..]/foo/Cargo.toml",
       "metadata": null,
+      "rust_version": null,
       "publish": null,
       "name": "bar",
       "readme": null,
@@ -493,6 +494,7 @@ fn update_precise_first_run() {
       "links": null,
       "manifest_path": "[..]/home/.cargo/registry/src/-[..]/serde-0.2.0/Cargo.toml",
       "metadata": null,
+      "rust_version": null,
       "publish": null,
       "name": "serde",
       "readme": null,
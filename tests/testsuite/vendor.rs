@@ -675,3 +675,242 @@ fn git_crlf_preservation() {
     let output = p.read_file("vendor/a/src/lib.rs");
     assert_eq!(input, output);
 }
+
+/// Publishes a version of `log`, which depends on `bar` and has features.
+fn log_package(vers: &str) {
+    Package::new("log", vers)
+        .dep("bar", "0.1")
+        .feature("std", &[])
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "log"
+                    version = "{}"
+
+                    [dependencies]
+                    bar = "0.1"
+
+                    [features]
+                    std = []
+                "#,
+                vers
+            ),
+        )
+        .file("src/lib.rs", "")
+        .publish();
+}
+
+#[cargo_test]
+fn vendor_local_registry() {
+    Package::new("bar", "0.1.0").publish();
+    log_package("0.3.5");
+    Package::new("altdep", "0.1.0").alternative(true).publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                log = { version = "0.3.5", features = ["std"] }
+                altdep = { version = "0.1", registry = "alternative" }
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    let output = p
+        .cargo("vendor --respect-source-config --format local-registry")
+        .exec_with_output()
+        .unwrap();
+    let output = String::from_utf8(output.stdout).unwrap();
+    assert!(output.contains("local-registry = \"vendor\""), "{}", output);
+    for file in &["bar-0.1.0.crate", "log-0.3.5.crate", "altdep-0.1.0.crate"] {
+        assert!(p.root().join("vendor").join(file).is_file(), "{}", file);
+    }
+    let index = p.read_file("vendor/index/3/l/log");
+    let entry: serde_json::Value = serde_json::from_str(&index).unwrap();
+    assert_eq!(entry["vers"], "0.3.5");
+    assert_eq!(entry["features"], serde_json::json!({"std": []}));
+    assert_eq!(entry["deps"][0]["name"], "bar");
+    assert_eq!(entry["deps"][0]["registry"], serde_json::Value::Null);
+
+    p.change_file(".cargo/config", &output);
+    p.cargo("check")
+        .with_stderr_contains("[UNPACKING] log v0.3.5 ([..])")
+        .with_stderr_contains("[UNPACKING] altdep v0.1.0 ([..])")
+        .run();
+
+    // Only the crates which changed are vendored again.
+    p.change_file(".cargo/config", "");
+    log_package("0.3.6");
+    p.cargo("update -p log").run();
+    p.cargo("vendor --respect-source-config --format local-registry")
+        .with_stderr_contains("[..]Vendoring log v0.3.6 ([..]) to [..]log-0.3.6.crate")
+        .with_stderr_does_not_contain("[..]Vendoring bar[..]")
+        .with_stderr_does_not_contain("[..]Vendoring altdep[..]")
+        .run();
+    assert!(!p.root().join("vendor/log-0.3.5.crate").exists());
+    let index = p.read_file("vendor/index/3/l/log");
+    assert_eq!(index.lines().count(), 1);
+    assert!(index.contains("\"vers\":\"0.3.6\""));
+}
+
+#[cargo_test]
+fn vendor_local_registry_git_dep() {
+    let git_project = git::new("gitdep", |project| {
+        project
+            .file("Cargo.toml", &basic_lib_manifest("gitdep"))
+            .file("src/lib.rs", "")
+    });
+    let p = project()
+        .file(
+            "Cargo.toml",
+            &format!(
+                r#"
+                    [package]
+                    name = "foo"
+                    version = "0.1.0"
+
+                    [dependencies]
+                    gitdep = {{ git = '{}' }}
+                "#,
+                git_project.url()
+            ),
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    p.cargo("vendor --respect-source-config --format local-registry")
+        .with_status(101)
+        .with_stderr_contains(
+            "\
+Caused by:
+  `gitdep v0.5.0 ([..])` can't be vendored in the `local-registry` format, \
+only packages from registries can
+",
+        )
+        .run();
+}
+
+#[cargo_test]
+fn vendor_for_platforms() {
+    Package::new("common", "0.1.0")
+        .file("src/lib.rs", "pub fn common() {}")
+        .publish();
+    Package::new("unixonly", "0.1.0")
+        .file("src/lib.rs", "pub fn unixonly() {}")
+        .publish();
+    Package::new("winsub", "0.1.0")
+        .file("src/lib.rs", "pub fn winsub() {}")
+        .publish();
+    Package::new("winonly", "0.1.0")
+        .dep("winsub", "0.1")
+        .file("src/lib.rs", "pub fn winonly() {}")
+        .publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [dependencies]
+                common = "0.1"
+
+                [target.'cfg(unix)'.dependencies]
+                unixonly = "0.1"
+
+                [target.'cfg(windows)'.dependencies]
+                winonly = "0.1"
+            "#,
+        )
+        .file(
+            "src/lib.rs",
+            "pub fn foo() { common::common(); #[cfg(unix)] unixonly::unixonly(); }",
+        )
+        .build();
+
+    p.cargo("vendor --respect-source-config --platform x86_64-unknown-linux-gnu")
+        .with_stderr_contains("[..]Vendoring common v0.1.0[..]")
+        .with_stderr_contains("[..]Vendoring unixonly v0.1.0[..]")
+        .with_stderr_does_not_contain("[..]Vendoring win[..]")
+        .run();
+    assert!(p.read_file("vendor/common/src/lib.rs").contains("common"));
+    // Only the manifests of the other packages are vendored, so that
+    // dependencies can still be resolved.
+    for name in &["winonly", "winsub"] {
+        assert!(p
+            .read_file(&format!("vendor/{}/Cargo.toml", name))
+            .contains(name));
+        assert_eq!(p.read_file(&format!("vendor/{}/src/lib.rs", name)), "");
+    }
+
+    add_vendor_config(&p);
+    if cfg!(unix) {
+        p.cargo("build --offline").run();
+    }
+
+    // Vendoring for all platforms again replaces the stubs.
+    p.change_file(".cargo/config", "");
+    p.cargo("vendor --respect-source-config --versioned-dirs")
+        .with_stderr_contains("[..]Vendoring winonly v0.1.0[..]")
+        .run();
+    assert!(p
+        .read_file("vendor/winonly-0.1.0/src/lib.rs")
+        .contains("winonly"));
+    p.cargo("vendor --respect-source-config --versioned-dirs --platform x86_64-pc-windows-msvc")
+        .with_stderr_does_not_contain("[..]Vendoring[..]")
+        .run();
+    assert_eq!(p.read_file("vendor/unixonly-0.1.0/src/lib.rs"), "");
+    p.cargo("vendor --respect-source-config --versioned-dirs")
+        .with_stderr_contains("[..]Vendoring unixonly v0.1.0[..]")
+        .with_stderr_does_not_contain("[..]Vendoring winonly[..]")
+        .run();
+}
+
+#[cargo_test]
+fn vendor_local_registry_for_platforms() {
+    Package::new("unixonly", "0.1.0")
+        .file("src/lib.rs", "pub fn unixonly() {}")
+        .publish();
+    Package::new("winonly", "0.1.0").publish();
+    let p = project()
+        .file(
+            "Cargo.toml",
+            r#"
+                [package]
+                name = "foo"
+                version = "0.1.0"
+
+                [target.'cfg(unix)'.dependencies]
+                unixonly = "0.1"
+
+                [target.'cfg(windows)'.dependencies]
+                winonly = "0.1"
+            "#,
+        )
+        .file("src/lib.rs", "")
+        .build();
+
+    let output = p
+        .cargo(
+            "vendor --respect-source-config --format local-registry \
+             --platform x86_64-unknown-linux-gnu",
+        )
+        .exec_with_output()
+        .unwrap();
+    assert!(p.root().join("vendor/unixonly-0.1.0.crate").is_file());
+    assert!(!p.root().join("vendor/winonly-0.1.0.crate").exists());
+    assert!(p.root().join("vendor/index/wi/no/winonly").is_file());
+
+    if cfg!(unix) {
+        p.change_file(".cargo/config", &String::from_utf8(output.stdout).unwrap());
+        p.cargo("build --offline").run();
+    }
+}